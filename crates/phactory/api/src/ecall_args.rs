@@ -31,6 +31,16 @@ pub struct InitArgs {
     /// Max number of checkpoint files kept
     pub max_checkpoint_files: u32,
 
+    /// Max number of delta checkpoints taken between two full checkpoints. 0 to disable deltas.
+    ///
+    /// A delta takes less disk space than a full checkpoint, but as much time to take, since the
+    /// whole state is still serialized. Restoring from a delta needs extra memory to hold the
+    /// plaintext of its base.
+    pub max_checkpoint_deltas: u32,
+
+    /// Where to back up the checkpoints, such as `dir:<path>` or `s3://<endpoint>/<bucket>?region=<region>`.
+    pub checkpoint_backup: Option<String>,
+
     /// Number of cores used to run fat contracts
     pub cores: u32,
 
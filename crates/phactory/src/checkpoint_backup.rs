@@ -0,0 +1,474 @@
+//! Backup of the checkpoints to a remote storage.
+//!
+//! Every checkpoint file is uploaded as an object named after the file, and an index object lists
+//! the files needed to restore the latest state, which are the latest full checkpoint and its
+//! latest delta. The objects no longer listed are removed from the backend. The checkpoints are
+//! encrypted with a key derived from the identity key, so the backend doesn't have to be trusted.
+//!
+//! The backend is configured with one of:
+//! - `dir:<path>`, a local directory, mainly for tests.
+//! - `s3://<endpoint>/<bucket>?region=<region>[&virtual_host=true]`, an S3 compatible storage,
+//!   with the credentials in the env `CHECKPOINT_BACKUP_ACCESS_KEY` and
+//!   `CHECKPOINT_BACKUP_SECRET_KEY`.
+
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, ErrorKind, Read},
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use reqwest::{
+    blocking::{Client, Response},
+    Method, StatusCode,
+};
+use reqwest_env_proxy::EnvProxyBuilder;
+
+use crate::{CHECKPOINT_FILE, DELTA_CHECKPOINT_FILE};
+
+const INDEX_OBJECT: &str = "checkpoint-index.json";
+/// Objects larger than this are uploaded to S3 in parts of this size, so that only one part is
+/// held in memory at a time.
+const S3_PART_SIZE: usize = 16 * 1024 * 1024;
+
+pub(crate) trait Backend: Send {
+    /// Store the object, reading its content to the end of `data`.
+    fn put(&self, name: &str, data: &mut dyn Read) -> Result<()>;
+    /// Returns None if the object doesn't exist.
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>>;
+    fn remove(&self, name: &str) -> Result<()>;
+}
+
+struct DirBackend {
+    dir: PathBuf,
+}
+
+impl Backend for DirBackend {
+    fn put(&self, name: &str, data: &mut dyn Read) -> Result<()> {
+        let path = self.dir.join(name);
+        let tmp_path = path.with_extension("tmp");
+        File::create(&tmp_path)
+            .and_then(|mut file| io::copy(data, &mut file))
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).context("Failed to read backup"),
+        }
+    }
+
+    fn remove(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.dir.join(name)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(err).context("Failed to remove backup")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+struct S3Backend {
+    endpoint: String,
+    region: String,
+    bucket: String,
+    access_key: String,
+    secret_key: String,
+    virtual_host_mode: bool,
+    client: Client,
+}
+
+impl S3Backend {
+    fn send(
+        &self,
+        method: Method,
+        name: &str,
+        query: &[(&str, &str)],
+        body: Option<Vec<u8>>,
+    ) -> Result<Option<Response>> {
+        let s3 = pink_s3::S3::new(
+            &self.endpoint,
+            &self.region,
+            &self.access_key,
+            &self.secret_key,
+        )
+        .map_err(|err| anyhow!("Invalid S3 config: {err:?}"))?;
+        let s3 = if self.virtual_host_mode {
+            s3.virtual_host_mode()
+        } else {
+            s3
+        };
+        let signed = s3.sign_request_with_query(
+            method.as_str(),
+            &self.bucket,
+            name,
+            query,
+            body.as_deref(),
+            chrono::Utc::now(),
+        );
+        let mut request = self.client.request(method, &signed.url);
+        for (key, value) in signed.headers {
+            // Set by the client according to the body
+            if key != "Content-Length" {
+                request = request.header(key, value);
+            }
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        let response = request.send().context("Failed to send S3 request")?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response)),
+            status => bail!("S3 request failed: {status}"),
+        }
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        name: &str,
+        query: &[(&str, &str)],
+        body: Option<Vec<u8>>,
+    ) -> Result<Option<Vec<u8>>> {
+        let Some(response) = self.send(method, name, query, body)? else {
+            return Ok(None);
+        };
+        let body = response.bytes().context("Failed to read S3 response")?;
+        Ok(Some(body.to_vec()))
+    }
+
+    /// Upload an object in parts, which are read one by one after the first one.
+    fn put_multipart(&self, name: &str, first_part: Vec<u8>, data: &mut dyn Read) -> Result<()> {
+        let response = self
+            .request(Method::POST, name, &[("uploads", "")], None)?
+            .ok_or_else(|| anyhow!("S3 bucket not found"))?;
+        let upload_id = xml_element(&response, "UploadId")
+            .ok_or_else(|| anyhow!("Invalid S3 multipart upload response"))?;
+        let result = self.upload_parts(name, &upload_id, first_part, data);
+        if result.is_err() {
+            // The storage keeps the uploaded parts until the upload is aborted.
+            let query = [("uploadId", upload_id.as_str())];
+            if let Err(err) = self.request(Method::DELETE, name, &query, None) {
+                warn!("Failed to abort S3 multipart upload: {err:?}");
+            }
+        }
+        result
+    }
+
+    fn upload_parts(
+        &self,
+        name: &str,
+        upload_id: &str,
+        mut part: Vec<u8>,
+        data: &mut dyn Read,
+    ) -> Result<()> {
+        let mut etags = vec![];
+        loop {
+            let part_number = (etags.len() + 1).to_string();
+            let query = [
+                ("partNumber", part_number.as_str()),
+                ("uploadId", upload_id),
+            ];
+            let response = self
+                .send(Method::PUT, name, &query, Some(part))?
+                .ok_or_else(|| anyhow!("S3 multipart upload not found"))?;
+            let etag = response
+                .headers()
+                .get("ETag")
+                .and_then(|etag| etag.to_str().ok())
+                .ok_or_else(|| anyhow!("Missing ETag of the uploaded part"))?;
+            etags.push(etag.to_string());
+            part = read_part(data).context("Failed to read checkpoint file")?;
+            if part.is_empty() {
+                break;
+            }
+        }
+        let mut body = String::from("<CompleteMultipartUpload>");
+        for (i, etag) in etags.iter().enumerate() {
+            let part_number = i + 1;
+            body +=
+                &format!("<Part><PartNumber>{part_number}</PartNumber><ETag>{etag}</ETag></Part>");
+        }
+        body += "</CompleteMultipartUpload>";
+        let query = [("uploadId", upload_id)];
+        let response = self
+            .request(Method::POST, name, &query, Some(body.into_bytes()))?
+            .ok_or_else(|| anyhow!("S3 multipart upload not found"))?;
+        // The completion can fail after the response status is sent.
+        if xml_element(&response, "Error").is_some() {
+            bail!("Failed to complete S3 multipart upload");
+        }
+        Ok(())
+    }
+}
+
+impl Backend for S3Backend {
+    fn put(&self, name: &str, data: &mut dyn Read) -> Result<()> {
+        let first_part = read_part(data).context("Failed to read checkpoint file")?;
+        if first_part.len() == S3_PART_SIZE {
+            return self.put_multipart(name, first_part, data);
+        }
+        self.request(Method::PUT, name, &[], Some(first_part))?
+            .ok_or_else(|| anyhow!("S3 bucket not found"))?;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.request(Method::GET, name, &[], None)
+    }
+
+    fn remove(&self, name: &str) -> Result<()> {
+        self.request(Method::DELETE, name, &[], None)?;
+        Ok(())
+    }
+}
+
+/// Read up to [`S3_PART_SIZE`] bytes.
+fn read_part(data: &mut dyn Read) -> io::Result<Vec<u8>> {
+    let mut part = vec![];
+    Read::take(data, S3_PART_SIZE as u64).read_to_end(&mut part)?;
+    Ok(part)
+}
+
+/// Get the text of the first element named `tag` in a S3 response.
+fn xml_element(xml: &[u8], tag: &str) -> Option<String> {
+    let xml = std::str::from_utf8(xml).ok()?;
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let len = xml[start..].find(&format!("</{tag}>"))?;
+    Some(xml[start..start + len].to_string())
+}
+
+pub(crate) fn open_backend(spec: &str) -> Result<Box<dyn Backend>> {
+    if let Some(dir) = spec.strip_prefix("dir:") {
+        fs::create_dir_all(dir).context("Failed to create the backup dir")?;
+        return Ok(Box::new(DirBackend { dir: dir.into() }));
+    }
+    let Some(url) = spec.strip_prefix("s3://") else {
+        bail!("Unsupported checkpoint backup: {spec}");
+    };
+    let (location, query) = url.split_once('?').unwrap_or((url, ""));
+    let (endpoint, bucket) = location
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Missing S3 bucket"))?;
+    let mut region = None;
+    let mut virtual_host_mode = false;
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        match param.split_once('=') {
+            Some(("region", value)) => region = Some(value.to_string()),
+            Some(("virtual_host", value)) => virtual_host_mode = value == "true",
+            _ => bail!("Unknown S3 parameter: {param}"),
+        }
+    }
+    let env = |name: &str| std::env::var(name).with_context(|| format!("Missing env {name}"));
+    let client = Client::builder()
+        .env_proxy(endpoint)
+        .build()
+        .context("Failed to create HTTP client")?;
+    Ok(Box::new(S3Backend {
+        endpoint: endpoint.into(),
+        region: region.ok_or_else(|| anyhow!("Missing S3 region"))?,
+        bucket: bucket.into(),
+        access_key: env("CHECKPOINT_BACKUP_ACCESS_KEY")?,
+        secret_key: env("CHECKPOINT_BACKUP_SECRET_KEY")?,
+        virtual_host_mode,
+        client,
+    }))
+}
+
+fn read_index(backend: &dyn Backend) -> Result<Vec<String>> {
+    let Some(data) = backend.get(INDEX_OBJECT)? else {
+        return Ok(vec![]);
+    };
+    serde_json::from_slice(&data).context("Invalid checkpoint backup index")
+}
+
+fn object_name(path: &Path) -> Result<String> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid checkpoint filename {}", path.display()))?;
+    Ok(name.to_string())
+}
+
+/// A checkpoint file to back up.
+///
+/// The file is opened when the backup is requested, so it stays readable even if the cleanup of
+/// the outdated checkpoints removes it before the upload.
+struct CheckpointFile {
+    name: String,
+    file: File,
+}
+
+impl CheckpointFile {
+    fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            name: object_name(path)?,
+            file: File::open(path)
+                .with_context(|| format!("Failed to open checkpoint file {}", path.display()))?,
+        })
+    }
+}
+
+/// Uploads the checkpoints in a background thread, to not block the block dispatching.
+pub(crate) struct CheckpointBackup {
+    tx: mpsc::Sender<Vec<CheckpointFile>>,
+}
+
+impl CheckpointBackup {
+    pub fn new(backend: Box<dyn Backend>) -> Self {
+        let (tx, rx) = mpsc::channel::<Vec<CheckpointFile>>();
+        std::thread::Builder::new()
+            .name("checkpoint-backup".into())
+            .spawn(move || {
+                let mut uploaded = BTreeSet::new();
+                while let Ok(files) = rx.recv() {
+                    // Only the latest state matters if the uploading falls behind.
+                    let files = rx.try_iter().last().unwrap_or(files);
+                    if let Err(err) = sync_backup(&*backend, &mut uploaded, files) {
+                        error!("Failed to back up checkpoint: {err:?}");
+                    }
+                }
+            })
+            .expect("Failed to spawn checkpoint backup thread");
+        Self { tx }
+    }
+
+    /// Back up the given files, which are needed to restore the latest checkpoint.
+    pub fn upload(&self, files: Vec<PathBuf>) {
+        let files = files
+            .iter()
+            .map(|path| CheckpointFile::open(path))
+            .collect::<Result<Vec<_>>>();
+        let files = match files {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Failed to back up checkpoint: {err:?}");
+                return;
+            }
+        };
+        if self.tx.send(files).is_err() {
+            error!("Checkpoint backup thread exited");
+        }
+    }
+}
+
+fn sync_backup(
+    backend: &dyn Backend,
+    uploaded: &mut BTreeSet<String>,
+    files: Vec<CheckpointFile>,
+) -> Result<()> {
+    let old_index = read_index(backend)?;
+    let mut index = vec![];
+    for CheckpointFile { name, mut file } in files {
+        if !uploaded.contains(&name) {
+            backend.put(&name, &mut file)?;
+            info!("Checkpoint {name} backed up");
+            uploaded.insert(name.clone());
+        }
+        index.push(name);
+    }
+    backend.put(INDEX_OBJECT, &mut &serde_json::to_vec(&index)?[..])?;
+    for name in old_index {
+        if !index.contains(&name) {
+            backend.remove(&name)?;
+            uploaded.remove(&name);
+        }
+    }
+    Ok(())
+}
+
+/// Download the checkpoint files listed in the backup index into `dir`.
+///
+/// Returns false if there is no backup.
+pub(crate) fn download_latest(backend: &dyn Backend, dir: &str) -> Result<bool> {
+    let index = read_index(backend)?;
+    if index.is_empty() {
+        return Ok(false);
+    }
+    for name in index {
+        // The index comes from an untrusted place, don't let it write elsewhere.
+        let valid = !name.contains(['/', '\\'])
+            && (name.starts_with(CHECKPOINT_FILE) || name.starts_with(DELTA_CHECKPOINT_FILE));
+        if !valid {
+            bail!("Invalid checkpoint name in the backup index: {name:?}");
+        }
+        let data = backend
+            .get(&name)?
+            .ok_or_else(|| anyhow!("Checkpoint {name} is missing in the backup"))?;
+        let path = Path::new(dir).join(&name);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, data)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .context("Failed to write checkpoint file")?;
+        info!("Downloaded checkpoint {name}");
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn dir_backup_works() {
+        let root = std::env::temp_dir().join(format!(
+            "checkpoint-backup-test-{}",
+            rand::thread_rng().gen::<u64>()
+        ));
+        let local = root.join("local");
+        let remote = root.join("remote");
+        fs::create_dir_all(&local).unwrap();
+        let backend = open_backend(&format!("dir:{}", remote.display())).unwrap();
+
+        let base = local.join(format!("{CHECKPOINT_FILE}-000000010"));
+        let delta = local.join(format!("{DELTA_CHECKPOINT_FILE}-000000020-000000010"));
+        fs::write(&base, b"base").unwrap();
+        fs::write(&delta, b"delta").unwrap();
+        let open = |paths: &[&PathBuf]| -> Vec<_> {
+            paths
+                .iter()
+                .map(|path| CheckpointFile::open(path).unwrap())
+                .collect()
+        };
+        let mut uploaded = BTreeSet::new();
+        sync_backup(&*backend, &mut uploaded, open(&[&base])).unwrap();
+        sync_backup(&*backend, &mut uploaded, open(&[&base, &delta])).unwrap();
+
+        let new_base = local.join(format!("{CHECKPOINT_FILE}-000000030"));
+        fs::write(&new_base, b"new base").unwrap();
+        let files = open(&[&new_base]);
+        // Removed by the cleanup before being uploaded
+        fs::remove_file(&new_base).unwrap();
+        sync_backup(&*backend, &mut uploaded, files).unwrap();
+        assert_eq!(backend.get(&object_name(&base).unwrap()).unwrap(), None);
+        assert_eq!(backend.get(&object_name(&delta).unwrap()).unwrap(), None);
+
+        let restored = root.join("restored");
+        fs::create_dir_all(&restored).unwrap();
+        assert!(download_latest(&*backend, &restored.to_string_lossy()).unwrap());
+        assert_eq!(
+            fs::read(restored.join(object_name(&new_base).unwrap())).unwrap(),
+            b"new base"
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn s3_xml_element_works() {
+        let response = b"<InitiateMultipartUploadResult><Bucket>ckpt</Bucket>\
+            <Key>checkpoint.seal-000000010</Key><UploadId>VXBsb2FkIElE</UploadId>\
+            </InitiateMultipartUploadResult>";
+        assert_eq!(
+            xml_element(response, "UploadId").as_deref(),
+            Some("VXBsb2FkIElE")
+        );
+        assert_eq!(xml_element(response, "Error"), None);
+    }
+}
@@ -0,0 +1,315 @@
+//! Offline inspection of checkpoint files.
+//!
+//! The restore path doesn't tell why a checkpoint fails to load, to keep the state out of the log.
+//! Operators can instead run the inspection on a given file, which decrypts it with the key in the
+//! sealed runtime data and reports the layout and a summary of the state, or where the decoding
+//! fails, without starting the worker.
+
+use std::{fs::File, io::Read, path::Path};
+
+use anyhow::{Context as _, Result};
+use phactory_api::{ecall_args::InitArgs, storage_sync::StorageSynchronizer};
+use phala_crypto::aead;
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Serialize,
+};
+
+use crate::{
+    checkpoint_filename_for, delta_checkpoint::BaseData, delta_checkpoint::DeltaReader,
+    derive_key_for_checkpoint, hex, parse_delta_checkpoint_blocks, Phactory, PhactoryLoader,
+};
+
+/// The top level elements of a checkpoint, in order.
+const SECTIONS: [&str; 4] = ["version", "benchmark", "phactory", "system"];
+
+#[derive(Serialize, Default, Debug)]
+pub struct CheckpointReport {
+    /// The base checkpoint if the file is a delta.
+    pub base: Option<String>,
+    /// Size of the decrypted data in bytes.
+    pub size: usize,
+    pub version: Option<u32>,
+    pub sections: Vec<Section>,
+    pub block_number: Option<chain::BlockNumber>,
+    pub clusters: Vec<ClusterSummary>,
+    pub mq_sequences: Vec<MqSequence>,
+    pub error: Option<DecodeError>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Section {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ClusterSummary {
+    pub id: String,
+    pub contracts: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MqSequence {
+    pub sender: String,
+    pub next_sequence: u64,
+    pub on_chain_sequence: u64,
+    pub pending_messages: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DecodeError {
+    /// The section where the decoding fails, if known.
+    pub section: Option<&'static str>,
+    /// Offset in the decrypted data.
+    pub offset: Option<u64>,
+    pub message: String,
+}
+
+impl DecodeError {
+    fn at(sections: &[Section], offset: Option<u64>, message: String) -> Self {
+        let section = offset.and_then(|offset| {
+            sections
+                .iter()
+                .find(|s| (s.offset..s.offset + s.size).contains(&(offset as usize)))
+                .map(|s| s.name)
+        });
+        Self {
+            section,
+            offset,
+            message,
+        }
+    }
+}
+
+fn read_plain(mut reader: impl Read) -> (Vec<u8>, Option<std::io::Error>) {
+    let mut plain = vec![];
+    // The data read before an error is kept, so that the sections before it can be reported.
+    let error = reader.read_to_end(&mut plain).err();
+    (plain, error)
+}
+
+/// Walk through the top level elements without decoding them into the state types.
+fn scan_sections(plain: &[u8], report: &mut CheckpointReport) -> Option<DecodeError> {
+    // The state is serialized as an indefinite-length array.
+    if plain.first() != Some(&0x9f) {
+        return Some(DecodeError::at(
+            &[],
+            Some(0),
+            "Not an indefinite-length array".into(),
+        ));
+    }
+    let mut de = serde_cbor::Deserializer::from_slice(&plain[1..]);
+    for name in SECTIONS {
+        let offset = de.byte_offset() + 1;
+        let result = if name == "version" {
+            u32::deserialize(&mut de).map(|version| report.version = Some(version))
+        } else {
+            IgnoredAny::deserialize(&mut de).map(|_| ())
+        };
+        if let Err(err) = result {
+            return Some(DecodeError {
+                section: Some(name),
+                offset: Some(err.offset() + 1),
+                message: err.to_string(),
+            });
+        }
+        report.sections.push(Section {
+            name,
+            offset,
+            size: de.byte_offset() + 1 - offset,
+        });
+    }
+    None
+}
+
+impl<Platform: pal::Platform + Serialize + DeserializeOwned> Phactory<Platform> {
+    /// Decrypt and inspect a checkpoint file, or a delta checkpoint together with its base.
+    pub fn inspect_checkpoint(
+        platform: &Platform,
+        args: &InitArgs,
+        filename: &Path,
+    ) -> Result<CheckpointReport> {
+        let runtime_data = Self::load_runtime_data(platform, &args.sealing_path)
+            .context("Failed to load persistent data")?;
+        let key128 = derive_key_for_checkpoint(&runtime_data.sk);
+        let open = |filename: &Path| -> Result<_> {
+            let file = File::open(filename)
+                .with_context(|| format!("Failed to open {}", filename.display()))?;
+            Ok(aead::stream::new_aes128gcm_reader(key128, file))
+        };
+
+        let mut report = CheckpointReport::default();
+        let (plain, read_error) = match parse_delta_checkpoint_blocks(filename) {
+            None => read_plain(open(filename)?),
+            Some((_block, base_block)) => {
+                let basedir = filename.parent().unwrap_or(Path::new("."));
+                let base_filename = checkpoint_filename_for(base_block, &basedir.to_string_lossy());
+                let base = BaseData::read(open(Path::new(&base_filename))?)
+                    .with_context(|| format!("Failed to read the base {base_filename}"))?;
+                report.base = Some(base_filename);
+                let reader = DeltaReader::new(open(filename)?, &base)
+                    .context("Failed to read the delta header")?;
+                read_plain(reader)
+            }
+        };
+        report.size = plain.len();
+
+        let scan_error = scan_sections(&plain, &mut report);
+        if let Some(err) = read_error {
+            report.error = Some(DecodeError::at(
+                &[],
+                Some(plain.len() as u64),
+                format!("Failed to read the data: {err}"),
+            ));
+            return Ok(report);
+        }
+        if scan_error.is_some() {
+            report.error = scan_error;
+            return Ok(report);
+        }
+
+        let PhactoryLoader(factory) =
+            match serde_cbor::from_slice::<PhactoryLoader<Platform>>(&plain) {
+                Ok(loader) => loader,
+                Err(err) => {
+                    report.error = Some(DecodeError::at(
+                        &report.sections,
+                        Some(err.offset()),
+                        err.to_string(),
+                    ));
+                    return Ok(report);
+                }
+            };
+        if let Some(state) = &factory.runtime_state {
+            let counters = state.storage_synchronizer.counters();
+            report.block_number = Some(counters.next_block_number.saturating_sub(1));
+            report.mq_sequences = state
+                .send_mq
+                .sequences()
+                .into_iter()
+                .map(|(sender, next_sequence)| MqSequence {
+                    on_chain_sequence: state.chain_storage.mq_sequence(&sender),
+                    pending_messages: state.send_mq.messages(&sender).len(),
+                    sender: sender.to_string(),
+                    next_sequence,
+                })
+                .collect();
+        }
+        if let Some(system) = &factory.system {
+            report.clusters = system
+                .contract_clusters
+                .iter()
+                .map(|(id, cluster)| ClusterSummary {
+                    id: hex(id),
+                    contracts: cluster.iter_contracts().map(hex).collect(),
+                })
+                .collect();
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PhactoryDumper, CHECKPOINT_VERSION};
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct TestPlatform;
+
+    impl pal::Sealing for TestPlatform {
+        type SealError = anyhow::Error;
+        type UnsealError = anyhow::Error;
+
+        fn seal_data(&self, _path: impl AsRef<Path>, _data: &[u8]) -> Result<()> {
+            Ok(())
+        }
+        fn unseal_data(&self, _path: impl AsRef<Path>) -> Result<Option<Vec<u8>>> {
+            Ok(None)
+        }
+    }
+
+    impl pal::RA for TestPlatform {
+        type Error = anyhow::Error;
+
+        fn create_attestation_report(
+            &self,
+            _provider: Option<phala_types::AttestationProvider>,
+            _data: &[u8],
+        ) -> Result<Vec<u8>> {
+            anyhow::bail!("No attestation in tests")
+        }
+        fn quote_test(&self, _provider: Option<phala_types::AttestationProvider>) -> Result<()> {
+            Ok(())
+        }
+        fn measurement(&self) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    impl pal::Machine for TestPlatform {
+        fn machine_id(&self) -> Vec<u8> {
+            vec![0; 16]
+        }
+        fn cpu_core_num(&self) -> u32 {
+            1
+        }
+        fn cpu_feature_level(&self) -> u32 {
+            1
+        }
+    }
+
+    impl pal::MemoryStats for TestPlatform {
+        fn memory_usage(&self) -> pal::MemoryUsage {
+            pal::MemoryUsage {
+                total_peak_used: 0,
+                rust_used: 0,
+                rust_peak_used: 0,
+                free: 0,
+            }
+        }
+    }
+
+    impl pal::AppInfo for TestPlatform {
+        fn app_version() -> pal::AppVersion {
+            pal::AppVersion {
+                major: 0,
+                minor: 0,
+                patch: 1,
+            }
+        }
+    }
+
+    #[test]
+    fn scan_sections_of_a_dumped_state() {
+        let phactory = Phactory::new(TestPlatform);
+        let plain = serde_cbor::to_vec(&PhactoryDumper(&phactory)).unwrap();
+
+        let mut report = CheckpointReport::default();
+        assert!(scan_sections(&plain, &mut report).is_none());
+        assert_eq!(report.version, Some(CHECKPOINT_VERSION));
+        let names: Vec<_> = report.sections.iter().map(|s| s.name).collect();
+        assert_eq!(names, SECTIONS);
+        // The sections are contiguous and followed by the break of the array.
+        let mut offset = 1;
+        for section in &report.sections {
+            assert_eq!(section.offset, offset);
+            offset += section.size;
+        }
+        assert_eq!(plain[offset..], [0xff]);
+
+        let phactory_section = &report.sections[2];
+        let truncated = &plain[..phactory_section.offset + 1];
+        let mut report = CheckpointReport::default();
+        let error = scan_sections(truncated, &mut report).unwrap();
+        assert_eq!(error.section, Some("phactory"));
+        assert_eq!(report.sections.len(), 2);
+
+        let mut report = CheckpointReport::default();
+        let error = scan_sections(&plain[1..], &mut report).unwrap();
+        assert_eq!(error.offset, Some(0));
+        assert!(report.sections.is_empty());
+    }
+}
@@ -370,6 +370,7 @@ pub mod cluster {
     use phala_serde_more as more;
     use phala_types::contract::messaging::ResourceType;
     use pink::{
+        runtime::HttpEgressPolicy,
         types::{AccountId, Balance, Hash},
         weights::Weight,
     };
@@ -475,6 +476,10 @@ pub mod cluster {
             self.storage.set_key_seed(seed);
         }
 
+        pub fn set_http_egress_policy(&mut self, policy: HttpEgressPolicy) {
+            self.storage.set_http_egress_policy(policy);
+        }
+
         pub fn upload_resource(
             &mut self,
             origin: &AccountId,
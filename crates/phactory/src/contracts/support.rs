@@ -1,6 +1,8 @@
 use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
 
 use parity_scale_codec::Decode;
 use phala_crypto::ecdh::EcdhPublicKey;
@@ -9,7 +11,7 @@ use phala_scheduler::RequestScheduler;
 use runtime::BlockNumber;
 use sidevm::{
     service::{Command as SidevmCommand, CommandSender, ExitReason},
-    OcallAborted, VmId,
+    OcallAborted, VmId, VmSnapshot,
 };
 
 use super::pink::cluster::ClusterKeeper;
@@ -24,6 +26,9 @@ use phactory_api::prpc as pb;
 
 use phala_serde_more as more;
 
+/// The pending reply of a sidevm snapshot request.
+pub(crate) type SidevmSnapshotReply = mpsc::Receiver<anyhow::Result<VmSnapshot>>;
+
 pub struct ExecuteEnv<'a, 'b> {
     pub block: &'a mut BlockInfo<'b>,
     pub contract_clusters: &'a mut ClusterKeeper,
@@ -110,6 +115,15 @@ struct SidevmInfo {
     start_time: String,
     auto_restart: bool,
     handle: Arc<Mutex<SidevmHandle>>,
+    /// Whether the running instance has enabled snapshots.
+    #[serde(skip)]
+    snapshot_enabled: Arc<AtomicBool>,
+    /// The state to resume the instance from. Only present while dumping or loading a checkpoint.
+    #[serde(default)]
+    snapshot: Option<VmSnapshot>,
+    /// The persistent storage of the instance. Only present while dumping or loading a checkpoint.
+    #[serde(default)]
+    storage: Option<StorageDump>,
 }
 
 pub(crate) enum SidevmCode {
@@ -271,13 +285,19 @@ impl FatContract {
             }
         };
 
-        let handle = if code.is_empty() {
+        let (handle, snapshot_enabled) = if code.is_empty() {
             info!("Sidevm code {code_hash:?} not found, waiting to be uploaded");
-            Arc::new(Mutex::new(SidevmHandle::Stopped(
-                ExitReason::WaitingForCode,
-            )))
+            let handle = SidevmHandle::Stopped(ExitReason::WaitingForCode);
+            (Arc::new(Mutex::new(handle)), Default::default())
         } else {
-            do_start_sidevm(spawner, &code, self.contract_id.0, self.weight)?
+            do_start_sidevm(
+                spawner,
+                &code,
+                self.contract_id.0,
+                &self.ecdh_key,
+                self.weight,
+                None,
+            )?
         };
 
         let start_time = chrono::Utc::now().to_rfc3339();
@@ -286,7 +306,10 @@ impl FatContract {
             code_hash,
             start_time,
             handle,
+            snapshot_enabled,
             auto_restart: true,
+            snapshot: None,
+            storage: None,
         });
         Ok(())
     }
@@ -297,7 +320,7 @@ impl FatContract {
     ) -> Result<()> {
         if let Some(sidevm_info) = &mut self.sidevm_info {
             let guard = sidevm_info.handle.lock().unwrap();
-            let handle = if let SidevmHandle::Stopped(reason) = &*guard {
+            let (handle, snapshot_enabled) = if let SidevmHandle::Stopped(reason) = &*guard {
                 let need_restart = match reason {
                     ExitReason::Exited(_) => false,
                     ExitReason::Stopped => false,
@@ -315,16 +338,94 @@ impl FatContract {
                     return Ok(());
                 }
                 sidevm_info.start_time = chrono::Utc::now().to_rfc3339();
-                do_start_sidevm(spawner, &sidevm_info.code, self.contract_id.0, self.weight)?
+                let start = |snapshot| {
+                    do_start_sidevm(
+                        spawner,
+                        &sidevm_info.code,
+                        self.contract_id.0,
+                        &self.ecdh_key,
+                        self.weight,
+                        snapshot,
+                    )
+                };
+                match sidevm_info.snapshot.take() {
+                    Some(snapshot) => start(Some(snapshot)).or_else(|err| {
+                        let vmid = sidevm::ShortId(&self.contract_id.0);
+                        warn!(target: "sidevm", "[{vmid}] Failed to resume from snapshot: {err:?}");
+                        start(None)
+                    })?,
+                    None => start(None)?,
+                }
             } else {
                 return Ok(());
             };
             drop(guard);
             sidevm_info.handle = handle;
+            sidevm_info.snapshot_enabled = snapshot_enabled;
         }
         Ok(())
     }
 
+    /// Ask the running sidevm instance for a snapshot, if the guest has enabled it, to resume the
+    /// instance from the checkpoint being dumped. The reply is awaited by [`Self::set_sidevm_snapshot`].
+    pub(crate) fn request_sidevm_snapshot(&self) -> Option<SidevmSnapshotReply> {
+        let Some(SidevmHandle::Running(tx)) = self.sidevm_handle() else {
+            return None;
+        };
+        let snapshot_enabled = &self.sidevm_info.as_ref()?.snapshot_enabled;
+        if !snapshot_enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let vmid = sidevm::ShortId(&self.contract_id.0);
+        let (reply_tx, reply_rx) = mpsc::sync_channel(1);
+        if tx.try_send(SidevmCommand::Snapshot(reply_tx)).is_err() {
+            error!(target: "sidevm", "[{vmid}] Failed to request snapshot");
+            return None;
+        }
+        Some(reply_rx)
+    }
+
+    /// Wait for the snapshot requested by [`Self::request_sidevm_snapshot`] until the deadline.
+    pub(crate) fn set_sidevm_snapshot(&mut self, reply_rx: SidevmSnapshotReply, deadline: Instant) {
+        let Some(sidevm_info) = &mut self.sidevm_info else {
+            return;
+        };
+        let vmid = sidevm::ShortId(&self.contract_id.0);
+        match reply_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Ok(snapshot)) => {
+                info!(target: "sidevm", "[{vmid}] Snapshot taken, memory size: {}", snapshot.memory_size());
+                sidevm_info.snapshot = Some(snapshot);
+            }
+            Ok(Err(err)) => debug!(target: "sidevm", "[{vmid}] No snapshot taken: {err}"),
+            Err(_) => error!(target: "sidevm", "[{vmid}] Timed out waiting for the snapshot"),
+        }
+    }
+
+    /// Include the persistent storage of the sidevm in the checkpoint being dumped.
+    pub(crate) fn dump_sidevm_storage(&mut self) {
+        if let Some(sidevm_info) = &mut self.sidevm_info {
+            sidevm_info.storage = Some(StorageDump::Dumping(self.contract_id.0));
+        }
+    }
+
+    /// Write the persistent storage loaded from a checkpoint back to the disk.
+    pub(crate) fn restore_sidevm_storage(&mut self) -> Result<()> {
+        let Some(sidevm_info) = &mut self.sidevm_info else {
+            return Ok(());
+        };
+        match sidevm_info.storage.take() {
+            Some(storage) => storage.restore(&self.contract_id.0),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn drop_sidevm_snapshot(&mut self) {
+        if let Some(sidevm_info) = &mut self.sidevm_info {
+            sidevm_info.snapshot = None;
+            sidevm_info.storage = None;
+        }
+    }
+
     pub(crate) fn push_message_to_sidevm(&self, message: SidevmCommand) -> Result<()> {
         let handle = self
             .sidevm_info
@@ -368,6 +469,9 @@ impl FatContract {
     }
 
     pub(crate) fn destroy(self, spawner: &sidevm::service::Spawner) {
+        if let Err(err) = remove_sidevm_storage(&self.contract_id.0) {
+            error!("Failed to remove sidevm storage: {:?}", err);
+        }
         if let Some(sidevm_info) = &self.sidevm_info {
             match sidevm_info.handle.lock().unwrap().clone() {
                 SidevmHandle::Stopped(_) => {}
@@ -427,17 +531,21 @@ fn do_start_sidevm(
     spawner: &sidevm::service::Spawner,
     code: &[u8],
     id: VmId,
+    key: &KeyPair,
     weight: u32,
-) -> Result<Arc<Mutex<SidevmHandle>>> {
+    snapshot: Option<VmSnapshot>,
+) -> Result<(Arc<Mutex<SidevmHandle>>, Arc<AtomicBool>)> {
     let max_memory_pages: u32 = 1024; // 64MB
     let gas_per_breath = 50_000_000_000_u64; // about 20 ms bench
-    let (sender, join_handle) = spawner.start(
+    let (sender, join_handle, snapshot_enabled) = spawner.start(
         code,
         max_memory_pages,
         id,
         gas_per_breath,
         local_cache_ops(),
+        open_sidevm_storage(&id, key)?,
         weight,
+        snapshot,
     )?;
     let handle = Arc::new(Mutex::new(SidevmHandle::Running(sender)));
     let cloned_handle = handle.clone();
@@ -450,7 +558,7 @@ fn do_start_sidevm(
         error!(target: "sidevm", "[{vmid}] Sidevm process terminated with reason: {:?}", reason);
         *cloned_handle.lock().unwrap() = SidevmHandle::Stopped(reason);
     });
-    Ok(handle)
+    Ok((handle, snapshot_enabled))
 }
 
 fn local_cache_ops() -> sidevm::DynCacheOps {
@@ -485,4 +593,7 @@ fn local_cache_ops() -> sidevm::DynCacheOps {
 }
 
 pub use keeper::*;
+pub(crate) use storage::set_sidevm_storage_path;
+use storage::{open_sidevm_storage, remove_sidevm_storage, StorageDump};
 mod keeper;
+mod storage;
@@ -2,6 +2,7 @@ use pink::runtime::ExecSideEffects;
 use serde::{Deserialize, Serialize};
 use sidevm::service::Spawner;
 use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
 use crate::{
     contracts::{pink::Pink, FatContract, TransactionContext},
@@ -15,6 +16,9 @@ use super::QueryContext;
 
 type ContractMap = BTreeMap<ContractId, FatContract>;
 
+/// How long to wait for the running sidevm instances to reach an idle point to be snapshotted.
+const SIDEVM_SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(5);
+
 macro_rules! define_any_native_contract {
     (pub enum $name:ident { $($contract:ident ($contract_type: tt),)* }) => {
         #[derive(Encode, Decode)]
@@ -124,6 +128,43 @@ impl ContractsKeeper {
         }
     }
 
+    /// Snapshot the sidevm instances which enabled it, to be resumed from the checkpoint.
+    ///
+    /// The instances are snapshotted concurrently, and the ones not reaching an idle point within
+    /// [`SIDEVM_SNAPSHOT_TIMEOUT`] are left without a snapshot. The persistent storages of all the
+    /// sidevms are included in the checkpoint as well.
+    pub fn snapshot_sidevms(&mut self) {
+        for contract in self.contracts.values_mut() {
+            contract.dump_sidevm_storage();
+        }
+        let requests: Vec<_> = self
+            .contracts
+            .iter()
+            .filter_map(|(id, contract)| Some((*id, contract.request_sidevm_snapshot()?)))
+            .collect();
+        let deadline = Instant::now() + SIDEVM_SNAPSHOT_TIMEOUT;
+        for (id, reply_rx) in requests {
+            if let Some(contract) = self.contracts.get_mut(&id) {
+                contract.set_sidevm_snapshot(reply_rx, deadline);
+            }
+        }
+    }
+
+    /// Write the sidevm storages loaded from the checkpoint back to the disk.
+    pub fn restore_sidevm_storages(&mut self) {
+        for contract in self.contracts.values_mut() {
+            if let Err(err) = contract.restore_sidevm_storage() {
+                error!("Failed to restore sidevm storage: {:?}", err);
+            }
+        }
+    }
+
+    pub fn drop_sidevm_snapshots(&mut self) {
+        for contract in self.contracts.values_mut() {
+            contract.drop_sidevm_snapshot();
+        }
+    }
+
     pub fn remove(&mut self, id: &ContractId) -> Option<FatContract> {
         self.contracts.remove(id)
     }
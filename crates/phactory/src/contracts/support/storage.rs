@@ -0,0 +1,329 @@
+//! The persistent storage of sidevm instances.
+//!
+//! Each VM owns a directory `<storage_path>/sidevm_storage/<vm id>/` on the worker disk, next to the
+//! checkpoints. Every entry is saved in its own file, named by a keyed hash of the entry key and
+//! sealed with a key derived from the contract key. Writes go to the disk immediately, and the
+//! sealed files are also saved in the checkpoints as a [`StorageDump`], so that restoring a
+//! checkpoint, locally or from the backup, brings the storage back to the same block as the VM.
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Context as _, Result};
+use parity_scale_codec::{Decode, Encode};
+use phala_crypto::aead;
+use phala_serde_more as more;
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sidevm::{DynStorageOps, OcallError, StorageOps, VmId};
+
+use crate::{hex, secret_channel::KeyPair};
+
+const STORAGE_DIR: &str = "sidevm_storage";
+/// The max total size of the sealed files of a single VM.
+const MAX_STORAGE_SIZE: u64 = 64 * 1024 * 1024;
+
+static STORAGE_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+type OpResult<T> = Result<T, OcallError>;
+
+pub(crate) fn set_sidevm_storage_path(storage_path: &str) {
+    *STORAGE_PATH.lock().unwrap() = Some(PathBuf::from(storage_path).join(STORAGE_DIR));
+}
+
+fn vm_storage_dir(id: &VmId) -> Option<PathBuf> {
+    let path = STORAGE_PATH.lock().unwrap();
+    Some(path.as_ref()?.join(hex(id)))
+}
+
+/// Open the storage of given VM, or returns None if the storage path is not configured.
+pub(crate) fn open_sidevm_storage(id: &VmId, key: &KeyPair) -> Result<Option<DynStorageOps>> {
+    let Some(dir) = vm_storage_dir(id) else {
+        return Ok(None);
+    };
+    let storage = SealedStorage::open(dir, key)?;
+    Ok(Some(Arc::new(storage)))
+}
+
+/// Remove the storage of given VM from the disk.
+pub(crate) fn remove_sidevm_storage(id: &VmId) -> Result<()> {
+    let Some(dir) = vm_storage_dir(id) else {
+        return Ok(());
+    };
+    match fs::remove_dir_all(&dir) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            Err(err).context("Failed to remove sidevm storage")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The sealed files of the storage of a VM, saved in the checkpoints.
+///
+/// A dump reads the files from the disk one by one while being serialized. A loaded one holds the
+/// files until they are written back by [`StorageDump::restore`].
+pub(crate) enum StorageDump {
+    Dumping(VmId),
+    Loaded(Vec<StorageFile>),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct StorageFile {
+    name: String,
+    #[serde(with = "more::scale_bytes")]
+    data: Vec<u8>,
+}
+
+impl StorageDump {
+    /// Replace the storage of the VM on the disk with the loaded files.
+    pub(crate) fn restore(self, id: &VmId) -> Result<()> {
+        let Self::Loaded(files) = self else {
+            return Ok(());
+        };
+        let Some(dir) = vm_storage_dir(id) else {
+            return Ok(());
+        };
+        remove_sidevm_storage(id)?;
+        fs::create_dir_all(&dir).context("Failed to create sidevm storage dir")?;
+        for StorageFile { name, data } in files {
+            if name.contains(['/', '\\']) || Path::new(&name).extension().is_some() {
+                bail!("Invalid sidevm storage file name: {name:?}");
+            }
+            fs::write(dir.join(name), data).context("Failed to restore sidevm storage")?;
+        }
+        Ok(())
+    }
+}
+
+/// The sealed files in `dir`, sorted to dump an unchanged storage into the same bytes.
+fn sealed_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+    let mut files = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        // Skip the temporary files of the writes in progress
+        if path.extension().is_none() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+impl Serialize for StorageDump {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeSeq};
+
+        let id = match self {
+            Self::Dumping(id) => id,
+            Self::Loaded(files) => return files.serialize(serializer),
+        };
+        let paths = match vm_storage_dir(id) {
+            Some(dir) => sealed_files(&dir).map_err(S::Error::custom)?,
+            None => vec![],
+        };
+        let mut seq = serializer.serialize_seq(None)?;
+        for path in paths {
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                // Removed by the VM after listed
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(S::Error::custom(err)),
+            };
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| S::Error::custom("Invalid sidevm storage file name"))?
+                .to_string();
+            seq.serialize_element(&StorageFile { name, data })?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for StorageDump {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::Loaded(Vec::deserialize(deserializer)?))
+    }
+}
+
+struct SealedStorage {
+    dir: PathBuf,
+    sealing_key: [u8; 32],
+    naming_key: [u8; 32],
+    /// Total size of the sealed files. The lock also serializes the writes.
+    used: Mutex<u64>,
+}
+
+impl SealedStorage {
+    fn open(dir: PathBuf, key: &KeyPair) -> Result<Self> {
+        fs::create_dir_all(&dir).context("Failed to create sidevm storage dir")?;
+        let mut used = 0;
+        for entry in fs::read_dir(&dir).context("Failed to read sidevm storage dir")? {
+            let entry = entry.context("Failed to read sidevm storage dir")?;
+            let path = entry.path();
+            if path.extension().is_some() {
+                // Leftover of an interrupted write
+                fs::remove_file(&path).context("Failed to remove temporary file")?;
+                continue;
+            }
+            used += entry.metadata().context("Failed to read metadata")?.len();
+        }
+        let secret = key.secret();
+        Ok(Self {
+            dir,
+            sealing_key: sp_core::blake2_256(&(&secret[..], b"/sidevm-storage/seal").encode()),
+            naming_key: sp_core::blake2_256(&(&secret[..], b"/sidevm-storage/name").encode()),
+            used: Mutex::new(used),
+        })
+    }
+
+    fn path_of(&self, key: &[u8]) -> PathBuf {
+        self.dir
+            .join(hex(sp_core::blake2_256(&(&self.naming_key, key).encode())))
+    }
+
+    fn read(&self, path: &Path, key: &[u8]) -> OpResult<Option<Vec<u8>>> {
+        let mut data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                error!(target: "sidevm", "Failed to read sidevm storage: {err}");
+                return Err(OcallError::IoError);
+            }
+        };
+        if data.len() < aead::IV_BYTES {
+            error!(target: "sidevm", "Corrupted sidevm storage file {}", path.display());
+            return Err(OcallError::IoError);
+        }
+        let (iv, cipher) = data.split_at_mut(aead::IV_BYTES);
+        let plain = aead::decrypt(iv, &self.sealing_key, cipher).map_err(|_| {
+            error!(target: "sidevm", "Failed to unseal sidevm storage file {}", path.display());
+            OcallError::IoError
+        })?;
+        let (stored_key, value): (Vec<u8>, Vec<u8>) =
+            Decode::decode(&mut &plain[..]).or(Err(OcallError::IoError))?;
+        if stored_key != key {
+            return Ok(None);
+        }
+        Ok(Some(value))
+    }
+
+    fn file_size(path: &Path) -> OpResult<u64> {
+        match fs::metadata(path) {
+            Ok(meta) => Ok(meta.len()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+            Err(_) => Err(OcallError::IoError),
+        }
+    }
+}
+
+impl StorageOps for SealedStorage {
+    fn get(&self, key: &[u8]) -> OpResult<Option<Vec<u8>>> {
+        self.read(&self.path_of(key), key)
+    }
+
+    fn set(&self, key: &[u8], value: &[u8]) -> OpResult<()> {
+        let mut data = (key, value).encode();
+        let iv: aead::IV = rand::thread_rng().gen();
+        aead::encrypt(&iv, &self.sealing_key, &mut data).or(Err(OcallError::IoError))?;
+        let sealed = [&iv[..], &data[..]].concat();
+
+        let mut used = self.used.lock().unwrap();
+        let path = self.path_of(key);
+        let old_size = Self::file_size(&path)?;
+        let new_used = *used - old_size + sealed.len() as u64;
+        if new_used > MAX_STORAGE_SIZE {
+            return Err(OcallError::ResourceLimited);
+        }
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, &sealed)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|err| {
+                error!(target: "sidevm", "Failed to write sidevm storage: {err}");
+                OcallError::IoError
+            })?;
+        *used = new_used;
+        Ok(())
+    }
+
+    fn remove(&self, key: &[u8]) -> OpResult<Option<Vec<u8>>> {
+        let mut used = self.used.lock().unwrap();
+        let path = self.path_of(key);
+        let value = self.read(&path, key)?;
+        if value.is_some() {
+            let size = Self::file_size(&path)?;
+            fs::remove_file(&path).or(Err(OcallError::IoError))?;
+            *used -= size;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_storage_works() {
+        let dir = std::env::temp_dir().join(format!(
+            "sidevm-storage-test-{}",
+            rand::thread_rng().gen::<u64>()
+        ));
+        let key = KeyPair::create(&[1; 32]).unwrap();
+        let storage = SealedStorage::open(dir.clone(), &key).unwrap();
+
+        assert_eq!(storage.get(b"foo").unwrap(), None);
+        storage.set(b"foo", b"bar").unwrap();
+        assert_eq!(storage.get(b"foo").unwrap(), Some(b"bar".to_vec()));
+        storage.set(b"foo", b"baz").unwrap();
+
+        // Reopen to make sure it is persisted
+        let storage = SealedStorage::open(dir.clone(), &key).unwrap();
+        assert_eq!(storage.get(b"foo").unwrap(), Some(b"baz".to_vec()));
+        assert!(*storage.used.lock().unwrap() > 0);
+
+        // The data is invisible to another key
+        let other_key = KeyPair::create(&[2; 32]).unwrap();
+        let other = SealedStorage::open(dir.clone(), &other_key).unwrap();
+        assert_eq!(other.get(b"foo").unwrap(), None);
+
+        assert_eq!(storage.remove(b"foo").unwrap(), Some(b"baz".to_vec()));
+        assert_eq!(storage.get(b"foo").unwrap(), None);
+        assert_eq!(*storage.used.lock().unwrap(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn storage_dump_restores_files() {
+        let root = std::env::temp_dir().join(format!(
+            "sidevm-storage-dump-test-{}",
+            rand::thread_rng().gen::<u64>()
+        ));
+        set_sidevm_storage_path(&root.to_string_lossy());
+        let id = [3; 32];
+        let key = KeyPair::create(&[1; 32]).unwrap();
+        let storage = open_sidevm_storage(&id, &key).unwrap().unwrap();
+        storage.set(b"foo", b"bar").unwrap();
+
+        let dumped = serde_cbor::to_vec(&StorageDump::Dumping(id)).unwrap();
+        storage.set(b"foo", b"baz").unwrap();
+        storage.set(b"new", b"value").unwrap();
+        drop(storage);
+
+        let dump: StorageDump = serde_cbor::from_slice(&dumped).unwrap();
+        dump.restore(&id).unwrap();
+        let storage = open_sidevm_storage(&id, &key).unwrap().unwrap();
+        assert_eq!(storage.get(b"foo").unwrap(), Some(b"bar".to_vec()));
+        assert_eq!(storage.get(b"new").unwrap(), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,431 @@
+//! Delta checkpoints.
+//!
+//! A full checkpoint serves as the base of the delta checkpoints taken after it. The plaintext of
+//! a checkpoint is split into content defined chunks, so the unchanged parts of the state, such as
+//! the untouched trie nodes and cluster storages, produce the same chunks in both checkpoints no
+//! matter what has been inserted or removed elsewhere. A delta carries only the chunks missing in
+//! its base and refers to the others by their hash.
+//!
+//! Deltas are always taken against the base rather than the previous delta, so a corrupted delta
+//! doesn't affect the others.
+//!
+//! Deltas only save disk space and backup traffic. Taking a delta still serializes and chunks the
+//! whole state, which costs as much CPU time as a full checkpoint, and restoring from a delta keeps
+//! the whole plaintext of its base in memory, see [`BaseData::read`].
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use parity_scale_codec::{Decode, Encode, IoReader};
+use sp_core::hashing::blake2_256;
+
+type Digest = [u8; 32];
+
+const DELTA_VERSION: u32 = 1;
+/// A chunk ends where the low bits of the rolling hash are all zero, which makes 8KB chunks on
+/// average.
+const CHUNK_MASK: u64 = (1 << 13) - 1;
+const MIN_CHUNK_SIZE: usize = 2 * 1024;
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Random values for the gear rolling hash, generated with splitmix64.
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state = 0_u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Splits a byte stream into content defined chunks.
+#[derive(Default)]
+struct Chunker {
+    buf: Vec<u8>,
+    hash: u64,
+}
+
+impl Chunker {
+    fn feed(
+        &mut self,
+        data: &[u8],
+        mut emit: impl FnMut(&[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        for &byte in data {
+            self.buf.push(byte);
+            self.hash = (self.hash << 1).wrapping_add(GEAR[byte as usize]);
+            let len = self.buf.len();
+            if (len >= MIN_CHUNK_SIZE && self.hash & CHUNK_MASK == 0) || len >= MAX_CHUNK_SIZE {
+                emit(&self.buf)?;
+                self.buf.clear();
+                self.hash = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, mut emit: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            emit(&self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+}
+
+/// The chunks of a base checkpoint.
+pub(crate) struct BaseIndex {
+    digest: Digest,
+    chunks: HashSet<Digest>,
+}
+
+impl BaseIndex {
+    fn new(chunks: Vec<Digest>) -> Self {
+        Self {
+            digest: blake2_256(&chunks.concat()),
+            chunks: chunks.into_iter().collect(),
+        }
+    }
+}
+
+/// Passes the plaintext of a base checkpoint through while indexing its chunks.
+pub(crate) struct BaseWriter<W> {
+    inner: W,
+    chunker: Chunker,
+    chunks: Vec<Digest>,
+}
+
+impl<W: Write> BaseWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            chunker: Default::default(),
+            chunks: Default::default(),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<BaseIndex> {
+        let chunks = &mut self.chunks;
+        self.chunker.finish(|chunk| {
+            chunks.push(blake2_256(chunk));
+            Ok(())
+        })?;
+        self.inner.flush()?;
+        Ok(BaseIndex::new(self.chunks))
+    }
+}
+
+impl<W: Write> Write for BaseWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.inner.write_all(data)?;
+        let chunks = &mut self.chunks;
+        self.chunker.feed(data, |chunk| {
+            chunks.push(blake2_256(chunk));
+            Ok(())
+        })?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Encode, Decode)]
+struct DeltaHeader {
+    version: u32,
+    base_digest: Digest,
+}
+
+#[derive(Encode, Decode)]
+enum DeltaChunk {
+    Base(Digest),
+    Data(Vec<u8>),
+    End,
+}
+
+/// Writes the chunks of a checkpoint plaintext which are missing in the base.
+pub(crate) struct DeltaWriter<'a, W> {
+    inner: W,
+    base: &'a BaseIndex,
+    chunker: Chunker,
+}
+
+impl<'a, W: Write> DeltaWriter<'a, W> {
+    pub fn new(mut inner: W, base: &'a BaseIndex) -> io::Result<Self> {
+        let header = DeltaHeader {
+            version: DELTA_VERSION,
+            base_digest: base.digest,
+        };
+        inner.write_all(&header.encode())?;
+        Ok(Self {
+            inner,
+            base,
+            chunker: Default::default(),
+        })
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        let (inner, base) = (&mut self.inner, self.base);
+        self.chunker
+            .finish(|chunk| write_delta_chunk(inner, base, chunk))?;
+        inner.write_all(&DeltaChunk::End.encode())?;
+        inner.flush()
+    }
+}
+
+impl<W: Write> Write for DeltaWriter<'_, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let (inner, base) = (&mut self.inner, self.base);
+        self.chunker
+            .feed(data, |chunk| write_delta_chunk(inner, base, chunk))?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn write_delta_chunk(out: &mut impl Write, base: &BaseIndex, chunk: &[u8]) -> io::Result<()> {
+    let digest = blake2_256(chunk);
+    let entry = if base.chunks.contains(&digest) {
+        DeltaChunk::Base(digest)
+    } else {
+        DeltaChunk::Data(chunk.to_vec())
+    };
+    out.write_all(&entry.encode())
+}
+
+/// The plaintext of a base checkpoint, indexed by chunk.
+pub(crate) struct BaseData {
+    plain: Vec<u8>,
+    digest: Digest,
+    chunks: HashMap<Digest, Range<usize>>,
+}
+
+impl BaseData {
+    /// Read and index the plaintext of a base checkpoint as it streams in.
+    ///
+    /// The deltas may refer to any chunk of the base, so the whole plaintext is kept in memory.
+    /// Restoring from a delta thus takes about as much memory as the plaintext of a full
+    /// checkpoint, in addition to the state being restored.
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut plain = vec![];
+        let mut digests = vec![];
+        let mut chunks = HashMap::new();
+        let mut offset = 0;
+        let mut index = |chunk: &[u8]| {
+            let digest = blake2_256(chunk);
+            chunks.entry(digest).or_insert(offset..offset + chunk.len());
+            digests.push(digest);
+            offset += chunk.len();
+            Ok(())
+        };
+        let mut chunker = Chunker::default();
+        let mut buf = vec![0; MAX_CHUNK_SIZE];
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            plain.extend_from_slice(&buf[..len]);
+            chunker.feed(&buf[..len], &mut index)?;
+        }
+        chunker.finish(&mut index)?;
+        Ok(Self {
+            plain,
+            digest: BaseIndex::new(digests).digest,
+            chunks,
+        })
+    }
+}
+
+enum Chunk {
+    Base(Range<usize>),
+    Data(Vec<u8>),
+}
+
+/// Reassembles the plaintext of a checkpoint from a delta and its base.
+pub(crate) struct DeltaReader<'a, R> {
+    reader: R,
+    base: &'a BaseData,
+    chunk: Chunk,
+    offset: usize,
+    ended: bool,
+}
+
+impl<'a, R: Read> DeltaReader<'a, R> {
+    pub fn new(mut reader: R, base: &'a BaseData) -> io::Result<Self> {
+        let header = DeltaHeader::decode(&mut IoReader(&mut reader))
+            .map_err(|_| invalid_data("Invalid delta header"))?;
+        if header.version != DELTA_VERSION {
+            return Err(invalid_data("Unsupported delta version"));
+        }
+        if header.base_digest != base.digest {
+            return Err(invalid_data("The delta doesn't match the base"));
+        }
+        Ok(Self {
+            reader,
+            base,
+            chunk: Chunk::Data(vec![]),
+            offset: 0,
+            ended: false,
+        })
+    }
+}
+
+impl<R: Read> Read for DeltaReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let data = match &self.chunk {
+                Chunk::Base(range) => &self.base.plain[range.clone()],
+                Chunk::Data(data) => &data[..],
+            };
+            if self.offset < data.len() || self.ended {
+                let n = buf.len().min(data.len() - self.offset);
+                buf[..n].copy_from_slice(&data[self.offset..self.offset + n]);
+                self.offset += n;
+                return Ok(n);
+            }
+            let entry = DeltaChunk::decode(&mut IoReader(&mut self.reader))
+                .map_err(|_| invalid_data("Invalid delta chunk"))?;
+            self.chunk = match entry {
+                DeltaChunk::Base(digest) => Chunk::Base(
+                    self.base
+                        .chunks
+                        .get(&digest)
+                        .cloned()
+                        .ok_or_else(|| invalid_data("Chunk missing in the base"))?,
+                ),
+                DeltaChunk::Data(data) => Chunk::Data(data),
+                DeltaChunk::End => {
+                    self.ended = true;
+                    Chunk::Data(vec![])
+                }
+            };
+            self.offset = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn delta_roundtrip_works() {
+        let mut rng = rand::thread_rng();
+        let base_plain: Vec<u8> = (0..1024 * 1024).map(|_| rng.gen()).collect();
+        let mut new_plain = base_plain.clone();
+        new_plain.splice(300_000..300_100, b"inserted".iter().copied());
+        new_plain.truncate(900_000);
+
+        let mut base_file = vec![];
+        let mut writer = BaseWriter::new(&mut base_file);
+        writer.write_all(&base_plain).unwrap();
+        let index = writer.finish().unwrap();
+        assert_eq!(base_file, base_plain);
+
+        let mut delta_file = vec![];
+        let mut writer = DeltaWriter::new(&mut delta_file, &index).unwrap();
+        writer.write_all(&new_plain).unwrap();
+        writer.finish().unwrap();
+        assert!(delta_file.len() < new_plain.len() / 4);
+
+        let base = BaseData::read(&base_file[..]).unwrap();
+        let mut restored = vec![];
+        DeltaReader::new(&delta_file[..], &base)
+            .unwrap()
+            .read_to_end(&mut restored)
+            .unwrap();
+        assert_eq!(restored, new_plain);
+
+        let other = BaseData::read(&new_plain[..]).unwrap();
+        assert!(DeltaReader::new(&delta_file[..], &other).is_err());
+    }
+
+    fn encrypt_to(key: &[u8], filename: String, write: impl FnOnce(&mut dyn Write)) {
+        let key128 = crate::derive_key_for_checkpoint(key);
+        let file = std::fs::File::create(filename).unwrap();
+        let nonce = rand::thread_rng().gen();
+        let mut writer = phala_crypto::aead::stream::new_aes128gcm_writer(key128, nonce, file);
+        write(&mut writer);
+        writer.flush().unwrap();
+    }
+
+    fn corrupt(filename: String) {
+        let mut data = std::fs::read(&filename).unwrap();
+        let mid = data.len() / 2;
+        data[mid] ^= 0xff;
+        std::fs::write(filename, data).unwrap();
+    }
+
+    #[test]
+    fn corrupted_checkpoints_fall_back_to_older_ones() {
+        use crate::{checkpoint_filename_for, delta_checkpoint_filename_for, load_checkpoint};
+
+        let mut rng = rand::thread_rng();
+        let dir = std::env::temp_dir().join(format!("delta-checkpoint-test-{}", rng.gen::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let basedir = dir.to_str().unwrap();
+        let key = b"identity key";
+        let states: Vec<Vec<u8>> = (0..4)
+            .map(|_| (0..256 * 1024).map(|_| rng.gen()).collect())
+            .collect();
+
+        for (base_block, block, base_plain, plain) in [
+            (10, 15, &states[0], &states[1]),
+            (20, 25, &states[2], &states[3]),
+        ] {
+            let mut index = None;
+            encrypt_to(key, checkpoint_filename_for(base_block, basedir), |out| {
+                let mut writer = BaseWriter::new(out);
+                writer.write_all(base_plain).unwrap();
+                index = Some(writer.finish().unwrap());
+            });
+            let index = index.unwrap();
+            let delta_file = delta_checkpoint_filename_for(block, base_block, basedir);
+            encrypt_to(key, delta_file, |out| {
+                let mut writer = DeltaWriter::new(out, &index).unwrap();
+                writer.write_all(plain).unwrap();
+                writer.finish().unwrap();
+            });
+        }
+        let load = |remove_corrupted| {
+            load_checkpoint(key, basedir, remove_corrupted, |reader: &mut dyn Read| {
+                let mut plain = vec![];
+                reader.read_to_end(&mut plain)?;
+                Ok(plain)
+            })
+        };
+
+        assert_eq!(load(false).unwrap(), Some(states[3].clone()));
+        // A corrupted delta falls back to its base.
+        corrupt(delta_checkpoint_filename_for(25, 20, basedir));
+        assert_eq!(load(false).unwrap(), Some(states[2].clone()));
+        // A corrupted base falls back to the older base and its delta.
+        corrupt(checkpoint_filename_for(20, basedir));
+        assert_eq!(load(true).unwrap(), Some(states[1].clone()));
+        assert!(!std::path::Path::new(&checkpoint_filename_for(20, basedir)).exists());
+
+        corrupt(delta_checkpoint_filename_for(15, 10, basedir));
+        corrupt(checkpoint_filename_for(10, basedir));
+        assert!(load(false).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
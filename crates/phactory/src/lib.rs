@@ -14,10 +14,15 @@ use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
+use crate::checkpoint_backup::CheckpointBackup;
+use crate::delta_checkpoint::{BaseData, BaseIndex, BaseWriter, DeltaReader, DeltaWriter};
 use crate::light_validation::LightValidation;
-use std::collections::BTreeMap;
-use std::{fs::File, io::ErrorKind, path::PathBuf};
-use std::{io::Write, marker::PhantomData};
+use std::collections::{BTreeMap, BTreeSet};
+use std::{fs::File, path::PathBuf};
+use std::{
+    io::{Read, Write},
+    marker::PhantomData,
+};
 use std::{path::Path, str};
 
 use anyhow::{anyhow, Context as _, Result};
@@ -50,6 +55,7 @@ use std::time::Instant;
 use types::Error;
 
 pub use chain::BlockNumber;
+pub use checkpoint_inspect::CheckpointReport;
 pub use contracts::pink;
 pub use prpc_service::RpcService;
 pub use storage::ChainStorage;
@@ -60,8 +66,11 @@ pub type PRuntimeLightValidation = LightValidation<chain::Runtime>;
 pub mod benchmark;
 
 mod bin_api_service;
+mod checkpoint_backup;
+mod checkpoint_inspect;
 mod contracts;
 mod cryptography;
+mod delta_checkpoint;
 mod light_validation;
 mod prpc_service;
 mod secret_channel;
@@ -101,12 +110,21 @@ impl RuntimeState {
 
 const RUNTIME_SEALED_DATA_FILE: &str = "runtime-data.seal";
 const CHECKPOINT_FILE: &str = "checkpoint.seal";
+const DELTA_CHECKPOINT_FILE: &str = "checkpoint-delta.seal";
 const CHECKPOINT_VERSION: u32 = 2;
 
 fn checkpoint_filename_for(block_number: chain::BlockNumber, basedir: &str) -> String {
     format!("{basedir}/{CHECKPOINT_FILE}-{block_number:0>9}")
 }
 
+fn delta_checkpoint_filename_for(
+    block_number: chain::BlockNumber,
+    base_block: chain::BlockNumber,
+    basedir: &str,
+) -> String {
+    format!("{basedir}/{DELTA_CHECKPOINT_FILE}-{block_number:0>9}-{base_block:0>9}")
+}
+
 fn checkpoint_info_filename_for(filename: &str) -> String {
     format!("{filename}.info.json")
 }
@@ -120,6 +138,13 @@ fn glob_checkpoint_files(basedir: &str) -> Result<impl Iterator<Item = PathBuf>,
     Ok(glob::glob(&pattern)?.filter_map(|path| path.ok()))
 }
 
+fn glob_delta_checkpoint_files(
+    basedir: &str,
+) -> Result<impl Iterator<Item = PathBuf>, PatternError> {
+    let pattern = format!("{basedir}/{DELTA_CHECKPOINT_FILE}-*");
+    Ok(glob::glob(&pattern)?.filter_map(|path| path.ok()))
+}
+
 fn glob_checkpoint_files_sorted(
     basedir: &str,
 ) -> Result<Vec<(chain::BlockNumber, PathBuf)>, PatternError> {
@@ -139,8 +164,37 @@ fn glob_checkpoint_files_sorted(
     Ok(files)
 }
 
+/// Returns (block, base block) if the file is a delta checkpoint.
+fn parse_delta_checkpoint_blocks(
+    filename: &Path,
+) -> Option<(chain::BlockNumber, chain::BlockNumber)> {
+    let filename = filename.file_name()?.to_str()?;
+    let blocks = filename.strip_prefix(DELTA_CHECKPOINT_FILE)?;
+    let mut parts = blocks.rsplit('-');
+    let base_block = parts.next()?.parse().ok()?;
+    let block_number = parts.next()?.parse().ok()?;
+    Some((block_number, base_block))
+}
+
+/// Returns the delta checkpoints as (block, base block, filename), newest first.
+fn glob_delta_checkpoint_files_sorted(
+    basedir: &str,
+) -> Result<Vec<(chain::BlockNumber, chain::BlockNumber, PathBuf)>, PatternError> {
+    let mut files = Vec::new();
+
+    for filename in glob_delta_checkpoint_files(basedir)? {
+        if let Some((block_number, base_block)) = parse_delta_checkpoint_blocks(&filename) {
+            files.push((block_number, base_block, filename));
+        }
+    }
+    files.sort_by_key(|(block_number, _, _)| std::cmp::Reverse(*block_number));
+    Ok(files)
+}
+
 fn maybe_remove_checkpoints(basedir: &str) {
-    match glob_checkpoint_files(basedir) {
+    let files = glob_checkpoint_files(basedir)
+        .and_then(|files| Ok(files.chain(glob_delta_checkpoint_files(basedir)?)));
+    match files {
         Err(err) => error!("Error globbing checkpoints: {:?}", err),
         Ok(iter) => {
             for filename in iter {
@@ -158,8 +212,10 @@ fn remove_outdated_checkpoints(
     current_block: chain::BlockNumber,
 ) -> Result<()> {
     let mut kept = 0_u32;
+    let mut kept_bases = BTreeSet::new();
     for (block, filename) in glob_checkpoint_files_sorted(basedir)? {
         if block > current_block {
+            kept_bases.insert(block);
             continue;
         }
         kept += 1;
@@ -170,11 +226,151 @@ fn remove_outdated_checkpoints(
                     info!("Removed {}", filename.display());
                 }
             }
+        } else {
+            kept_bases.insert(block);
+        }
+    }
+    // A delta is useless without its base
+    for (_block, base_block, filename) in glob_delta_checkpoint_files_sorted(basedir)? {
+        if kept_bases.contains(&base_block) {
+            continue;
+        }
+        match remove_checkpoint(&filename) {
+            Err(e) => error!("Failed to remove checkpoint {}: {e}", filename.display()),
+            Ok(_) => {
+                info!("Removed {}", filename.display());
+            }
         }
     }
     Ok(())
 }
 
+/// Load the newest checkpoint in `basedir` that can be loaded, passing its plaintext to `decode`.
+///
+/// The bases are tried from the newest, each one first with its deltas, newest first, and then
+/// alone, so a corrupted delta or base falls back to the older checkpoints. Returns `None` if
+/// there is no checkpoint at all.
+fn load_checkpoint<T>(
+    key: &[u8],
+    basedir: &str,
+    remove_corrupted: bool,
+    decode: impl Fn(&mut dyn Read) -> Result<T>,
+) -> Result<Option<T>> {
+    let key128 = derive_key_for_checkpoint(key);
+    let bases = glob_checkpoint_files_sorted(basedir).context("Glob checkpoint files failed")?;
+    if bases.is_empty() {
+        return Ok(None);
+    }
+    let deltas =
+        glob_delta_checkpoint_files_sorted(basedir).context("Glob checkpoint files failed")?;
+    for (base_block, base_filename) in &bases {
+        let base_deltas: Vec<_> = deltas
+            .iter()
+            .filter(|(_, base, _)| base == base_block)
+            .map(|(_, _, filename)| filename.as_path())
+            .collect();
+        if !base_deltas.is_empty() {
+            let state = load_delta_checkpoints(
+                key128,
+                base_filename,
+                &base_deltas,
+                remove_corrupted,
+                &decode,
+            )?;
+            if let Some(state) = state {
+                return Ok(Some(state));
+            }
+        }
+
+        info!("Loading checkpoint from file {:?}", base_filename);
+        let result = File::open(base_filename)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                let mut dec_reader = aead::stream::new_aes128gcm_reader(key128, file);
+                decode(&mut dec_reader)
+            });
+        match result {
+            Ok(state) => {
+                info!("Succeeded to load checkpoint file {:?}", base_filename);
+                return Ok(Some(state));
+            }
+            Err(_err /*Don't leak it into the log*/) => {
+                error!("Failed to load checkpoint file {:?}", base_filename);
+                remove_corrupted_checkpoint(base_filename, remove_corrupted)?;
+            }
+        }
+    }
+    anyhow::bail!("None of the checkpoint files could be loaded");
+}
+
+/// Try the deltas of a base from the newest to the oldest. Returns None if none of them is
+/// loaded, in which case the caller falls back to the base.
+fn load_delta_checkpoints<T>(
+    key128: [u8; 16],
+    base_filename: &Path,
+    deltas: &[&Path],
+    remove_corrupted: bool,
+    decode: &impl Fn(&mut dyn Read) -> Result<T>,
+) -> Result<Option<T>> {
+    let base = File::open(base_filename)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| {
+            let dec_reader = aead::stream::new_aes128gcm_reader(key128, file);
+            Ok(BaseData::read(dec_reader)?)
+        });
+    let Ok(base) = base else {
+        error!("Failed to read checkpoint base {:?}", base_filename);
+        return Ok(None);
+    };
+    for filename in deltas {
+        info!("Loading delta checkpoint from file {:?}", filename);
+        let result = File::open(filename)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                let dec_reader = aead::stream::new_aes128gcm_reader(key128, file);
+                decode(&mut DeltaReader::new(dec_reader, &base)?)
+            });
+        match result {
+            Ok(state) => {
+                info!("Succeeded to load delta checkpoint file {:?}", filename);
+                return Ok(Some(state));
+            }
+            Err(_err /*Don't leak it into the log*/) => {
+                error!("Failed to load delta checkpoint file {:?}", filename);
+                remove_corrupted_checkpoint(filename, remove_corrupted)?;
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn remove_corrupted_checkpoint(filename: &Path, remove_corrupted: bool) -> Result<()> {
+    if remove_corrupted {
+        error!("Removing {:?}", filename);
+        std::fs::remove_file(filename).context("Failed to remove corrupted checkpoint file")?;
+    }
+    Ok(())
+}
+
+fn open_checkpoint_backup(args: &InitArgs) -> Option<CheckpointBackup> {
+    let spec = args.checkpoint_backup.as_ref()?;
+    match checkpoint_backup::open_backend(spec) {
+        Ok(backend) => Some(CheckpointBackup::new(backend)),
+        Err(err) => {
+            error!("Failed to open the checkpoint backup: {err:?}");
+            None
+        }
+    }
+}
+
+fn download_checkpoint_backup(args: &InitArgs) -> Result<bool> {
+    let Some(spec) = &args.checkpoint_backup else {
+        return Ok(false);
+    };
+    let backend = checkpoint_backup::open_backend(spec)?;
+    checkpoint_backup::download_latest(&*backend, &args.storage_path)
+}
+
 fn remove_checkpoint(filename: &Path) -> Result<()> {
     std::fs::remove_file(filename).context("Failed to remove checkpoint file")?;
     let info_filename = checkpoint_info_filename_for(&filename.display().to_string());
@@ -232,10 +428,18 @@ pub struct Phactory<Platform> {
     // tmp key for WorkerKey handover encryption
     #[serde(skip)]
     pub(crate) handover_ecdh_key: Option<EcdhKey>,
+    /// The DCAP root CAs to verify the handover server with, before the chain state is available.
+    #[serde(skip)]
+    handover_dcap_root_certs: Vec<Vec<u8>>,
 
     #[serde(skip)]
     #[serde(default = "Instant::now")]
     last_checkpoint: Instant,
+    /// The last full checkpoint that deltas are taken against, with the number of deltas taken.
+    #[serde(skip)]
+    checkpoint_base: Option<(chain::BlockNumber, BaseIndex, u32)>,
+    #[serde(skip)]
+    checkpoint_backup: Option<CheckpointBackup>,
     #[serde(skip)]
     #[serde(default = "default_query_scheduler")]
     query_scheduler: RequestScheduler<ContractId>,
@@ -272,7 +476,10 @@ impl<Platform: pal::Platform> Phactory<Platform> {
             endpoints: Default::default(),
             signed_endpoints: None,
             handover_ecdh_key: None,
+            handover_dcap_root_certs: Default::default(),
             last_checkpoint: Instant::now(),
+            checkpoint_base: None,
+            checkpoint_backup: None,
             query_scheduler: default_query_scheduler(),
             netconfig: Default::default(),
             can_load_chain_state: false,
@@ -294,10 +501,14 @@ impl<Platform: pal::Platform> Phactory<Platform> {
         }
 
         self.can_load_chain_state = !system::gk_master_key_exists(&args.sealing_path);
+        contracts::set_sidevm_storage_path(&args.storage_path);
+        self.checkpoint_backup = open_checkpoint_backup(&args);
         self.args = args;
     }
 
     pub fn set_args(&mut self, args: InitArgs) {
+        contracts::set_sidevm_storage_path(&args.storage_path);
+        self.checkpoint_backup = open_checkpoint_backup(&args);
         self.args = args;
         if let Some(system) = &mut self.system {
             system.sealing_path = self.args.sealing_path.clone();
@@ -305,6 +516,16 @@ impl<Platform: pal::Platform> Phactory<Platform> {
         }
     }
 
+    /// Sets up the attestation of a handover client, which is not told by pherry.
+    pub fn set_handover_attestation(
+        &mut self,
+        provider: Option<AttestationProvider>,
+        dcap_root_certs: Vec<Vec<u8>>,
+    ) {
+        self.attestation_provider = provider;
+        self.handover_dcap_root_certs = dcap_root_certs;
+    }
+
     fn init_runtime_data(
         &self,
         genesis_block_hash: H256,
@@ -494,13 +715,56 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> Phactory<Platform>
             .context("Take checkpoint failed, runtime is not ready")?
             .identity_key
             .dump_secret_key();
-        let checkpoint_file = checkpoint_filename_for(current_block, &self.args.storage_path);
+        let basedir = &self.args.storage_path;
+        // Take a delta if the base is still there, otherwise a full checkpoint as a new base.
+        let base = self
+            .checkpoint_base
+            .take()
+            .filter(|(base_block, _, n_deltas)| {
+                *n_deltas < self.args.max_checkpoint_deltas
+                    && Path::new(&checkpoint_filename_for(*base_block, basedir)).exists()
+            });
+        let checkpoint_file = match &base {
+            Some((base_block, _, _)) => {
+                delta_checkpoint_filename_for(current_block, *base_block, basedir)
+            }
+            None => checkpoint_filename_for(current_block, basedir),
+        };
         info!("Taking checkpoint to {checkpoint_file}...");
         self.save_checkpoint_info(&checkpoint_file)?;
         let file = File::create(&checkpoint_file).context("Failed to create checkpoint file")?;
-        self.take_checkpoint_to_writer(&key, file)
-            .context("Take checkpoint to writer failed")?;
+        let key128 = derive_key_for_checkpoint(&key);
+        let nonce = rand::thread_rng().gen();
+        let mut enc_writer = aead::stream::new_aes128gcm_writer(key128, nonce, file);
+        self.checkpoint_base = match base {
+            Some((base_block, index, n_deltas)) => {
+                let mut writer = DeltaWriter::new(&mut enc_writer, &index)
+                    .context("Failed to write checkpoint")?;
+                self.dump_state_to(&mut writer)?;
+                writer.finish().context("Failed to write checkpoint")?;
+                Some((base_block, index, n_deltas + 1))
+            }
+            None => {
+                let mut writer = BaseWriter::new(&mut enc_writer);
+                self.dump_state_to(&mut writer)?;
+                let index = writer.finish().context("Failed to write checkpoint")?;
+                Some((current_block, index, 0))
+            }
+        };
+        enc_writer
+            .flush()
+            .context("Failed to flush encrypted writer")?;
         info!("Checkpoint saved to {}", checkpoint_file);
+        if let (Some(backup), Some((base_block, _, _))) =
+            (&self.checkpoint_backup, &self.checkpoint_base)
+        {
+            let base_file = checkpoint_filename_for(*base_block, &self.args.storage_path);
+            let mut files = vec![PathBuf::from(&checkpoint_file)];
+            if base_file != checkpoint_file {
+                files.insert(0, base_file.into());
+            }
+            backup.upload(files);
+        }
         self.last_checkpoint = Instant::now();
         remove_outdated_checkpoints(
             &self.args.storage_path,
@@ -530,14 +794,25 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> Phactory<Platform>
         let key128 = derive_key_for_checkpoint(key);
         let nonce = rand::thread_rng().gen();
         let mut enc_writer = aead::stream::new_aes128gcm_writer(key128, nonce, writer);
-        serde_cbor::ser::to_writer(&mut enc_writer, &PhactoryDumper(self))
-            .context("Failed to write checkpoint")?;
+        self.dump_state_to(&mut enc_writer)?;
         enc_writer
             .flush()
             .context("Failed to flush encrypted writer")?;
         Ok(())
     }
 
+    fn dump_state_to<W: std::io::Write>(&mut self, writer: W) -> anyhow::Result<()> {
+        if let Some(system) = &mut self.system {
+            system.contracts.snapshot_sidevms();
+        }
+        let result = serde_cbor::ser::to_writer(writer, &PhactoryDumper(self))
+            .context("Failed to write checkpoint");
+        if let Some(system) = &mut self.system {
+            system.contracts.drop_sidevm_snapshots();
+        }
+        result
+    }
+
     pub fn restore_from_checkpoint(
         platform: &Platform,
         args: &InitArgs,
@@ -548,51 +823,20 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> Phactory<Platform>
         };
         let files = glob_checkpoint_files_sorted(&args.storage_path)
             .context("Glob checkpoint files failed")?;
-        if files.is_empty() {
-            return Ok(None);
-        }
-        let (_block, ckpt_filename) = &files[0];
-
-        let file = match File::open(ckpt_filename) {
-            Ok(file) => file,
-            Err(err) if matches!(err.kind(), ErrorKind::NotFound) => {
-                // This should never happen unless it was removed just after the glob.
-                anyhow::bail!("Checkpoint file {:?} is not found", ckpt_filename);
-            }
-            Err(err) => {
-                error!(
-                    "Failed to open checkpoint file {:?}: {:?}",
-                    ckpt_filename, err
-                );
-                if args.remove_corrupted_checkpoint {
-                    error!("Removing {:?}", ckpt_filename);
-                    std::fs::remove_file(ckpt_filename)
-                        .context("Failed to remove corrupted checkpoint file")?;
-                }
-                anyhow::bail!(
-                    "Failed to open checkpoint file {:?}: {:?}",
-                    ckpt_filename,
-                    err
-                );
-            }
-        };
-
-        info!("Loading checkpoint from file {:?}", ckpt_filename);
-        match Self::restore_from_checkpoint_reader(&runtime_data.sk, file, args) {
-            Ok(state) => {
-                info!("Succeeded to load checkpoint file {:?}", ckpt_filename);
-                Ok(Some(state))
-            }
-            Err(_err /*Don't leak it into the log*/) => {
-                error!("Failed to load checkpoint file {:?}", ckpt_filename);
-                if args.remove_corrupted_checkpoint {
-                    error!("Removing {:?}", ckpt_filename);
-                    std::fs::remove_file(ckpt_filename)
-                        .context("Failed to remove corrupted checkpoint file")?;
-                }
-                anyhow::bail!("Failed to load checkpoint file {:?}", ckpt_filename);
+        if files.is_empty() && args.checkpoint_backup.is_some() {
+            info!("No local checkpoint found, trying to restore from the backup");
+            match download_checkpoint_backup(args) {
+                Ok(true) => {}
+                Ok(false) => info!("No checkpoint found in the backup"),
+                Err(err) => error!("Failed to download checkpoint from the backup: {err:?}"),
             }
         }
+        load_checkpoint(
+            &runtime_data.sk,
+            &args.storage_path,
+            args.remove_corrupted_checkpoint,
+            |reader| Self::restore_from_plain_reader(reader, args),
+        )
     }
 
     pub fn restore_from_checkpoint_reader<R: std::io::Read>(
@@ -602,9 +846,16 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> Phactory<Platform>
     ) -> anyhow::Result<Self> {
         let key128 = derive_key_for_checkpoint(key);
         let dec_reader = aead::stream::new_aes128gcm_reader(key128, reader);
+        Self::restore_from_plain_reader(dec_reader, args)
+    }
+
+    fn restore_from_plain_reader<R: std::io::Read>(
+        reader: R,
+        args: &InitArgs,
+    ) -> anyhow::Result<Self> {
         system::sidevm_config(args.cores as _);
         let PhactoryLoader(mut factory) =
-            serde_cbor::de::from_reader(dec_reader).context("Failed to decode state")?;
+            serde_cbor::de::from_reader(reader).context("Failed to decode state")?;
         factory.set_args(args.clone());
         factory
             .on_restored()
@@ -5,7 +5,8 @@ use std::sync::{Arc, Mutex, MutexGuard};
 
 use crate::benchmark::Flags;
 use crate::hex;
-use crate::system::{System, MAX_SUPPORTED_CONSENSUS_VERSION};
+use crate::system::{SidevmQuery, System, MAX_SUPPORTED_CONSENSUS_VERSION};
+use sidevm::OcallError;
 
 use super::*;
 use crate::contracts::ContractClusterId;
@@ -21,7 +22,13 @@ use phala_crypto::{
     key_share,
     sr25519::{Persistence, KDF},
 };
-use phala_pallets::utils::attestation::{validate as validate_attestation_report, IasFields};
+use phala_pallets::utils::attestation::{
+    validate as validate_attestation_report, ConfidentialReport, Error as AttestationError,
+    IasFields,
+};
+use phala_pallets::utils::attestation_dcap::{
+    DcapCollateral, Fmspc, QeIdentity, QeTcbLevel, TcbInfo, TcbLevel, TcbStatus,
+};
 use phala_types::contract::contract_id_preimage;
 use phala_types::{
     contract, messaging::EncryptedKey, wrap_content_to_sign, AttestationReport,
@@ -61,6 +68,14 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> Phactory<Platform>
             .ok_or_else(|| from_display("Runtime not initialized"))
     }
 
+    /// The DCAP collateral to verify the handover peer with, see [`HandoverDcapCollateral`].
+    fn handover_dcap_collateral(&self) -> HandoverDcapCollateral<'_> {
+        match &self.runtime_state {
+            Some(state) => HandoverDcapCollateral::OnChain(&state.chain_storage),
+            None => HandoverDcapCollateral::Shipped(self.handover_dcap_root_certs.clone()),
+        }
+    }
+
     pub(crate) fn current_block(&mut self) -> RpcResult<(BlockNumber, u64)> {
         let now_ms = self.runtime_state()?.chain_storage.timestamp_now();
         let block = self
@@ -635,6 +650,53 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> Phactory<Platform>
         })
     }
 
+    /// Make a query to `contract_id` on behalf of the contract `from`, which owns a sidevm
+    /// instance. Only contracts in the same cluster can be queried.
+    fn sidevm_contract_query(
+        &mut self,
+        from: &ContractId,
+        contract_id: &ContractId,
+        payload: Vec<u8>,
+    ) -> Result<
+        impl Future<
+            Output = Result<
+                (types::OpaqueReply, ContractClusterId, ExecSideEffects),
+                types::OpaqueError,
+            >,
+        >,
+        OcallError,
+    > {
+        if self.args.safe_mode_level > 0 {
+            return Err(OcallError::UnsupportedOperation);
+        }
+        let query_scheduler = self.query_scheduler.clone();
+        let system = self
+            .system
+            .as_mut()
+            .ok_or(OcallError::UnsupportedOperation)?;
+        let cluster_of = |id: &ContractId| {
+            system
+                .contracts
+                .get(id)
+                .map(|contract| contract.cluster_id())
+        };
+        let Some(cluster_id) = cluster_of(from) else {
+            return Err(OcallError::NotFound);
+        };
+        if cluster_of(contract_id) != Some(cluster_id) {
+            return Err(OcallError::NotFound);
+        }
+        let query = crate::contracts::pink::Query::InkMessage {
+            payload,
+            deposit: 0,
+            transfer: 0,
+        };
+        let origin = chain::AccountId::from(from.0);
+        system
+            .make_query(contract_id, Some(&origin), query.encode(), query_scheduler)
+            .map_err(query_error_to_ocall)
+    }
+
     fn handle_inbound_messages(&mut self, block_number: chain::BlockNumber) -> RpcResult<()> {
         let state = self
             .runtime_state
@@ -921,10 +983,103 @@ pub struct RpcService<Platform> {
     pub(crate) phactory: Arc<Mutex<Phactory<Platform>>>,
 }
 
-impl<Platform: pal::Platform> RpcService<Platform> {
+impl<Platform> RpcService<Platform>
+where
+    Platform: pal::Platform + Serialize + DeserializeOwned,
+{
     pub fn new(platform: Platform) -> RpcService<Platform> {
-        RpcService {
-            phactory: Arc::new(Mutex::new(Phactory::new(platform))),
+        let phactory = Arc::new(Mutex::new(Phactory::new(platform)));
+        let weak_phactory = Arc::downgrade(&phactory);
+        crate::system::set_sidevm_query_handler(move |query| {
+            if let Some(phactory) = weak_phactory.upgrade() {
+                tokio::spawn(serve_sidevm_query(phactory, query));
+            }
+        });
+        RpcService { phactory }
+    }
+}
+
+/// Serve a contract query issued by a sidevm instance, replying the output or the error to the
+/// guest.
+async fn serve_sidevm_query<Platform>(phactory: Arc<Mutex<Phactory<Platform>>>, query: SidevmQuery)
+where
+    Platform: pal::Platform + Serialize + DeserializeOwned,
+{
+    let SidevmQuery {
+        from,
+        contract_id,
+        payload,
+        reply_tx,
+    } = query;
+    let result = sidevm_query(phactory, from, contract_id, payload).await;
+    if let Err(err) = &result {
+        warn!("Sidevm contract query failed: {err:?}");
+    }
+    let _ = reply_tx.send(result);
+}
+
+async fn sidevm_query<Platform>(
+    phactory: Arc<Mutex<Phactory<Platform>>>,
+    from: ContractId,
+    contract_id: ContractId,
+    payload: Vec<u8>,
+) -> Result<Vec<u8>, OcallError>
+where
+    Platform: pal::Platform + Serialize + DeserializeOwned,
+{
+    // The phactory lock can be held for long by block dispatching, so it is never waited for on
+    // the runtime threads.
+    let query_future = {
+        let phactory = phactory.clone();
+        tokio::task::spawn_blocking(move || {
+            phactory
+                .lock()
+                .unwrap()
+                .sidevm_contract_query(&from, &contract_id, payload)
+        })
+        .await
+        .or(Err(OcallError::IoError))??
+    };
+    let (reply, cluster_id, effects) = query_future.await.map_err(query_error_to_ocall)?;
+    tokio::task::spawn_blocking(move || {
+        phactory
+            .lock()
+            .unwrap()
+            .apply_side_effects(cluster_id, effects)
+    })
+    .await
+    .or(Err(OcallError::IoError))?;
+    decode_sidevm_reply(&reply)
+}
+
+fn query_error_to_ocall(err: types::OpaqueError) -> OcallError {
+    match err {
+        types::OpaqueError::ContractNotFound => OcallError::NotFound,
+        types::OpaqueError::DecodeError => OcallError::InvalidEncoding,
+        types::OpaqueError::InvalidSignature => OcallError::InvalidParameter,
+        types::OpaqueError::OtherError(err) => {
+            warn!("Sidevm contract query error: {err}");
+            OcallError::IoError
+        }
+    }
+}
+
+/// Decode the reply of a sidevm contract query into the output or the error seen by the guest.
+fn decode_sidevm_reply(reply: &[u8]) -> Result<Vec<u8>, OcallError> {
+    use crate::contracts::pink::{QueryError, Response};
+
+    let response = Result::<Response, QueryError>::decode(&mut &reply[..])
+        .or(Err(OcallError::InvalidEncoding))?;
+    match response {
+        Ok(Response::Payload(output)) => Ok(output),
+        Err(QueryError::BadOrigin) => Err(OcallError::InvalidParameter),
+        Err(QueryError::SidevmNotFound) => Err(OcallError::NotFound),
+        Err(QueryError::ServiceUnavailable) => Err(OcallError::UnsupportedOperation),
+        Err(QueryError::Timeout) => Err(OcallError::ResourceLimited),
+        Err(QueryError::NoResponse) => Err(OcallError::EndOfFile),
+        Err(QueryError::RuntimeError(err)) => {
+            warn!("Sidevm contract query error: {err}");
+            Err(OcallError::IoError)
         }
     }
 }
@@ -990,6 +1145,84 @@ impl<Platform: pal::Platform> RpcService<Platform> {
     }
 }
 
+/// The DCAP collateral used in the worker key handover.
+enum HandoverDcapCollateral<'a> {
+    /// The collateral on chain. Used by the server, which evaluates the TCB level of the client
+    /// before handing the key out, and rejects the platforms of unknown FMSPC.
+    OnChain(&'a ChainStorage),
+    /// The root CAs shipped with the pRuntime. Used by the client, which has no chain state yet.
+    /// It accepts the server at any TCB level, with a QE signed by Intel and without checking the
+    /// revocation of its PCK certificate, since the server holds the key already.
+    Shipped(Vec<Vec<u8>>),
+}
+
+impl DcapCollateral for HandoverDcapCollateral<'_> {
+    fn trusted_root_certs(&self) -> Vec<Vec<u8>> {
+        match self {
+            Self::OnChain(chain_storage) => chain_storage.dcap_root_certs(),
+            Self::Shipped(certs) => certs.clone(),
+        }
+    }
+
+    fn tcb_info(&self, fmspc: &Fmspc) -> Option<TcbInfo> {
+        match self {
+            Self::OnChain(chain_storage) => chain_storage.dcap_tcb_info(fmspc),
+            Self::Shipped(_) => Some(TcbInfo {
+                levels: vec![TcbLevel {
+                    sgx_components: [0; 16],
+                    pce_svn: 0,
+                    status: TcbStatus::UpToDate,
+                    advisory_ids: vec![],
+                }],
+            }),
+        }
+    }
+
+    fn qe_identity(&self) -> Option<QeIdentity> {
+        use phala_pallets::utils::constants::*;
+        match self {
+            Self::OnChain(chain_storage) => chain_storage.dcap_qe_identity(),
+            Self::Shipped(_) => Some(QeIdentity {
+                mr_signer: DCAP_QE_MR_SIGNER,
+                isv_prod_id: DCAP_QE_ISV_PROD_ID,
+                miscselect: 0,
+                miscselect_mask: u32::MAX,
+                attributes: DCAP_QE_ATTRIBUTES,
+                attributes_mask: DCAP_QE_ATTRIBUTES_MASK,
+                tcb_levels: vec![QeTcbLevel {
+                    isv_svn: 0,
+                    status: TcbStatus::UpToDate,
+                    advisory_ids: vec![],
+                }],
+            }),
+        }
+    }
+
+    fn pck_crls(&self) -> Option<Vec<Vec<u8>>> {
+        match self {
+            Self::OnChain(chain_storage) => Some(chain_storage.dcap_pck_crls()),
+            Self::Shipped(_) => None,
+        }
+    }
+}
+
+fn validate_handover_attestation(
+    attestation: Option<AttestationReport>,
+    payload_hash: &[u8; 32],
+    now: u64,
+    dcap_collateral: &HandoverDcapCollateral,
+) -> Result<ConfidentialReport, AttestationError> {
+    validate_attestation_report(
+        attestation,
+        payload_hash,
+        now,
+        false,
+        vec![],
+        false,
+        dcap_collateral,
+    )
+}
+
 fn create_attestation_report_on<Platform: pal::Platform>(
     platform: &Platform,
     attestation_provider: Option<AttestationProvider>,
@@ -1185,10 +1418,11 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> PhactoryApi for Rpc
         let mut phactory = self.lock_phactory();
         let attestation_provider = phactory.attestation_provider;
         let dev_mode = phactory.dev_mode;
-        let in_sgx = attestation_provider == Some(AttestationProvider::Ias);
+        let in_sgx = matches!(
+            attestation_provider,
+            Some(AttestationProvider::Ias | AttestationProvider::Dcap)
+        );
         let (block_number, now_ms) = phactory.current_block()?;
-        let system = phactory.system()?;
-        let my_identity_key = system.identity_key.clone();
 
         // 1. verify client RA report to ensure it's in sgx
         // this also ensure the message integrity
@@ -1205,20 +1439,20 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> PhactoryApi for Rpc
             // The time from attestation report is generated by IAS, thus trusted. By default, it's valid for **10h**.
             // By ensuring our system timestamp is within the valid period, we know that this pRuntime is not hold back by
             // malicious workers.
-            validate_attestation_report(
-                attn_to_validate.clone(),
+            let report = validate_handover_attestation(
+                attn_to_validate,
                 &payload_hash,
                 block_sec,
-                false,
-                vec![],
-                false,
+                &phactory.handover_dcap_collateral(),
             )
             .map_err(|_| from_display("Invalid client RA report"))?;
-            attn_to_validate
+            Some(report)
         } else {
             info!("Skip client RA report check in dev mode");
             None
         };
+        let system = phactory.system()?;
+        let my_identity_key = system.identity_key.clone();
         // 2. verify challenge validity to prevent replay attack
         let challenge = challenge_handler.challenge;
         if !system.verify_worker_key_challenge(&challenge) {
@@ -1267,19 +1501,9 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> PhactoryApi for Rpc
                 .get_pruntime_added_at(&my_runtime_hash)
                 .ok_or_else(|| from_display("Server pRuntime not allowed on chain"))?;
 
-            let attestation =
-                attestation.ok_or_else(|| from_display("Client attestation not found"))?;
-            let runtime_hash = match attestation {
-                AttestationReport::SgxIas {
-                    ra_report,
-                    signature: _,
-                    raw_signing_cert: _,
-                } => {
-                    let (ias_fields, _) = IasFields::from_ias_report(&ra_report)
-                        .map_err(|_| from_display("Invalid client RA report"))?;
-                    ias_fields.extend_mrenclave()
-                }
-            };
+            let runtime_hash = attestation
+                .ok_or_else(|| from_display("Client attestation not found"))?
+                .runtime_hash;
             let req_runtime_timestamp = runtime_state
                 .chain_storage
                 .get_pruntime_added_at(&runtime_hash)
@@ -1377,7 +1601,9 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> PhactoryApi for Rpc
         let attestation = if !dev_mode {
             Some(create_attestation_report_on(
                 &phactory.platform,
-                Some(AttestationProvider::Ias),
+                phactory
+                    .attestation_provider
+                    .or(Some(AttestationProvider::Ias)),
                 &handler_hash,
             )?)
         } else {
@@ -1405,13 +1631,11 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> PhactoryApi for Rpc
             let attn_to_validate =
                 Option::<AttestationReport>::decode(&mut &raw_attestation.encoded_report[..])
                     .map_err(|_| from_display("Decode server attestation failed"))?;
-            validate_attestation_report(
+            validate_handover_attestation(
                 attn_to_validate,
                 &worker_key_hash,
                 now(),
-                false,
-                vec![],
-                false,
+                &phactory.handover_dcap_collateral(),
             )
             .map_err(|_| from_display("Invalid server RA report"))?;
         } else {
@@ -1600,3 +1824,126 @@ impl<Platform: pal::Platform + Serialize + DeserializeOwned> PhactoryApi for Rpc
 fn try_decode_hex(hex_str: &str) -> Result<Vec<u8>, hex::FromHexError> {
     hex::decode(hex_str.strip_prefix("0x").unwrap_or(hex_str))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light_validation::utils::{storage_map_prefix_twox_64_concat, storage_prefix};
+
+    // Generated by `scripts/gen-dcap-sample.py`, see the attestation tests of phala-pallets
+    const DCAP_QUOTE_SAMPLE: &[u8] = include_bytes!("../../../pallets/phala/sample/dcap_quote.bin");
+    const DCAP_ROOT_CA_SAMPLE: &[u8] =
+        include_bytes!("../../../pallets/phala/sample/dcap_root_ca.der");
+    const DCAP_PCK_CRL_SAMPLE: &[u8] =
+        include_bytes!("../../../pallets/phala/sample/dcap_pck_crl.der");
+    const DCAP_PCK_CRL_REVOKED_SAMPLE: &[u8] =
+        include_bytes!("../../../pallets/phala/sample/dcap_pck_crl_revoked.der");
+    const DCAP_USER_DATA_HASH: [u8; 32] =
+        hex_literal::hex!("73e18180bbe6f42aa62023fd7951919fb03f7cf0dc6725f51fa424ab751f215a");
+    const DCAP_FMSPC: Fmspc = hex_literal::hex!("00906ed50000");
+
+    fn chain_storage_with_tcb(fmspc: Fmspc, status: TcbStatus, pck_crl: &[u8]) -> ChainStorage {
+        let tcb_info = TcbInfo {
+            levels: vec![TcbLevel {
+                sgx_components: [4, 4, 3, 3, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                pce_svn: 11,
+                status,
+                advisory_ids: vec![],
+            }],
+        };
+        // The QE of the sample has ISVSVN 8
+        let mut qe_identity = HandoverDcapCollateral::Shipped(vec![])
+            .qe_identity()
+            .unwrap();
+        qe_identity.tcb_levels[0].isv_svn = 8;
+        ChainStorage::from_pairs(
+            [
+                (
+                    storage_prefix("PhalaRegistry", "DcapRootCerts"),
+                    vec![DCAP_ROOT_CA_SAMPLE.to_vec()].encode(),
+                ),
+                (
+                    storage_map_prefix_twox_64_concat(b"PhalaRegistry", b"DcapTcbInfo", &fmspc),
+                    tcb_info.encode(),
+                ),
+                (
+                    storage_prefix("PhalaRegistry", "DcapQeIdentity"),
+                    qe_identity.encode(),
+                ),
+                (
+                    storage_prefix("PhalaRegistry", "DcapPckCrls"),
+                    vec![pck_crl.to_vec()].encode(),
+                ),
+            ]
+            .into_iter(),
+        )
+    }
+
+    fn validate_sample(collateral: &HandoverDcapCollateral) -> Result<u8, AttestationError> {
+        let quote = AttestationReport::SgxDcap {
+            quote: DCAP_QUOTE_SAMPLE.to_vec(),
+        };
+        validate_handover_attestation(Some(quote), &DCAP_USER_DATA_HASH, 1700000000, collateral)
+            .map(|report| report.confidence_level)
+    }
+
+    #[test]
+    fn handover_checks_dcap_tcb_on_chain() {
+        let up_to_date =
+            chain_storage_with_tcb(DCAP_FMSPC, TcbStatus::UpToDate, DCAP_PCK_CRL_SAMPLE);
+        assert_eq!(
+            validate_sample(&HandoverDcapCollateral::OnChain(&up_to_date)),
+            Ok(1)
+        );
+
+        let revoked = chain_storage_with_tcb(DCAP_FMSPC, TcbStatus::Revoked, DCAP_PCK_CRL_SAMPLE);
+        assert_eq!(
+            validate_sample(&HandoverDcapCollateral::OnChain(&revoked)),
+            Err(AttestationError::TcbRevoked)
+        );
+
+        let unknown_fmspc =
+            chain_storage_with_tcb([0; 6], TcbStatus::UpToDate, DCAP_PCK_CRL_SAMPLE);
+        assert_eq!(
+            validate_sample(&HandoverDcapCollateral::OnChain(&unknown_fmspc)),
+            Err(AttestationError::UnknownTcbInfo)
+        );
+
+        let revoked_pck =
+            chain_storage_with_tcb(DCAP_FMSPC, TcbStatus::UpToDate, DCAP_PCK_CRL_REVOKED_SAMPLE);
+        assert_eq!(
+            validate_sample(&HandoverDcapCollateral::OnChain(&revoked_pck)),
+            Err(AttestationError::PckCertRevoked)
+        );
+
+        let shipped = HandoverDcapCollateral::Shipped(vec![DCAP_ROOT_CA_SAMPLE.to_vec()]);
+        assert_eq!(validate_sample(&shipped), Ok(1));
+    }
+
+    #[test]
+    fn sidevm_query_replies_the_output_or_the_error() {
+        use crate::contracts::pink::{QueryError, Response};
+
+        let reply = Ok::<_, QueryError>(Response::Payload(b"output".to_vec())).encode();
+        assert_eq!(decode_sidevm_reply(&reply).unwrap(), b"output");
+
+        let reply = Err::<Response, _>(QueryError::SidevmNotFound).encode();
+        assert!(matches!(
+            decode_sidevm_reply(&reply),
+            Err(OcallError::NotFound)
+        ));
+        let reply = Err::<Response, _>(QueryError::RuntimeError("trapped".into())).encode();
+        assert!(matches!(
+            decode_sidevm_reply(&reply),
+            Err(OcallError::IoError)
+        ));
+        assert!(matches!(
+            decode_sidevm_reply(&[0xff]),
+            Err(OcallError::InvalidEncoding)
+        ));
+        assert!(matches!(
+            query_error_to_ocall(types::OpaqueError::ContractNotFound),
+            OcallError::NotFound
+        ));
+    }
+}
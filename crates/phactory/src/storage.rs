@@ -165,6 +165,27 @@ mod storage_ext {
             self.execute_with(pallet_registry::PRuntimeConsensusVersion::<chain::Runtime>::get)
         }
 
+        pub(crate) fn dcap_root_certs(&self) -> Vec<Vec<u8>> {
+            self.execute_with(pallet_registry::DcapRootCerts::<chain::Runtime>::get)
+        }
+
+        pub(crate) fn dcap_tcb_info(
+            &self,
+            fmspc: &phala_pallets::utils::attestation_dcap::Fmspc,
+        ) -> Option<phala_pallets::utils::attestation_dcap::TcbInfo> {
+            self.execute_with(|| pallet_registry::DcapTcbInfo::<chain::Runtime>::get(fmspc))
+        }
+
+        pub(crate) fn dcap_qe_identity(
+            &self,
+        ) -> Option<phala_pallets::utils::attestation_dcap::QeIdentity> {
+            self.execute_with(pallet_registry::DcapQeIdentity::<chain::Runtime>::get)
+        }
+
+        pub(crate) fn dcap_pck_crls(&self) -> Vec<Vec<u8>> {
+            self.execute_with(pallet_registry::DcapPckCrls::<chain::Runtime>::get)
+        }
+
         pub(crate) fn is_pruntime_in_whitelist(&self, measurement: &[u8]) -> bool {
             let list = self.execute_with(pallet_registry::PRuntimeAllowList::<chain::Runtime>::get);
             for hash in list.iter() {
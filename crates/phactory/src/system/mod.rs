@@ -60,6 +60,8 @@ use pink::runtime::{HookPoint, PinkEvent};
 use std::cell::Cell;
 use std::convert::TryFrom;
 use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
 
 pub type TransactionResult = Result<pink::runtime::ExecSideEffects, TransactionError>;
 
@@ -486,10 +488,47 @@ fn create_sidevm_service(worker_threads: usize) -> Spawner {
             let id = hex_fmt::HexFmt(&id[..4]);
             info!("Sidevm {id} terminated with reason: {reason:?}");
         }
+        Report::ContractQuery {
+            from,
+            contract_id,
+            payload,
+            reply_tx,
+        } => {
+            let handler = SIDEVM_QUERY_HANDLER.lock().unwrap();
+            let Some(handler) = handler.as_ref() else {
+                info!("No handler for sidevm contract query, dropped");
+                let _ = reply_tx.send(Err(sidevm::OcallError::UnsupportedOperation));
+                return;
+            };
+            handler(SidevmQuery {
+                from: from.into(),
+                contract_id: contract_id.into(),
+                payload,
+                reply_tx,
+            });
+        }
     }));
     spawner
 }
 
+/// A contract query issued by a sidevm instance.
+pub(crate) struct SidevmQuery {
+    /// The contract owning the sidevm instance.
+    pub from: ContractId,
+    pub contract_id: ContractId,
+    pub payload: Vec<u8>,
+    pub reply_tx: oneshot::Sender<Result<Vec<u8>, sidevm::OcallError>>,
+}
+
+type SidevmQueryHandler = Box<dyn Fn(SidevmQuery) + Send + Sync>;
+
+static SIDEVM_QUERY_HANDLER: Mutex<Option<SidevmQueryHandler>> = Mutex::new(None);
+
+/// Set the handler serving the contract queries issued by sidevm instances.
+pub(crate) fn set_sidevm_query_handler(handler: impl Fn(SidevmQuery) + Send + Sync + 'static) {
+    *SIDEVM_QUERY_HANDLER.lock().unwrap() = Some(Box::new(handler));
+}
+
 impl<Platform: pal::Platform> System<Platform> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -1663,6 +1702,7 @@ impl<Platform: pal::Platform> System<Platform> {
 impl<P: pal::Platform> System<P> {
     pub fn on_restored(&mut self, safe_mode_level: u8) -> Result<()> {
         ::pink::runtime::set_worker_pubkey(self.ecdh_key.public());
+        self.contracts.restore_sidevm_storages();
         if safe_mode_level > 0 {
             return Ok(());
         }
@@ -1982,6 +2022,11 @@ pub(crate) fn apply_pink_events(
                     cluster.config.version
                 );
             }
+            PinkEvent::SetHttpEgressPolicy(policy) => {
+                ensure_system!();
+                info!("Set HTTP egress policy for {cluster_id:?} to {policy:?}");
+                cluster.set_http_egress_policy(policy);
+            }
         }
     }
 }
@@ -111,6 +111,12 @@ impl MessageSendQueue {
             .unwrap_or_default()
     }
 
+    /// The next sequence number of each sender.
+    pub fn sequences(&self) -> BTreeMap<SenderId, u64> {
+        let inner = self.inner.lock();
+        inner.iter().map(|(k, v)| (k.clone(), v.sequence)).collect()
+    }
+
     pub fn count_messages(&self) -> usize {
         self.inner
             .lock()
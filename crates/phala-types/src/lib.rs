@@ -440,6 +440,13 @@ pub enum AttestationReport {
         signature: Vec<u8>,
         raw_signing_cert: Vec<u8>,
     },
+    /// An SGX ECDSA quote, with the PCK certificate chain embedded in its certification data
+    SgxDcap { quote: Vec<u8> },
+    /// A fake report made by the simulated platform, only accepted by chains in dev mode
+    Simulated {
+        runtime_hash: Vec<u8>,
+        report_data: Vec<u8>,
+    },
 }
 
 #[cfg_attr(feature = "enable_serde", derive(Serialize, Deserialize))]
@@ -449,6 +456,10 @@ pub enum AttestationProvider {
     Root,
     #[cfg_attr(feature = "enable_serde", serde(rename = "ias"))]
     Ias,
+    #[cfg_attr(feature = "enable_serde", serde(rename = "dcap"))]
+    Dcap,
+    #[cfg_attr(feature = "enable_serde", serde(rename = "simulated"))]
+    Simulated,
 }
 
 #[derive(Encode, Decode, PartialEq, Eq, Debug, Default, Clone, TypeInfo)]
@@ -12,7 +12,7 @@ mod system {
     use alloc::string::String;
     use ink::storage::Mapping;
     use pink::system::{ContractDeposit, ContractDepositRef, DriverError, Error, Result};
-    use pink::{HookPoint, PinkEnvironment};
+    use pink::{chain_extension::HttpEgressPolicy, HookPoint, PinkEnvironment};
 
     /// Pink's system contract.
     #[ink(storage)]
@@ -152,6 +152,13 @@ mod system {
             pink::upgrade_system_contract(owner);
             Ok(())
         }
+
+        #[ink(message)]
+        fn set_http_egress_policy(&self, policy: HttpEgressPolicy) -> Result<()> {
+            self.ensure_owner()?;
+            pink::set_http_egress_policy(policy);
+            Ok(())
+        }
     }
 
     impl ContractDeposit for System {
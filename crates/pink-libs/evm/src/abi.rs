@@ -0,0 +1,249 @@
+//! Solidity contract ABI encoding and decoding.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use primitive_types::{H160, U256};
+
+use crate::Error;
+
+/// A value passed to or returned from a contract function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Address(H160),
+    /// Any of `uint8` .. `uint256`.
+    Uint(U256),
+    /// Any of `int8` .. `int256`, in the two's complement representation.
+    Int(U256),
+    Bool(bool),
+    /// `bytes1` .. `bytes32`.
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    /// `T[]`
+    Array(Vec<Token>),
+    /// `T[k]`
+    FixedArray(Vec<Token>),
+    Tuple(Vec<Token>),
+}
+
+/// The type of a value to be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamType {
+    Address,
+    Uint(usize),
+    Int(usize),
+    Bool,
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<ParamType>),
+}
+
+impl Token {
+    fn is_dynamic(&self) -> bool {
+        match self {
+            Token::Bytes(_) | Token::String(_) | Token::Array(_) => true,
+            Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+                tokens.iter().any(Token::is_dynamic)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ParamType {
+    fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(inner, _) => inner.is_dynamic(),
+            ParamType::Tuple(types) => types.iter().any(ParamType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// Size of the head of a static type.
+    fn head_size(&self) -> usize {
+        match self {
+            ParamType::FixedArray(inner, len) if !inner.is_dynamic() => inner.head_size() * len,
+            ParamType::Tuple(types) if !self.is_dynamic() => {
+                types.iter().map(ParamType::head_size).sum()
+            }
+            _ => 32,
+        }
+    }
+}
+
+/// Returns the 4-byte selector of a function signature such as `transfer(address,uint256)`.
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    let hash = sp_core_hashing::keccak_256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Encodes the tokens as the arguments of a function call, without a selector.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let head_size: usize = tokens
+        .iter()
+        .map(|t| {
+            if t.is_dynamic() {
+                32
+            } else {
+                encode_token(t).len()
+            }
+        })
+        .sum();
+    let mut head = Vec::with_capacity(head_size);
+    let mut tail = Vec::new();
+    for token in tokens {
+        if token.is_dynamic() {
+            head.extend_from_slice(&word(U256::from(head_size + tail.len())));
+            tail.extend(encode_token(token));
+        } else {
+            head.extend(encode_token(token));
+        }
+    }
+    head.extend(tail);
+    head
+}
+
+/// Encodes a function call, i.e. the selector of `signature` followed by the encoded arguments.
+///
+/// # Example
+/// ```
+/// use pink_evm::abi::{encode_call, Token};
+///
+/// let data = encode_call("baz(uint32,bool)", &[Token::Uint(69.into()), Token::Bool(true)]);
+/// assert_eq!(&data[..4], &[0xcd, 0xcd, 0x77, 0xc0]);
+/// ```
+pub fn encode_call(signature: &str, tokens: &[Token]) -> Vec<u8> {
+    let mut data = function_selector(signature).to_vec();
+    data.extend(encode(tokens));
+    data
+}
+
+fn encode_token(token: &Token) -> Vec<u8> {
+    match token {
+        Token::Address(addr) => {
+            let mut buf = [0u8; 32];
+            buf[12..].copy_from_slice(addr.as_bytes());
+            buf.to_vec()
+        }
+        Token::Uint(v) | Token::Int(v) => word(*v).to_vec(),
+        Token::Bool(b) => word(U256::from(*b as u8)).to_vec(),
+        Token::FixedBytes(bytes) => pad_right(bytes),
+        Token::Bytes(bytes) => {
+            let mut out = word(U256::from(bytes.len())).to_vec();
+            out.extend(pad_right(bytes));
+            out
+        }
+        Token::String(s) => encode_token(&Token::Bytes(s.as_bytes().to_vec())),
+        Token::Array(tokens) => {
+            let mut out = word(U256::from(tokens.len())).to_vec();
+            out.extend(encode(tokens));
+            out
+        }
+        Token::FixedArray(tokens) | Token::Tuple(tokens) => encode(tokens),
+    }
+}
+
+fn word(value: U256) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    value.to_big_endian(&mut buf);
+    buf
+}
+
+fn pad_right(bytes: &[u8]) -> Vec<u8> {
+    let mut out = bytes.to_vec();
+    out.resize((bytes.len() + 31) / 32 * 32, 0);
+    out
+}
+
+/// Decodes ABI encoded data, e.g. the return value of `eth_call`, into tokens of the given types.
+pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, Error> {
+    decode_params(types, data, 0)
+}
+
+fn decode_params(types: &[ParamType], data: &[u8], base: usize) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::with_capacity(types.len());
+    let mut offset = base;
+    for ty in types {
+        if ty.is_dynamic() {
+            let pointer = read_usize(data, offset)?;
+            let at = advance(base, pointer)?;
+            tokens.push(decode_param(ty, data, at)?);
+            offset = advance(offset, 32)?;
+        } else {
+            tokens.push(decode_param(ty, data, offset)?);
+            offset = advance(offset, ty.head_size())?;
+        }
+    }
+    Ok(tokens)
+}
+
+fn decode_param(ty: &ParamType, data: &[u8], at: usize) -> Result<Token, Error> {
+    let token = match ty {
+        ParamType::Address => Token::Address(H160::from_slice(&read_word(data, at)?[12..])),
+        ParamType::Uint(_) => Token::Uint(U256::from_big_endian(read_word(data, at)?)),
+        ParamType::Int(_) => Token::Int(U256::from_big_endian(read_word(data, at)?)),
+        ParamType::Bool => {
+            let value = U256::from_big_endian(read_word(data, at)?);
+            if value > U256::one() {
+                return Err(Error::AbiDecodeFailed);
+            }
+            Token::Bool(!value.is_zero())
+        }
+        ParamType::FixedBytes(len) => {
+            if *len > 32 {
+                return Err(Error::AbiDecodeFailed);
+            }
+            Token::FixedBytes(read_word(data, at)?[..*len].to_vec())
+        }
+        ParamType::Bytes => Token::Bytes(read_bytes(data, at)?.to_vec()),
+        ParamType::String => Token::String(
+            String::from_utf8(read_bytes(data, at)?.to_vec()).or(Err(Error::AbiDecodeFailed))?,
+        ),
+        ParamType::Array(inner) => {
+            let len = read_usize(data, at)?;
+            // Every element takes at least one word, which bounds the allocation below.
+            if len > data.len() / 32 {
+                return Err(Error::AbiDecodeFailed);
+            }
+            let types = vec![(**inner).clone(); len];
+            Token::Array(decode_params(&types, data, advance(at, 32)?)?)
+        }
+        ParamType::FixedArray(inner, len) => {
+            let types = vec![(**inner).clone(); *len];
+            Token::FixedArray(decode_params(&types, data, at)?)
+        }
+        ParamType::Tuple(types) => Token::Tuple(decode_params(types, data, at)?),
+    };
+    Ok(token)
+}
+
+/// Moves a position in the input forward. Positions come from untrusted input, so they must
+/// never be added up unchecked.
+fn advance(at: usize, by: usize) -> Result<usize, Error> {
+    at.checked_add(by).ok_or(Error::AbiDecodeFailed)
+}
+
+fn read_word(data: &[u8], at: usize) -> Result<&[u8], Error> {
+    data.get(at..advance(at, 32)?).ok_or(Error::AbiDecodeFailed)
+}
+
+fn read_usize(data: &[u8], at: usize) -> Result<usize, Error> {
+    let value = U256::from_big_endian(read_word(data, at)?);
+    if value > U256::from(u32::MAX) {
+        return Err(Error::AbiDecodeFailed);
+    }
+    Ok(value.as_usize())
+}
+
+fn read_bytes(data: &[u8], at: usize) -> Result<&[u8], Error> {
+    let len = read_usize(data, at)?;
+    let start = advance(at, 32)?;
+    data.get(start..advance(start, len)?)
+        .ok_or(Error::AbiDecodeFailed)
+}
@@ -0,0 +1,398 @@
+//! Build, sign and submit Ethereum (EVM) transactions from pink contracts.
+//!
+//! Transactions are RLP encoded and signed in the contract with `ecdsa_sign_prehashed`, and the
+//! JSON-RPC requests are sent through `pink::http_request`.
+//!
+//! # Example
+//! ```ignore
+//! use pink::chain_extension::{signing, SigType};
+//! use pink_evm::{abi::Token, LegacyTransaction};
+//!
+//! let key = signing::derive_key(SigType::Ecdsa, b"evm-sender");
+//! let sender = pink_evm::address_of_key(&key)?;
+//! let nonce = pink_evm::get_transaction_count(rpc, sender)?;
+//! let tx = LegacyTransaction {
+//!     nonce,
+//!     gas_price: 20_000_000_000u64.into(),
+//!     gas_limit: 100_000,
+//!     to: Some(token_contract),
+//!     data: pink_evm::abi::encode_call(
+//!         "transfer(address,uint256)",
+//!         &[Token::Address(receiver), Token::Uint(amount)],
+//!     ),
+//!     chain_id: 1,
+//!     ..Default::default()
+//! };
+//! let tx_hash = pink_evm::send_raw_transaction(rpc, &tx.sign(&key))?;
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use pink_extension::chain_extension::{signing, SigType};
+use primitive_types::{H160, H256};
+use scale::{Decode, Encode};
+
+pub mod abi;
+pub mod rlp;
+mod rpc;
+mod transaction;
+
+use rpc::{call_rpc, decode_hex, decode_quantity};
+pub use transaction::{transaction_hash, Eip1559Transaction, LegacyTransaction};
+
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Error {
+    /// The RPC node didn't answer with HTTP 200.
+    RequestFailed,
+    /// The response body is not a valid JSON-RPC response.
+    InvalidBody,
+    /// The RPC node returned a JSON-RPC error object.
+    Rpc {
+        code: i64,
+        message: String,
+    },
+    /// The given key or public key is not a valid secp256k1 key.
+    InvalidKey,
+    AbiDecodeFailed,
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Executes a message call against the latest block without creating a transaction, and returns
+/// the raw return data.
+pub fn eth_call(rpc_node: &str, to: H160, data: &[u8]) -> Result<Vec<u8>> {
+    let data = hex::encode(data);
+    let params = format!(r#"[{{"to":"0x{to:x}","data":"0x{data}"}},"latest"]"#);
+    let result = call_rpc(rpc_node, "eth_call", &params)?;
+    decode_hex(&result)
+}
+
+/// Calls a contract function by its signature and decodes the return values.
+///
+/// # Example
+/// ```ignore
+/// let balance = pink_evm::call_function(
+///     rpc,
+///     token_contract,
+///     "balanceOf(address)",
+///     &[Token::Address(owner)],
+///     &[ParamType::Uint(256)],
+/// )?;
+/// ```
+pub fn call_function(
+    rpc_node: &str,
+    to: H160,
+    signature: &str,
+    args: &[abi::Token],
+    outputs: &[abi::ParamType],
+) -> Result<Vec<abi::Token>> {
+    let output = eth_call(rpc_node, to, &abi::encode_call(signature, args))?;
+    abi::decode(outputs, &output)
+}
+
+/// Gets the nonce to be used by the next transaction of the account, including the transactions
+/// still pending in the mempool.
+pub fn get_transaction_count(rpc_node: &str, address: H160) -> Result<u64> {
+    let params = format!(r#"["0x{address:x}","pending"]"#);
+    let result = call_rpc(rpc_node, "eth_getTransactionCount", &params)?;
+    decode_quantity(&result)
+}
+
+/// Submits a signed raw transaction and returns its hash.
+pub fn send_raw_transaction(rpc_node: &str, raw_tx: &[u8]) -> Result<H256> {
+    let params = format!(r#"["0x{}"]"#, hex::encode(raw_tx));
+    let result = call_rpc(rpc_node, "eth_sendRawTransaction", &params)?;
+    let hash = decode_hex(&result)?;
+    if hash.len() != 32 {
+        return Err(Error::InvalidBody);
+    }
+    Ok(H256::from_slice(&hash))
+}
+
+/// Gets the address of a compressed secp256k1 public key.
+pub fn address_of(pubkey: &[u8]) -> Result<H160> {
+    use k256::elliptic_curve::sec1::ToEncodedPoint;
+    let pubkey = k256::PublicKey::from_sec1_bytes(pubkey).or(Err(Error::InvalidKey))?;
+    let point = pubkey.to_encoded_point(false);
+    // Skip the 0x04 tag of the uncompressed point
+    let hash = sp_core_hashing::keccak_256(&point.as_bytes()[1..]);
+    Ok(H160::from_slice(&hash[12..]))
+}
+
+/// Gets the address of a secp256k1 private key.
+pub fn address_of_key(key: &[u8]) -> Result<H160> {
+    address_of(&signing::get_public_key(key, SigType::Ecdsa))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::abi::{ParamType, Token};
+    use super::*;
+    use hex_literal::hex;
+    use pink_extension::chain_extension::{mock, HttpRequest, HttpResponse};
+    use primitive_types::U256;
+
+    const RPC: &str = "http://localhost:8545";
+
+    fn mock_rpc(fixture: &'static [u8], check: impl Fn(&str) + 'static) {
+        mock::mock_http_request(move |request: HttpRequest| {
+            assert_eq!(request.method, "POST");
+            check(core::str::from_utf8(&request.body).unwrap());
+            HttpResponse::ok(fixture.to_vec())
+        });
+    }
+
+    fn eip155_example() -> LegacyTransaction {
+        LegacyTransaction {
+            nonce: 9,
+            gas_price: U256::from(20_000_000_000u64),
+            gas_limit: 21000,
+            to: Some(H160([0x35; 20])),
+            value: U256::from(1_000_000_000_000_000_000u64),
+            data: Vec::new(),
+            chain_id: 1,
+        }
+    }
+
+    /// The example in https://eips.ethereum.org/EIPS/eip-155
+    #[test]
+    fn can_sign_legacy_transaction() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        let tx = eip155_example();
+        assert_eq!(
+            tx.signing_payload(),
+            hex!("ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080")
+        );
+        assert_eq!(
+            tx.signing_hash(),
+            hex!("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")
+        );
+        assert_eq!(
+            tx.sign(&[0x46; 32]),
+            hex!("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83")
+        );
+        assert_eq!(
+            address_of_key(&[0x46; 32]).unwrap(),
+            H160(hex!("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"))
+        );
+    }
+
+    #[test]
+    fn can_sign_eip1559_transaction() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        let key = [0x46; 32];
+        let tx = Eip1559Transaction {
+            chain_id: 1,
+            nonce: 9,
+            max_priority_fee_per_gas: U256::from(1_000_000_000u64),
+            max_fee_per_gas: U256::from(20_000_000_000u64),
+            gas_limit: 21000,
+            to: Some(H160([0x35; 20])),
+            value: U256::from(1_000_000_000_000_000_000u64),
+            data: Vec::new(),
+            access_list: Vec::new(),
+        };
+        let payload = tx.signing_payload();
+        assert_eq!(payload[0], 0x02);
+        assert_eq!(
+            payload[1..],
+            hex!("f00109843b9aca008504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080c0")
+        );
+
+        let raw = tx.sign(&key);
+        assert_eq!(raw[0], 0x02);
+        // The signed list is the unsigned one followed by y_parity, r and s.
+        assert_eq!(raw[3..payload.len() + 1], payload[2..]);
+        let sig = signing::ecdsa_sign_prehashed(&key, tx.signing_hash());
+        let pubkey = signing::get_public_key(&key, SigType::Ecdsa);
+        assert!(signing::ecdsa_verify_prehashed(
+            sig,
+            tx.signing_hash(),
+            pubkey.try_into().unwrap()
+        ));
+    }
+
+    /// Examples in https://docs.soliditylang.org/en/latest/abi-spec.html#examples
+    #[test]
+    fn can_encode_abi() {
+        let data = abi::encode_call(
+            "baz(uint32,bool)",
+            &[Token::Uint(69.into()), Token::Bool(true)],
+        );
+        assert_eq!(
+            data,
+            hex!(
+                "cdcd77c0"
+                "0000000000000000000000000000000000000000000000000000000000000045"
+                "0000000000000000000000000000000000000000000000000000000000000001"
+            )
+        );
+
+        let data = abi::encode_call(
+            "sam(bytes,bool,uint256[])",
+            &[
+                Token::Bytes(b"dave".to_vec()),
+                Token::Bool(true),
+                Token::Array(vec![
+                    Token::Uint(1.into()),
+                    Token::Uint(2.into()),
+                    Token::Uint(3.into()),
+                ]),
+            ],
+        );
+        assert_eq!(
+            data,
+            hex!(
+                "a5643bf2"
+                "0000000000000000000000000000000000000000000000000000000000000060"
+                "0000000000000000000000000000000000000000000000000000000000000001"
+                "00000000000000000000000000000000000000000000000000000000000000a0"
+                "0000000000000000000000000000000000000000000000000000000000000004"
+                "6461766500000000000000000000000000000000000000000000000000000000"
+                "0000000000000000000000000000000000000000000000000000000000000003"
+                "0000000000000000000000000000000000000000000000000000000000000001"
+                "0000000000000000000000000000000000000000000000000000000000000002"
+                "0000000000000000000000000000000000000000000000000000000000000003"
+            )
+        );
+    }
+
+    #[test]
+    fn can_decode_abi() {
+        let tokens = vec![
+            Token::Address(H160([0x11; 20])),
+            Token::String("hello".into()),
+            Token::Tuple(vec![Token::Uint(7.into()), Token::Bytes(vec![1, 2, 3])]),
+            Token::FixedArray(vec![Token::Bool(true), Token::Bool(false)]),
+        ];
+        let types = [
+            ParamType::Address,
+            ParamType::String,
+            ParamType::Tuple(vec![ParamType::Uint(256), ParamType::Bytes]),
+            ParamType::FixedArray(Box::new(ParamType::Bool), 2),
+        ];
+        let data = abi::encode(&tokens);
+        assert_eq!(abi::decode(&types, &data).unwrap(), tokens);
+        assert_eq!(
+            abi::decode(&types, &data[..data.len() - 1]),
+            Err(Error::AbiDecodeFailed)
+        );
+    }
+
+    #[test]
+    fn decode_abi_rejects_overflowing_offsets() {
+        let word = |value: u32| {
+            let mut word = [0u8; 32];
+            word[28..].copy_from_slice(&value.to_be_bytes());
+            word
+        };
+        // A pointer to the very end of the address space, followed by a huge length.
+        let data = [word(u32::MAX), word(u32::MAX)].concat();
+        for ty in [
+            ParamType::Bytes,
+            ParamType::String,
+            ParamType::Array(Box::new(ParamType::Uint(256))),
+        ] {
+            assert_eq!(abi::decode(&[ty], &data), Err(Error::AbiDecodeFailed));
+        }
+        // A valid pointer to a length running past the end of the data.
+        let data = [word(32), word(u32::MAX)].concat();
+        assert_eq!(
+            abi::decode(&[ParamType::Bytes], &data),
+            Err(Error::AbiDecodeFailed)
+        );
+    }
+
+    #[test]
+    fn can_call_function() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        mock_rpc(include_bytes!("../tests/fixtures/eth_call.json"), |body| {
+            assert!(body.contains(r#""method":"eth_call""#));
+            // balanceOf(0x1111..11)
+            assert!(body.contains(
+                "70a082310000000000000000000000001111111111111111111111111111111111111111"
+            ));
+        });
+        let balance = call_function(
+            RPC,
+            H160([0x22; 20]),
+            "balanceOf(address)",
+            &[Token::Address(H160([0x11; 20]))],
+            &[ParamType::Uint(256)],
+        )
+        .unwrap();
+        assert_eq!(balance, vec![Token::Uint(U256::from(1_000_000u64))]);
+    }
+
+    #[test]
+    fn can_get_transaction_count() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        mock_rpc(
+            include_bytes!("../tests/fixtures/eth_getTransactionCount.json"),
+            |body| {
+                assert!(body.contains(
+                    r#""params":["0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f","pending"]"#
+                ));
+            },
+        );
+        let address = H160(hex!("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"));
+        assert_eq!(get_transaction_count(RPC, address), Ok(26));
+    }
+
+    #[test]
+    fn can_send_raw_transaction() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        let raw = eip155_example().sign(&[0x46; 32]);
+        let raw_hex = hex::encode(&raw);
+        mock_rpc(
+            include_bytes!("../tests/fixtures/eth_sendRawTransaction.json"),
+            move |body| {
+                assert!(body.contains(r#""method":"eth_sendRawTransaction""#));
+                assert!(body.contains(&raw_hex));
+            },
+        );
+        assert_eq!(
+            send_raw_transaction(RPC, &raw),
+            Ok(H256(hex!(
+                "33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+            )))
+        );
+    }
+
+    #[test]
+    fn rpc_errors_are_reported() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        mock_rpc(include_bytes!("../tests/fixtures/rpc_error.json"), |_| ());
+        let raw = eip155_example().sign(&[0x46; 32]);
+        assert_eq!(
+            send_raw_transaction(RPC, &raw),
+            Err(Error::Rpc {
+                code: -32000,
+                message: "nonce too low".into()
+            })
+        );
+
+        mock_rpc(
+            br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"execution reverted: \"paused\""}}"#,
+            |_| (),
+        );
+        match send_raw_transaction(RPC, &raw) {
+            Err(Error::Rpc { code, message }) => {
+                assert_eq!(code, -32000);
+                assert!(message.starts_with("execution reverted"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        mock::mock_http_request(|_| HttpResponse::not_found());
+        assert_eq!(
+            get_transaction_count(RPC, H160::zero()),
+            Err(Error::RequestFailed)
+        );
+    }
+}
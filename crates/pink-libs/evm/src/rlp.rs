@@ -0,0 +1,109 @@
+//! Minimal RLP encoder covering what transaction serialization needs.
+
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+
+/// Types that can be written as a single RLP item.
+pub trait Encodable {
+    fn rlp_append(&self, out: &mut Vec<u8>);
+}
+
+impl Encodable for [u8] {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        if self.len() == 1 && self[0] < 0x80 {
+            out.push(self[0]);
+        } else {
+            encode_length(out, 0x80, self.len());
+            out.extend_from_slice(self);
+        }
+    }
+}
+
+impl Encodable for Vec<u8> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        self.as_slice().rlp_append(out)
+    }
+}
+
+impl Encodable for u64 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        trim_leading_zeros(&self.to_be_bytes()).rlp_append(out)
+    }
+}
+
+impl Encodable for U256 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut buf = [0u8; 32];
+        self.to_big_endian(&mut buf);
+        trim_leading_zeros(&buf).rlp_append(out)
+    }
+}
+
+impl Encodable for H160 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        self.as_bytes().rlp_append(out)
+    }
+}
+
+impl Encodable for H256 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        self.as_bytes().rlp_append(out)
+    }
+}
+
+/// `None` is encoded as the empty string, which is how contract creation is expressed in the `to`
+/// field of a transaction.
+impl<T: Encodable> Encodable for Option<T> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(v) => v.rlp_append(out),
+            None => out.push(0x80),
+        }
+    }
+}
+
+/// Builds an RLP list item by item.
+#[derive(Default)]
+pub struct ListBuilder {
+    payload: Vec<u8>,
+}
+
+impl ListBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn append<T: Encodable + ?Sized>(&mut self, item: &T) -> &mut Self {
+        item.rlp_append(&mut self.payload);
+        self
+    }
+
+    /// Appends an already encoded item, e.g. a nested list.
+    pub fn append_raw(&mut self, encoded: &[u8]) -> &mut Self {
+        self.payload.extend_from_slice(encoded);
+        self
+    }
+
+    pub fn finish(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.payload.len() + 9);
+        encode_length(&mut out, 0xc0, self.payload.len());
+        out.extend_from_slice(&self.payload);
+        out
+    }
+}
+
+fn encode_length(out: &mut Vec<u8>, offset: u8, len: usize) {
+    if len < 56 {
+        out.push(offset + len as u8);
+    } else {
+        let len_bytes = (len as u64).to_be_bytes();
+        let len_bytes = trim_leading_zeros(&len_bytes);
+        out.push(offset + 55 + len_bytes.len() as u8);
+        out.extend_from_slice(len_bytes);
+    }
+}
+
+fn trim_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
@@ -0,0 +1,62 @@
+use crate::Error;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use pink_extension::http_post;
+use pink_json as json;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse<'a> {
+    #[serde(borrow)]
+    result: Option<&'a str>,
+    error: Option<RpcErrorObject>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+/// Calls a JSON-RPC method whose result is a hex string and returns that string.
+///
+/// `params` is the already serialized JSON array of parameters.
+pub fn call_rpc(rpc_node: &str, method: &str, params: &str) -> Result<String, Error> {
+    let data =
+        format!(r#"{{"id":1,"jsonrpc":"2.0","method":"{method}","params":{params}}}"#).into_bytes();
+    let content_length = format!("{}", data.len());
+    let headers: Vec<(String, String)> = vec![
+        ("Content-Type".into(), "application/json".into()),
+        ("Content-Length".into(), content_length),
+    ];
+    let response = http_post!(rpc_node, data, headers);
+
+    if response.status_code != 200 {
+        return Err(Error::RequestFailed);
+    }
+
+    let resp: RpcResponse = json::from_slice(&response.body).or(Err(Error::InvalidBody))?;
+    if let Some(err) = resp.error {
+        return Err(Error::Rpc {
+            code: err.code,
+            message: err.message,
+        });
+    }
+    resp.result
+        .map(ToString::to_string)
+        .ok_or(Error::InvalidBody)
+}
+
+/// Decodes a `0x` prefixed hex string.
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, Error> {
+    let s = s.strip_prefix("0x").ok_or(Error::InvalidBody)?;
+    hex::decode(s).or(Err(Error::InvalidBody))
+}
+
+/// Decodes a `0x` prefixed hex quantity, which has no leading zeros and may have an odd length.
+pub fn decode_quantity(s: &str) -> Result<u64, Error> {
+    let s = s.strip_prefix("0x").ok_or(Error::InvalidBody)?;
+    u64::from_str_radix(s, 16).or(Err(Error::InvalidBody))
+}
@@ -0,0 +1,148 @@
+//! Ethereum transactions and their signing.
+
+use alloc::vec::Vec;
+use pink_extension::chain_extension::signing;
+use primitive_types::{H160, H256, U256};
+
+use crate::rlp::ListBuilder;
+
+/// EIP-2718 type byte of EIP-1559 transactions.
+const EIP1559_TX_TYPE: u8 = 0x02;
+
+/// A pre-EIP-2718 transaction, signed with EIP-155 replay protection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LegacyTransaction {
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    /// The callee, or `None` to deploy a contract.
+    pub to: Option<H160>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub chain_id: u64,
+}
+
+/// An EIP-1559 (type 2) transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Eip1559Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    /// The callee, or `None` to deploy a contract.
+    pub to: Option<H160>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<(H160, Vec<H256>)>,
+}
+
+/// An ECDSA signature split into its components.
+struct RecoverableSignature {
+    r: U256,
+    s: U256,
+    recovery_id: u64,
+}
+
+fn sign_hash(key: &[u8], hash: [u8; 32]) -> RecoverableSignature {
+    let signature = signing::ecdsa_sign_prehashed(key, hash);
+    RecoverableSignature {
+        r: U256::from_big_endian(&signature[..32]),
+        s: U256::from_big_endian(&signature[32..64]),
+        recovery_id: signature[64] as u64,
+    }
+}
+
+impl LegacyTransaction {
+    fn append_fields(&self, list: &mut ListBuilder) {
+        list.append(&self.nonce)
+            .append(&self.gas_price)
+            .append(&self.gas_limit)
+            .append(&self.to)
+            .append(&self.value)
+            .append(&self.data);
+    }
+
+    /// The EIP-155 signing payload: `rlp([nonce, gasPrice, gas, to, value, data, chainId, 0, 0])`.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut list = ListBuilder::new();
+        self.append_fields(&mut list);
+        list.append(&self.chain_id).append(&0u64).append(&0u64);
+        list.finish()
+    }
+
+    /// The hash to be signed by the sender.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        sp_core_hashing::keccak_256(&self.signing_payload())
+    }
+
+    /// Signs the transaction with the given secp256k1 private key and returns the raw transaction,
+    /// ready for `eth_sendRawTransaction`.
+    pub fn sign(&self, key: &[u8]) -> Vec<u8> {
+        let sig = sign_hash(key, self.signing_hash());
+        let v = sig.recovery_id + self.chain_id * 2 + 35;
+        let mut list = ListBuilder::new();
+        self.append_fields(&mut list);
+        list.append(&v).append(&sig.r).append(&sig.s);
+        list.finish()
+    }
+}
+
+impl Eip1559Transaction {
+    fn append_fields(&self, list: &mut ListBuilder) {
+        let mut access_list = ListBuilder::new();
+        for (address, storage_keys) in &self.access_list {
+            let mut keys = ListBuilder::new();
+            for key in storage_keys {
+                keys.append(key);
+            }
+            let mut item = ListBuilder::new();
+            item.append(address).append_raw(&keys.finish());
+            access_list.append_raw(&item.finish());
+        }
+        list.append(&self.chain_id)
+            .append(&self.nonce)
+            .append(&self.max_priority_fee_per_gas)
+            .append(&self.max_fee_per_gas)
+            .append(&self.gas_limit)
+            .append(&self.to)
+            .append(&self.value)
+            .append(&self.data)
+            .append_raw(&access_list.finish());
+    }
+
+    /// The signing payload: `0x02 || rlp([chainId, nonce, maxPriorityFeePerGas, maxFeePerGas,
+    /// gas, to, value, data, accessList])`.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut list = ListBuilder::new();
+        self.append_fields(&mut list);
+        typed(EIP1559_TX_TYPE, list.finish())
+    }
+
+    /// The hash to be signed by the sender.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        sp_core_hashing::keccak_256(&self.signing_payload())
+    }
+
+    /// Signs the transaction with the given secp256k1 private key and returns the raw transaction,
+    /// ready for `eth_sendRawTransaction`.
+    pub fn sign(&self, key: &[u8]) -> Vec<u8> {
+        let sig = sign_hash(key, self.signing_hash());
+        let mut list = ListBuilder::new();
+        self.append_fields(&mut list);
+        list.append(&sig.recovery_id).append(&sig.r).append(&sig.s);
+        typed(EIP1559_TX_TYPE, list.finish())
+    }
+}
+
+fn typed(tx_type: u8, payload: Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 1);
+    out.push(tx_type);
+    out.extend(payload);
+    out
+}
+
+/// The hash of a signed raw transaction, as returned by `eth_sendRawTransaction`.
+pub fn transaction_hash(raw_tx: &[u8]) -> H256 {
+    H256(sp_core_hashing::keccak_256(raw_tx))
+}
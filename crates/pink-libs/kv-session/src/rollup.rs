@@ -3,7 +3,7 @@ use alloc::{
     vec::Vec,
 };
 
-use crate::traits::{BumpVersion, Key, KvSnapshot, KvTransaction, QueueIndex, Value};
+use crate::traits::{BumpVersion, Condition, Key, KvSnapshot, KvTransaction, QueueIndex, Value};
 use crate::Result;
 
 pub enum VersionLayout {
@@ -17,15 +17,16 @@ pub enum VersionLayout {
 pub struct RollUpTransaction {
     /// Should be the block hash for a blockchain backend
     pub snapshot_id: Value,
-    pub conditions: Vec<(Key, Option<Value>)>,
+    pub conditions: Vec<Condition>,
     pub updates: Vec<(Key, Option<Value>)>,
     pub queue_head: Option<QueueIndex>,
+    pub named_queue_heads: Vec<(Vec<u8>, QueueIndex)>,
 }
 
 impl RollUpTransaction {
     /// Returns true if there are some updates in this transaction.
     pub fn has_updates(&self) -> bool {
-        self.queue_head.is_some() || !self.updates.is_empty()
+        self.queue_head.is_some() || !self.named_queue_heads.is_empty() || !self.updates.is_empty()
     }
 }
 
@@ -60,18 +61,20 @@ where
         .accessed_keys
         .into_iter()
         .map(|k| {
-            (
+            Condition::Eq(
                 concat(&k, &postfix),
                 versions.get(&concat(&k, &postfix)).and_then(Clone::clone),
             )
         })
         .collect();
 
-    if let Some(lock_key) = tx.queue_lock {
+    for lock_key in tx.queue_locks {
         let lock_value = kvdb.get(&lock_key)?;
-        conditions.push((lock_key, lock_value));
+        conditions.push(Condition::Eq(lock_key, lock_value));
     }
 
+    conditions.extend(tx.conditions);
+
     let mut updates = tx.value_updates;
     {
         // Auto bump the versions of keys we have written to
@@ -93,6 +96,7 @@ where
         updates,
         snapshot_id: kvdb.snapshot_id()?,
         queue_head: tx.queue_head,
+        named_queue_heads: tx.named_queue_heads,
     })
 }
 
@@ -105,7 +109,7 @@ mod tests {
     use scale::{Decode, Encode};
 
     use crate::{
-        traits::{KvSession, QueueIndexCodec, QueueSession, Value},
+        traits::{CmpOp, KvSession, QueueIndexCodec, QueueSession, Value},
         Error, ReadTracker, Session,
     };
 
@@ -199,8 +203,8 @@ mod tests {
         assert_eq!(
             rollup.conditions,
             [
-                (b"A_ver".to_vec(), None), // Require version of A to be None
-                (b"B_ver".to_vec(), Some(10000_u32.encode()))  // Require version of B to be 10000
+                Condition::Eq(b"A_ver".to_vec(), None), // Require version of A to be None
+                Condition::Eq(b"B_ver".to_vec(), Some(10000_u32.encode())) // Require version of B to be 10000
             ]
         );
         assert_eq!(
@@ -267,9 +271,94 @@ mod tests {
         assert_eq!(
             tx.conditions,
             // Should lock on the head cursor
-            vec![(b"TestQ/_head".to_vec(), Some(0_u128.encode()))]
+            vec![Condition::Eq(
+                b"TestQ/_head".to_vec(),
+                Some(0_u128.encode())
+            )]
         );
         assert_eq!(tx.updates, vec![]);
         assert_eq!(tx.queue_head, Some(final_head));
     }
+
+    #[test]
+    fn user_conditions_works() {
+        let kvdb = MockSnapshot::default();
+        kvdb.set(b"counter", &5_u64.encode());
+
+        let mut session =
+            Session::<_, _, ScaleCodec>::new(kvdb, ReadTracker::new(), b"_q/").unwrap();
+        session.require(Condition::Cmp(b"counter".to_vec(), CmpOp::Ge, 5));
+        session.require(Condition::Exists(b"owner".to_vec()));
+        assert_eq!(session.get(b"counter").unwrap(), Some(5_u64.encode()));
+        session.put(b"counter", 6_u64.encode());
+        let (tx, kvdb) = session.commit();
+
+        let tx = rollup(
+            &kvdb,
+            tx,
+            VersionLayout::Standalone {
+                key_postfix: "_ver".into(),
+            },
+        )
+        .unwrap();
+        // User conditions come after the version conditions
+        assert_eq!(
+            tx.conditions,
+            vec![
+                Condition::Eq(b"counter_ver".to_vec(), None),
+                Condition::Cmp(b"counter".to_vec(), CmpOp::Ge, 5),
+                Condition::Exists(b"owner".to_vec()),
+            ]
+        );
+        assert_eq!(
+            tx.updates,
+            vec![
+                (b"counter".to_vec(), Some(6_u64.encode())),
+                (b"counter_ver".to_vec(), Some(1_u32.encode())),
+            ]
+        );
+    }
+
+    #[test]
+    fn pop_named_queue_works() {
+        let kvdb = MockSnapshot::default();
+
+        // Set up some test data
+        kvdb.set(b"TestQ/_head", &0_u32.encode());
+        kvdb.set(b"TestQ/_tail", &1_u32.encode());
+        kvdb.set(b"TestQ/\x00\x00\x00\x00", b"foo");
+        kvdb.set(b"TestQ/\x0cbar_head", &5_u32.encode());
+        kvdb.set(b"TestQ/\x0cbar_tail", &7_u32.encode());
+        kvdb.set(b"TestQ/\x0cbar\x05\x00\x00\x00", b"bar5");
+        kvdb.set(b"TestQ/\x0cbar\x06\x00\x00\x00", b"bar6");
+
+        let mut queue =
+            Session::<_, _, ScaleCodec>::new(kvdb, ReadTracker::new(), b"TestQ/").unwrap();
+        assert_eq!(queue.pop_from(b"bar"), Ok(Some(b"bar5".to_vec())));
+        assert_eq!(queue.pop_from(b"baz"), Ok(None));
+        // The default queue is untouched
+        assert_eq!(queue.queue_length(), 1);
+        let (tx, kvdb) = queue.commit();
+        let tx = rollup(
+            &kvdb,
+            tx,
+            VersionLayout::Standalone {
+                key_postfix: "_ver".into(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            tx.conditions,
+            // Should lock on the head cursor of the popped queue only
+            vec![Condition::Eq(
+                b"TestQ/\x0cbar_head".to_vec(),
+                Some(5_u32.encode())
+            )]
+        );
+        assert_eq!(tx.updates, vec![]);
+        assert_eq!(tx.queue_head, None);
+        assert_eq!(tx.named_queue_heads, vec![(b"bar".to_vec(), 6)]);
+        assert!(tx.has_updates());
+    }
 }
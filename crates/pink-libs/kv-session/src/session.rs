@@ -1,9 +1,12 @@
 use core::marker::PhantomData;
 
 use alloc::vec::Vec;
-use alloc::{borrow::ToOwned, collections::BTreeMap};
+use alloc::{
+    borrow::ToOwned,
+    collections::{btree_map::Entry, BTreeMap},
+};
 
-use crate::traits::{QueueIndex, QueueIndexCodec, QueueSession};
+use crate::traits::{Condition, QueueIndex, QueueIndexCodec, QueueSession};
 use crate::{
     traits::{AccessTracking, KvSession, KvSnapshot, KvTransaction},
     Result,
@@ -43,6 +46,10 @@ impl<Codec: QueueIndexCodec> QueueInfo<Codec> {
         })
     }
 
+    fn lock_key(&self) -> Option<Vec<u8>> {
+        self.popped.then(|| [&self.prefix[..], b"_head"].concat())
+    }
+
     pub fn pop(&mut self, kvdb: &impl KvSnapshot) -> Result<Option<Vec<u8>>> {
         if self.head == self.tail {
             return Ok(None);
@@ -59,7 +66,10 @@ pub struct Session<Snapshot, AccessTracker, Codec> {
     kvdb: Snapshot,
     tracker: AccessTracker,
     updates: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    conditions: Vec<Condition>,
+    queue_prefix: Vec<u8>,
     queue: QueueInfo<Codec>,
+    named_queues: BTreeMap<Vec<u8>, QueueInfo<Codec>>,
 }
 
 impl<Snap, Tracker, Codec> Session<Snap, Tracker, Codec>
@@ -74,10 +84,22 @@ where
             kvdb,
             tracker,
             updates: Default::default(),
+            conditions: Default::default(),
+            queue_prefix: queue_prefix.to_vec(),
             queue,
+            named_queues: Default::default(),
         })
     }
 
+    /// Requires the condition to hold on chain when the transaction is applied
+    ///
+    /// The condition is checked against the storage at the time of the rollup rather than the
+    /// snapshot, e.g. `Condition::Cmp(key, CmpOp::Lt, now)` rejects the transaction if someone
+    /// else has updated the timestamp in the meantime.
+    pub fn require(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
     pub fn commit(self) -> (KvTransaction, Snap) {
         let (accessed_keys, version_updates) = self.tracker.collect_into();
         (
@@ -90,11 +112,19 @@ where
                 } else {
                     None
                 },
-                queue_lock: if self.queue.popped {
-                    Some([&self.queue.prefix[..], b"_head"].concat())
-                } else {
-                    None
-                },
+                named_queue_heads: self
+                    .named_queues
+                    .iter()
+                    .filter(|(_, queue)| queue.popped)
+                    .map(|(name, queue)| (name.clone(), queue.head))
+                    .collect(),
+                queue_locks: self
+                    .queue
+                    .lock_key()
+                    .into_iter()
+                    .chain(self.named_queues.values().filter_map(QueueInfo::lock_key))
+                    .collect(),
+                conditions: self.conditions,
             },
             self.kvdb,
         )
@@ -147,6 +177,20 @@ where
     fn pop(&mut self) -> Result<Option<crate::traits::Value>> {
         self.queue.pop(&self.kvdb)
     }
+
+    fn pop_from(&mut self, queue: &[u8]) -> Result<Option<crate::traits::Value>> {
+        if queue.is_empty() {
+            return self.pop();
+        }
+        let info = match self.named_queues.entry(queue.to_vec()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let prefix = [&self.queue_prefix[..], &Codec::encode_name(queue)].concat();
+                entry.insert(QueueInfo::new(prefix, &self.kvdb)?)
+            }
+        };
+        info.pop(&self.kvdb)
+    }
 }
 
 #[cfg(test)]
@@ -11,7 +11,36 @@ pub struct KvTransaction {
     pub version_updates: Vec<Key>,
     pub value_updates: Vec<(Key, Option<Value>)>,
     pub queue_head: Option<QueueIndex>,
-    pub queue_lock: Option<Key>,
+    /// New heads of the named sub-queues that have been popped
+    pub named_queue_heads: Vec<(Vec<u8>, QueueIndex)>,
+    /// Head keys of the popped queues, which must not be changed by others before the rollup
+    pub queue_locks: Vec<Key>,
+    /// Extra conditions required by the user
+    pub conditions: Vec<Condition>,
+}
+
+/// A condition on the storage which must hold when the transaction is applied
+///
+/// Mirrors the `Cond` of the offchain-rollup anchor pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// The value of the key equals to the given value, or the key is absent if `None`
+    Eq(Key, Option<Value>),
+    /// The key exists
+    Exists(Key),
+    /// The value of the key, decoded as a SCALE-encoded unsigned integer, compared to the given
+    /// number with the operator is true. An absent key is treated as zero.
+    Cmp(Key, CmpOp, u128),
+    /// The value of the key starts with the given bytes
+    HasPrefix(Key, Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
 }
 
 pub trait BumpVersion {
@@ -44,12 +73,35 @@ pub trait KvSession {
 }
 
 pub trait QueueSession {
+    /// Pops a message from the default queue
     fn pop(&mut self) -> Result<Option<Value>>;
+    /// Pops a message from the named sub-queue. The empty name refers to the default queue.
+    fn pop_from(&mut self, queue: &[u8]) -> Result<Option<Value>>;
 }
 
 pub trait QueueIndexCodec {
     fn encode(number: QueueIndex) -> Vec<u8>;
     fn decode(raw: impl AsRef<[u8]>) -> Result<QueueIndex>;
+
+    /// Encodes the name of a sub-queue to be appended to the queue prefix
+    ///
+    /// Defaults to the SCALE encoding of a byte vector, which is what the offchain-rollup
+    /// anchor pallet uses.
+    fn encode_name(name: &[u8]) -> Vec<u8> {
+        let len = name.len() as u32;
+        let mut buf = Vec::with_capacity(name.len() + 4);
+        match len {
+            0..=0x3f => buf.push((len as u8) << 2),
+            0x40..=0x3fff => buf.extend_from_slice(&(((len as u16) << 2) | 0b01).to_le_bytes()),
+            0x4000..=0x3fff_ffff => buf.extend_from_slice(&((len << 2) | 0b10).to_le_bytes()),
+            _ => {
+                buf.push(0b11);
+                buf.extend_from_slice(&len.to_le_bytes());
+            }
+        }
+        buf.extend_from_slice(name);
+        buf
+    }
 }
 
 pub trait AccessTracking {
@@ -0,0 +1,160 @@
+//! A client spreading requests over several RPC nodes
+
+use crate::rpc::{call_rpc, call_rpc_batch};
+use crate::traits::common::Error;
+use crate::Result;
+use alloc::string::String;
+use alloc::vec::Vec;
+use primitive_types::H256;
+use scale::{Decode, Encode};
+
+/// How long a quorum request waits for the slowest node
+const QUORUM_TIMEOUT_MS: u64 = 5_000;
+
+/// How an `RpcClient` combines its endpoints
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum RpcMode {
+    /// Tries the endpoints one by one and returns the first successful answer
+    Failover,
+    /// Queries all endpoints concurrently and returns the answer given by at least this many of
+    /// them
+    Quorum(u32),
+}
+
+/// An RPC client holding several endpoints of the same chain
+///
+/// # Example
+/// ```ignore
+/// use pink_subrpc::{RpcClient, RpcMode};
+///
+/// let client = RpcClient::new([
+///     "https://khala-api.phala.network/api",
+///     "https://khala.api.onfinality.io/public",
+///     "https://khala-rpc.dwellir.com",
+/// ])
+/// .with_mode(RpcMode::Quorum(2));
+/// let nonce = client.get_next_nonce(&addr)?;
+/// ```
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct RpcClient {
+    endpoints: Vec<String>,
+    mode: RpcMode,
+}
+
+impl RpcClient {
+    /// Creates a client in failover mode
+    ///
+    /// Duplicate endpoints are dropped, so that a node listed twice can't cast two votes in
+    /// quorum mode.
+    pub fn new<S: Into<String>>(endpoints: impl IntoIterator<Item = S>) -> Self {
+        let mut unique: Vec<String> = Vec::new();
+        for endpoint in endpoints {
+            let endpoint = endpoint.into();
+            if !unique.iter().any(|known| same_endpoint(known, &endpoint)) {
+                unique.push(endpoint);
+            }
+        }
+        Self {
+            endpoints: unique,
+            mode: RpcMode::Failover,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: RpcMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    pub fn mode(&self) -> RpcMode {
+        self.mode
+    }
+
+    /// Gets the storage, see `get_storage`
+    pub fn get_storage(&self, key: &[u8], at: Option<H256>) -> Result<Option<Vec<u8>>> {
+        self.request(crate::get_storage_request(key, at), crate::parse_storage)
+    }
+
+    /// Gets the next nonce of the target account, see `get_next_nonce`
+    ///
+    /// In quorum mode, nodes which haven't seen the latest pending transactions of the account
+    /// may disagree, in which case `QuorumNotReached` is returned.
+    pub fn get_next_nonce(&self, ss58_addr: &str) -> Result<u64> {
+        self.request(
+            crate::get_next_nonce_request(ss58_addr),
+            crate::parse_next_nonce,
+        )
+    }
+
+    /// Gets the block hash at a certain height, see `get_block_hash`
+    pub fn get_block_hash(&self, block_number: Option<u32>) -> Result<H256> {
+        self.request(
+            crate::get_block_hash_request(block_number),
+            crate::parse_block_hash,
+        )
+    }
+
+    /// Submits the signed extrinsic, see `send_transaction`
+    ///
+    /// In quorum mode the extrinsic is submitted to every endpoint. Nodes sharing a transaction
+    /// pool with another endpoint may reject it as already imported, so the threshold should
+    /// account for that.
+    pub fn send_transaction(&self, signed_tx: &[u8]) -> Result<Vec<u8>> {
+        self.request(
+            crate::send_transaction_request(signed_tx),
+            crate::parse_transaction_hash,
+        )
+    }
+
+    fn request<T: PartialEq>(&self, data: Vec<u8>, parse: fn(&[u8]) -> Result<T>) -> Result<T> {
+        match self.mode {
+            RpcMode::Failover => {
+                let mut last_err = Error::SubRPCRequestFailed;
+                for endpoint in &self.endpoints {
+                    match call_rpc(endpoint, data.clone()).and_then(|body| parse(&body)) {
+                        Ok(value) => return Ok(value),
+                        Err(err) => last_err = err,
+                    }
+                }
+                Err(last_err)
+            }
+            RpcMode::Quorum(threshold) => {
+                let threshold = threshold as usize;
+                if threshold == 0 || threshold > self.endpoints.len() {
+                    return Err(Error::QuorumNotReached);
+                }
+                let answers = call_rpc_batch(&self.endpoints, data, QUORUM_TIMEOUT_MS)
+                    .into_iter()
+                    .filter_map(|body| parse(&body.ok()?).ok());
+                let mut tally: Vec<(T, usize)> = Vec::new();
+                for answer in answers {
+                    let index = match tally.iter().position(|(value, _)| *value == answer) {
+                        Some(index) => {
+                            tally[index].1 += 1;
+                            index
+                        }
+                        None => {
+                            tally.push((answer, 1));
+                            tally.len() - 1
+                        }
+                    };
+                    if tally[index].1 >= threshold {
+                        return Ok(tally.swap_remove(index).0);
+                    }
+                }
+                Err(Error::QuorumNotReached)
+            }
+        }
+    }
+}
+
+/// Whether two endpoint URLs point to the same node, ignoring the case and a trailing slash
+fn same_endpoint(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/')
+        .eq_ignore_ascii_case(b.trim_end_matches('/'))
+}
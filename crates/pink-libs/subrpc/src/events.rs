@@ -0,0 +1,184 @@
+//! Types to decode the `System.Events` storage
+//!
+//! The events of a block can't be decoded without knowing the event types of every pallet in the
+//! runtime, so the runtime event enum is left to the caller. It only needs to expose the events
+//! of the System pallet through `AsSystemEvent`:
+//!
+//! ```ignore
+//! #[derive(Decode)]
+//! enum RuntimeEvent {
+//!     #[codec(index = 0)]
+//!     System(SystemEvent),
+//!     #[codec(index = 10)]
+//!     Balances(BalancesEvent),
+//! }
+//!
+//! impl AsSystemEvent for RuntimeEvent {
+//!     fn as_system_event(&self) -> Option<&SystemEvent> {
+//!         match self {
+//!             RuntimeEvent::System(event) => Some(event),
+//!             _ => None,
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! The types are copied from `frame_system` and `sp_runtime` (polkadot-v0.9.37) to avoid a full
+//! dependency on them.
+
+use alloc::vec::Vec;
+use primitive_types::H256;
+use scale::{Decode, Encode};
+
+/// A phase of a block's execution.
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Phase {
+    /// Applying an extrinsic.
+    ApplyExtrinsic(u32),
+    /// Finalizing the block.
+    Finalization,
+    /// Initializing the block.
+    Initialization,
+}
+
+/// Record of an event happening.
+#[derive(Encode, Decode, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct EventRecord<E> {
+    /// The phase of the block it happened in.
+    pub phase: Phase,
+    /// The event itself.
+    pub event: E,
+    /// The list of the topics this event has.
+    pub topics: Vec<H256>,
+}
+
+/// Events of the System pallet.
+#[derive(Encode, Decode, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum SystemEvent {
+    /// An extrinsic completed successfully.
+    ExtrinsicSuccess { dispatch_info: DispatchInfo },
+    /// An extrinsic failed.
+    ExtrinsicFailed {
+        dispatch_error: DispatchError,
+        dispatch_info: DispatchInfo,
+    },
+    /// `:code` was updated.
+    CodeUpdated,
+    /// A new account was created.
+    NewAccount { account: [u8; 32] },
+    /// An account was reaped.
+    KilledAccount { account: [u8; 32] },
+    /// An on-chain remark happened.
+    Remarked { sender: [u8; 32], hash: H256 },
+}
+
+/// Exposes the System pallet events of a runtime event enum.
+pub trait AsSystemEvent {
+    fn as_system_event(&self) -> Option<&SystemEvent>;
+}
+
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct Weight {
+    #[codec(compact)]
+    pub ref_time: u64,
+    #[codec(compact)]
+    pub proof_size: u64,
+}
+
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum DispatchClass {
+    Normal,
+    Operational,
+    Mandatory,
+}
+
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Pays {
+    Yes,
+    No,
+}
+
+/// Weight information of a dispatched call.
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct DispatchInfo {
+    pub weight: Weight,
+    pub class: DispatchClass,
+    pub pays_fee: Pays,
+}
+
+/// Reason why a dispatch call failed.
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum DispatchError {
+    /// Some error occurred. The message is not encoded on chain.
+    Other,
+    /// Failed to lookup some data.
+    CannotLookup,
+    /// A bad origin.
+    BadOrigin,
+    /// A custom error in a module.
+    Module(ModuleError),
+    /// At least one consumer is remaining so the account cannot be destroyed.
+    ConsumerRemaining,
+    /// There are no providers so the account cannot be created.
+    NoProviders,
+    /// There are too many consumers so the account cannot be created.
+    TooManyConsumers,
+    /// An error to do with tokens.
+    Token(TokenError),
+    /// An arithmetic error.
+    Arithmetic(ArithmeticError),
+    /// The number of transactional layers has been reached, or we are not in a transactional
+    /// layer.
+    Transactional(TransactionalError),
+    /// Resources exhausted, e.g. attempt to read/write data which is too large to manipulate.
+    Exhausted,
+    /// The state is corrupt; this is generally not going to fix itself.
+    Corruption,
+    /// Some resource (e.g. a preimage) is unavailable right now. This might fix itself later.
+    Unavailable,
+}
+
+/// A custom error in a module.
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ModuleError {
+    /// Module index, matching the metadata module index.
+    pub index: u8,
+    /// Module specific error value.
+    pub error: [u8; 4],
+}
+
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum TokenError {
+    NoFunds,
+    WouldDie,
+    BelowMinimum,
+    CannotCreate,
+    UnknownAsset,
+    Frozen,
+    Unsupported,
+}
+
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum ArithmeticError {
+    Underflow,
+    Overflow,
+    DivisionByZero,
+}
+
+#[derive(Encode, Decode, Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum TransactionalError {
+    LimitReached,
+    NoLayer,
+}
@@ -13,16 +13,22 @@ use pink_extension::chain_extension::{signing, SigType};
 use pink_json as json;
 use scale::{Compact, Decode, Encode};
 
+mod client;
+pub mod events;
 pub mod hasher;
 mod objects;
 mod primitives;
+mod proof;
 mod rpc;
 mod ss58;
 pub mod storage;
 mod transaction;
 
+pub use client::{RpcClient, RpcMode};
+use events::{AsSystemEvent, DispatchError, EventRecord, Phase, SystemEvent};
 use objects::*;
 pub use primitives::era::Era;
+pub use proof::verify_storage_proof;
 use rpc::call_rpc;
 pub use ss58::{get_ss58addr_version, Ss58Codec};
 use transaction::{MultiAddress, MultiSignature, Signature, UnsignedExtrinsic};
@@ -39,6 +45,8 @@ pub mod traits {
             InvalidSignature,
             Ss58,
             ParseFailed,
+            InvalidProof,
+            QuorumNotReached,
         }
     }
 }
@@ -49,33 +57,97 @@ pub type Result<T> = core::result::Result<T, Error>;
 
 /// Gets the storage from the give RPC node
 pub fn get_storage(rpc_node: &str, key: &[u8], at: Option<H256>) -> Result<Option<Vec<u8>>> {
+    let resp_body = call_rpc(rpc_node, get_storage_request(key, at))?;
+    parse_storage(&resp_body)
+}
+
+pub(crate) fn get_storage_request(key: &[u8], at: Option<H256>) -> Vec<u8> {
     let hex_key = format!("0x{}", hex::encode(key));
     let maybe_hex_at = at.map_or("null".to_string(), |h| format!("\"0x{h:x}\""));
-    let data = format!(
+    format!(
         r#"{{"id":1,"jsonrpc":"2.0","method":"state_getStorage","params":["{hex_key}", {maybe_hex_at}]}}"#
     )
-    .into_bytes();
-    let resp_body = call_rpc(rpc_node, data)?;
-    let resp: GetStorageResponse = json::from_slice(&resp_body).or(Err(Error::InvalidBody))?;
+    .into_bytes()
+}
+
+pub(crate) fn parse_storage(resp_body: &[u8]) -> Result<Option<Vec<u8>>> {
+    let resp: GetStorageResponse = json::from_slice(resp_body).or(Err(Error::InvalidBody))?;
     match resp.result {
         Some(h) => hex::decode(&h[2..]).map(Some).or(Err(Error::InvalidBody)),
         None => Ok(None),
     }
 }
 
+/// Gets the storage read proof of the given keys from the RPC node
+///
+/// The proof is the list of encoded trie nodes needed to look up the keys at block `at`, or at
+/// the best block if `at` is `None`.
+pub fn get_read_proof(rpc_node: &str, keys: &[&[u8]], at: Option<H256>) -> Result<Vec<Vec<u8>>> {
+    let hex_keys = keys
+        .iter()
+        .map(|key| format!("\"0x{}\"", hex::encode(key)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let maybe_hex_at = at.map_or("null".to_string(), |h| format!("\"0x{h:x}\""));
+    let data = format!(
+        r#"{{"id":1,"jsonrpc":"2.0","method":"state_getReadProof","params":[[{hex_keys}], {maybe_hex_at}]}}"#
+    )
+    .into_bytes();
+    let resp_body = call_rpc(rpc_node, data)?;
+    let resp: ReadProofResponse = json::from_slice(&resp_body).or(Err(Error::InvalidBody))?;
+    resp.result
+        .proof
+        .iter()
+        .map(|node| {
+            let node = node.strip_prefix("0x").ok_or(Error::InvalidBody)?;
+            hex::decode(node).or(Err(Error::InvalidBody))
+        })
+        .collect()
+}
+
+/// Gets the storage from the given RPC node and verifies it with a storage proof
+///
+/// The proof is checked against the state root in the header of block `at`, and the header is
+/// checked to hash to `at`, so a dishonest node can't forge the value of a block the caller
+/// trusts. If `at` is `None` the best block hash is taken from the same node, which then has to
+/// be trusted as well.
+pub fn get_storage_with_proof(
+    rpc_node: &str,
+    key: &[u8],
+    at: Option<H256>,
+) -> Result<Option<Vec<u8>>> {
+    // Pin the block so that the header and the proof are taken from the same state
+    let at = match at {
+        Some(hash) => hash,
+        None => get_block_hash(rpc_node, None)?,
+    };
+    let (header, hash) = get_header_with_hash(rpc_node, Some(at))?;
+    if hash != at {
+        return Err(Error::InvalidProof);
+    }
+    let proof = get_read_proof(rpc_node, &[key], Some(at))?;
+    verify_storage_proof(H256(header.state_root), key, &proof)
+}
+
 /// Gets the next nonce of the target account
 ///
 /// Nonce represents how many transactions the account has successfully issued
 /// TODO: simplify
 pub fn get_next_nonce(rpc_node: &str, ss58_addr: &str) -> core::result::Result<u64, Error> {
+    let resp_body = call_rpc(rpc_node, get_next_nonce_request(ss58_addr))?;
+    parse_next_nonce(&resp_body)
+}
+
+pub(crate) fn get_next_nonce_request(ss58_addr: &str) -> Vec<u8> {
     // TODO: can we contruct the json object using serde_json_core?
-    let data = format!(
+    format!(
         r#"{{"id":1,"jsonrpc":"2.0","method":"system_accountNextIndex","params":["{ss58_addr}"]}}"#
     )
-    .into_bytes();
-    let resp_body = call_rpc(rpc_node, data)?;
+    .into_bytes()
+}
 
-    let next_nonce: NextNonce = json::from_slice(&resp_body).or(Err(Error::InvalidBody))?;
+pub(crate) fn parse_next_nonce(resp_body: &[u8]) -> Result<u64> {
+    let next_nonce: NextNonce = json::from_slice(resp_body).or(Err(Error::InvalidBody))?;
 
     let next_nonce_ok = NextNonceOk {
         next_nonce: next_nonce.result,
@@ -136,13 +208,18 @@ pub fn get_block_hash(
     rpc_node: &str,
     block_number: Option<u32>,
 ) -> core::result::Result<H256, Error> {
+    let resp_body = call_rpc(rpc_node, get_block_hash_request(block_number))?;
+    parse_block_hash(&resp_body)
+}
+
+pub(crate) fn get_block_hash_request(block_number: Option<u32>) -> Vec<u8> {
     let param = block_number.map_or("null".to_string(), |n| format!("{n}"));
-    let data = format!(
-        r#"{{"id":1, "jsonrpc":"2.0", "method": "chain_getBlockHash","params":[{param}]}}"#
-    )
-    .into_bytes();
-    let resp_body = call_rpc(rpc_node, data)?;
-    let genesis_hash: GenesisHash = json::from_slice(&resp_body).or(Err(Error::InvalidBody))?;
+    format!(r#"{{"id":1, "jsonrpc":"2.0", "method": "chain_getBlockHash","params":[{param}]}}"#)
+        .into_bytes()
+}
+
+pub(crate) fn parse_block_hash(resp_body: &[u8]) -> Result<H256> {
+    let genesis_hash: GenesisHash = json::from_slice(resp_body).or(Err(Error::InvalidBody))?;
     // bypass prefix 0x
     let genesis_hash_result = &genesis_hash.result[2..];
     let decoded_hash = hex::decode(genesis_hash_result).or(Err(Error::InvalidBody))?;
@@ -156,15 +233,19 @@ pub fn get_header(
     rpc_node: &str,
     block_hash: Option<H256>,
 ) -> core::result::Result<BlockHeaderOk, Error> {
+    get_header_with_hash(rpc_node, block_hash).map(|(header, _)| header)
+}
+
+/// Gets the block header along with its hash, which is computed locally rather than trusted
+/// from the RPC node
+fn get_header_with_hash(rpc_node: &str, block_hash: Option<H256>) -> Result<(BlockHeaderOk, H256)> {
     let param = block_hash.map_or("null".to_string(), |h| format!("\"0x{h:x}\""));
 
     let data =
         format!(r#"{{"id":1, "jsonrpc":"2.0", "method": "chain_getHeader","params":[{param}]}}"#)
             .into_bytes();
     let resp_body = call_rpc(rpc_node, data)?;
-    let header: BlockHeader = json::from_slice(&resp_body)
-        .or(Err(Error::InvalidBody))
-        .unwrap();
+    let header: BlockHeader = json::from_slice(&resp_body).or(Err(Error::InvalidBody))?;
     let header_result = header.result;
     let decoded_parent_hash =
         hex::decode(&header_result.parent_hash[2..]).or(Err(Error::InvalidBody))?;
@@ -172,14 +253,25 @@ pub fn get_header(
         hex::decode(&header_result.state_root[2..]).or(Err(Error::InvalidBody))?;
     let decoded_extrinsics_root =
         hex::decode(&header_result.extrinsics_root[2..]).or(Err(Error::InvalidBody))?;
-    Ok(BlockHeaderOk {
+    let header = BlockHeaderOk {
         parent_hash: decoded_parent_hash.try_into().or(Err(Error::InvalidBody))?,
         number: u32::from_str_radix(&header_result.number[2..], 16).expect("block number overflow"),
         state_root: decoded_state_root.try_into().or(Err(Error::InvalidBody))?,
         extrinsics_root: decoded_extrinsics_root
             .try_into()
             .or(Err(Error::InvalidBody))?,
-    })
+    };
+    // The digest items are already SCALE encoded, so the header encoding is the fields above
+    // followed by the item count and the items
+    let logs = &header_result.digest.logs;
+    let mut encoded = header.encode();
+    Compact(logs.len() as u32).encode_to(&mut encoded);
+    for log in logs {
+        let log = log.strip_prefix("0x").ok_or(Error::InvalidBody)?;
+        encoded.extend(hex::decode(log).or(Err(Error::InvalidBody))?);
+    }
+    let hash = H256(sp_core_hashing::blake2_256(&encoded));
+    Ok((header, hash))
 }
 
 #[derive(Default, Encode, Decode, Clone, Debug, PartialEq)]
@@ -193,17 +285,26 @@ pub struct ExtraParam {
     pub era: Option<Era>,
 }
 
-fn compute_era(block_number: u64) -> core::result::Result<Era, Error> {
-    // The transaction longevity, should be a power of two between 4 and 65536. unit: bloc
-    let longevity = 4;
-    let period = longevity;
-    let phase = block_number % period;
-    Ok(Era::Mortal(period, phase))
+/// The default transaction longevity in blocks
+const DEFAULT_ERA_PERIOD: u64 = 4;
+
+/// Creates a mortal era valid for `period` blocks from the best block
+///
+/// Returns the era and the hash of the block it begins at, which must be used as the
+/// `era_checkpoint` of `create_transaction_ext`.
+pub fn create_mortal_era(rpc_node: &str, period: u64) -> Result<(Era, H256)> {
+    let best_number = get_header(rpc_node, None)?.number as u64;
+    let era = Era::mortal(period, best_number);
+    let checkpoint = get_block_hash(rpc_node, Some(era.birth(best_number) as u32))?;
+    Ok((era, checkpoint))
 }
 
 /// Creates an extrinsic
 ///
 /// An extended version of `create_transaction`, fine-grain
+///
+/// `era_checkpoint` must be the genesis hash for `Era::Immortal`, or the hash of the block at
+/// `era.birth(..)` for a mortal era, see `create_mortal_era`.
 #[allow(clippy::too_many_arguments)]
 pub fn create_transaction_ext<T: Encode>(
     signer: &[u8; 32],
@@ -293,11 +394,14 @@ pub fn create_transaction<T: Encode>(
     let transaction_version = runtime_version.transaction_version;
     let (era_checkpoint, era) = match extra.era {
         Some(Era::Immortal) => (genesis_hash, Era::Immortal),
-        _ => {
-            let latest_block_hash = get_block_hash(rpc_node, None)?;
-            let header = get_header(rpc_node, Some(latest_block_hash))?;
-            let era = extra.era.unwrap_or(compute_era(header.number as u64)?);
-            (latest_block_hash.into(), era)
+        Some(era) => {
+            let best_number = get_header(rpc_node, None)?.number as u64;
+            let checkpoint = get_block_hash(rpc_node, Some(era.birth(best_number) as u32))?;
+            (checkpoint.into(), era)
+        }
+        None => {
+            let (era, checkpoint) = create_mortal_era(rpc_node, DEFAULT_ERA_PERIOD)?;
+            (checkpoint.into(), era)
         }
     };
     let tip = extra.tip;
@@ -326,15 +430,130 @@ pub fn create_transaction<T: Encode>(
 }
 
 pub fn send_transaction(rpc_node: &str, signed_tx: &[u8]) -> core::result::Result<Vec<u8>, Error> {
+    let resp_body = call_rpc(rpc_node, send_transaction_request(signed_tx))?;
+    parse_transaction_hash(&resp_body)
+}
+
+pub(crate) fn send_transaction_request(signed_tx: &[u8]) -> Vec<u8> {
     let tx_hex = hex::encode(signed_tx);
+    format!(r#"{{"id":1,"jsonrpc":"2.0","method":"author_submitExtrinsic","params":["{tx_hex}"]}}"#)
+        .into_bytes()
+}
+
+pub(crate) fn parse_transaction_hash(resp_body: &[u8]) -> Result<Vec<u8>> {
+    let resp: TransactionResponse = json::from_slice(resp_body).or(Err(Error::InvalidBody))?;
+
+    hex::decode(&resp.result[2..]).or(Err(Error::InvalidBody))
+}
+
+/// Gets the encoded extrinsics of the given block
+pub fn get_block_extrinsics(rpc_node: &str, block_hash: H256) -> Result<Vec<Vec<u8>>> {
     let data = format!(
-        r#"{{"id":1,"jsonrpc":"2.0","method":"author_submitExtrinsic","params":["{tx_hex}"]}}"#
+        r#"{{"id":1,"jsonrpc":"2.0","method":"chain_getBlock","params":["0x{block_hash:x}"]}}"#
     )
     .into_bytes();
     let resp_body = call_rpc(rpc_node, data)?;
-    let resp: TransactionResponse = json::from_slice(&resp_body).or(Err(Error::InvalidBody))?;
+    let resp: BlockResponse = json::from_slice(&resp_body).or(Err(Error::InvalidBody))?;
+    let block = resp.result.ok_or(Error::InvalidBody)?.block;
+    block
+        .extrinsics
+        .iter()
+        .map(|xt| {
+            let xt = xt.strip_prefix("0x").ok_or(Error::InvalidBody)?;
+            hex::decode(xt).or(Err(Error::InvalidBody))
+        })
+        .collect()
+}
 
-    hex::decode(&resp.result[2..]).or(Err(Error::InvalidBody))
+/// Gets the events emitted in the given block
+///
+/// `E` is the runtime event type of the chain, see the `events` module.
+pub fn get_events<E: Decode>(rpc_node: &str, block_hash: H256) -> Result<Vec<EventRecord<E>>> {
+    let key = storage::storage_prefix("System", "Events");
+    match get_storage(rpc_node, &key, Some(block_hash))? {
+        Some(events) => Decode::decode(&mut &events[..]).or(Err(Error::ParseFailed)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The maximum number of blocks scanned by one `get_extrinsic_status` call
+const MAX_SCANNED_BLOCKS: u64 = 16;
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum ExtrinsicStatus {
+    /// The extrinsic isn't found up to `next_block` (exclusive), but may still be included
+    Pending { next_block: u32 },
+    /// The era of the extrinsic has ended without it being included, so it can never be
+    /// included and it's safe to submit a new one
+    Expired,
+    /// The extrinsic is included in a block
+    InBlock {
+        block_number: u32,
+        block_hash: H256,
+        index: u32,
+        /// The outcome reported by `ExtrinsicSuccess` or `ExtrinsicFailed`
+        result: core::result::Result<(), DispatchError>,
+    },
+}
+
+/// Looks for an extrinsic in the chain and gets its outcome
+///
+/// Scans the blocks from `from_block` up to the best block, or to the end of `era` if it comes
+/// first, for the extrinsic with the hash `tx_hash` returned by `send_transaction`.
+/// `from_block` should be the best block when the extrinsic was submitted, and `era` the era it
+/// was created with. At most 16 blocks are scanned per call, so keep calling with the returned
+/// `next_block` while the status is `Pending`.
+///
+/// `E` is the runtime event type of the chain, see the `events` module.
+pub fn get_extrinsic_status<E: Decode + AsSystemEvent>(
+    rpc_node: &str,
+    tx_hash: &[u8],
+    from_block: u32,
+    era: Era,
+) -> Result<ExtrinsicStatus> {
+    let best_number = get_header(rpc_node, None)?.number as u64;
+    let death = era.death(from_block as u64);
+    let last = best_number
+        .min(death - 1)
+        .min(from_block as u64 + MAX_SCANNED_BLOCKS - 1);
+
+    let mut next_block = from_block;
+    while next_block as u64 <= last {
+        let block_number = next_block;
+        next_block += 1;
+
+        let block_hash = get_block_hash(rpc_node, Some(block_number))?;
+        let extrinsics = get_block_extrinsics(rpc_node, block_hash)?;
+        let Some(index) = extrinsics
+            .iter()
+            .position(|xt| sp_core_hashing::blake2_256(xt)[..] == *tx_hash)
+        else {
+            continue;
+        };
+        let index = index as u32;
+        let result = get_events::<E>(rpc_node, block_hash)?
+            .iter()
+            .filter(|record| record.phase == Phase::ApplyExtrinsic(index))
+            .find_map(|record| match record.event.as_system_event()? {
+                SystemEvent::ExtrinsicSuccess { .. } => Some(Ok(())),
+                SystemEvent::ExtrinsicFailed { dispatch_error, .. } => Some(Err(*dispatch_error)),
+                _ => None,
+            })
+            .ok_or(Error::ParseFailed)?;
+        return Ok(ExtrinsicStatus::InBlock {
+            block_number,
+            block_hash,
+            index,
+            result,
+        });
+    }
+
+    if next_block as u64 >= death {
+        Ok(ExtrinsicStatus::Expired)
+    } else {
+        Ok(ExtrinsicStatus::Pending { next_block })
+    }
 }
 
 #[cfg(test)]
@@ -528,6 +747,239 @@ mod tests {
         dbg!(hex::encode(tx_id));
     }
 
+    #[test]
+    fn can_get_storage_with_proof() {
+        use pink_extension::chain_extension::{mock, HttpRequest, HttpResponse};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        // A trie with `0x1234 => "hello"` and `0x1256 => 0x0001..27`, see `proof::tests`
+        let header = r#"{"jsonrpc":"2.0","result":{"parentHash":"0x0000000000000000000000000000000000000000000000000000000000000000","number":"0x10","stateRoot":"0xc32a6ff1b0e3e9fce3b61ad996408f3095c4e2ede6dc5e123a1b06f8e3ab5cba","extrinsicsRoot":"0x0000000000000000000000000000000000000000000000000000000000000000","digest":{"logs":["0x0661757261200100000000000000"]}},"id":1}"#;
+        let proof = r#"{"jsonrpc":"2.0","result":{"at":"0x0101010101010101010101010101010101010101010101010101010101010101","proof":["0x821228002041041468656c6c6f80ec2d1251f1d3731f3bac1fadd1627f33a362be7308d4ed1e0208ff078c3adac5"]},"id":1}"#;
+        mock::mock_http_request(move |request: HttpRequest| {
+            let body = String::from_utf8(request.body).unwrap();
+            let resp = if body.contains("chain_getHeader") {
+                header
+            } else if body.contains("state_getReadProof") {
+                // The proof must be taken at the block the header was checked for
+                assert!(body.contains(r#"], "0x5e240c05"#));
+                proof
+            } else {
+                panic!("unexpected request: {body}");
+            };
+            HttpResponse::ok(resp.as_bytes().to_vec())
+        });
+
+        // The blake2_256 hash of the header above
+        let at = Some(H256(hex!(
+            "5e240c051a7f884bd4251bd8fc437707b734b3d9a420aa0904e2cc937a933b97"
+        )));
+        assert_eq!(
+            get_storage_with_proof("http://localhost:9933", &hex!("1234"), at),
+            Ok(Some(b"hello".to_vec()))
+        );
+        assert_eq!(
+            get_storage_with_proof("http://localhost:9933", &hex!("1274"), at),
+            Ok(None)
+        );
+        // The proof doesn't contain the node holding this key
+        assert_eq!(
+            get_storage_with_proof("http://localhost:9933", &hex!("1256"), at),
+            Err(Error::InvalidProof)
+        );
+        // The node returns the header of another block
+        assert_eq!(
+            get_storage_with_proof("http://localhost:9933", &hex!("1234"), Some(H256([1; 32]))),
+            Err(Error::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn rpc_client_fails_over() {
+        use pink_extension::chain_extension::{mock, HttpRequest, HttpResponse};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        mock::mock_http_request(|request: HttpRequest| {
+            if request.url == "http://bad" {
+                return HttpResponse::not_found();
+            }
+            HttpResponse::ok(br#"{"jsonrpc":"2.0","result":42,"id":1}"#.to_vec())
+        });
+
+        let client = RpcClient::new(["http://bad", "http://good"]);
+        assert_eq!(client.get_next_nonce("addr"), Ok(42));
+        let client = RpcClient::new(["http://bad"]);
+        assert_eq!(
+            client.get_next_nonce("addr"),
+            Err(Error::SubRPCRequestFailed)
+        );
+    }
+
+    #[test]
+    fn rpc_client_reaches_quorum() {
+        use pink_extension::chain_extension::{mock, HttpRequest, HttpResponse};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+        mock::mock_batch_http_request(|requests: Vec<HttpRequest>, _timeout_ms| {
+            Ok(requests
+                .iter()
+                .map(|request| {
+                    let hash = match request.url.as_str() {
+                        "http://liar" => {
+                            "2222222222222222222222222222222222222222222222222222222222222222"
+                        }
+                        "http://down" => return Ok(HttpResponse::not_found()),
+                        _ => "1111111111111111111111111111111111111111111111111111111111111111",
+                    };
+                    let body = format!(r#"{{"jsonrpc":"2.0","result":"0x{hash}","id":1}}"#);
+                    Ok(HttpResponse::ok(body.into_bytes()))
+                })
+                .collect())
+        });
+
+        let client = RpcClient::new(["http://liar", "http://down", "http://a", "http://b"])
+            .with_mode(RpcMode::Quorum(2));
+        assert_eq!(client.get_block_hash(None), Ok(H256([0x11; 32])));
+        let client = client.with_mode(RpcMode::Quorum(3));
+        assert_eq!(client.get_block_hash(None), Err(Error::QuorumNotReached));
+        let client = client.with_mode(RpcMode::Quorum(5));
+        assert_eq!(client.get_block_hash(None), Err(Error::QuorumNotReached));
+
+        // A node listed several times only votes once
+        let client = RpcClient::new(["http://liar", "http://liar/", "HTTP://LIAR", "http://a"])
+            .with_mode(RpcMode::Quorum(2));
+        assert_eq!(client.endpoints(), ["http://liar", "http://a"]);
+        assert_eq!(client.get_block_hash(None), Err(Error::QuorumNotReached));
+    }
+
+    #[test]
+    fn can_create_mortal_era() {
+        let era = Era::mortal(64, 1000);
+        assert_eq!(era, Era::Mortal(64, 40));
+        assert_eq!(era.birth(1000), 1000);
+        assert_eq!(era.birth(1010), 1000);
+        assert_eq!(era.death(1010), 1064);
+
+        // The period is rounded up and the phase quantized for long periods
+        let era = Era::mortal(5000, 10001);
+        assert_eq!(era, Era::Mortal(8192, 1808));
+        assert_eq!(era.birth(10001), 10000);
+        assert_eq!(era.death(10001), 18192);
+        assert_eq!(Era::decode(&mut &era.encode()[..]), Ok(era));
+
+        assert_eq!(Era::mortal(1, 7), Era::Mortal(4, 3));
+        assert_eq!(Era::Immortal.death(7), u64::MAX);
+    }
+
+    #[test]
+    fn can_get_extrinsic_status() {
+        use events::*;
+        use pink_extension::chain_extension::{mock, HttpRequest, HttpResponse};
+
+        #[derive(Encode, Decode, Debug)]
+        enum RuntimeEvent {
+            #[codec(index = 0)]
+            System(SystemEvent),
+            #[codec(index = 3)]
+            Balances(u8),
+        }
+
+        impl AsSystemEvent for RuntimeEvent {
+            fn as_system_event(&self) -> Option<&SystemEvent> {
+                match self {
+                    RuntimeEvent::System(event) => Some(event),
+                    _ => None,
+                }
+            }
+        }
+
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let our_xt = hex!("0c010203").to_vec();
+        let tx_hash = sp_core_hashing::blake2_256(&our_xt);
+        let dispatch_info = DispatchInfo {
+            weight: Weight {
+                ref_time: 1000,
+                proof_size: 0,
+            },
+            class: DispatchClass::Normal,
+            pays_fee: Pays::Yes,
+        };
+        let module_error = DispatchError::Module(ModuleError {
+            index: 3,
+            error: [2, 0, 0, 0],
+        });
+        let record = |phase, event| EventRecord {
+            phase,
+            event,
+            topics: vec![],
+        };
+        let events = vec![
+            record(
+                Phase::ApplyExtrinsic(0),
+                RuntimeEvent::System(SystemEvent::ExtrinsicSuccess { dispatch_info }),
+            ),
+            record(Phase::ApplyExtrinsic(1), RuntimeEvent::Balances(7)),
+            record(
+                Phase::ApplyExtrinsic(1),
+                RuntimeEvent::System(SystemEvent::ExtrinsicFailed {
+                    dispatch_error: module_error,
+                    dispatch_info,
+                }),
+            ),
+        ];
+        let events_hex = hex::encode(events.encode());
+
+        // Blocks 100..=102 exist, our extrinsic is the second one in block 102
+        mock::mock_http_request(move |request: HttpRequest| {
+            let body = String::from_utf8(request.body).unwrap();
+            let result = if body.contains("chain_getHeader") {
+                r#"{"parentHash":"0x0000000000000000000000000000000000000000000000000000000000000000","number":"0x66","stateRoot":"0x0000000000000000000000000000000000000000000000000000000000000000","extrinsicsRoot":"0x0000000000000000000000000000000000000000000000000000000000000000"}"#.to_string()
+            } else if body.contains("chain_getBlockHash") {
+                let number: u8 = if body.contains("[100]") {
+                    100
+                } else if body.contains("[101]") {
+                    101
+                } else {
+                    102
+                };
+                format!(r#""0x{}""#, hex::encode([number; 32]))
+            } else if body.contains("chain_getBlock") {
+                let xts = if body.contains(&hex::encode([102u8; 32])) {
+                    r#""0x0c000000","0x0c010203""#
+                } else {
+                    r#""0x0c000000""#
+                };
+                format!(r#"{{"block":{{"extrinsics":[{xts}]}},"justifications":null}}"#)
+            } else if body.contains("state_getStorage") {
+                format!(r#""0x{events_hex}""#)
+            } else {
+                panic!("unexpected request: {body}");
+            };
+            let resp = format!(r#"{{"jsonrpc":"2.0","result":{result},"id":1}}"#);
+            HttpResponse::ok(resp.into_bytes())
+        });
+
+        let rpc_node = "http://localhost:9933";
+        let era = Era::mortal(4, 100);
+        assert_eq!(
+            get_extrinsic_status::<RuntimeEvent>(rpc_node, &tx_hash, 100, era),
+            Ok(ExtrinsicStatus::InBlock {
+                block_number: 102,
+                block_hash: H256([102; 32]),
+                index: 1,
+                result: Err(module_error),
+            })
+        );
+        // Not in blocks 100 and 101 and the era lasts until 103
+        assert_eq!(
+            get_extrinsic_status::<RuntimeEvent>(rpc_node, &[0; 32], 100, era),
+            Ok(ExtrinsicStatus::Pending { next_block: 103 })
+        );
+        // The era of a transaction created at block 96 ended at block 100
+        assert_eq!(
+            get_extrinsic_status::<RuntimeEvent>(rpc_node, &[0; 32], 99, Era::mortal(4, 96)),
+            Ok(ExtrinsicStatus::Expired)
+        );
+    }
+
     #[test]
     #[ignore = "this is very expensive so we don't test it often"]
     fn test_read_storage() {
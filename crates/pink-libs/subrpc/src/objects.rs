@@ -22,6 +22,16 @@ pub struct BlockHeaderResult<'a> {
     pub(crate) state_root: &'a str,
     #[serde(alias = "extrinsicsRoot")]
     pub(crate) extrinsics_root: &'a str,
+    #[serde(borrow, default)]
+    pub(crate) digest: BlockDigest<'a>,
+}
+
+#[derive(Default, Deserialize, Encode, Clone, Debug, PartialEq)]
+#[serde(bound(deserialize = "alloc::vec::Vec<&'a str>: Deserialize<'de>"))]
+pub struct BlockDigest<'a> {
+    /// The hex encoded `DigestItem`s
+    #[serde(borrow)]
+    pub(crate) logs: Vec<&'a str>,
 }
 
 #[derive(Encode, Decode, Clone, Debug, PartialEq)]
@@ -112,6 +122,23 @@ pub struct GetStorageResponse<'a> {
     pub(crate) id: u32,
 }
 
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct ReadProofResponse<'a> {
+    pub(crate) jsonrpc: &'a str,
+    #[serde(borrow)]
+    pub(crate) result: ReadProofResult<'a>,
+    pub(crate) id: u32,
+}
+
+#[derive(Deserialize, Encode, Clone, Debug, PartialEq)]
+#[serde(bound(deserialize = "alloc::vec::Vec<&'a str>: Deserialize<'de>"))]
+pub struct ReadProofResult<'a> {
+    pub(crate) at: &'a str,
+    #[serde(borrow)]
+    pub(crate) proof: Vec<&'a str>,
+}
+
 /// Wraps an already encoded byte vector, prevents being encoded as a raw byte vector as part of
 /// the transaction payload
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -122,3 +149,25 @@ impl scale::Encode for Encoded {
         self.0.to_owned()
     }
 }
+
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct BlockResponse<'a> {
+    pub(crate) jsonrpc: &'a str,
+    #[serde(borrow)]
+    pub(crate) result: Option<SignedBlock<'a>>,
+    pub(crate) id: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SignedBlock<'a> {
+    #[serde(borrow)]
+    pub(crate) block: Block<'a>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(bound(deserialize = "alloc::vec::Vec<&'a str>: Deserialize<'de>"))]
+pub struct Block<'a> {
+    #[serde(borrow)]
+    pub(crate) extrinsics: Vec<&'a str>,
+}
@@ -22,6 +22,41 @@ pub enum Era {
     /// of `system` module.
     Mortal(Period, Phase),
 }
+impl Era {
+    /// Creates a mortal era valid for `period` blocks, starting at block `current`
+    ///
+    /// The period is rounded up to a power of two between 4 and 65536, and the phase is
+    /// quantized, so the era may actually begin a few blocks before `current`. The transaction
+    /// must be signed with the hash of the block returned by `birth(current)`.
+    pub fn mortal(period: u64, current: u64) -> Self {
+        let period = period
+            .checked_next_power_of_two()
+            .unwrap_or(1 << 16)
+            .clamp(4, 1 << 16);
+        let phase = current % period;
+        let quantize_factor = (period >> 12).max(1);
+        let quantized_phase = phase / quantize_factor * quantize_factor;
+
+        Self::Mortal(period, quantized_phase)
+    }
+
+    /// Gets the block number of the start of the era at block `current`
+    pub fn birth(self, current: u64) -> u64 {
+        match self {
+            Self::Immortal => 0,
+            Self::Mortal(period, phase) => (current.max(phase) - phase) / period * period + phase,
+        }
+    }
+
+    /// Gets the block number of the first block at which the era has ended
+    pub fn death(self, current: u64) -> u64 {
+        match self {
+            Self::Immortal => u64::MAX,
+            Self::Mortal(period, _) => self.birth(current) + period,
+        }
+    }
+}
+
 impl Encode for Era {
     fn encode_to<T: Output + ?Sized>(&self, output: &mut T) {
         match self {
@@ -0,0 +1,371 @@
+//! Verification of storage read proofs as returned by `state_getReadProof`.
+//!
+//! A read proof is the set of trie nodes visited when looking up the keys, so the lookup can be
+//! replayed against a trusted state root. Only the Substrate trie layout with blake2-256 hashing
+//! (state version 0 and 1) is supported.
+
+use crate::traits::common::Error;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use primitive_types::H256;
+use scale::{Compact, Decode};
+
+const HASH_LEN: usize = 32;
+
+const EMPTY_TRIE: u8 = 0;
+const LEAF_PREFIX_MASK: u8 = 0b01 << 6;
+const BRANCH_WITHOUT_MASK: u8 = 0b10 << 6;
+const BRANCH_WITH_MASK: u8 = 0b11 << 6;
+const ALT_HASHING_LEAF_PREFIX_MASK: u8 = 0b001 << 5;
+const ALT_HASHING_BRANCH_WITH_MASK: u8 = 0b0001 << 4;
+
+enum NodeHeader {
+    Null,
+    Branch { has_value: bool, nibbles: usize },
+    Leaf { nibbles: usize },
+    HashedValueBranch { nibbles: usize },
+    HashedValueLeaf { nibbles: usize },
+}
+
+enum Value<'a> {
+    Inline(&'a [u8]),
+    Hashed(&'a [u8]),
+}
+
+enum Child<'a> {
+    Hash(&'a [u8]),
+    Inline(&'a [u8]),
+}
+
+enum Node<'a> {
+    Empty,
+    Leaf {
+        partial: NibbleSlice<'a>,
+        value: Value<'a>,
+    },
+    Branch {
+        partial: NibbleSlice<'a>,
+        value: Option<Value<'a>>,
+        children: [Option<Child<'a>>; 16],
+    },
+}
+
+/// A nibble sequence packed in bytes, starting at nibble `offset`.
+#[derive(Clone, Copy)]
+struct NibbleSlice<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> NibbleSlice<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn len(&self) -> usize {
+        self.data.len() * 2 - self.offset
+    }
+
+    fn at(&self, i: usize) -> u8 {
+        let i = self.offset + i;
+        if i % 2 == 0 {
+            self.data[i / 2] >> 4
+        } else {
+            self.data[i / 2] & 0x0f
+        }
+    }
+
+    fn mid(&self, n: usize) -> Self {
+        Self {
+            data: self.data,
+            offset: self.offset + n,
+        }
+    }
+
+    fn starts_with(&self, prefix: &NibbleSlice) -> bool {
+        self.len() >= prefix.len() && (0..prefix.len()).all(|i| self.at(i) == prefix.at(i))
+    }
+
+    fn equals(&self, other: &NibbleSlice) -> bool {
+        self.len() == other.len() && self.starts_with(other)
+    }
+}
+
+fn read_byte(input: &mut &[u8]) -> Result<u8, Error> {
+    let (byte, rest) = input.split_first().ok_or(Error::InvalidProof)?;
+    *input = rest;
+    Ok(*byte)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+    if input.len() < len {
+        return Err(Error::InvalidProof);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn read_compact_bytes<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let len = Compact::<u32>::decode(input)
+        .or(Err(Error::InvalidProof))?
+        .0;
+    read_bytes(input, len as usize)
+}
+
+fn decode_size(first: u8, input: &mut &[u8], prefix_bits: u32) -> Result<usize, Error> {
+    let max_value = 255u8 >> prefix_bits;
+    let mut result = (first & max_value) as usize;
+    if result < max_value as usize {
+        return Ok(result);
+    }
+    result -= 1;
+    loop {
+        let n = read_byte(input)? as usize;
+        if n < 255 {
+            return Ok(result + n + 1);
+        }
+        result += 255;
+    }
+}
+
+fn decode_header(input: &mut &[u8]) -> Result<NodeHeader, Error> {
+    let first = read_byte(input)?;
+    if first == EMPTY_TRIE {
+        return Ok(NodeHeader::Null);
+    }
+    let header = match first & (0b11 << 6) {
+        LEAF_PREFIX_MASK => NodeHeader::Leaf {
+            nibbles: decode_size(first, input, 2)?,
+        },
+        BRANCH_WITH_MASK => NodeHeader::Branch {
+            has_value: true,
+            nibbles: decode_size(first, input, 2)?,
+        },
+        BRANCH_WITHOUT_MASK => NodeHeader::Branch {
+            has_value: false,
+            nibbles: decode_size(first, input, 2)?,
+        },
+        _ => {
+            if first & (0b111 << 5) == ALT_HASHING_LEAF_PREFIX_MASK {
+                NodeHeader::HashedValueLeaf {
+                    nibbles: decode_size(first, input, 3)?,
+                }
+            } else if first & (0b1111 << 4) == ALT_HASHING_BRANCH_WITH_MASK {
+                NodeHeader::HashedValueBranch {
+                    nibbles: decode_size(first, input, 4)?,
+                }
+            } else {
+                return Err(Error::InvalidProof);
+            }
+        }
+    };
+    Ok(header)
+}
+
+fn decode_partial<'a>(input: &mut &'a [u8], nibbles: usize) -> Result<NibbleSlice<'a>, Error> {
+    let data = read_bytes(input, (nibbles + 1) / 2)?;
+    let offset = nibbles % 2;
+    // The padding nibble of an odd length partial key must be zero
+    if offset == 1 && data[0] >> 4 != 0 {
+        return Err(Error::InvalidProof);
+    }
+    Ok(NibbleSlice { data, offset })
+}
+
+fn decode_node(mut input: &[u8]) -> Result<Node, Error> {
+    let input = &mut input;
+    let node = match decode_header(input)? {
+        NodeHeader::Null => Node::Empty,
+        NodeHeader::Leaf { nibbles } => Node::Leaf {
+            partial: decode_partial(input, nibbles)?,
+            value: Value::Inline(read_compact_bytes(input)?),
+        },
+        NodeHeader::HashedValueLeaf { nibbles } => Node::Leaf {
+            partial: decode_partial(input, nibbles)?,
+            value: Value::Hashed(read_bytes(input, HASH_LEN)?),
+        },
+        NodeHeader::Branch { has_value, nibbles } => {
+            let partial = decode_partial(input, nibbles)?;
+            let bitmap = u16::decode(input).or(Err(Error::InvalidProof))?;
+            let value = if has_value {
+                Some(Value::Inline(read_compact_bytes(input)?))
+            } else {
+                None
+            };
+            Node::Branch {
+                partial,
+                value,
+                children: decode_children(input, bitmap)?,
+            }
+        }
+        NodeHeader::HashedValueBranch { nibbles } => {
+            let partial = decode_partial(input, nibbles)?;
+            let bitmap = u16::decode(input).or(Err(Error::InvalidProof))?;
+            let value = Some(Value::Hashed(read_bytes(input, HASH_LEN)?));
+            Node::Branch {
+                partial,
+                value,
+                children: decode_children(input, bitmap)?,
+            }
+        }
+    };
+    Ok(node)
+}
+
+fn decode_children<'a>(
+    input: &mut &'a [u8],
+    bitmap: u16,
+) -> Result<[Option<Child<'a>>; 16], Error> {
+    let mut children: [Option<Child>; 16] = Default::default();
+    for (i, child) in children.iter_mut().enumerate() {
+        if bitmap & (1 << i) == 0 {
+            continue;
+        }
+        let data = read_compact_bytes(input)?;
+        *child = Some(if data.len() == HASH_LEN {
+            Child::Hash(data)
+        } else {
+            Child::Inline(data)
+        });
+    }
+    Ok(children)
+}
+
+/// Verifies a storage read proof against `state_root` and returns the value of `key`.
+///
+/// Returns `Ok(None)` if the proof shows that the key doesn't exist, and `Err(InvalidProof)` if the
+/// proof is malformed or doesn't contain the nodes needed to look up the key.
+pub fn verify_storage_proof(
+    state_root: H256,
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, Error> {
+    let db: BTreeMap<[u8; 32], &[u8]> = proof
+        .iter()
+        .map(|node| (sp_core_hashing::blake2_256(node), node.as_slice()))
+        .collect();
+
+    let mut key = NibbleSlice::new(key);
+    let mut node_data = lookup(&db, state_root.as_bytes())?;
+    loop {
+        let child = match decode_node(node_data)? {
+            Node::Empty => return Ok(None),
+            Node::Leaf { partial, value } => {
+                if key.equals(&partial) {
+                    return read_value(&db, value);
+                }
+                return Ok(None);
+            }
+            Node::Branch {
+                partial,
+                value,
+                mut children,
+            } => {
+                if !key.starts_with(&partial) {
+                    return Ok(None);
+                }
+                key = key.mid(partial.len());
+                if key.len() == 0 {
+                    return value.map_or(Ok(None), |v| read_value(&db, v));
+                }
+                let child = children[key.at(0) as usize].take();
+                key = key.mid(1);
+                child
+            }
+        };
+        node_data = match child {
+            None => return Ok(None),
+            Some(Child::Hash(hash)) => lookup(&db, hash)?,
+            Some(Child::Inline(data)) => data,
+        };
+    }
+}
+
+fn lookup<'a>(db: &BTreeMap<[u8; 32], &'a [u8]>, hash: &[u8]) -> Result<&'a [u8], Error> {
+    let hash: [u8; 32] = hash.try_into().or(Err(Error::InvalidProof))?;
+    db.get(&hash).copied().ok_or(Error::InvalidProof)
+}
+
+fn read_value(db: &BTreeMap<[u8; 32], &[u8]>, value: Value) -> Result<Option<Vec<u8>>, Error> {
+    match value {
+        Value::Inline(v) => Ok(Some(v.to_vec())),
+        Value::Hashed(hash) => lookup(db, hash).map(|v| Some(v.to_vec())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    /// A trie with the entries `0x1234 => "hello"` and `0x1256 => 0x0001..27`:
+    ///
+    /// - a root branch with partial key `12` and children at nibbles `3` and `5`
+    /// - an inline leaf with partial key `4` and an inline value
+    /// - a leaf with partial key `6` and a hashed value (state version 1)
+    fn test_proof() -> (H256, Vec<Vec<u8>>) {
+        let root = hex!("821228002041041468656c6c6f80ec2d1251f1d3731f3bac1fadd1627f33a362be7308d4ed1e0208ff078c3adac5");
+        let leaf = hex!("210670a3082dfc7582b9d252939a474338db1f94a6dcc7724709377797d17ff51ac5");
+        let value = hex!(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f2021222324252627"
+        );
+        let state_root = H256(hex!(
+            "c32a6ff1b0e3e9fce3b61ad996408f3095c4e2ede6dc5e123a1b06f8e3ab5cba"
+        ));
+        (
+            state_root,
+            vec![root.to_vec(), leaf.to_vec(), value.to_vec()],
+        )
+    }
+
+    #[test]
+    fn can_verify_existing_keys() {
+        let (root, proof) = test_proof();
+        assert_eq!(
+            verify_storage_proof(root, &hex!("1234"), &proof),
+            Ok(Some(b"hello".to_vec()))
+        );
+        assert_eq!(
+            verify_storage_proof(root, &hex!("1256"), &proof),
+            Ok(Some((0u8..40).collect()))
+        );
+    }
+
+    #[test]
+    fn can_verify_absent_keys() {
+        let (root, proof) = test_proof();
+        // Diverges in the leaf
+        assert_eq!(verify_storage_proof(root, &hex!("1237"), &proof), Ok(None));
+        // No child at nibble 7
+        assert_eq!(verify_storage_proof(root, &hex!("1274"), &proof), Ok(None));
+        // Diverges in the root partial key
+        assert_eq!(verify_storage_proof(root, &hex!("1334"), &proof), Ok(None));
+        // Ends in the root branch which has no value
+        assert_eq!(verify_storage_proof(root, &hex!("12"), &proof), Ok(None));
+    }
+
+    #[test]
+    fn rejects_bad_proofs() {
+        let (root, proof) = test_proof();
+        assert_eq!(
+            verify_storage_proof(H256::zero(), &hex!("1234"), &proof),
+            Err(Error::InvalidProof)
+        );
+        // Missing the hashed value
+        assert_eq!(
+            verify_storage_proof(root, &hex!("1256"), &proof[..2]),
+            Err(Error::InvalidProof)
+        );
+        // Missing the leaf node
+        assert_eq!(
+            verify_storage_proof(root, &hex!("1256"), &proof[..1]),
+            Err(Error::InvalidProof)
+        );
+        // The inline leaf needs no other nodes
+        assert_eq!(
+            verify_storage_proof(root, &hex!("1234"), &proof[..1]),
+            Ok(Some(b"hello".to_vec()))
+        );
+    }
+}
@@ -3,14 +3,22 @@ use alloc::format;
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
+use pink_extension::chain_extension::HttpRequest;
 use pink_extension::http_post;
 
-pub fn call_rpc(rpc_node: &str, data: Vec<u8>) -> core::result::Result<Vec<u8>, Error> {
+/// The maximum number of requests the runtime accepts in one `batch_http_request`
+const MAX_BATCH_SIZE: usize = 10;
+
+fn json_headers(data: &[u8]) -> Vec<(String, String)> {
     let content_length = format!("{}", data.len());
-    let headers: Vec<(String, String)> = vec![
+    vec![
         ("Content-Type".into(), "application/json".into()),
         ("Content-Length".into(), content_length),
-    ];
+    ]
+}
+
+pub fn call_rpc(rpc_node: &str, data: Vec<u8>) -> core::result::Result<Vec<u8>, Error> {
+    let headers = json_headers(&data);
     let response = http_post!(rpc_node, data, headers);
 
     if response.status_code != 200 {
@@ -19,3 +27,34 @@ pub fn call_rpc(rpc_node: &str, data: Vec<u8>) -> core::result::Result<Vec<u8>,
 
     Ok(response.body)
 }
+
+/// Sends the same request to all the given nodes concurrently
+///
+/// The results are in the same order as `rpc_nodes`.
+pub fn call_rpc_batch(
+    rpc_nodes: &[String],
+    data: Vec<u8>,
+    timeout_ms: u64,
+) -> Vec<core::result::Result<Vec<u8>, Error>> {
+    let headers = json_headers(&data);
+    let mut results = Vec::with_capacity(rpc_nodes.len());
+    for chunk in rpc_nodes.chunks(MAX_BATCH_SIZE) {
+        let requests = chunk
+            .iter()
+            .map(|node| HttpRequest {
+                url: node.clone(),
+                method: "POST".into(),
+                headers: headers.clone(),
+                body: data.clone(),
+            })
+            .collect();
+        match pink_extension::ext().batch_http_request(requests, timeout_ms) {
+            Ok(responses) => results.extend(responses.into_iter().map(|resp| match resp {
+                Ok(resp) if resp.status_code == 200 => Ok(resp.body),
+                _ => Err(Error::SubRPCRequestFailed),
+            })),
+            Err(_) => results.extend(chunk.iter().map(|_| Err(Error::SubRPCRequestFailed))),
+        }
+    }
+    results
+}
@@ -1,19 +1,22 @@
 use std::borrow::Cow;
 use std::{
     fmt::Display,
+    io::Read as _,
     str::FromStr,
-    time::{Duration, SystemTime},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
 };
 
 use pink_extension::{
     chain_extension::{
-        self as ext, HttpRequest, HttpResponse, PinkExtBackend, SigType, StorageQuotaExceeded,
+        self as ext, HttpEgressPolicy, HttpRequest, HttpRequestError, HttpResponse, PinkExtBackend,
+        SigType, StorageQuotaExceeded,
     },
     Balance, EcdhPublicKey, EcdsaPublicKey, EcdsaSignature, Hash,
 };
 use reqwest::{
     header::{HeaderMap, HeaderName, HeaderValue},
-    Method,
+    redirect, Method, StatusCode,
 };
 use reqwest_env_proxy::EnvProxyBuilder;
 use sp_core::{ByteArray as _, Pair};
@@ -26,6 +29,27 @@ pub trait PinkRuntimeEnv {
 
     fn address(&self) -> &Self::AccountId;
     fn call_elapsed(&self) -> Option<Duration>;
+
+    /// Check if the contract is allowed to send an HTTP request to `host`.
+    ///
+    /// Called before every outbound HTTP request. Allows everything by default.
+    fn check_http_egress(&self, _host: &str) -> Result<(), HttpRequestError> {
+        Ok(())
+    }
+
+    /// The max size of HTTP response bodies the contract can receive.
+    ///
+    /// Values greater than the hardcoded 256KB limit are ignored.
+    fn max_http_body_size(&self) -> usize {
+        MAX_BODY_SIZE
+    }
+
+    /// The egress policy to check again on every redirect hop of an HTTP request.
+    ///
+    /// Redirects to any host are followed by default.
+    fn http_redirect_policy(&self) -> Option<HttpEgressPolicy> {
+        None
+    }
 }
 
 pub struct DefaultPinkExtension<'a, T, Error> {
@@ -42,87 +66,33 @@ impl<'a, T, E> DefaultPinkExtension<'a, T, E> {
     }
 }
 
+impl<T: PinkRuntimeEnv, E> DefaultPinkExtension<'_, T, E> {
+    fn check_egress(&self, request: &HttpRequest) -> Result<(), HttpRequestError> {
+        let url: reqwest::Url = request.url.parse().or(Err(HttpRequestError::InvalidUrl))?;
+        self.env
+            .check_http_egress(url.host_str().unwrap_or_default())
+    }
+
+    fn max_body_size(&self) -> usize {
+        self.env.max_http_body_size().min(MAX_BODY_SIZE)
+    }
+}
+
 impl<T: PinkRuntimeEnv, E: From<&'static str>> PinkExtBackend for DefaultPinkExtension<'_, T, E> {
     type Error = E;
     fn http_request(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error> {
-        // Hardcoded limitations for now
-        const MAX_QUERY_TIME: Duration = Duration::from_secs(10);
-        const MAX_BODY_SIZE: usize = 1024 * 256; // 256KB
-
-        let elapsed = self.env.call_elapsed().ok_or("Invalid exec env")?;
-        if elapsed >= MAX_QUERY_TIME {
-            return Err("Query time limitation exceeded".into());
-        }
-        let timeout = MAX_QUERY_TIME.saturating_sub(elapsed);
-
-        let url: reqwest::Url = request.url.parse().or(Err("Invalid url"))?;
-
-        let client = reqwest::blocking::Client::builder()
-            .timeout(timeout)
-            .env_proxy(url.host_str().unwrap_or_default())
-            .build()
-            .or(Err("Failed to create client"))?;
-
-        let method: Method =
-            FromStr::from_str(request.method.as_str()).or(Err("Invalid HTTP method"))?;
-        let mut headers = HeaderMap::new();
-        for (key, value) in &request.headers {
-            let key = HeaderName::from_str(key.as_str()).or(Err("Invalid HTTP header key"))?;
-            let value = HeaderValue::from_str(value).or(Err("Invalid HTTP header value"))?;
-            headers.insert(key, value);
-        }
-
-        let result = client
-            .request(method, url)
-            .headers(headers)
-            .body(request.body)
-            .send();
-
-        let mut response = match result {
-            Ok(response) => response,
-            Err(err) => {
-                // If there is somthing wrong with the network, we can not inspect the reason too
-                // much here. Let it return a non-standard 523 here.
-                log::info!("HTTP request error: {err}");
-                return Ok(HttpResponse {
-                    status_code: 523,
-                    reason_phrase: "Unreachable".into(),
-                    body: format!("{err:?}").into_bytes(),
-                    headers: vec![],
-                });
-            }
-        };
-
-        let headers: Vec<_> = response
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().into()))
-            .collect();
-
-        let mut body = Vec::new();
-        let mut writer = LimitedWriter::new(&mut body, MAX_BODY_SIZE);
-
-        if let Err(err) = response.copy_to(&mut writer) {
-            log::info!("Failed to read HTTP body: {err}");
-            return Ok(HttpResponse {
-                status_code: 524,
-                reason_phrase: "IO Error".into(),
-                body: format!("{err:?}").into_bytes(),
-                headers: vec![],
-            });
-        };
-
-        let response = HttpResponse {
-            status_code: response.status().as_u16(),
-            reason_phrase: response
-                .status()
-                .canonical_reason()
-                .unwrap_or_default()
-                .into(),
-            body,
-            headers,
-        };
-        Ok(response)
+        let timeout = remaining_query_time(self.env)?;
+        self.check_egress(&request)
+            .map_err(RequestFailure::Rejected)
+            .and_then(|_| {
+                fetch(
+                    request,
+                    timeout,
+                    self.max_body_size(),
+                    self.env.http_redirect_policy(),
+                )
+            })
+            .or_else(|failure| failure.into_legacy_response().map_err(Into::into))
     }
 
     fn sign(
@@ -144,6 +114,8 @@ impl<T: PinkRuntimeEnv, E: From<&'static str>> PinkExtBackend for DefaultPinkExt
             SigType::Sr25519 => sign_with!(sr25519),
             SigType::Ed25519 => sign_with!(ed25519),
             SigType::Ecdsa => sign_with!(ecdsa),
+            SigType::SchnorrSecp256k1 => schnorr_sign(&key, &message)?,
+            SigType::Bls12381 => bls_sign(&key, &message)?,
         })
     }
 
@@ -163,6 +135,8 @@ impl<T: PinkRuntimeEnv, E: From<&'static str>> PinkExtBackend for DefaultPinkExt
             SigType::Sr25519 => verify_with!(sr25519),
             SigType::Ed25519 => verify_with!(ed25519),
             SigType::Ecdsa => verify_with!(ecdsa),
+            SigType::SchnorrSecp256k1 => schnorr_verify(&pubkey, &message, &signature),
+            SigType::Bls12381 => bls_verify(&pubkey, &message, &signature),
         })
     }
 
@@ -176,6 +150,14 @@ impl<T: PinkRuntimeEnv, E: From<&'static str>> PinkExtBackend for DefaultPinkExt
         Ok(key.as_ref().secret.to_bytes().to_vec())
     }
 
+    fn derive_key(&self, sigtype: SigType, salt: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
+        // This default implementation is for unit tests. The host should override this.
+        match key_derivation_tag(&sigtype) {
+            None => self.derive_sr25519_key(salt),
+            Some(tag) => Ok(sp_core::blake2_256(&[&salt[..], tag].concat()).to_vec()),
+        }
+    }
+
     fn get_public_key(&self, sigtype: SigType, key: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
         macro_rules! public_key_with {
             ($sigtype:ident) => {{
@@ -189,6 +171,8 @@ impl<T: PinkRuntimeEnv, E: From<&'static str>> PinkExtBackend for DefaultPinkExt
             SigType::Ed25519 => public_key_with!(ed25519),
             SigType::Sr25519 => public_key_with!(sr25519),
             SigType::Ecdsa => public_key_with!(ecdsa),
+            SigType::SchnorrSecp256k1 => schnorr_public_key(&key)?,
+            SigType::Bls12381 => bls_public_key(&key)?,
         };
         Ok(pubkey)
     }
@@ -280,12 +264,522 @@ impl<T: PinkRuntimeEnv, E: From<&'static str>> PinkExtBackend for DefaultPinkExt
     fn worker_pubkey(&self) -> Result<EcdhPublicKey, Self::Error> {
         Ok(Default::default())
     }
+
+    fn batch_http_request(
+        &self,
+        requests: Vec<HttpRequest>,
+        timeout_ms: u64,
+    ) -> Result<ext::BatchHttpResult, Self::Error> {
+        let timeout = remaining_query_time(self.env)?.min(Duration::from_millis(timeout_ms));
+        if requests.len() > MAX_CONCURRENT_REQUESTS {
+            return Ok(Err(HttpRequestError::TooManyRequests));
+        }
+        let requests = requests
+            .into_iter()
+            .map(|request| self.check_egress(&request).map(|_| request))
+            .collect();
+        Ok(Ok(batch_http_request(
+            requests,
+            timeout,
+            self.max_body_size(),
+            self.env.http_redirect_policy(),
+        )))
+    }
+
+    fn try_http_request(
+        &self,
+        request: HttpRequest,
+    ) -> Result<Result<HttpResponse, HttpRequestError>, Self::Error> {
+        let timeout = remaining_query_time(self.env)?;
+        Ok(self.check_egress(&request).and_then(|_| {
+            http_request(
+                request,
+                timeout,
+                self.max_body_size(),
+                self.env.http_redirect_policy(),
+            )
+        }))
+    }
+
+    fn ranged_http_request(
+        &self,
+        request: HttpRequest,
+        offset: u64,
+        length: u32,
+    ) -> Result<Result<HttpResponse, HttpRequestError>, Self::Error> {
+        let timeout = remaining_query_time(self.env)?;
+        Ok(self.check_egress(&request).and_then(|_| {
+            let length = length.min(self.max_body_size() as u32);
+            ranged_http_request(
+                request,
+                offset,
+                length,
+                timeout,
+                self.env.http_redirect_policy(),
+            )
+        }))
+    }
+}
+
+// Hardcoded limitations for now
+const MAX_QUERY_TIME: Duration = Duration::from_secs(10);
+const MAX_BODY_SIZE: usize = 1024 * 256; // 256KB
+const MAX_CONCURRENT_REQUESTS: usize = 10;
+const MAX_BATCH_WORKERS: usize = 5;
+
+fn remaining_query_time<T: PinkRuntimeEnv>(env: &T) -> Result<Duration, &'static str> {
+    let elapsed = env.call_elapsed().ok_or("Invalid exec env")?;
+    if elapsed >= MAX_QUERY_TIME {
+        return Err("Query time limitation exceeded");
+    }
+    Ok(MAX_QUERY_TIME.saturating_sub(elapsed))
+}
+
+/// Send a single HTTP request, blocking until the response body is fully read or the timeout
+/// is reached.
+pub fn http_request(
+    request: HttpRequest,
+    timeout: Duration,
+    max_body_size: usize,
+    redirect_policy: Option<HttpEgressPolicy>,
+) -> Result<HttpResponse, HttpRequestError> {
+    fetch(request, timeout, max_body_size, redirect_policy).map_err(RequestFailure::into_error)
+}
+
+/// Fetch at most `length` bytes of the response body, starting at `offset`.
+///
+/// A `Range` header is added to the request. If the server ignores it and responds with the full
+/// body, the leading `offset` bytes are skipped while streaming so that no more than `length`
+/// bytes are ever kept in memory. `length` is capped at `MAX_BODY_SIZE`.
+pub fn ranged_http_request(
+    mut request: HttpRequest,
+    offset: u64,
+    length: u32,
+    timeout: Duration,
+    redirect_policy: Option<HttpEgressPolicy>,
+) -> Result<HttpResponse, HttpRequestError> {
+    let length = (length as u64).min(MAX_BODY_SIZE as u64);
+    let last = offset
+        .checked_add(length)
+        .and_then(|end| end.checked_sub(1))
+        .filter(|_| length > 0)
+        .ok_or(HttpRequestError::InvalidRange)?;
+    request
+        .headers
+        .push(("Range".into(), format!("bytes={offset}-{last}")));
+
+    let client = new_client(redirect_policy).map_err(RequestFailure::into_error)?;
+    let mut response =
+        send_request(&client, request, timeout).map_err(RequestFailure::into_error)?;
+
+    let skip = if response.status() == StatusCode::OK {
+        // The server doesn't support range requests, skip the leading bytes by ourselves.
+        offset
+    } else {
+        0
+    };
+    let mut body = Vec::new();
+    let result = std::io::copy(&mut (&mut response).take(skip), &mut std::io::sink())
+        .and_then(|_| (&mut response).take(length).read_to_end(&mut body));
+    if let Err(err) = result {
+        log::info!("Failed to read HTTP body: {err}");
+        if err.kind() == std::io::ErrorKind::TimedOut {
+            return Err(HttpRequestError::Timeout);
+        }
+        return Err(HttpRequestError::NetworkError);
+    }
+
+    Ok(make_response(&response, body))
+}
+
+/// Why an outbound HTTP request failed.
+///
+/// The underlying reqwest errors are kept so that the legacy `http_request` can keep reporting
+/// them in the body of its non-standard 523/524 responses.
+enum RequestFailure {
+    /// The request was rejected before being sent.
+    Rejected(HttpRequestError),
+    /// Failed to send the request or to receive the response head.
+    Send(reqwest::Error),
+    /// Failed to read the response body.
+    ReadBody {
+        error: reqwest::Error,
+        too_large: bool,
+    },
+}
+
+impl RequestFailure {
+    fn into_error(self) -> HttpRequestError {
+        match self {
+            Self::Rejected(err) => err,
+            Self::ReadBody {
+                too_large: true, ..
+            } => HttpRequestError::ResponseTooLarge,
+            Self::Send(error) | Self::ReadBody { error, .. } => classify_error(&error),
+        }
+    }
+
+    /// Report the failure the same way as `http_request` always did.
+    fn into_legacy_response(self) -> Result<HttpResponse, &'static str> {
+        let (status_code, reason_phrase, body) = match self {
+            Self::Rejected(HttpRequestError::InvalidUrl) => return Err("Invalid url"),
+            Self::Rejected(HttpRequestError::FailedToCreateClient) => {
+                return Err("Failed to create client")
+            }
+            Self::Rejected(HttpRequestError::InvalidMethod) => return Err("Invalid HTTP method"),
+            Self::Rejected(HttpRequestError::InvalidHeaderName) => {
+                return Err("Invalid HTTP header key")
+            }
+            Self::Rejected(HttpRequestError::InvalidHeaderValue) => {
+                return Err("Invalid HTTP header value")
+            }
+            // Denied by the egress policy of the cluster.
+            Self::Rejected(err) => (523, "Unreachable", format!("{err:?}")),
+            // If there is somthing wrong with the network, we can not inspect the reason too
+            // much here. Let it return a non-standard 523 here.
+            Self::Send(err) => (523, "Unreachable", format!("{err:?}")),
+            Self::ReadBody { error, .. } => (524, "IO Error", format!("{error:?}")),
+        };
+        Ok(HttpResponse {
+            status_code,
+            reason_phrase: reason_phrase.into(),
+            body: body.into_bytes(),
+            headers: vec![],
+        })
+    }
+}
+
+/// Create a client following redirects only to hosts allowed by `redirect_policy`.
+///
+/// The policy is checked on every hop, otherwise an allowed host could redirect the request to
+/// anywhere.
+fn new_client(
+    redirect_policy: Option<HttpEgressPolicy>,
+) -> Result<reqwest::blocking::Client, RequestFailure> {
+    let redirect = match redirect_policy {
+        None => redirect::Policy::default(),
+        Some(policy) => redirect::Policy::custom(move |attempt| {
+            let host = attempt.url().host_str().unwrap_or_default();
+            if policy.is_host_allowed(host) {
+                redirect::Policy::default().redirect(attempt)
+            } else {
+                log::info!("HTTP redirect to {host} denied by the egress policy");
+                attempt.error(RedirectDenied)
+            }
+        }),
+    };
+    reqwest::blocking::Client::builder()
+        .env_proxy_per_host()
+        .redirect(redirect)
+        .build()
+        .or(Err(RequestFailure::Rejected(
+            HttpRequestError::FailedToCreateClient,
+        )))
+}
+
+fn send_request(
+    client: &reqwest::blocking::Client,
+    request: HttpRequest,
+    timeout: Duration,
+) -> Result<reqwest::blocking::Response, RequestFailure> {
+    let rejected = RequestFailure::Rejected;
+    let url: reqwest::Url = request
+        .url
+        .parse()
+        .or(Err(rejected(HttpRequestError::InvalidUrl)))?;
+
+    let method: Method = FromStr::from_str(request.method.as_str())
+        .or(Err(rejected(HttpRequestError::InvalidMethod)))?;
+    let mut headers = HeaderMap::new();
+    for (key, value) in &request.headers {
+        let key = HeaderName::from_str(key.as_str())
+            .or(Err(rejected(HttpRequestError::InvalidHeaderName)))?;
+        let value =
+            HeaderValue::from_str(value).or(Err(rejected(HttpRequestError::InvalidHeaderValue)))?;
+        headers.insert(key, value);
+    }
+
+    client
+        .request(method, url)
+        .timeout(timeout)
+        .headers(headers)
+        .body(request.body)
+        .send()
+        .map_err(|err| {
+            log::info!("HTTP request error: {err}");
+            RequestFailure::Send(err)
+        })
+}
+
+/// Send a request and read the whole response body.
+fn fetch(
+    request: HttpRequest,
+    timeout: Duration,
+    max_body_size: usize,
+    redirect_policy: Option<HttpEgressPolicy>,
+) -> Result<HttpResponse, RequestFailure> {
+    let client = new_client(redirect_policy)?;
+    fetch_with(&client, request, timeout, max_body_size)
+}
+
+fn fetch_with(
+    client: &reqwest::blocking::Client,
+    request: HttpRequest,
+    timeout: Duration,
+    max_body_size: usize,
+) -> Result<HttpResponse, RequestFailure> {
+    let mut response = send_request(client, request, timeout)?;
+
+    let mut body = Vec::new();
+    let mut writer = LimitedWriter::new(&mut body, max_body_size);
+
+    if let Err(error) = response.copy_to(&mut writer) {
+        log::info!("Failed to read HTTP body: {error}");
+        return Err(RequestFailure::ReadBody {
+            too_large: writer.exceeded,
+            error,
+        });
+    };
+
+    Ok(make_response(&response, body))
+}
+
+fn make_response(response: &reqwest::blocking::Response, body: Vec<u8>) -> HttpResponse {
+    let headers: Vec<_> = response
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().into()))
+        .collect();
+    HttpResponse {
+        status_code: response.status().as_u16(),
+        reason_phrase: response
+            .status()
+            .canonical_reason()
+            .unwrap_or_default()
+            .into(),
+        body,
+        headers,
+    }
+}
+
+/// Map a reqwest error to the typed error reported to contracts.
+fn classify_error(err: &reqwest::Error) -> HttpRequestError {
+    if err.is_timeout() {
+        return HttpRequestError::Timeout;
+    }
+    if err.is_redirect() {
+        let denied = std::iter::successors(std::error::Error::source(err), |inner| inner.source())
+            .any(|inner| inner.is::<RedirectDenied>());
+        if denied {
+            return HttpRequestError::NotAllowed;
+        }
+    }
+    if !err.is_connect() {
+        return HttpRequestError::NetworkError;
+    }
+    // reqwest doesn't tell why the connection failed, so look at the types of the errors behind.
+    let mut source = std::error::Error::source(err);
+    while let Some(inner) = source {
+        if is_tls_error(inner) {
+            return HttpRequestError::TlsError;
+        }
+        let refused = inner
+            .downcast_ref::<std::io::Error>()
+            .map_or(false, |io_err| {
+                io_err.kind() == std::io::ErrorKind::ConnectionRefused
+            });
+        if refused {
+            return HttpRequestError::ConnectionRefused;
+        }
+        source = inner.source();
+    }
+    // The errors of the name resolver used by hyper are plain `std::io::Error`s, which look the
+    // same as the socket errors. So look the host up again to tell whether it was the cause.
+    match err.url() {
+        Some(url) if is_unresolvable(url) => HttpRequestError::DnsError,
+        _ => HttpRequestError::NetworkError,
+    }
+}
+
+/// Check if the host of `url` can't be resolved, as hyper resolves it when connecting.
+///
+/// Hosts behind a proxy are resolved by the proxy, so are never reported as unresolvable.
+fn is_unresolvable(url: &reqwest::Url) -> bool {
+    use std::net::ToSocketAddrs;
+
+    let Some(domain) = url.domain() else {
+        return false;
+    };
+    if reqwest_env_proxy::has_env_proxy(domain) {
+        return false;
+    }
+    let port = url.port_or_known_default().unwrap_or(0);
+    (domain, port).to_socket_addrs().is_err()
+}
+
+/// A redirect rejected by the egress policy.
+#[derive(Debug)]
+struct RedirectDenied;
+
+impl Display for RedirectDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("redirect denied by the egress policy")
+    }
+}
+
+impl std::error::Error for RedirectDenied {}
+
+/// Check if `err` is, or wraps in an `std::io::Error`, a rustls error.
+fn is_tls_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    if err.is::<rustls::Error>() {
+        return true;
+    }
+    match err
+        .downcast_ref::<std::io::Error>()
+        .and_then(|io_err| io_err.get_ref())
+    {
+        Some(inner) => is_tls_error(inner),
+        None => false,
+    }
+}
+
+/// Send a batch of HTTP requests concurrently.
+///
+/// The requests share one client and are sent by at most `MAX_BATCH_WORKERS` threads. All of
+/// them must finish within `timeout`, so requests still waiting in the queue get the remaining
+/// time only. Requests that already failed a pre-check are passed through as is.
+pub fn batch_http_request(
+    requests: Vec<Result<HttpRequest, HttpRequestError>>,
+    timeout: Duration,
+    max_body_size: usize,
+    redirect_policy: Option<HttpEgressPolicy>,
+) -> Vec<Result<HttpResponse, HttpRequestError>> {
+    let client = match new_client(redirect_policy) {
+        Ok(client) => client,
+        Err(failure) => {
+            let err = failure.into_error();
+            return requests
+                .into_iter()
+                .map(|request| request.and(Err(err)))
+                .collect();
+        }
+    };
+    let deadline = Instant::now() + timeout;
+    let count = requests.len();
+    let queue = Mutex::new(requests.into_iter().enumerate());
+    let results = Mutex::new(
+        (0..count)
+            .map(|_| Err(HttpRequestError::NetworkError))
+            .collect::<Vec<_>>(),
+    );
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..count.min(MAX_BATCH_WORKERS))
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let Some((index, request)) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+                    let result = request.and_then(|request| {
+                        let timeout = deadline.saturating_duration_since(Instant::now());
+                        if timeout.is_zero() {
+                            return Err(HttpRequestError::Timeout);
+                        }
+                        fetch_with(&client, request, timeout, max_body_size)
+                            .map_err(RequestFailure::into_error)
+                    });
+                    results.lock().unwrap()[index] = result;
+                })
+            })
+            .collect();
+        for worker in workers {
+            // A panicked worker leaves its requests reported as network errors.
+            let _ = worker.join();
+        }
+    });
+    results.into_inner().unwrap_or_else(|err| err.into_inner())
+}
+
+/// The tag appended to the derivation path of keys other than sr25519.
+///
+/// These values are part of the key derivation path and must never change.
+pub fn key_derivation_tag(sigtype: &SigType) -> Option<&'static [u8]> {
+    match sigtype {
+        SigType::Sr25519 => None,
+        SigType::Ed25519 => Some(b"ed25519"),
+        SigType::Ecdsa => Some(b"ecdsa"),
+        SigType::SchnorrSecp256k1 => Some(b"schnorr-secp256k1"),
+        SigType::Bls12381 => Some(b"bls12-381"),
+    }
+}
+
+/// Sign a 32-byte message hash with BIP-340 Schnorr.
+///
+/// The auxiliary randomness is left zero so that the signature is deterministic.
+fn schnorr_sign(key: &[u8], message: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let key = k256::schnorr::SigningKey::from_bytes(key).or(Err("Invalid key"))?;
+    let message: &[u8; 32] = message
+        .try_into()
+        .or(Err("Schnorr message must be a 32-byte hash"))?;
+    let signature = key
+        .try_sign_prehashed(message, &[0; 32])
+        .or(Err("Failed to sign"))?;
+    Ok(signature.as_bytes().to_vec())
+}
+
+fn schnorr_verify(pubkey: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let Ok(pubkey) = k256::schnorr::VerifyingKey::from_bytes(pubkey) else {
+        return false;
+    };
+    let Ok(message) = <&[u8; 32]>::try_from(message) else {
+        return false;
+    };
+    let Ok(signature) = k256::schnorr::Signature::try_from(signature) else {
+        return false;
+    };
+    pubkey.verify_prehashed(message, &signature).is_ok()
+}
+
+fn schnorr_public_key(key: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let key = k256::schnorr::SigningKey::from_bytes(key).or(Err("Invalid key"))?;
+    Ok(key.verifying_key().to_bytes().to_vec())
+}
+
+/// The ciphersuite used by Ethereum consensus.
+const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+fn bls_secret_key(seed: &[u8]) -> Result<blst::min_pk::SecretKey, &'static str> {
+    if seed.len() != 32 {
+        return Err("Invalid key");
+    }
+    blst::min_pk::SecretKey::key_gen(seed, &[]).or(Err("Invalid key"))
+}
+
+fn bls_sign(key: &[u8], message: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let key = bls_secret_key(key)?;
+    Ok(key.sign(message, BLS_DST, &[]).compress().to_vec())
+}
+
+fn bls_verify(pubkey: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let Ok(pubkey) = blst::min_pk::PublicKey::uncompress(pubkey) else {
+        return false;
+    };
+    let Ok(signature) = blst::min_pk::Signature::uncompress(signature) else {
+        return false;
+    };
+    let result = signature.verify(true, message, BLS_DST, &[], &pubkey, true);
+    result == blst::BLST_ERROR::BLST_SUCCESS
+}
+
+fn bls_public_key(key: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let key = bls_secret_key(key)?;
+    Ok(key.sk_to_pk().compress().to_vec())
 }
 
 struct LimitedWriter<W> {
     writer: W,
     written: usize,
     limit: usize,
+    exceeded: bool,
 }
 
 impl<W> LimitedWriter<W> {
@@ -294,6 +788,7 @@ impl<W> LimitedWriter<W> {
             writer,
             written: 0,
             limit,
+            exceeded: false,
         }
     }
 }
@@ -301,6 +796,7 @@ impl<W> LimitedWriter<W> {
 impl<W: std::io::Write> std::io::Write for LimitedWriter<W> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         if self.written + buf.len() > self.limit {
+            self.exceeded = true;
             return Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "Buffer limit exceeded",
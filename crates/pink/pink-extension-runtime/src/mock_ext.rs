@@ -53,6 +53,10 @@ impl ext::PinkExtBackend for MockExtension {
         super::DefaultPinkExtension::new(self).derive_sr25519_key(salt)
     }
 
+    fn derive_key(&self, sigtype: SigType, salt: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
+        super::DefaultPinkExtension::new(self).derive_key(sigtype, salt)
+    }
+
     fn get_public_key(&self, sigtype: SigType, key: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
         super::DefaultPinkExtension::new(self).get_public_key(sigtype, key)
     }
@@ -129,6 +133,30 @@ impl ext::PinkExtBackend for MockExtension {
     fn worker_pubkey(&self) -> Result<crate::EcdhPublicKey, Self::Error> {
         Ok(Default::default())
     }
+
+    fn batch_http_request(
+        &self,
+        requests: Vec<ext::HttpRequest>,
+        timeout_ms: u64,
+    ) -> Result<ext::BatchHttpResult, Self::Error> {
+        super::DefaultPinkExtension::new(self).batch_http_request(requests, timeout_ms)
+    }
+
+    fn try_http_request(
+        &self,
+        request: ext::HttpRequest,
+    ) -> Result<Result<ext::HttpResponse, ext::HttpRequestError>, Self::Error> {
+        super::DefaultPinkExtension::new(self).try_http_request(request)
+    }
+
+    fn ranged_http_request(
+        &self,
+        request: ext::HttpRequest,
+        offset: u64,
+        length: u32,
+    ) -> Result<Result<ext::HttpResponse, ext::HttpRequestError>, Self::Error> {
+        super::DefaultPinkExtension::new(self).ranged_http_request(request, offset, length)
+    }
 }
 
 thread_local! {
@@ -2,7 +2,9 @@ use alloc::borrow::Cow;
 use alloc::vec::Vec;
 use ink::ChainExtensionInstance;
 
-pub use http_request::{HttpRequest, HttpResponse};
+pub use http_request::{
+    BatchHttpResult, HttpEgressPolicy, HttpRequest, HttpRequestError, HttpResponse,
+};
 pub use ink::primitives::AccountId;
 pub use signing::SigType;
 
@@ -102,6 +104,10 @@ impl ink::env::chain_extension::FromStatusCode for ErrorCode {
 pub trait PinkExt {
     type ErrorCode = ErrorCode;
 
+    /// Send an HTTP request. (Query only)
+    ///
+    /// Network failures are reported as responses with the non-standard status code 523 and
+    /// body reading failures with 524. Use `try_http_request` to get a typed error instead.
     #[ink(extension = 1, handle_status = false)]
     fn http_request(request: HttpRequest) -> HttpResponse;
 
@@ -111,6 +117,8 @@ pub trait PinkExt {
     #[ink(extension = 3, handle_status = false)]
     fn verify(sigtype: SigType, pubkey: &[u8], message: &[u8], signature: &[u8]) -> bool;
 
+    /// Derive a sr25519 secret key from the contract key. Same as
+    /// `derive_key(SigType::Sr25519, salt)`.
     #[ink(extension = 4, handle_status = false)]
     fn derive_sr25519_key(salt: Cow<[u8]>) -> Vec<u8>;
 
@@ -185,6 +193,54 @@ pub trait PinkExt {
     /// Get current millis since unix epoch from the OS. (Query only)
     #[ink(extension = 18, handle_status = false)]
     fn untrusted_millis_since_unix_epoch() -> u64;
+
+    /// Send a batch of HTTP requests concurrently. (Query only)
+    ///
+    /// The requests are sent in parallel by the host, and the call returns when all of them are
+    /// finished or `timeout_ms` is reached, whichever comes first. The timeout is capped by the
+    /// remaining query time. Results are returned in the same order as the requests.
+    ///
+    /// At most 10 requests are allowed in a single batch, otherwise
+    /// `Err(HttpRequestError::TooManyRequests)` is returned.
+    #[ink(extension = 19, handle_status = false)]
+    fn batch_http_request(requests: Vec<HttpRequest>, timeout_ms: u64) -> BatchHttpResult;
+
+    /// Send an HTTP request and report failures with a typed error. (Query only)
+    ///
+    /// Unlike `http_request`, failures such as DNS errors, TLS errors, timeouts or an oversized
+    /// response body are returned as `Err(HttpRequestError)` rather than fabricated responses.
+    #[ink(extension = 20, handle_status = false)]
+    fn try_http_request(request: HttpRequest) -> Result<HttpResponse, HttpRequestError>;
+
+    /// Fetch a chunk of a large HTTP response body. (Query only)
+    ///
+    /// Returns at most `length` bytes of the body starting at `offset`, so that contracts can page
+    /// through responses larger than the 256KB limit of `http_request`. `length` is capped at
+    /// 256KB.
+    ///
+    /// A `Range` header is sent to the server. If the server honours it, the response status is
+    /// 206 and the `Content-Range` header tells the total size. Otherwise the host skips the
+    /// leading bytes of the full body by itself and the status is the original one (usually 200).
+    #[ink(extension = 21, handle_status = false)]
+    fn ranged_http_request(
+        request: HttpRequest,
+        offset: u64,
+        length: u32,
+    ) -> Result<HttpResponse, HttpRequestError>;
+
+    /// Derive a secret key of the given signature type from the contract key.
+    ///
+    /// The keys are rooted at the key seed of the cluster, which is derived from the cluster
+    /// key, so the derived keys stay the same when the cluster migrates to other workers.
+    ///
+    /// The derivation paths are stable:
+    /// - `Sr25519`: the 64-byte secret of `kdf(seed, [contract_address, salt, "keygen"])`, the
+    ///   same as `derive_sr25519_key`.
+    /// - Other types: the 32-byte `blake2_256` of the secret of
+    ///   `kdf(seed, [contract_address, salt, "keygen", tag])`, where `tag` is `"ed25519"`,
+    ///   `"ecdsa"`, `"schnorr-secp256k1"` or `"bls12-381"`.
+    #[ink(extension = 22, handle_status = false)]
+    fn derive_key(sigtype: SigType, salt: &[u8]) -> Vec<u8>;
 }
 
 pub fn pink_extension_instance() -> <PinkExt as ChainExtensionInstance>::Instance {
@@ -38,6 +38,99 @@ impl HttpResponse {
     }
 }
 
+#[derive(scale::Encode, scale::Decode, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum HttpRequestError {
+    InvalidUrl,
+    InvalidMethod,
+    InvalidHeaderName,
+    InvalidHeaderValue,
+    FailedToCreateClient,
+    Timeout,
+    NotAllowed,
+    TooManyRequests,
+    NetworkError,
+    ResponseTooLarge,
+    DnsError,
+    ConnectionRefused,
+    TlsError,
+    InvalidRange,
+}
+
+impl HttpRequestError {
+    pub fn display(&self) -> &'static str {
+        match self {
+            Self::InvalidUrl => "Invalid URL",
+            Self::InvalidMethod => "Invalid HTTP method",
+            Self::InvalidHeaderName => "Invalid HTTP header key",
+            Self::InvalidHeaderValue => "Invalid HTTP header value",
+            Self::FailedToCreateClient => "Failed to create client",
+            Self::Timeout => "Request timed out",
+            Self::NotAllowed => "Request not allowed",
+            Self::TooManyRequests => "Too many requests",
+            Self::NetworkError => "Network error",
+            Self::ResponseTooLarge => "Response too large",
+            Self::DnsError => "Failed to resolve the host name",
+            Self::ConnectionRefused => "Connection refused",
+            Self::TlsError => "TLS handshake failed",
+            Self::InvalidRange => "Invalid range",
+        }
+    }
+}
+
+impl From<scale::Error> for HttpRequestError {
+    fn from(_value: scale::Error) -> Self {
+        crate::panic!("chain_ext: failed to decode output")
+    }
+}
+
+/// The outbound HTTP policy of a cluster.
+///
+/// Set by the cluster owner through the system contract and enforced by the runtime on every
+/// HTTP request issued by contracts in the cluster.
+#[derive(scale::Encode, scale::Decode, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct HttpEgressPolicy {
+    /// Hosts that contracts are allowed to reach. An entry like `*.example.com` matches any
+    /// subdomain of `example.com`. An empty list allows all hosts.
+    pub allowed_hosts: Vec<String>,
+    /// Max number of HTTP requests a contract can send per minute.
+    pub max_requests_per_minute: Option<u32>,
+    /// Max size of a response body in bytes. Can not exceed the runtime limit of 256KB.
+    pub max_body_size: Option<u32>,
+}
+
+impl HttpEgressPolicy {
+    /// Check if the given host is allowed by the policy.
+    ///
+    /// Host names are compared case-insensitively, ignoring a trailing root dot.
+    pub fn is_host_allowed(&self, host: &str) -> bool {
+        if self.allowed_hosts.is_empty() {
+            return true;
+        }
+        let host = normalize_host(host);
+        self.allowed_hosts.iter().any(|pattern| {
+            let pattern = normalize_host(pattern);
+            match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .map_or(false, |sub| sub.ends_with('.') && sub.len() > 1),
+                None => pattern == host,
+            }
+        })
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// The result of a batch of HTTP requests.
+///
+/// The outer error is returned if the batch as a whole is rejected (e.g. too many requests),
+/// otherwise each request gets its own result in the same order as the input.
+pub type BatchHttpResult = Result<Vec<Result<HttpResponse, HttpRequestError>>, HttpRequestError>;
+
 #[macro_export]
 macro_rules! http_req {
     ($method: expr, $url: expr, $data: expr, $headers: expr) => {{
@@ -2,12 +2,23 @@ use alloc::vec::Vec;
 
 use crate::{EcdsaPublicKey, EcdsaSignature, Hash};
 
-#[derive(scale::Encode, scale::Decode)]
+#[derive(scale::Encode, scale::Decode, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum SigType {
     Ed25519,
     Sr25519,
     Ecdsa,
+    /// BIP-340 Schnorr signatures over secp256k1, as used by Bitcoin Taproot.
+    ///
+    /// The key is a 32-byte secret, the public key is the 32-byte x-only point and the message
+    /// must be a 32-byte hash. Signatures are 64 bytes.
+    SchnorrSecp256k1,
+    /// BLS signatures over BLS12-381, as used by Ethereum consensus.
+    ///
+    /// Uses the minimal-pubkey-size variant with the proof-of-possession ciphersuite. The key is
+    /// a 32-byte seed from which the secret key is generated, the public key is a 48-byte
+    /// compressed G1 point and signatures are 96-byte compressed G2 points.
+    Bls12381,
 }
 
 /// Sign a message with a private key.
@@ -67,6 +78,17 @@ pub fn derive_sr25519_key(salt: &[u8]) -> Vec<u8> {
     crate::ext().derive_sr25519_key(salt.into())
 }
 
+/// Derive a private key of the given signature type from the contract key
+///
+/// # Examples
+/// ```ignore
+/// let privkey = derive_key(SigType::Ecdsa, b"a spoon of salt");
+/// let pubkey = get_public_key(&privkey, SigType::Ecdsa);
+/// ```
+pub fn derive_key(sigtype: SigType, salt: &[u8]) -> Vec<u8> {
+    crate::ext().derive_key(sigtype, salt)
+}
+
 /// Get the public key from a private key
 ///
 /// # Examples
@@ -106,6 +106,8 @@ pub enum PinkEvent {
     SetContractWeight { contract: AccountId, weight: u32 },
     /// Upgrade the system contract to latest version.
     UpgradeSystemContract { storage_payer: AccountId },
+    /// Set the outbound HTTP policy for current cluster.
+    SetHttpEgressPolicy(chain_extension::HttpEgressPolicy),
 }
 
 impl PinkEvent {
@@ -122,6 +124,7 @@ impl PinkEvent {
             PinkEvent::SetLogHandler(_) => false,
             PinkEvent::SetContractWeight { .. } => false,
             PinkEvent::UpgradeSystemContract { .. } => false,
+            PinkEvent::SetHttpEgressPolicy(_) => false,
         }
     }
 
@@ -138,6 +141,7 @@ impl PinkEvent {
             PinkEvent::SetLogHandler(_) => "SetLogHandler",
             PinkEvent::SetContractWeight { .. } => "SetContractWeight",
             PinkEvent::UpgradeSystemContract { .. } => "UpgradeSystemContract",
+            PinkEvent::SetHttpEgressPolicy(_) => "SetHttpEgressPolicy",
         }
     }
 }
@@ -256,6 +260,12 @@ pub fn upgrade_system_contract(storage_payer: AccountId) {
     emit_event::<PinkEnvironment, _>(PinkEvent::UpgradeSystemContract { storage_payer });
 }
 
+/// Set the outbound HTTP policy of current cluster
+/// The caller must be the system contract.
+pub fn set_http_egress_policy(policy: chain_extension::HttpEgressPolicy) {
+    emit_event::<PinkEnvironment, _>(PinkEvent::SetHttpEgressPolicy(policy));
+}
+
 /// Pink defined environment. Used this environment to access the fat contract runtime features.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
@@ -286,4 +296,32 @@ mod tests {
     fn test_event_topics() {
         insta::assert_debug_snapshot!(super::PinkEvent::event_topic());
     }
+
+    #[test]
+    fn test_egress_policy_host_matching() {
+        use super::chain_extension::HttpEgressPolicy;
+
+        let policy = HttpEgressPolicy::default();
+        assert!(policy.is_host_allowed("example.com"));
+
+        let policy = HttpEgressPolicy {
+            allowed_hosts: vec!["example.com".into(), "*.phala.network".into()],
+            ..Default::default()
+        };
+        assert!(policy.is_host_allowed("example.com"));
+        assert!(!policy.is_host_allowed("www.example.com"));
+        assert!(policy.is_host_allowed("api.phala.network"));
+        assert!(!policy.is_host_allowed("phala.network"));
+        assert!(!policy.is_host_allowed("evilphala.network"));
+        assert!(policy.is_host_allowed("Example.COM"));
+        assert!(policy.is_host_allowed("example.com."));
+        assert!(policy.is_host_allowed("API.Phala.Network"));
+
+        let policy = HttpEgressPolicy {
+            allowed_hosts: vec!["Example.com".into(), "*.PHALA.network".into()],
+            ..Default::default()
+        };
+        assert!(policy.is_host_allowed("example.com"));
+        assert!(policy.is_host_allowed("api.phala.network"));
+    }
 }
@@ -5,7 +5,7 @@ use pink_extension_macro as pink;
 use alloc::string::String;
 use scale::{Decode, Encode};
 
-use crate::{AccountId, Balance, Hash};
+use crate::{chain_extension::HttpEgressPolicy, AccountId, Balance, Hash};
 
 /// Errors that can occur upon calling the system contract.
 #[derive(Debug, PartialEq, Eq, Encode, Decode)]
@@ -90,6 +90,12 @@ pub trait System {
     /// Upgrade the system contract to the latest version.
     #[ink(message)]
     fn upgrade_system_contract(&self) -> Result<()>;
+
+    /// Set the outbound HTTP policy of the cluster.
+    ///
+    /// The caller must be the owner of the cluster.
+    #[ink(message)]
+    fn set_http_egress_policy(&self, policy: HttpEgressPolicy) -> Result<()>;
 }
 
 /// Errors that can occur upon calling a driver contract.
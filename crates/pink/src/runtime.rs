@@ -17,7 +17,9 @@ use pallet_contracts::{Config, Frame, Schedule};
 use sp_runtime::{generic::Header, traits::IdentityLookup, Perbill};
 
 pub use extension::{get_side_effects, ExecSideEffects};
-pub use pink_extension::{EcdhPublicKey, HookPoint, Message, OspMessage, PinkEvent};
+pub use pink_extension::{
+    chain_extension::HttpEgressPolicy, EcdhPublicKey, HookPoint, Message, OspMessage, PinkEvent,
+};
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<PinkRuntime>;
 type Block = frame_system::mocking::MockBlock<PinkRuntime>;
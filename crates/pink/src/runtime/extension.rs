@@ -5,7 +5,7 @@ use frame_support::traits::Currency;
 use pallet_contracts::chain_extension::{
     ChainExtension, Environment, Ext, InitState, Result as ExtResult, RetVal,
 };
-use phala_crypto::sr25519::{Persistence, KDF};
+use phala_crypto::sr25519::{Persistence, Sr25519SecretKey, KDF};
 use phala_types::contract::ConvertTo;
 use pink_extension::{
     chain_extension::{
@@ -13,7 +13,9 @@ use pink_extension::{
     },
     dispatch_ext_call, CacheOp, EcdhPublicKey, EcdsaPublicKey, EcdsaSignature, Hash, PinkEvent,
 };
-use pink_extension_runtime::{local_cache, DefaultPinkExtension, PinkRuntimeEnv};
+use pink_extension_runtime::{
+    key_derivation_tag, local_cache, DefaultPinkExtension, PinkRuntimeEnv,
+};
 use scale::{Decode, Encode};
 use sp_core::H256;
 use sp_runtime::{AccountId32, DispatchError};
@@ -152,9 +154,78 @@ impl PinkRuntimeEnv for CallInQuery {
     fn call_elapsed(&self) -> Option<Duration> {
         get_call_elapsed()
     }
+
+    fn check_http_egress(&self, host: &str) -> Result<(), ext::HttpRequestError> {
+        let Some(policy) = crate::runtime::Pink::http_egress_policy() else {
+            return Ok(());
+        };
+        if !policy.is_host_allowed(host) {
+            let message = format!("HTTP request to {host} denied by the egress policy");
+            super::emit_log(&self.address, LOG_LEVEL_WARN, message);
+            return Err(ext::HttpRequestError::NotAllowed);
+        }
+        if let Some(limit) = policy.max_requests_per_minute {
+            if !rate_limiter::acquire(&self.address, limit) {
+                let message = format!("HTTP request to {host} denied by the rate limit");
+                super::emit_log(&self.address, LOG_LEVEL_WARN, message);
+                return Err(ext::HttpRequestError::TooManyRequests);
+            }
+        }
+        Ok(())
+    }
+
+    fn max_http_body_size(&self) -> usize {
+        crate::runtime::Pink::http_egress_policy()
+            .and_then(|policy| policy.max_body_size)
+            .map(|size| size as usize)
+            .unwrap_or(usize::MAX)
+    }
+
+    fn http_redirect_policy(&self) -> Option<ext::HttpEgressPolicy> {
+        crate::runtime::Pink::http_egress_policy()
+    }
+}
+
+const LOG_LEVEL_WARN: u8 = 2;
+
+mod rate_limiter {
+    use super::AccountId;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    /// Request counters of each contract in the current window.
+    static COUNTERS: Mutex<BTreeMap<AccountId, (Instant, u32)>> = Mutex::new(BTreeMap::new());
+
+    /// Count a request for `contract`. Returns false if it has exceeded `limit` in the current
+    /// minute.
+    pub(super) fn acquire(contract: &AccountId, limit: u32) -> bool {
+        let now = Instant::now();
+        let mut counters = COUNTERS.lock().unwrap();
+        counters.retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+        let (_, count) = counters.entry(contract.clone()).or_insert((now, 0));
+        if *count >= limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
 }
 
 impl CallInQuery {
+    /// Derive a sr25519 secret key from the cluster key seed with the given path.
+    fn derive_secret(&self, info: &[&[u8]]) -> Result<Sr25519SecretKey, DispatchError> {
+        let seed =
+            crate::runtime::Pink::key_seed().ok_or(DispatchError::Other("Key seed missing"))?;
+        let seed_key = sp_core::sr25519::Pair::restore_from_secret_key(&seed);
+        let derived_pair = seed_key
+            .derive_sr25519_pair(info)
+            .or(Err(DispatchError::Other("Failed to derive sr25519 pair")))?;
+        Ok(derived_pair.dump_secret_key())
+    }
+
     fn ensure_system(&self) -> Result<(), DispatchError> {
         let contract: AccountId32 = self.address.convert_to();
         if Some(contract) != crate::runtime::Pink::system_contract() {
@@ -190,18 +261,21 @@ impl PinkExtBackend for CallInQuery {
     }
 
     fn derive_sr25519_key(&self, salt: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
-        let seed =
-            crate::runtime::Pink::key_seed().ok_or(DispatchError::Other("Key seed missing"))?;
-        let seed_key = sp_core::sr25519::Pair::restore_from_secret_key(&seed);
         let contract_address: &[u8] = self.address.as_ref();
-        let derived_pair = seed_key
-            .derive_sr25519_pair(&[contract_address, &salt, b"keygen"])
-            .or(Err(DispatchError::Other("Failed to derive sr25519 pair")))?;
-        let priviate_key = derived_pair.dump_secret_key();
+        let priviate_key = self.derive_secret(&[contract_address, &salt, b"keygen"])?;
         let priviate_key: &[u8] = priviate_key.as_ref();
         Ok(priviate_key.to_vec())
     }
 
+    fn derive_key(&self, sigtype: SigType, salt: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
+        let Some(tag) = key_derivation_tag(&sigtype) else {
+            return self.derive_sr25519_key(salt);
+        };
+        let contract_address: &[u8] = self.address.as_ref();
+        let secret = self.derive_secret(&[contract_address, &salt, b"keygen", tag])?;
+        Ok(sp_core::blake2_256(&secret).to_vec())
+    }
+
     fn get_public_key(&self, sigtype: SigType, key: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
         DefaultPinkExtension::new(self).get_public_key(sigtype, key)
     }
@@ -281,6 +355,30 @@ impl PinkExtBackend for CallInQuery {
     fn worker_pubkey(&self) -> Result<EcdhPublicKey, Self::Error> {
         Ok(self.worker_pubkey)
     }
+
+    fn batch_http_request(
+        &self,
+        requests: Vec<HttpRequest>,
+        timeout_ms: u64,
+    ) -> Result<ext::BatchHttpResult, Self::Error> {
+        DefaultPinkExtension::new(self).batch_http_request(requests, timeout_ms)
+    }
+
+    fn try_http_request(
+        &self,
+        request: HttpRequest,
+    ) -> Result<Result<HttpResponse, ext::HttpRequestError>, Self::Error> {
+        DefaultPinkExtension::new(self).try_http_request(request)
+    }
+
+    fn ranged_http_request(
+        &self,
+        request: HttpRequest,
+        offset: u64,
+        length: u32,
+    ) -> Result<Result<HttpResponse, ext::HttpRequestError>, Self::Error> {
+        DefaultPinkExtension::new(self).ranged_http_request(request, offset, length)
+    }
 }
 
 struct CallInCommand {
@@ -327,6 +425,10 @@ impl PinkExtBackend for CallInCommand {
         self.as_in_query.derive_sr25519_key(salt)
     }
 
+    fn derive_key(&self, sigtype: SigType, salt: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
+        self.as_in_query.derive_key(sigtype, salt)
+    }
+
     fn get_public_key(&self, sigtype: SigType, key: Cow<[u8]>) -> Result<Vec<u8>, Self::Error> {
         self.as_in_query.get_public_key(sigtype, key)
     }
@@ -419,4 +521,99 @@ impl PinkExtBackend for CallInCommand {
     fn worker_pubkey(&self) -> Result<EcdhPublicKey, Self::Error> {
         Ok(Default::default())
     }
+
+    fn batch_http_request(
+        &self,
+        _requests: Vec<HttpRequest>,
+        _timeout_ms: u64,
+    ) -> Result<ext::BatchHttpResult, Self::Error> {
+        Ok(Err(ext::HttpRequestError::NotAllowed))
+    }
+
+    fn try_http_request(
+        &self,
+        _request: HttpRequest,
+    ) -> Result<Result<HttpResponse, ext::HttpRequestError>, Self::Error> {
+        Ok(Err(ext::HttpRequestError::NotAllowed))
+    }
+
+    fn ranged_http_request(
+        &self,
+        _request: HttpRequest,
+        _offset: u64,
+        _length: u32,
+    ) -> Result<Result<HttpResponse, ext::HttpRequestError>, Self::Error> {
+        Ok(Err(ext::HttpRequestError::NotAllowed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{tests::exec, using_mode, Pink};
+    use pink_extension::chain_extension::{HttpEgressPolicy, HttpRequestError};
+
+    fn get(url: String) -> HttpRequest {
+        HttpRequest {
+            url,
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    /// Serve a redirect to `localhost` on the same port, which the policy below doesn't allow.
+    fn serve_redirect() -> std::net::SocketAddr {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let response = format!(
+                    "HTTP/1.1 302 Found\r\nlocation: http://localhost:{}/\r\ncontent-length: 0\r\n\r\n",
+                    addr.port()
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn http_requests_are_checked_against_the_egress_policy() {
+        exec::execute_with(|| {
+            Pink::set_http_egress_policy(HttpEgressPolicy {
+                allowed_hosts: vec!["127.0.0.1".into()],
+                ..Default::default()
+            });
+            using_mode(CallMode::Query, None, || {
+                let call = CallInQuery {
+                    address: AccountId::new([1; 32]),
+                    worker_pubkey: Default::default(),
+                };
+
+                let result = call.try_http_request(get("http://example.com/".into()));
+                assert_eq!(result.unwrap().err(), Some(HttpRequestError::NotAllowed));
+                let results = call
+                    .batch_http_request(vec![get("http://EXAMPLE.com/".into())], 1000)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(
+                    results[0].as_ref().err(),
+                    Some(&HttpRequestError::NotAllowed)
+                );
+                let response = call
+                    .http_request(get("http://example.com/".into()))
+                    .unwrap();
+                assert_eq!(response.status_code, 523);
+
+                // An allowed host can not redirect the request to a disallowed one.
+                let addr = serve_redirect();
+                let result = call.try_http_request(get(format!("http://{addr}/")));
+                assert_eq!(result.unwrap().err(), Some(HttpRequestError::NotAllowed));
+            });
+        });
+    }
 }
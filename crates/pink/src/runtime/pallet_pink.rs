@@ -69,6 +69,12 @@ pub mod pallet {
     #[pallet::getter(fn system_contract)]
     pub(crate) type SystemContract<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
 
+    /// The outbound HTTP policy of the cluster
+    #[pallet::storage]
+    #[pallet::getter(fn http_egress_policy)]
+    pub(crate) type HttpEgressPolicy<T: Config> =
+        StorageValue<_, pink_extension::chain_extension::HttpEgressPolicy, OptionQuery>;
+
     #[pallet::pallet]
     #[pallet::without_storage_info]
     pub struct Pallet<T>(PhantomData<T>);
@@ -121,6 +127,10 @@ pub mod pallet {
             <SystemContract<T>>::put(address);
         }
 
+        pub fn set_http_egress_policy(policy: pink_extension::chain_extension::HttpEgressPolicy) {
+            <HttpEgressPolicy<T>>::put(policy);
+        }
+
         pub fn pay_for_gas(user: &T::AccountId, gas: Weight) -> DispatchResult {
             Self::pay(user, Self::convert(gas))
         }
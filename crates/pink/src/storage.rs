@@ -9,6 +9,7 @@ use frame_system::RawOrigin;
 use pallet_contracts::Determinism;
 use phala_crypto::sr25519::Sr25519SecretKey;
 use phala_trie_storage::{deserialize_trie_backend, serialize_trie_backend, MemoryDB};
+use pink_extension::chain_extension::HttpEgressPolicy;
 use serde::{Deserialize, Serialize};
 use sp_runtime::DispatchError;
 use sp_state_machine::backend::AsTrieBackend;
@@ -212,6 +213,17 @@ where
         self.execute_with(true, None, PalletPink::system_contract).0
     }
 
+    pub fn set_http_egress_policy(&mut self, policy: HttpEgressPolicy) {
+        self.execute_mut(false, None, move || {
+            PalletPink::set_http_egress_policy(policy);
+        });
+    }
+
+    pub fn http_egress_policy(&self) -> Option<HttpEgressPolicy> {
+        self.execute_with(true, None, PalletPink::http_egress_policy)
+            .0
+    }
+
     pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
         self.backend.storage(key).ok().flatten()
     }
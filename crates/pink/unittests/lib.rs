@@ -51,6 +51,43 @@ mod tests {
         assert!(!pass);
     }
 
+    #[test]
+    fn test_schnorr_and_bls_signing() {
+        use pink::chain_extension::signing as sig;
+        use pink::chain_extension::SigType;
+
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let privkey = [1u8; 32];
+        let message = [2u8; 32];
+        for sigtype in [SigType::SchnorrSecp256k1, SigType::Bls12381] {
+            let pubkey = sig::get_public_key(&privkey, sigtype.clone());
+            let signature = sig::sign(&message, &privkey, sigtype.clone());
+            assert!(sig::verify(&message, &pubkey, &signature, sigtype.clone()));
+            assert!(!sig::verify(&[3u8; 32], &pubkey, &signature, sigtype));
+        }
+    }
+
+    #[test]
+    fn test_derive_key() {
+        use pink::chain_extension::signing as sig;
+        use pink::chain_extension::SigType;
+
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let privkey = sig::derive_key(SigType::Sr25519, b"salt");
+        assert_eq!(privkey, sig::derive_sr25519_key(b"salt"));
+
+        let privkey = sig::derive_key(SigType::Ecdsa, b"salt");
+        assert_eq!(privkey.len(), 32);
+        assert_eq!(privkey, sig::derive_key(SigType::Ecdsa, b"salt"));
+        assert_ne!(privkey, sig::derive_key(SigType::Ed25519, b"salt"));
+
+        let pubkey = sig::get_public_key(&privkey, SigType::Ecdsa);
+        let signature = sig::sign(b"hello", &privkey, SigType::Ecdsa);
+        assert!(sig::verify(b"hello", &pubkey, &signature, SigType::Ecdsa));
+    }
+
     #[test]
     fn local_cache_works_thread0() {
         pink_extension_runtime::mock_ext::mock_all_ext();
@@ -90,4 +127,271 @@ mod tests {
             .as_millis();
         assert!((now as i128 - ms as i128).abs() < 100);
     }
+
+    /// Serve every connection to a random local port with `handler`.
+    fn serve(handler: fn(std::net::TcpStream)) -> std::net::SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                std::thread::spawn(move || handler(stream));
+            }
+        });
+        addr
+    }
+
+    /// A URL that nobody listens on.
+    fn unreachable_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    }
+
+    #[test]
+    fn http_request_keeps_legacy_unreachable_response() {
+        use pink::chain_extension::HttpRequest;
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let response = pink::ext().http_request(HttpRequest {
+            url: unreachable_url(),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        });
+        assert_eq!(response.status_code, 523);
+        assert_eq!(response.reason_phrase, "Unreachable");
+        assert!(response.body.starts_with(b"reqwest::Error"));
+    }
+
+    #[test]
+    fn batch_http_request_rejects_too_many_requests() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let requests = (0..11)
+            .map(|_| HttpRequest {
+                url: "http://localhost".into(),
+                method: "GET".into(),
+                headers: vec![],
+                body: vec![],
+            })
+            .collect();
+        let result = pink::ext().batch_http_request(requests, 1000);
+        assert_eq!(result.err(), Some(HttpRequestError::TooManyRequests));
+    }
+
+    #[test]
+    fn batch_http_request_reports_errors_per_request() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let request = |url: &str, method: &str| HttpRequest {
+            url: url.into(),
+            method: method.into(),
+            headers: vec![],
+            body: vec![],
+        };
+        let requests = vec![
+            request("not a url", "GET"),
+            request("http://localhost", "NOT A METHOD"),
+        ];
+        let results = pink::ext().batch_http_request(requests, 1000).unwrap();
+        let errors: Vec<_> = results.into_iter().map(|r| r.err()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                Some(HttpRequestError::InvalidUrl),
+                Some(HttpRequestError::InvalidMethod)
+            ]
+        );
+    }
+
+    #[test]
+    fn try_http_request_returns_typed_error() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let result = pink::ext().try_http_request(HttpRequest {
+            url: "not a url".into(),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        });
+        assert_eq!(result.err(), Some(HttpRequestError::InvalidUrl));
+    }
+
+    #[test]
+    fn ranged_http_request_rejects_empty_range() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let request = HttpRequest {
+            url: "http://localhost".into(),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        };
+        let result = pink::ext().ranged_http_request(request, 0, 0);
+        assert_eq!(result.err(), Some(HttpRequestError::InvalidRange));
+    }
+
+    #[test]
+    fn try_http_request_reports_connection_refused() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let result = pink::ext().try_http_request(HttpRequest {
+            url: unreachable_url(),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        });
+        assert_eq!(result.err(), Some(HttpRequestError::ConnectionRefused));
+    }
+
+    #[test]
+    fn try_http_request_reports_dns_error() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        // The .invalid TLD is reserved to never resolve.
+        let result = pink::ext().try_http_request(HttpRequest {
+            url: "http://unresolvable.invalid/".into(),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        });
+        assert_eq!(result.err(), Some(HttpRequestError::DnsError));
+    }
+
+    #[test]
+    fn try_http_request_reports_tls_error() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        use std::io::{Read, Write};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        // Answer the TLS handshake with plain HTTP.
+        let addr = serve(|mut stream| {
+            let _ = stream.read(&mut [0; 1024]);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n");
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        });
+        let result = pink::ext().try_http_request(HttpRequest {
+            url: format!("https://{addr}/"),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        });
+        assert_eq!(result.err(), Some(HttpRequestError::TlsError));
+    }
+
+    #[test]
+    fn batch_http_request_reports_timeout() {
+        use pink::chain_extension::{HttpRequest, HttpRequestError};
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        // Accept the connection but never respond.
+        let addr = serve(|stream| {
+            std::thread::sleep(std::time::Duration::from_secs(3));
+            drop(stream);
+        });
+        let request = HttpRequest {
+            url: format!("http://{addr}/"),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        };
+        let results = pink::ext().batch_http_request(vec![request], 200).unwrap();
+        assert_eq!(results[0].as_ref().err(), Some(&HttpRequestError::Timeout));
+    }
+
+    const RANGE_BODY: &[u8] = b"0123456789";
+
+    /// Read the head of an HTTP request, lowercased.
+    fn read_request_head(stream: &mut std::net::TcpStream) -> String {
+        use std::io::Read;
+
+        let mut head = Vec::new();
+        let mut byte = [0u8];
+        while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap_or(0) == 1 {
+            head.push(byte[0]);
+        }
+        String::from_utf8_lossy(&head).to_ascii_lowercase()
+    }
+
+    /// Serve `RANGE_BODY`, honoring `Range: bytes=first-last` headers.
+    fn serve_ranges(mut stream: std::net::TcpStream) {
+        use std::io::Write;
+
+        let head = read_request_head(&mut stream);
+        let (first, last) = head
+            .lines()
+            .find_map(|line| line.strip_prefix("range: bytes="))
+            .and_then(|range| range.split_once('-'))
+            .map(|(first, last)| (first.parse().unwrap(), last.parse::<usize>().unwrap()))
+            .unwrap_or((0, RANGE_BODY.len() - 1));
+        let response = if first >= RANGE_BODY.len() {
+            b"HTTP/1.1 416 Range Not Satisfiable\r\ncontent-length: 0\r\n\r\n".to_vec()
+        } else {
+            let part = &RANGE_BODY[first..=last.min(RANGE_BODY.len() - 1)];
+            let head = format!(
+                "HTTP/1.1 206 Partial Content\r\ncontent-length: {}\r\n\r\n",
+                part.len()
+            );
+            [head.as_bytes(), part].concat()
+        };
+        let _ = stream.write_all(&response);
+    }
+
+    /// Serve the whole `RANGE_BODY` regardless of the `Range` header.
+    fn serve_ignoring_ranges(mut stream: std::net::TcpStream) {
+        use std::io::Write;
+
+        read_request_head(&mut stream);
+        let head = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n",
+            RANGE_BODY.len()
+        );
+        let _ = stream.write_all(&[head.as_bytes(), RANGE_BODY].concat());
+    }
+
+    fn fetch_range(addr: std::net::SocketAddr, offset: u64, length: u32) -> (u16, Vec<u8>) {
+        use pink::chain_extension::HttpRequest;
+
+        let request = HttpRequest {
+            url: format!("http://{addr}/"),
+            method: "GET".into(),
+            headers: vec![],
+            body: vec![],
+        };
+        let response = pink::ext()
+            .ranged_http_request(request, offset, length)
+            .unwrap();
+        (response.status_code, response.body)
+    }
+
+    #[test]
+    fn ranged_http_request_fetches_a_range() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let addr = serve(serve_ranges);
+        assert_eq!(fetch_range(addr, 2, 4), (206, b"2345".to_vec()));
+        assert_eq!(fetch_range(addr, 8, 4), (206, b"89".to_vec()));
+    }
+
+    #[test]
+    fn ranged_http_request_reports_out_of_range_offset() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let addr = serve(serve_ranges);
+        assert_eq!(fetch_range(addr, 20, 4), (416, vec![]));
+    }
+
+    #[test]
+    fn ranged_http_request_skips_bytes_if_range_is_ignored() {
+        pink_extension_runtime::mock_ext::mock_all_ext();
+
+        let addr = serve(serve_ignoring_ranges);
+        assert_eq!(fetch_range(addr, 2, 4), (200, b"2345".to_vec()));
+        assert_eq!(fetch_range(addr, 20, 4), (200, vec![]));
+    }
 }
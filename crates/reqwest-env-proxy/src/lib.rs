@@ -2,22 +2,39 @@ use reqwest::Proxy;
 
 pub trait EnvProxyBuilder {
     fn env_proxy(self, domain: &str) -> Self;
+
+    /// Like `env_proxy`, but picks the proxy for the host of each request, so that the client can
+    /// be shared by requests to different domains.
+    fn env_proxy_per_host(self) -> Self;
 }
 
-fn proxies_from_env(domain: &str) -> Option<(Proxy, Proxy)> {
+fn proxy_uri_from_env(domain: &str) -> Option<String> {
     let uri = if domain.ends_with(".i2p") {
         std::env::var("i2p_proxy").ok()
     } else {
         None
     };
 
-    let uri = uri.or_else(|| std::env::var("all_proxy").ok())?;
+    uri.or_else(|| std::env::var("all_proxy").ok())
+}
+
+/// Whether the requests to `domain` go through a proxy configured in the environment.
+pub fn has_env_proxy(domain: &str) -> bool {
+    proxy_uri_from_env(domain).is_some()
+}
+
+fn proxies_from_env(domain: &str) -> Option<(Proxy, Proxy)> {
+    let uri = proxy_uri_from_env(domain)?;
 
     let http_proxy = Proxy::http(&uri).ok()?;
     let https_proxy = Proxy::https(&uri).ok()?;
     Some((http_proxy, https_proxy))
 }
 
+fn per_host_proxy() -> Proxy {
+    Proxy::custom(|url| proxy_uri_from_env(url.host_str().unwrap_or_default()))
+}
+
 impl EnvProxyBuilder for reqwest::ClientBuilder {
     fn env_proxy(self, domain: &str) -> Self {
         match proxies_from_env(domain) {
@@ -25,6 +42,10 @@ impl EnvProxyBuilder for reqwest::ClientBuilder {
             None => self,
         }
     }
+
+    fn env_proxy_per_host(self) -> Self {
+        self.proxy(per_host_proxy())
+    }
 }
 
 #[cfg(feature = "blocking")]
@@ -35,4 +56,8 @@ impl EnvProxyBuilder for reqwest::blocking::ClientBuilder {
             None => self,
         }
     }
+
+    fn env_proxy_per_host(self) -> Self {
+        self.proxy(per_host_proxy())
+    }
 }
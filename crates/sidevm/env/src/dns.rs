@@ -0,0 +1,52 @@
+//! Types used by the DNS resolution ocall.
+use scale::{Decode, Encode};
+
+use crate::args_stack::I32Convertible;
+use crate::{OcallError, Result};
+
+/// Type of the DNS records to look up.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordType {
+    /// IPv4 addresses.
+    A = 1,
+    /// IPv6 addresses.
+    Aaaa = 2,
+    /// Text records.
+    Txt = 3,
+    /// Service locations.
+    Srv = 4,
+}
+
+impl I32Convertible for RecordType {
+    fn to_i32(&self) -> i32 {
+        *self as i32
+    }
+    fn from_i32(i: i32) -> Result<Self> {
+        match i {
+            1 => Ok(RecordType::A),
+            2 => Ok(RecordType::Aaaa),
+            3 => Ok(RecordType::Txt),
+            4 => Ok(RecordType::Srv),
+            _ => Err(OcallError::InvalidParameter),
+        }
+    }
+}
+
+/// A DNS record returned by the `resolve` ocall.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub enum Record {
+    /// An IPv4 address.
+    A([u8; 4]),
+    /// An IPv6 address.
+    Aaaa([u8; 16]),
+    /// The character strings of a text record.
+    Txt(Vec<Vec<u8>>),
+    /// A service location.
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+}
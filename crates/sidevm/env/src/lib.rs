@@ -21,6 +21,7 @@ pub use sidevm_macro::main;
 pub use tasks::spawn;
 
 mod args_stack;
+pub mod dns;
 mod ocall_def;
 pub mod tasks;
 pub mod messages;
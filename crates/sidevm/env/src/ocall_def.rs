@@ -1,5 +1,6 @@
 use super::*;
 use crate::args_stack::{I32Convertible, RetDecode, StackedArgs};
+use crate::dns::RecordType;
 use crate::tls::{TlsClientConfig, TlsServerConfig};
 use std::borrow::Cow;
 
@@ -50,6 +51,10 @@ pub trait OcallFuncs {
     #[ocall(id = 113)]
     fn getrandom(buf: &mut [u8]) -> Result<()>;
 
+    /// Allow the host to snapshot the VM at idle points and resume it after the worker restarts.
+    #[ocall(id = 114)]
+    fn enable_snapshot(enable: bool) -> Result<()>;
+
     /// Create a timer given a duration of time in milliseconds.
     #[ocall(id = 201)]
     fn create_timer(timeout: i32) -> Result<i32>;
@@ -85,6 +90,29 @@ pub trait OcallFuncs {
     #[ocall(id = 214, encode_input)]
     fn tcp_connect_tls(host: String, port: u16, config: TlsClientConfig) -> Result<i32>;
 
+    /// Create a UDP socket bound to given address.
+    #[ocall(id = 215)]
+    fn udp_bind(addr: &str) -> Result<i32>;
+
+    /// Send a datagram to given address. Low level support for UdpSocket::send_to.
+    ///
+    /// The address must be a literal socket address such as `1.1.1.1:53`.
+    #[ocall(id = 216, encode_input)]
+    fn udp_send_to(waker_id: i32, resource_id: i32, addr: Cow<str>, data: Cow<[u8]>)
+        -> Result<u32>;
+
+    /// Receive a datagram. Returns the size of the datagram and the address of the sender.
+    ///
+    /// The datagram is truncated if it doesn't fit in `buf`.
+    #[ocall(id = 217, encode_output)]
+    fn udp_recv_from(waker_id: i32, resource_id: i32, buf: &mut [u8]) -> Result<(u32, String)>;
+
+    /// Look up the DNS records of given type for the name.
+    ///
+    /// Returns a resource id. Poll it to get the SCALE encoded `Vec<dns::Record>`.
+    #[ocall(id = 218)]
+    fn resolve(name: &str, record_type: RecordType) -> Result<i32>;
+
     /// Print log message.
     #[ocall(id = 220)]
     fn log(level: log::Level, message: &str) -> Result<()>;
@@ -110,6 +138,28 @@ pub trait OcallFuncs {
     /// Create input channel
     #[ocall(id = 240, encode_output)]
     fn create_input_channel(ch: InputChannel) -> Result<i32>;
+
+    /// Get value from the persistent storage of this VM.
+    #[ocall(id = 250, encode_output)]
+    fn storage_get(key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Set value to the persistent storage of this VM.
+    #[ocall(id = 251)]
+    fn storage_set(key: &[u8], value: &[u8]) -> Result<()>;
+
+    /// Remove a value from the persistent storage of this VM.
+    ///
+    /// Returns the previous value if it existed.
+    #[ocall(id = 252, encode_output)]
+    fn storage_remove(key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Query a contract in the same cluster with an ink message, using the contract of this VM as
+    /// the origin.
+    ///
+    /// Returns a resource id. Poll it to get the SCALE encoded `ContractExecResult` of the read-only
+    /// call against the cluster storage.
+    #[ocall(id = 260)]
+    fn contract_query(contract_id: &[u8], payload: &[u8]) -> Result<i32>;
 }
 
 #[repr(u8)]
@@ -52,6 +52,9 @@ impl GuestWaker {
                 .tasks
                 .clone()
         });
+        if let Some(tasks) = tasks.upgrade() {
+            tasks.note_waker(id);
+        }
         Self {
             inner: Arc::new(GuestWakerInner { tasks, id }),
         }
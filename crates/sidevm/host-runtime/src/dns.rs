@@ -0,0 +1,57 @@
+use once_cell::sync::Lazy;
+use sidevm_env::{
+    dns::{Record, RecordType},
+    OcallError, Result,
+};
+use trust_dns_resolver::{error::ResolveErrorKind, TokioAsyncResolver};
+
+static RESOLVER: Lazy<Option<TokioAsyncResolver>> =
+    Lazy::new(|| match TokioAsyncResolver::tokio_from_system_conf() {
+        Ok(resolver) => Some(resolver),
+        Err(err) => {
+            log::error!("Failed to create the DNS resolver: {}", err);
+            None
+        }
+    });
+
+/// Look up the records of given type, returning an empty list if there is no such record.
+pub(crate) async fn resolve(name: &str, record_type: RecordType) -> Result<Vec<Record>> {
+    let resolver = RESOLVER.as_ref().ok_or(OcallError::IoError)?;
+    let result = match record_type {
+        RecordType::A => resolver
+            .ipv4_lookup(name)
+            .await
+            .map(|lookup| lookup.iter().map(|ip| Record::A(ip.octets())).collect()),
+        RecordType::Aaaa => resolver
+            .ipv6_lookup(name)
+            .await
+            .map(|lookup| lookup.iter().map(|ip| Record::Aaaa(ip.octets())).collect()),
+        RecordType::Txt => resolver.txt_lookup(name).await.map(|lookup| {
+            lookup
+                .iter()
+                .map(|txt| Record::Txt(txt.txt_data().iter().map(|s| s.to_vec()).collect()))
+                .collect()
+        }),
+        RecordType::Srv => resolver.srv_lookup(name).await.map(|lookup| {
+            lookup
+                .iter()
+                .map(|srv| Record::Srv {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port(),
+                    target: srv.target().to_utf8(),
+                })
+                .collect()
+        }),
+    };
+    match result {
+        Ok(records) => Ok(records),
+        Err(err) => match err.kind() {
+            ResolveErrorKind::NoRecordsFound { .. } => Ok(vec![]),
+            _ => {
+                log::error!("DNS lookup error: {}", err);
+                Err(OcallError::IoError)
+            }
+        },
+    }
+}
@@ -4,15 +4,19 @@ use std::{
     collections::VecDeque,
     fmt,
     future::Future,
+    net::SocketAddr,
     ops::{Deref, DerefMut},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        Arc, Mutex,
+    },
     task::Poll::{Pending, Ready},
     time::Duration,
 };
 
 use serde::{Deserialize, Serialize};
 use tokio::{
-    net::TcpListener,
+    net::{TcpListener, UdpSocket},
     sync::mpsc::{error::TrySendError, Sender},
     sync::oneshot::Sender as OneshotSender,
 };
@@ -22,6 +26,7 @@ use wasmer::{
 };
 
 use env::{
+    dns::RecordType,
     messages::{AccountId, QueryRequest, SystemMessage},
     tls::{TlsClientConfig, TlsServerConfig},
     IntPtr, IntRet, OcallError, Result, RetEncode,
@@ -34,12 +39,17 @@ use wasmer_middlewares::metering;
 use crate::{
     async_context::{get_task_cx, set_task_env, GuestWaker},
     resource::{Resource, ResourceKeeper},
+    service::Report,
     tls::{load_tls_config, TlsStream},
+    vfs::Vfs,
     VmId,
 };
 
+mod snapshot;
 mod wasi_env;
 
+pub use snapshot::VmSnapshot;
+
 pub struct FnEnvMut<'a, T> {
     store: StoreMut<'a>,
     inner: T,
@@ -82,8 +92,13 @@ fn _sizeof_i32_must_eq_to_intptr() {
     let _ = core::mem::transmute::<i32, IntPtr>;
 }
 
-pub fn create_env(id: VmId, store: &mut Store, cache_ops: DynCacheOps) -> (Env, Imports) {
-    let raw_env = Env::new(id, cache_ops);
+pub fn create_env(
+    id: VmId,
+    store: &mut Store,
+    cache_ops: DynCacheOps,
+    storage_ops: Option<DynStorageOps>,
+) -> (Env, Imports) {
+    let raw_env = Env::new(id, cache_ops, storage_ops);
     let env = FunctionEnv::new(store, raw_env.clone());
     let wasi_imports = wasi_env::wasi_imports(store, &env);
     (
@@ -110,18 +125,25 @@ pub(crate) struct TaskSet {
     awake_tasks: dashmap::DashSet<i32>,
     /// Guest waker ids that are ready to be woken up, or to be dropped if negative.
     pub(crate) awake_wakers: Mutex<VecDeque<i32>>,
+    /// The max guest waker id ever seen by the host.
+    max_waker_id: AtomicI32,
 }
 
 impl TaskSet {
-    fn with_task0() -> Self {
+    pub(crate) fn with_task0() -> Self {
         let awake_tasks = dashmap::DashSet::new();
         awake_tasks.insert(0);
         Self {
             awake_tasks,
             awake_wakers: Default::default(),
+            max_waker_id: AtomicI32::new(-1),
         }
     }
 
+    pub(crate) fn note_waker(&self, waker_id: i32) {
+        self.max_waker_id.fetch_max(waker_id, Ordering::Relaxed);
+    }
+
     pub(crate) fn push_task(&self, task_id: i32) {
         self.awake_tasks.insert(task_id);
     }
@@ -151,6 +173,17 @@ pub trait CacheOps {
 
 pub type DynCacheOps = &'static (dyn CacheOps + Send + Sync);
 
+/// The persistent storage of a single VM instance.
+pub trait StorageOps {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+    fn set(&self, key: &[u8], value: &[u8]) -> Result<()>;
+    fn remove(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+}
+
+pub type DynStorageOps = Arc<dyn StorageOps + Send + Sync>;
+
+const INPUT_CHANNEL_SIZE: usize = 20;
+
 struct VmMemory(Option<Memory>);
 
 pub(crate) struct EnvInner {
@@ -166,8 +199,13 @@ pub(crate) struct EnvInner {
     awake_tasks: Arc<TaskSet>,
     current_task: i32,
     cache_ops: DynCacheOps,
+    storage_ops: Option<DynStorageOps>,
     weight: u32,
     instance: Option<Instance>,
+    vfs: Vfs,
+    report_tx: Option<Sender<Report>>,
+    /// Whether the guest enabled snapshots, shared with the host to only snapshot such VMs.
+    snapshot_enabled: Arc<AtomicBool>,
 }
 
 impl VmMemory {
@@ -225,7 +263,7 @@ pub struct Env {
 }
 
 impl Env {
-    fn new(id: VmId, cache_ops: DynCacheOps) -> Self {
+    fn new(id: VmId, cache_ops: DynCacheOps, storage_ops: Option<DynStorageOps>) -> Self {
         Self {
             inner: Arc::new(Mutex::new(EnvInner {
                 memory: VmMemory(None),
@@ -240,8 +278,12 @@ impl Env {
                 awake_tasks: Arc::new(TaskSet::with_task0()),
                 current_task: 0,
                 cache_ops,
+                storage_ops,
                 weight: 1,
                 instance: None,
+                vfs: Default::default(),
+                report_tx: None,
+                snapshot_enabled: Default::default(),
             })),
         }
     }
@@ -250,6 +292,11 @@ impl Env {
         self.inner.lock().unwrap().memory.0 = Some(memory);
     }
 
+    /// The flag telling whether the guest has enabled snapshots.
+    pub(crate) fn snapshot_enabled(&self) -> Arc<AtomicBool> {
+        self.inner.lock().unwrap().snapshot_enabled.clone()
+    }
+
     pub fn cleanup(&self) {
         // Cut up the reference cycle to avoid leaks.
         self.inner.lock().unwrap().memory.0 = None;
@@ -306,6 +353,10 @@ impl Env {
         self.inner.lock().unwrap().gas_per_breath = gas;
     }
 
+    pub fn set_report_tx(&self, report_tx: Sender<Report>) {
+        self.inner.lock().unwrap().report_tx = Some(report_tx);
+    }
+
     pub fn reset_gas_to_breath(&self, store: &mut impl AsStoreMut) {
         let guard = self.inner.lock().unwrap();
         let instance = guard
@@ -330,6 +381,11 @@ impl Env {
         log::debug!(target: "sidevm", "[{}] Updated weight to {}", vm_id, weight);
     }
 
+    /// Preload the virtual filesystem with the files in given tar bundle.
+    pub fn load_fs_bundle(&self, bundle: &[u8]) -> anyhow::Result<()> {
+        self.inner.lock().unwrap().vfs.load_tar(bundle)
+    }
+
     pub fn set_instance(&self, instance: Instance) {
         self.inner.lock().unwrap().instance = Some(instance);
     }
@@ -400,6 +456,11 @@ impl<'a, 'b> env::OcallFuncs for FnEnvMut<'a, &'b mut EnvInner> {
         Ok(())
     }
 
+    fn enable_snapshot(&mut self, enable: bool) -> Result<()> {
+        self.snapshot_enabled.store(enable, Ordering::Relaxed);
+        Ok(())
+    }
+
     fn tcp_listen(&mut self, addr: Cow<str>, tls_config: Option<TlsServerConfig>) -> Result<i32> {
         let std_listener = std::net::TcpListener::bind(&*addr).or(Err(OcallError::IoError))?;
         std_listener
@@ -472,6 +533,73 @@ impl<'a, 'b> env::OcallFuncs for FnEnvMut<'a, &'b mut EnvInner> {
         self.resources.push(Resource::TlsConnect(Box::pin(fut)))
     }
 
+    fn udp_bind(&mut self, addr: &str) -> Result<i32> {
+        // UDP can't go through the proxy, so it would reveal the real IP of the worker
+        if proxy_enabled() {
+            return Err(OcallError::UnsupportedOperation);
+        }
+        let std_socket = std::net::UdpSocket::bind(addr).or(Err(OcallError::IoError))?;
+        std_socket
+            .set_nonblocking(true)
+            .or(Err(OcallError::IoError))?;
+        let socket = UdpSocket::from_std(std_socket).or(Err(OcallError::IoError))?;
+        self.resources.push(Resource::UdpSocket(socket))
+    }
+
+    fn udp_send_to(
+        &mut self,
+        waker_id: i32,
+        resource_id: i32,
+        addr: Cow<str>,
+        data: Cow<[u8]>,
+    ) -> Result<u32> {
+        let target: SocketAddr = addr.parse().or(Err(OcallError::InvalidParameter))?;
+        let waker = GuestWaker::from_id(waker_id);
+        let socket = match self.resources.get_mut(resource_id)? {
+            Resource::UdpSocket(socket) => socket,
+            _ => return Err(OcallError::UnsupportedOperation),
+        };
+        match get_task_cx(waker, |cx| socket.poll_send_to(cx, &data, target)) {
+            Pending => Err(OcallError::Pending),
+            Ready(result) => result.map(|sz| sz as _).or(Err(OcallError::IoError)),
+        }
+    }
+
+    fn udp_recv_from(
+        &mut self,
+        waker_id: i32,
+        resource_id: i32,
+        buf: &mut [u8],
+    ) -> Result<(u32, String)> {
+        let waker = GuestWaker::from_id(waker_id);
+        let socket = match self.resources.get_mut(resource_id)? {
+            Resource::UdpSocket(socket) => socket,
+            _ => return Err(OcallError::UnsupportedOperation),
+        };
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        match get_task_cx(waker, |cx| socket.poll_recv_from(cx, &mut buf)) {
+            Pending => Err(OcallError::Pending),
+            Ready(result) => {
+                let addr = result.or(Err(OcallError::IoError))?;
+                Ok((buf.filled().len() as _, addr.to_string()))
+            }
+        }
+    }
+
+    fn resolve(&mut self, name: &str, record_type: RecordType) -> Result<i32> {
+        if name.len() > 253 {
+            return Err(OcallError::InvalidParameter);
+        }
+        // The lookups can't go through the proxy, so they would reveal the real IP of the worker
+        if proxy_enabled() {
+            return Err(OcallError::UnsupportedOperation);
+        }
+        let name = name.to_owned();
+        let fut = async move { crate::dns::resolve(&name, record_type).await };
+        self.resources
+            .push(Resource::DnsLookup(Some(Box::pin(fut))))
+    }
+
     fn log(&mut self, level: log::Level, message: &str) -> Result<()> {
         let task = self.current_task;
         let vm_id = ShortId(&self.id);
@@ -496,6 +624,18 @@ impl<'a, 'b> env::OcallFuncs for FnEnvMut<'a, &'b mut EnvInner> {
         self.cache_ops.remove(&self.id[..], key)
     }
 
+    fn storage_get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.storage()?.get(key)
+    }
+
+    fn storage_set(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.storage()?.set(key, value)
+    }
+
+    fn storage_remove(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.storage()?.remove(key)
+    }
+
     fn awake_wakers(&mut self) -> Result<Vec<i32>> {
         Ok(self
             .awake_tasks
@@ -535,8 +675,8 @@ impl<'a, 'b> env::OcallFuncs for FnEnvMut<'a, &'b mut EnvInner> {
                 if $field.is_some() {
                     return Err(OcallError::AlreadyExists);
                 }
-                let (tx, rx) = tokio::sync::mpsc::channel(20);
-                let res = self.resources.push(Resource::ChannelRx(rx))?;
+                let (tx, rx) = tokio::sync::mpsc::channel(INPUT_CHANNEL_SIZE);
+                let res = self.resources.push(Resource::ChannelRx { kind: ch, rx })?;
                 $field = Some(tx);
                 Ok(res)
             }};
@@ -548,6 +688,26 @@ impl<'a, 'b> env::OcallFuncs for FnEnvMut<'a, &'b mut EnvInner> {
         }
     }
 
+    fn contract_query(&mut self, contract_id: &[u8], payload: &[u8]) -> Result<i32> {
+        let contract_id = contract_id
+            .try_into()
+            .or(Err(OcallError::InvalidParameter))?;
+        let report_tx = self
+            .report_tx
+            .as_ref()
+            .ok_or(OcallError::UnsupportedOperation)?;
+        let (reply_tx, rx) = tokio::sync::oneshot::channel();
+        report_tx
+            .try_send(Report::ContractQuery {
+                from: self.id,
+                contract_id,
+                payload: payload.to_vec(),
+                reply_tx,
+            })
+            .or(Err(OcallError::ResourceLimited))?;
+        self.resources.push(Resource::OneshotRx(Some(rx)))
+    }
+
     fn gas_remaining(&mut self) -> Result<u8> {
         self.inner.pay(&mut self.store, 1_000_000)?;
         Ok(if self.gas_per_breath == 0 {
@@ -573,6 +733,12 @@ impl EnvInner {
         }
     }
 
+    fn storage(&self) -> Result<&DynStorageOps> {
+        self.storage_ops
+            .as_ref()
+            .ok_or(OcallError::UnsupportedOperation)
+    }
+
     fn is_stifled(&mut self, store: &mut impl AsStoreMut) -> bool {
         let instance = self.instance.as_ref().expect("BUG: instance is not set");
         match metering::get_remaining_points(store, instance) {
@@ -604,17 +770,22 @@ impl EnvInner {
     }
 }
 
-async fn tcp_connect(host: &str, port: u16) -> std::io::Result<tokio::net::TcpStream> {
-    fn get_proxy(key: &str) -> Option<String> {
-        std::env::var(key).ok().and_then(|uri| {
-            if uri.trim().is_empty() {
-                None
-            } else {
-                Some(uri)
-            }
-        })
-    }
+fn get_proxy(key: &str) -> Option<String> {
+    std::env::var(key).ok().and_then(|uri| {
+        if uri.trim().is_empty() {
+            None
+        } else {
+            Some(uri)
+        }
+    })
+}
 
+/// Whether the outgoing connections are configured to go through a proxy
+fn proxy_enabled() -> bool {
+    get_proxy("all_proxy").is_some() || get_proxy("i2p_proxy").is_some()
+}
+
+async fn tcp_connect(host: &str, port: u16) -> std::io::Result<tokio::net::TcpStream> {
     let proxy_url = if host.ends_with(".i2p") {
         get_proxy("i2p_proxy")
     } else {
@@ -721,3 +892,74 @@ impl fmt::Display for OcallAborted {
 }
 
 impl std::error::Error for OcallAborted {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use env::OcallFuncs;
+
+    /// Serializes the tests changing the proxy environment variables
+    pub(crate) static PROXY_ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    pub(crate) struct NoCache;
+    impl CacheOps for NoCache {
+        fn get(&self, _contract: &[u8], _key: &[u8]) -> Result<Option<Vec<u8>>> {
+            Ok(None)
+        }
+        fn set(&self, _contract: &[u8], _key: &[u8], _value: &[u8]) -> Result<()> {
+            Ok(())
+        }
+        fn set_expiration(&self, _contract: &[u8], _key: &[u8], _secs: u64) -> Result<()> {
+            Ok(())
+        }
+        fn remove(&self, _contract: &[u8], _key: &[u8]) -> Result<Option<Vec<u8>>> {
+            Ok(None)
+        }
+    }
+
+    /// Runs `f` with the ocall functions of a fresh VM env
+    pub(crate) fn with_ocall_env<T>(f: impl FnOnce(&mut FnEnvMut<'_, &mut EnvInner>) -> T) -> T {
+        let env = Env::new([0; 32], &NoCache, None);
+        let mut store = Store::default();
+        let mut guard = env.inner.lock().unwrap();
+        f(&mut FnEnvMut::new(&mut store, &mut *guard))
+    }
+
+    #[tokio::test]
+    async fn udp_is_refused_behind_a_proxy() {
+        let _guard = PROXY_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("all_proxy");
+        std::env::remove_var("i2p_proxy");
+        with_ocall_env(|env| assert!(env.udp_bind("127.0.0.1:0").is_ok()));
+
+        for key in ["all_proxy", "i2p_proxy"] {
+            std::env::set_var(key, "socks5://127.0.0.1:9050");
+            with_ocall_env(|env| {
+                assert!(matches!(
+                    env.udp_bind("127.0.0.1:0"),
+                    Err(OcallError::UnsupportedOperation)
+                ))
+            });
+            std::env::remove_var(key);
+        }
+    }
+
+    #[test]
+    fn dns_is_refused_behind_a_proxy() {
+        let _guard = PROXY_ENV_LOCK.lock().unwrap();
+        std::env::remove_var("all_proxy");
+        std::env::remove_var("i2p_proxy");
+        with_ocall_env(|env| assert!(env.resolve("example.com", RecordType::A).is_ok()));
+
+        for key in ["all_proxy", "i2p_proxy"] {
+            std::env::set_var(key, "socks5://127.0.0.1:9050");
+            with_ocall_env(|env| {
+                assert!(matches!(
+                    env.resolve("example.com", RecordType::A),
+                    Err(OcallError::UnsupportedOperation)
+                ))
+            });
+            std::env::remove_var(key);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Snapshots of the guest state, used to resume a VM across worker restarts.
+//!
+//! A snapshot is taken at an idle point between two polls of the guest, where no ocall is in
+//! progress. It consists of the linear memory, the exported mutable globals, the resource table
+//! and the virtual filesystem. Globals that are not exported are assumed to hold their initial
+//! values at idle points, which is the case for the shadow stack pointer emitted by rustc.
+//!
+//! Input channels and timers are recreated on restore. Other resources, such as sockets and
+//! pending ocall results, are bound to the host and are replaced by [`Resource::Lost`], which looks
+//! like a broken connection to the guest. Messages buffered in the input channels are not kept.
+
+use std::{sync::atomic::Ordering, time::Duration};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use sidevm_env::InputChannel;
+use tokio::time::Instant;
+use wasmer::{AsStoreMut, Extern, Mutability, Pages, Value};
+
+use super::{Env, EnvInner, INPUT_CHANNEL_SIZE};
+use crate::{
+    resource::{Resource, ResourceKeeper},
+    vfs::Vfs,
+};
+
+#[derive(Serialize, Deserialize)]
+enum GlobalValue {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+}
+
+impl GlobalValue {
+    fn from_value(value: Value) -> Option<Self> {
+        Some(match value {
+            Value::I32(v) => Self::I32(v),
+            Value::I64(v) => Self::I64(v),
+            Value::F32(v) => Self::F32(v.to_bits()),
+            Value::F64(v) => Self::F64(v.to_bits()),
+            _ => return None,
+        })
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Self::I32(v) => Value::I32(v),
+            Self::I64(v) => Value::I64(v),
+            Self::F32(v) => Value::F32(f32::from_bits(v)),
+            Self::F64(v) => Value::F64(f64::from_bits(v)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum ResourceSnapshot {
+    /// An input channel, by the discriminant of [`InputChannel`].
+    InputChannel(u8),
+    /// A timer with the remaining milliseconds.
+    Sleep(u64),
+    Lost,
+}
+
+impl ResourceSnapshot {
+    fn of(res: &Resource) -> Self {
+        match res {
+            Resource::ChannelRx { kind, .. } => Self::InputChannel(*kind as u8),
+            Resource::Sleep(sleep) => {
+                let remaining = sleep.deadline().saturating_duration_since(Instant::now());
+                Self::Sleep(remaining.as_millis() as u64)
+            }
+            _ => Self::Lost,
+        }
+    }
+}
+
+/// The guest state of a VM taken at an idle point.
+#[derive(Serialize, Deserialize)]
+pub struct VmSnapshot {
+    memory_pages: u32,
+    /// The linear memory with the trailing zeros trimmed.
+    #[serde(with = "serde_bytes")]
+    memory: Vec<u8>,
+    globals: Vec<(String, GlobalValue)>,
+    resources: Vec<Option<ResourceSnapshot>>,
+    awake_tasks: Vec<i32>,
+    awake_wakers: Vec<i32>,
+    max_waker_id: i32,
+    ocall_trace_enabled: bool,
+    vfs: Vfs,
+}
+
+impl VmSnapshot {
+    /// The size of the linear memory in bytes.
+    pub fn memory_size(&self) -> usize {
+        self.memory_pages as usize * wasmer::WASM_PAGE_SIZE
+    }
+}
+
+impl Env {
+    /// Take a snapshot of the guest. Must not be called while the guest is being polled.
+    pub(crate) fn snapshot(&self, store: &mut impl AsStoreMut) -> Result<VmSnapshot> {
+        let guard = self.inner.lock().unwrap();
+        if !guard.snapshot_enabled.load(Ordering::Relaxed) {
+            bail!("Snapshot is not enabled by the guest");
+        }
+        let instance = guard.instance.as_ref().context("No instance")?;
+        let mut globals = vec![];
+        for (name, ext) in instance.exports.iter() {
+            let Extern::Global(global) = ext else {
+                continue;
+            };
+            if global.ty(&*store).mutability != Mutability::Var {
+                continue;
+            }
+            let value = GlobalValue::from_value(global.get(&mut *store))
+                .ok_or_else(|| anyhow!("Unsupported type of global {name}"))?;
+            globals.push((name.clone(), value));
+        }
+
+        let view = guard.memory.unwrap_ref().view(&*store);
+        let mut memory = vec![0; view.data_size() as usize];
+        view.read(0, &mut memory)
+            .context("Failed to read the memory")?;
+        let used = memory
+            .iter()
+            .rposition(|b| *b != 0)
+            .map_or(0, |pos| pos + 1);
+        memory.truncate(used);
+
+        let tasks = &guard.awake_tasks;
+        Ok(VmSnapshot {
+            memory_pages: view.size().0,
+            memory,
+            globals,
+            resources: guard
+                .resources
+                .slots()
+                .iter()
+                .map(|res| res.as_ref().map(ResourceSnapshot::of))
+                .collect(),
+            awake_tasks: tasks.awake_tasks.iter().map(|id| *id).collect(),
+            awake_wakers: tasks.awake_wakers.lock().unwrap().iter().copied().collect(),
+            max_waker_id: tasks.max_waker_id.load(Ordering::Relaxed),
+            ocall_trace_enabled: guard.ocall_trace_enabled,
+            vfs: guard.vfs.clone(),
+        })
+    }
+
+    /// Restore the guest state into a freshly created instance.
+    pub(crate) fn restore(&self, store: &mut impl AsStoreMut, snapshot: VmSnapshot) -> Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let instance = inner.instance.as_ref().context("No instance")?;
+        for (name, value) in snapshot.globals {
+            instance
+                .exports
+                .get_global(&name)
+                .with_context(|| format!("Missing global {name}"))?
+                .set(&mut *store, value.into_value())
+                .with_context(|| format!("Failed to set global {name}"))?;
+        }
+
+        let memory = inner.memory.unwrap_ref();
+        let initial_pages = memory.view(&*store).size().0;
+        if snapshot.memory_pages < initial_pages {
+            bail!("The snapshot memory is smaller than the initial memory");
+        }
+        memory
+            .grow(&mut *store, Pages(snapshot.memory_pages - initial_pages))
+            .context("Failed to grow the memory")?;
+        let view = memory.view(&*store);
+        view.write(0, &snapshot.memory)
+            .context("Failed to write the memory")?;
+        // Clear what the data segments initialized beyond the trimmed memory.
+        let initial_size = initial_pages as usize * wasmer::WASM_PAGE_SIZE;
+        if snapshot.memory.len() < initial_size {
+            view.write(
+                snapshot.memory.len() as u64,
+                &vec![0; initial_size - snapshot.memory.len()],
+            )
+            .context("Failed to write the memory")?;
+        }
+
+        let resources = snapshot
+            .resources
+            .into_iter()
+            .map(|res| res.map(|res| inner.restore_resource(res)).transpose())
+            .collect::<Result<_>>()?;
+        inner.resources = ResourceKeeper::from_slots(resources);
+
+        let tasks = &inner.awake_tasks;
+        for task_id in snapshot.awake_tasks {
+            tasks.push_task(task_id);
+        }
+        let mut awake_wakers = tasks.awake_wakers.lock().unwrap();
+        awake_wakers.extend(snapshot.awake_wakers);
+        // All the guest wakers were held by the resources of the previous host. Wake them up to let
+        // the guest poll the restored resources again, and then drop them.
+        for waker_id in 0..=snapshot.max_waker_id {
+            awake_wakers.push_back(waker_id);
+            awake_wakers.push_back(-1 - waker_id);
+        }
+        drop(awake_wakers);
+        tasks.note_waker(snapshot.max_waker_id);
+
+        inner.ocall_trace_enabled = snapshot.ocall_trace_enabled;
+        inner.vfs = snapshot.vfs;
+        inner.snapshot_enabled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl EnvInner {
+    fn restore_resource(&mut self, res: ResourceSnapshot) -> Result<Resource> {
+        Ok(match res {
+            ResourceSnapshot::InputChannel(kind) => {
+                let (tx, rx) = tokio::sync::mpsc::channel(INPUT_CHANNEL_SIZE);
+                let kind = match kind {
+                    1 => InputChannel::SystemMessage,
+                    2 => InputChannel::GeneralMessage,
+                    3 => InputChannel::Query,
+                    _ => bail!("Invalid input channel {kind}"),
+                };
+                match kind {
+                    InputChannel::SystemMessage => self.sys_message_tx = Some(tx),
+                    InputChannel::GeneralMessage => self.message_tx = Some(tx),
+                    InputChannel::Query => self.query_tx = Some(tx),
+                }
+                Resource::ChannelRx { kind, rx }
+            }
+            ResourceSnapshot::Sleep(ms) => {
+                Resource::Sleep(Box::pin(tokio::time::sleep(Duration::from_millis(ms))))
+            }
+            ResourceSnapshot::Lost => Resource::Lost,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::{create_env, tests::NoCache};
+    use crate::vfs::ROOT_FD;
+    use wasmer::{Instance, Module, Store};
+    use wasmer_wasi_types::wasi::{Fdflags, Oflags};
+
+    const GUEST: &str = r#"(module
+        (memory (export "memory") 1)
+        (global (export "counter") (mut i32) (i32.const 0))
+        (func (export "sidevm_poll") (result i32) i32.const 0))"#;
+
+    fn instantiate(store: &mut Store) -> Env {
+        let module = Module::new(&*store, GUEST).unwrap();
+        let (env, imports) = create_env([0; 32], store, &NoCache, None);
+        let instance = Instance::new(store, &module, &imports).unwrap();
+        env.set_memory(instance.exports.get_memory("memory").unwrap().clone());
+        env.set_instance(instance);
+        env
+    }
+
+    fn contains(data: &[u8], needle: &[u8]) -> bool {
+        data.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut store = Store::default();
+        let env = instantiate(&mut store);
+        assert!(env.snapshot(&mut store).is_err());
+        {
+            let mut guard = env.inner.lock().unwrap();
+            guard.snapshot_enabled.store(true, Ordering::Relaxed);
+            let fd = guard
+                .vfs
+                .open(ROOT_FD, "a.txt", Oflags::CREATE, Fdflags::empty())
+                .unwrap();
+            guard.vfs.write(fd, b"file content").unwrap();
+            let view = guard.memory.unwrap_ref().view(&store);
+            view.write(1024, b"memory content").unwrap();
+            let instance = guard.instance.as_ref().unwrap();
+            let counter = instance.exports.get_global("counter").unwrap();
+            counter.set(&mut store, Value::I32(42)).unwrap();
+        }
+
+        let snapshot = env.snapshot(&mut store).unwrap();
+        let encoded = serde_cbor::to_vec(&snapshot).unwrap();
+        // The bytes are encoded as byte strings rather than arrays of integers.
+        assert!(contains(&encoded, b"memory content"));
+        assert!(contains(&encoded, b"file content"));
+        let snapshot: VmSnapshot = serde_cbor::from_slice(&encoded).unwrap();
+
+        let mut store = Store::default();
+        let env = instantiate(&mut store);
+        env.restore(&mut store, snapshot).unwrap();
+        let mut guard = env.inner.lock().unwrap();
+        assert!(guard.snapshot_enabled.load(Ordering::Relaxed));
+        let mut buf = [0; 14];
+        let view = guard.memory.unwrap_ref().view(&store);
+        view.read(1024, &mut buf).unwrap();
+        assert_eq!(&buf, b"memory content");
+        let instance = guard.instance.as_ref().unwrap();
+        let counter = instance.exports.get_global("counter").unwrap();
+        assert_eq!(counter.get(&mut store).i32(), Some(42));
+        let fd = guard
+            .vfs
+            .open(ROOT_FD, "a.txt", Oflags::empty(), Fdflags::empty())
+            .unwrap();
+        assert_eq!(guard.vfs.read(fd, 100).unwrap(), b"file content");
+    }
+}
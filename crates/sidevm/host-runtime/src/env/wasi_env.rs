@@ -1,15 +1,22 @@
 use super::{Env as WasiEnv, Result};
+use crate::vfs::{self, Stat, Vfs};
 use libc::{clock_getres, clock_gettime, timespec, CLOCK_MONOTONIC, CLOCK_REALTIME};
 use sidevm_env::{OcallError, OcallFuncs};
 use thiserror::Error;
 use wasmer::{
-    namespace, AsStoreMut, Exports, Function, FunctionEnv, FunctionEnvMut, Memory32, WasmPtr,
+    namespace, AsStoreMut, Exports, Function, FunctionEnv, FunctionEnvMut, Memory32, MemoryView,
+    ValueType, WasmPtr,
 };
 use wasmer_wasi_types::{
     types::*,
     wasi::{self, Errno},
 };
 
+/// The max bytes moved between the guest buffers and the host by a single read or write.
+///
+/// Larger requests are cut short, which the guest must handle anyway.
+const MAX_IO_SIZE: usize = 16 * 1024 * 1024;
+
 /// This is returned in `RuntimeError`.
 /// Use `downcast` or `downcast_ref` to retrieve the `ExitCode`.
 #[derive(Error, Debug)]
@@ -84,6 +91,123 @@ pub(crate) fn wasi_imports(store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEn
     }
 }
 
+/// Run a filesystem operation with the VM memory and the virtual filesystem.
+fn with_fs(
+    env: &FunctionEnvMut<WasiEnv>,
+    f: impl FnOnce(&MemoryView, &mut Vfs) -> Result<(), Errno>,
+) -> Errno {
+    let mut guard = env.data().inner.lock().unwrap();
+    let inner = &mut *guard;
+    let memory = inner.memory.unwrap_ref().view(env);
+    match f(&memory, &mut inner.vfs) {
+        Ok(()) => Errno::Success,
+        Err(err) => err,
+    }
+}
+
+fn write_value<T: ValueType>(memory: &MemoryView, ptr: WasmPtr<T>, value: T) -> Result<(), Errno> {
+    ptr.deref(memory).write(value).or(Err(Errno::Fault))
+}
+
+fn write_bytes(memory: &MemoryView, offset: u32, data: &[u8]) -> Result<(), Errno> {
+    memory.write(offset as _, data).or(Err(Errno::Fault))
+}
+
+fn read_path(memory: &MemoryView, path: WasmPtr<u8>, path_len: u32) -> Result<String, Errno> {
+    path.read_utf8_string(memory, path_len)
+        .or(Err(Errno::Inval))
+}
+
+/// Fails if the guest buffer is not inside the VM memory.
+fn check_buf(memory: &MemoryView, offset: u32, len: u32) -> Result<(), Errno> {
+    if offset as u64 + len as u64 > memory.data_size() {
+        return Err(Errno::Fault);
+    }
+    Ok(())
+}
+
+/// Gather the data from the guest buffers, at most `MAX_IO_SIZE` bytes.
+fn read_from_iovs(
+    memory: &MemoryView,
+    iovs: WasmPtr<__wasi_ciovec_t<Memory32>>,
+    iovs_len: u32,
+) -> Result<Vec<u8>, Errno> {
+    let iovs = iovs
+        .slice(memory, iovs_len)
+        .and_then(|iovs| iovs.read_to_vec())
+        .or(Err(Errno::Fault))?;
+    let mut data = vec![];
+    for iov in iovs {
+        // Validate before allocating, the lengths are given by the guest
+        check_buf(memory, iov.buf.offset(), iov.buf_len)?;
+        let start = data.len();
+        let len = (iov.buf_len as usize).min(MAX_IO_SIZE - start);
+        data.resize(start + len, 0);
+        memory
+            .read(iov.buf.offset() as _, &mut data[start..])
+            .or(Err(Errno::Fault))?;
+        if data.len() == MAX_IO_SIZE {
+            break;
+        }
+    }
+    Ok(data)
+}
+
+/// Scatter the data returned by `read` to the guest buffers. Returns the number of bytes read.
+fn read_to_iovs(
+    memory: &MemoryView,
+    iovs: WasmPtr<__wasi_iovec_t<Memory32>>,
+    iovs_len: u32,
+    read: impl FnOnce(usize) -> Result<Vec<u8>, Errno>,
+) -> Result<u32, Errno> {
+    let iovs = iovs
+        .slice(memory, iovs_len)
+        .and_then(|iovs| iovs.read_to_vec())
+        .or(Err(Errno::Fault))?;
+    for iov in &iovs {
+        check_buf(memory, iov.buf.offset(), iov.buf_len)?;
+    }
+    let len = iovs
+        .iter()
+        .map(|iov| iov.buf_len as usize)
+        .sum::<usize>()
+        .min(MAX_IO_SIZE);
+    let data = read(len)?;
+    let mut rest = &data[..];
+    for iov in iovs {
+        if rest.is_empty() {
+            break;
+        }
+        let len = rest.len().min(iov.buf_len as usize);
+        write_bytes(memory, iov.buf.offset(), &rest[..len])?;
+        rest = &rest[len..];
+    }
+    Ok(data.len() as u32)
+}
+
+fn filestat(stat: Stat) -> wasi::Filestat {
+    wasi::Filestat {
+        st_dev: 0,
+        st_ino: stat.inode,
+        st_filetype: stat.filetype,
+        st_nlink: 1,
+        st_size: stat.size,
+        st_atim: stat.mtime,
+        st_mtim: stat.mtime,
+        st_ctim: stat.mtime,
+    }
+}
+
+fn new_mtime(st_mtim: wasi::Timestamp, fst_flags: wasi::Fstflags) -> Option<u64> {
+    if fst_flags.contains(wasi::Fstflags::SET_MTIM_NOW) {
+        Some(vfs::now())
+    } else if fst_flags.contains(wasi::Fstflags::SET_MTIM) {
+        Some(st_mtim)
+    } else {
+        None
+    }
+}
+
 pub fn args_get(
     _env: FunctionEnvMut<WasiEnv>,
     _argv: WasmPtr<WasmPtr<u8>>,
@@ -196,201 +320,286 @@ pub fn fd_advise(
 }
 
 pub fn fd_allocate(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _offset: wasi::Filesize,
-    _len: wasi::Filesize,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    offset: wasi::Filesize,
+    len: wasi::Filesize,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |_, fs| fs.allocate(fd, offset, len))
 }
 
-pub fn fd_close(_env: FunctionEnvMut<WasiEnv>, _fd: wasi::Fd) -> Errno {
-    Errno::Nosys
+pub fn fd_close(env: FunctionEnvMut<WasiEnv>, fd: wasi::Fd) -> Errno {
+    with_fs(&env, |_, fs| fs.close(fd))
 }
 
-pub fn fd_datasync(_env: FunctionEnvMut<WasiEnv>, _fd: wasi::Fd) -> Errno {
-    Errno::Nosys
+pub fn fd_datasync(env: FunctionEnvMut<WasiEnv>, fd: wasi::Fd) -> Errno {
+    with_fs(&env, |_, fs| fs.stat(fd).map(drop))
 }
 
 pub fn fd_fdstat_get(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _buf_ptr: WasmPtr<wasi::Fdstat>,
-) -> Errno {
-    Errno::Nosys
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    buf_ptr: WasmPtr<wasi::Fdstat>,
+) -> Errno {
+    with_fs(&env, |memory, fs| {
+        let (filetype, flags) = if fd < vfs::ROOT_FD {
+            (wasi::Filetype::CharacterDevice, wasi::Fdflags::empty())
+        } else {
+            fs.fdflags(fd)?
+        };
+        let stat = wasi::Fdstat {
+            fs_filetype: filetype,
+            fs_flags: flags,
+            fs_rights_base: wasi::Rights::all(),
+            fs_rights_inheriting: wasi::Rights::all(),
+        };
+        write_value(memory, buf_ptr, stat)
+    })
 }
 
 pub fn fd_fdstat_set_flags(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _flags: wasi::Fdflags,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    flags: wasi::Fdflags,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |_, fs| fs.set_fdflags(fd, flags))
 }
 
 pub fn fd_fdstat_set_rights(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
     _fs_rights_base: wasi::Rights,
     _fs_rights_inheriting: wasi::Rights,
 ) -> Errno {
-    Errno::Nosys
+    // Rights are not enforced
+    with_fs(&env, |_, fs| fs.stat(fd).map(drop))
 }
 
 pub fn fd_filestat_get(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _buf: WasmPtr<wasi::Filestat>,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    buf: WasmPtr<wasi::Filestat>,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        write_value(memory, buf, filestat(fs.stat(fd)?))
+    })
 }
 
 pub fn fd_filestat_set_size(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _st_size: wasi::Filesize,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    st_size: wasi::Filesize,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |_, fs| fs.set_size(fd, st_size))
 }
 
 pub fn fd_filestat_set_times(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
     _st_atim: wasi::Timestamp,
-    _st_mtim: wasi::Timestamp,
-    _fst_flags: wasi::Fstflags,
+    st_mtim: wasi::Timestamp,
+    fst_flags: wasi::Fstflags,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |_, fs| {
+        fs.set_mtime(fd, new_mtime(st_mtim, fst_flags))
+    })
 }
 
 pub fn fd_pread(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _iovs: WasmPtr<__wasi_iovec_t<Memory32>>,
-    _iovs_len: u32,
-    _offset: wasi::Filesize,
-    _nread: WasmPtr<u32>,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    iovs: WasmPtr<__wasi_iovec_t<Memory32>>,
+    iovs_len: u32,
+    offset: wasi::Filesize,
+    nread: WasmPtr<u32>,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        let n = read_to_iovs(memory, iovs, iovs_len, |len| fs.pread(fd, len, offset))?;
+        write_value(memory, nread, n)
+    })
 }
 
 pub fn fd_prestat_get(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _buf: WasmPtr<wasi::Prestat>,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    buf: WasmPtr<wasi::Prestat>,
 ) -> Errno {
-    Errno::Badf
+    with_fs(&env, |memory, fs| {
+        let name = fs.prestat_name(fd)?;
+        // struct prestat { u8 tag = dir; u32 pr_name_len; }
+        let mut prestat = [0u8; 8];
+        prestat[4..].copy_from_slice(&(name.len() as u32).to_le_bytes());
+        write_bytes(memory, buf.offset(), &prestat)
+    })
 }
 
 pub fn fd_prestat_dir_name(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _path: WasmPtr<u8>,
-    _path_len: u32,
-) -> Errno {
-    Errno::Nosys
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    path: WasmPtr<u8>,
+    path_len: u32,
+) -> Errno {
+    with_fs(&env, |memory, fs| {
+        let name = fs.prestat_name(fd)?;
+        if (path_len as usize) < name.len() {
+            return Err(Errno::Overflow);
+        }
+        write_bytes(memory, path.offset(), name.as_bytes())
+    })
 }
 
 pub fn fd_pwrite(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _iovs: WasmPtr<__wasi_ciovec_t<Memory32>>,
-    _iovs_len: u32,
-    _offset: wasi::Filesize,
-    _nwritten: WasmPtr<u32>,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    iovs: WasmPtr<__wasi_ciovec_t<Memory32>>,
+    iovs_len: u32,
+    offset: wasi::Filesize,
+    nwritten: WasmPtr<u32>,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        let data = read_from_iovs(memory, iovs, iovs_len)?;
+        let n = fs.pwrite(fd, &data, offset)?;
+        write_value(memory, nwritten, n as u32)
+    })
 }
 
 pub fn fd_read(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _iovs: WasmPtr<__wasi_iovec_t<Memory32>>,
-    _iovs_len: u32,
-    _nread: WasmPtr<u32>,
-) -> Errno {
-    Errno::Nosys
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    iovs: WasmPtr<__wasi_iovec_t<Memory32>>,
+    iovs_len: u32,
+    nread: WasmPtr<u32>,
+) -> Errno {
+    with_fs(&env, |memory, fs| {
+        let n = read_to_iovs(memory, iovs, iovs_len, |len| {
+            if fd < vfs::ROOT_FD {
+                // The stdin is always at EOF
+                Ok(vec![])
+            } else {
+                fs.read(fd, len)
+            }
+        })?;
+        write_value(memory, nread, n)
+    })
 }
 
 pub fn fd_readdir(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _buf: WasmPtr<u8>,
-    _buf_len: u32,
-    _cookie: wasi::Dircookie,
-    _bufused: WasmPtr<u32>,
-) -> Errno {
-    Errno::Nosys
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    buf: WasmPtr<u8>,
+    buf_len: u32,
+    cookie: wasi::Dircookie,
+    bufused: WasmPtr<u32>,
+) -> Errno {
+    with_fs(&env, |memory, fs| {
+        let buf_len = buf_len as usize;
+        let mut dirents = vec![];
+        for entry in fs.read_dir(fd, cookie)? {
+            // struct dirent { u64 d_next; u64 d_ino; u32 d_namlen; u8 d_type; } followed by the name
+            dirents.extend_from_slice(&entry.next.to_le_bytes());
+            dirents.extend_from_slice(&entry.inode.to_le_bytes());
+            dirents.extend_from_slice(&(entry.name.len() as u32).to_le_bytes());
+            dirents.extend_from_slice(&[entry.filetype as u8, 0, 0, 0]);
+            dirents.extend_from_slice(entry.name.as_bytes());
+            if dirents.len() >= buf_len {
+                // A truncated last entry tells the guest to call again with a larger buffer.
+                break;
+            }
+        }
+        dirents.truncate(buf_len);
+        write_bytes(memory, buf.offset(), &dirents)?;
+        write_value(memory, bufused, dirents.len() as u32)
+    })
 }
 
-pub fn fd_renumber(_env: FunctionEnvMut<WasiEnv>, _from: wasi::Fd, _to: wasi::Fd) -> Errno {
-    Errno::Nosys
+pub fn fd_renumber(env: FunctionEnvMut<WasiEnv>, from: wasi::Fd, to: wasi::Fd) -> Errno {
+    with_fs(&env, |_, fs| fs.renumber(from, to))
 }
 
 pub fn fd_seek(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _offset: wasi::FileDelta,
-    _whence: wasi::Whence,
-    _newoffset: WasmPtr<wasi::Filesize>,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    offset: wasi::FileDelta,
+    whence: wasi::Whence,
+    newoffset: WasmPtr<wasi::Filesize>,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        let offset = fs.seek(fd, offset, whence)?;
+        write_value(memory, newoffset, offset)
+    })
 }
 
-pub fn fd_sync(_env: FunctionEnvMut<WasiEnv>, _fd: wasi::Fd) -> Errno {
-    Errno::Nosys
+pub fn fd_sync(env: FunctionEnvMut<WasiEnv>, fd: wasi::Fd) -> Errno {
+    with_fs(&env, |_, fs| fs.stat(fd).map(drop))
 }
 
 pub fn fd_tell(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _offset: WasmPtr<wasi::Filesize>,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    offset: WasmPtr<wasi::Filesize>,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| write_value(memory, offset, fs.tell(fd)?))
 }
 
 pub fn fd_write(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _iovs: WasmPtr<__wasi_ciovec_t<Memory32>>,
-    _iovs_len: u32,
-    _nwritten: WasmPtr<u32>,
-) -> Errno {
-    Errno::Success
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    iovs: WasmPtr<__wasi_ciovec_t<Memory32>>,
+    iovs_len: u32,
+    nwritten: WasmPtr<u32>,
+) -> Errno {
+    with_fs(&env, |memory, fs| {
+        let data = read_from_iovs(memory, iovs, iovs_len)?;
+        let n = if fd < vfs::ROOT_FD {
+            // The stdout and stderr are discarded
+            data.len()
+        } else {
+            fs.write(fd, &data)?
+        };
+        write_value(memory, nwritten, n as u32)
+    })
 }
 
 pub fn path_create_directory(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _path: WasmPtr<u8>,
-    _path_len: u32,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    path: WasmPtr<u8>,
+    path_len: u32,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        fs.create_dir(fd, &read_path(memory, path, path_len)?)
+    })
 }
 
 pub fn path_filestat_get(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
     _flags: wasi::LookupFlags,
-    _path: WasmPtr<u8>,
-    _path_len: u32,
-    _buf: WasmPtr<wasi::Filestat>,
+    path: WasmPtr<u8>,
+    path_len: u32,
+    buf: WasmPtr<wasi::Filestat>,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        let stat = fs.path_stat(fd, &read_path(memory, path, path_len)?)?;
+        write_value(memory, buf, filestat(stat))
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn path_filestat_set_times(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
     _flags: wasi::LookupFlags,
-    _path: WasmPtr<u8>,
-    _path_len: u32,
+    path: WasmPtr<u8>,
+    path_len: u32,
     _st_atim: wasi::Timestamp,
-    _st_mtim: wasi::Timestamp,
-    _fst_flags: wasi::Fstflags,
+    st_mtim: wasi::Timestamp,
+    fst_flags: wasi::Fstflags,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        let path = read_path(memory, path, path_len)?;
+        fs.path_set_mtime(fd, &path, new_mtime(st_mtim, fst_flags))
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -409,18 +618,22 @@ pub fn path_link(
 
 #[allow(clippy::too_many_arguments)]
 pub fn path_open(
-    _env: FunctionEnvMut<WasiEnv>,
-    _dirfd: wasi::Fd,
+    env: FunctionEnvMut<WasiEnv>,
+    dirfd: wasi::Fd,
     _dirflags: wasi::LookupFlags,
-    _path: WasmPtr<u8>,
-    _path_len: u32,
-    _o_flags: wasi::Oflags,
+    path: WasmPtr<u8>,
+    path_len: u32,
+    o_flags: wasi::Oflags,
     _fs_rights_base: wasi::Rights,
     _fs_rights_inheriting: wasi::Rights,
-    _fs_flags: wasi::Fdflags,
-    _fd: WasmPtr<wasi::Fd>,
+    fs_flags: wasi::Fdflags,
+    fd: WasmPtr<wasi::Fd>,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        let path = read_path(memory, path, path_len)?;
+        let new_fd = fs.open(dirfd, &path, o_flags, fs_flags)?;
+        write_value(memory, fd, new_fd)
+    })
 }
 
 pub fn path_readlink(
@@ -436,24 +649,30 @@ pub fn path_readlink(
 }
 
 pub fn path_remove_directory(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _path: WasmPtr<u8>,
-    _path_len: u32,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    path: WasmPtr<u8>,
+    path_len: u32,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        fs.remove_dir(fd, &read_path(memory, path, path_len)?)
+    })
 }
 
 pub fn path_rename(
-    _env: FunctionEnvMut<WasiEnv>,
-    _old_fd: wasi::Fd,
-    _old_path: WasmPtr<u8>,
-    _old_path_len: u32,
-    _new_fd: wasi::Fd,
-    _new_path: WasmPtr<u8>,
-    _new_path_len: u32,
+    env: FunctionEnvMut<WasiEnv>,
+    old_fd: wasi::Fd,
+    old_path: WasmPtr<u8>,
+    old_path_len: u32,
+    new_fd: wasi::Fd,
+    new_path: WasmPtr<u8>,
+    new_path_len: u32,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        let old_path = read_path(memory, old_path, old_path_len)?;
+        let new_path = read_path(memory, new_path, new_path_len)?;
+        fs.rename(old_fd, &old_path, new_fd, &new_path)
+    })
 }
 
 pub fn path_symlink(
@@ -468,12 +687,14 @@ pub fn path_symlink(
 }
 
 pub fn path_unlink_file(
-    _env: FunctionEnvMut<WasiEnv>,
-    _fd: wasi::Fd,
-    _path: WasmPtr<u8>,
-    _path_len: u32,
+    env: FunctionEnvMut<WasiEnv>,
+    fd: wasi::Fd,
+    path: WasmPtr<u8>,
+    path_len: u32,
 ) -> Errno {
-    Errno::Nosys
+    with_fs(&env, |memory, fs| {
+        fs.unlink_file(fd, &read_path(memory, path, path_len)?)
+    })
 }
 
 pub fn poll_oneoff(
@@ -499,6 +720,9 @@ pub fn random_get(mut env: FunctionEnvMut<WasiEnv>, buf: u32, buf_len: u32) -> R
     let mut env_guard = inner.lock().unwrap();
 
     let inner = &mut *env_guard;
+    if check_buf(&inner.memory.unwrap_ref().view(&env), buf, buf_len).is_err() {
+        return Ok(Errno::Fault);
+    }
     let mut u8_buffer = vec![0; buf_len as usize];
     inner.make_mut(&mut env).getrandom(&mut u8_buffer)?;
     inner
@@ -540,3 +764,156 @@ pub fn sock_send(
 pub fn sock_shutdown(_env: FunctionEnvMut<WasiEnv>, _sock: wasi::Fd, _how: SdFlags) -> Errno {
     Errno::Nosys
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::tests::NoCache;
+    use wasmer::{Memory, MemoryType, Store};
+
+    const IOVS: u32 = 0;
+    const OUT: u32 = 64;
+    const PATH: u32 = 128;
+    const BUF: u32 = 1024;
+
+    /// Calls the WASI functions the way a guest does
+    struct Guest {
+        store: Store,
+        memory: Memory,
+        env: FunctionEnv<WasiEnv>,
+    }
+
+    impl Guest {
+        fn new(quota: u64) -> Self {
+            let mut store = Store::default();
+            let memory = Memory::new(&mut store, MemoryType::new(1, None, false)).unwrap();
+            let env = WasiEnv::new([0; 32], &NoCache, None);
+            env.set_memory(memory.clone());
+            env.inner.lock().unwrap().vfs = Vfs::new(quota);
+            let env = FunctionEnv::new(&mut store, env);
+            Self { store, memory, env }
+        }
+
+        fn call<T>(&mut self, f: impl FnOnce(FunctionEnvMut<WasiEnv>) -> T) -> T {
+            f(self.env.clone().into_mut(&mut self.store))
+        }
+
+        fn fs<T>(&mut self, f: impl FnOnce(&mut Vfs) -> T) -> T {
+            f(&mut self.env.as_ref(&self.store).inner.lock().unwrap().vfs)
+        }
+
+        fn poke(&mut self, offset: u32, data: &[u8]) {
+            self.memory
+                .view(&self.store)
+                .write(offset as _, data)
+                .unwrap();
+        }
+
+        fn peek_u32(&mut self, offset: u32) -> u32 {
+            let mut buf = [0; 4];
+            self.memory
+                .view(&self.store)
+                .read(offset as _, &mut buf)
+                .unwrap();
+            u32::from_le_bytes(buf)
+        }
+
+        /// Writes the (offset, len) pairs as an iovec array at `IOVS`
+        fn set_iovs(&mut self, iovs: &[(u32, u32)]) {
+            let raw: Vec<u8> = iovs
+                .iter()
+                .flat_map(|(offset, len)| [offset.to_le_bytes(), len.to_le_bytes()].concat())
+                .collect();
+            self.poke(IOVS, &raw);
+        }
+
+        fn fd_write(&mut self, fd: u32, iovs: &[(u32, u32)]) -> Result<u32, Errno> {
+            self.set_iovs(iovs);
+            let len = iovs.len() as u32;
+            match self.call(|env| fd_write(env, fd, WasmPtr::new(IOVS), len, WasmPtr::new(OUT))) {
+                Errno::Success => Ok(self.peek_u32(OUT)),
+                err => Err(err),
+            }
+        }
+
+        fn path_rename(&mut self, from: &str, to: &str) -> Errno {
+            let (from_len, to_len) = (from.len() as u32, to.len() as u32);
+            self.poke(PATH, from.as_bytes());
+            self.poke(PATH + from_len, to.as_bytes());
+            self.call(|env| {
+                path_rename(
+                    env,
+                    vfs::ROOT_FD,
+                    WasmPtr::new(PATH),
+                    from_len,
+                    vfs::ROOT_FD,
+                    WasmPtr::new(PATH + from_len),
+                    to_len,
+                )
+            })
+        }
+
+        fn create(&mut self, path: &str) -> u32 {
+            self.fs(|fs| {
+                fs.open(
+                    vfs::ROOT_FD,
+                    path,
+                    wasi::Oflags::CREATE,
+                    wasi::Fdflags::empty(),
+                )
+                .unwrap()
+            })
+        }
+    }
+
+    #[test]
+    fn iovs_out_of_memory_are_rejected() {
+        let mut guest = Guest::new(vfs::DEFAULT_QUOTA);
+        let fd = guest.create("a");
+        guest.poke(BUF, b"hello");
+        assert_eq!(guest.fd_write(fd, &[(BUF, 3), (BUF + 3, 2)]), Ok(5));
+        assert_eq!(guest.fs(|fs| fs.pread(fd, 100, 0)), Ok(b"hello".to_vec()));
+
+        // A huge length must be refused before the host allocates anything
+        for fd in [1, fd] {
+            assert_eq!(guest.fd_write(fd, &[(BUF, u32::MAX)]), Err(Errno::Fault));
+            assert_eq!(
+                guest.fd_write(fd, &[(BUF, 1), (u32::MAX, 2)]),
+                Err(Errno::Fault)
+            );
+        }
+        let page = 64 * 1024;
+        assert_eq!(guest.fd_write(1, &[(0, page)]), Ok(page));
+        assert_eq!(guest.fd_write(1, &[(1, page)]), Err(Errno::Fault));
+        assert_eq!(guest.fs(|fs| fs.stat(fd).unwrap().size), 5);
+    }
+
+    #[test]
+    fn writes_are_limited_by_the_quota() {
+        let mut guest = Guest::new(4096);
+        let fd = guest.create("a");
+        assert_eq!(guest.fd_write(fd, &[(BUF, 4096)]), Err(Errno::Nospc));
+        assert_eq!(guest.fd_write(fd, &[(BUF, 1024)]), Ok(1024));
+        assert_eq!(guest.fs(|fs| fs.stat(fd).unwrap().size), 1024);
+    }
+
+    #[test]
+    fn rename_works() {
+        let mut guest = Guest::new(vfs::DEFAULT_QUOTA);
+        let fd = guest.create("a");
+        guest.poke(BUF, b"hello");
+        assert_eq!(guest.fd_write(fd, &[(BUF, 5)]), Ok(5));
+        guest.create("b");
+
+        assert_eq!(guest.path_rename("a", "b"), Errno::Success);
+        assert_eq!(guest.path_rename("a", "c"), Errno::Noent);
+        assert_eq!(
+            guest.fs(|fs| fs.path_stat(vfs::ROOT_FD, "b").map(|stat| stat.size)),
+            Ok(5)
+        );
+        assert_eq!(
+            guest.fs(|fs| fs.path_stat(vfs::ROOT_FD, "a").err()),
+            Some(Errno::Noent)
+        );
+    }
+}
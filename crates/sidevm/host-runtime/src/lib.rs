@@ -1,4 +1,5 @@
 mod async_context;
+mod dns;
 mod env;
 pub mod instrument;
 mod metering;
@@ -6,8 +7,11 @@ mod resource;
 mod run;
 pub mod service;
 mod tls;
+mod vfs;
 
-pub use env::{CacheOps, DynCacheOps, OcallAborted, ShortId};
+pub use env::{
+    CacheOps, DynCacheOps, DynStorageOps, OcallAborted, ShortId, StorageOps, VmSnapshot,
+};
 
 pub type VmId = [u8; 32];
 pub use run::WasmRun;
@@ -1,14 +1,15 @@
 use futures::pin_mut;
-use sidevm_env::{OcallError, Result};
+use scale::Encode;
+use sidevm_env::{dns::Record, InputChannel, OcallError, Result};
 use std::future::Future;
 use std::io::ErrorKind;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Poll::*;
 use tokio::io::{AsyncRead, AsyncWrite as _};
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::mpsc::Receiver;
-use tokio::sync::oneshot::Sender;
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::sync::oneshot::{Receiver, Sender};
 use tokio::time::Sleep;
 use tokio_rustls::rustls::ServerConfig;
 use Resource::*;
@@ -18,8 +19,13 @@ use crate::tls::TlsStream;
 
 pub enum Resource {
     Sleep(Pin<Box<Sleep>>),
-    ChannelRx(Receiver<Vec<u8>>),
+    ChannelRx {
+        kind: InputChannel,
+        rx: mpsc::Receiver<Vec<u8>>,
+    },
     OneshotTx(Option<Sender<Vec<u8>>>),
+    /// A pending reply, or `None` once it is taken
+    OneshotRx(Option<Receiver<Result<Vec<u8>>>>),
     TcpListener {
         listener: TcpListener,
         tls_config: Option<Arc<ServerConfig>>,
@@ -28,6 +34,12 @@ pub enum Resource {
     TlsStream(Box<TlsStream>),
     TcpConnect(Pin<Box<dyn Future<Output = std::io::Result<TcpStream>> + Send>>),
     TlsConnect(Pin<Box<dyn Future<Output = std::io::Result<TlsStream>> + Send>>),
+    UdpSocket(UdpSocket),
+    /// A pending DNS lookup, or `None` once its records are taken
+    DnsLookup(Option<Pin<Box<dyn Future<Output = Result<Vec<Record>>> + Send>>>),
+    /// A resource bound to the previous host, which is gone after the VM is restored from a
+    /// snapshot.
+    Lost,
 }
 
 impl Resource {
@@ -36,7 +48,7 @@ impl Resource {
         let waker = GuestWaker::from_id(waker_id);
 
         match self {
-            ChannelRx(rx) => {
+            ChannelRx { rx, .. } => {
                 let fut = rx.recv();
                 futures::pin_mut!(fut);
                 match poll_in_task_cx(waker, fut) {
@@ -45,6 +57,30 @@ impl Resource {
                     Pending => Err(OcallError::Pending),
                 }
             }
+            OneshotRx(slot) => {
+                // A completed receiver must not be polled again
+                let rx = slot.as_mut().ok_or(OcallError::EndOfFile)?;
+                match poll_in_task_cx(waker, Pin::new(rx)) {
+                    Ready(reply) => {
+                        *slot = None;
+                        // The sender is dropped without a reply
+                        reply.unwrap_or(Err(OcallError::IoError))
+                    }
+                    Pending => Err(OcallError::Pending),
+                }
+            }
+            DnsLookup(lookup) => {
+                // A completed future must not be polled again
+                let fut = lookup.as_mut().ok_or(OcallError::EndOfFile)?;
+                match poll_in_task_cx(waker, fut.as_mut()) {
+                    Ready(records) => {
+                        *lookup = None;
+                        records.map(|records| records.encode())
+                    }
+                    Pending => Err(OcallError::Pending),
+                }
+            }
+            Lost => Err(OcallError::IoError),
             _ => Err(OcallError::UnsupportedOperation),
         }
     }
@@ -75,6 +111,7 @@ impl Resource {
                     }
                 }
             }
+            Lost => Err(OcallError::IoError),
             _ => Err(OcallError::UnsupportedOperation),
         }
     }
@@ -112,6 +149,7 @@ impl Resource {
                     Ready(Ok(())) => Ok(buf.filled().len() as _),
                 }
             }
+            Lost => Err(OcallError::IoError),
             _ => Err(OcallError::UnsupportedOperation),
         }
     }
@@ -143,6 +181,7 @@ impl Resource {
                     Ready(Ok(sz)) => Ok(sz as _),
                 }
             }
+            Lost => Err(OcallError::IoError),
             _ => Err(OcallError::UnsupportedOperation),
         }
     }
@@ -166,6 +205,7 @@ impl Resource {
                     Ready(Ok(())) => Ok(()),
                 }
             }
+            Lost => Err(OcallError::IoError),
             _ => Err(OcallError::UnsupportedOperation),
         }
     }
@@ -213,4 +253,62 @@ impl ResourceKeeper {
         }
         self.resources[resource_id].take()
     }
+
+    /// All the resource slots, indexed by the resource id.
+    pub fn slots(&self) -> &[Option<Resource>] {
+        &self.resources
+    }
+
+    pub fn from_slots(resources: Vec<Option<Resource>>) -> Self {
+        Self { resources }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::async_context::{set_task_cx, set_task_env};
+    use crate::env::TaskSet;
+    use std::task::Context;
+
+    /// Polls the resource the way the guest does, inside a task context
+    pub(crate) fn poll(res: &mut Resource) -> Result<Vec<u8>> {
+        let tasks = Arc::new(TaskSet::with_task0());
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        set_task_env(tasks, 0, || set_task_cx(&mut cx, || res.poll(0)))
+    }
+
+    #[test]
+    fn dns_lookup_fails_when_polled_after_ready() {
+        let records = vec![Record::A([127, 0, 0, 1])];
+        let answer = records.clone();
+        let mut res = DnsLookup(Some(Box::pin(async move { Ok(answer) })));
+        assert_eq!(poll(&mut res).unwrap(), records.encode());
+        assert!(matches!(poll(&mut res), Err(OcallError::EndOfFile)));
+    }
+
+    #[test]
+    fn oneshot_rx_replies_once() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut res = OneshotRx(Some(rx));
+        assert!(matches!(poll(&mut res), Err(OcallError::Pending)));
+        tx.send(Ok(b"reply".to_vec())).unwrap();
+        assert_eq!(poll(&mut res).unwrap(), b"reply");
+        assert!(matches!(poll(&mut res), Err(OcallError::EndOfFile)));
+    }
+
+    #[test]
+    fn oneshot_rx_reports_the_error() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut res = OneshotRx(Some(rx));
+        tx.send(Err(OcallError::NotFound)).unwrap();
+        assert!(matches!(poll(&mut res), Err(OcallError::NotFound)));
+        assert!(matches!(poll(&mut res), Err(OcallError::EndOfFile)));
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<Result<Vec<u8>>>();
+        let mut res = OneshotRx(Some(rx));
+        drop(tx);
+        assert!(matches!(poll(&mut res), Err(OcallError::IoError)));
+    }
 }
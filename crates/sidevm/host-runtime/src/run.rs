@@ -11,8 +11,8 @@ use wasmer_compiler_llvm::LLVM;
 use wasmer_compiler_singlepass::Singlepass;
 use wasmer_tunables::LimitingTunables;
 
-use crate::env::DynCacheOps;
-use crate::{async_context, env, metering::metering, VmId};
+use crate::env::{DynCacheOps, DynStorageOps, VmSnapshot};
+use crate::{async_context, env, metering::metering, vfs, VmId};
 
 pub struct WasmRun {
     id: VmId,
@@ -29,14 +29,17 @@ impl Drop for WasmRun {
 }
 
 impl WasmRun {
+    #[allow(clippy::too_many_arguments)]
     pub fn run(
         code: &[u8],
         max_pages: u32,
         id: crate::VmId,
         gas_per_breath: u64,
         cache_ops: DynCacheOps,
+        storage_ops: Option<DynStorageOps>,
         scheduler: TaskScheduler<VmId>,
         weight: u32,
+        snapshot: Option<VmSnapshot>,
     ) -> Result<(WasmRun, env::Env)> {
         let compiler_env = std::env::var("WASMER_COMPILER");
         let compiler_env = compiler_env
@@ -61,7 +64,11 @@ impl WasmRun {
         let tunables = LimitingTunables::new(base, Pages(max_pages));
         let mut store = Store::new_with_tunables(&engine, tunables);
         let module = Module::new(&store, code)?;
-        let (env, import_object) = env::create_env(id, &mut store, cache_ops);
+        let (env, import_object) = env::create_env(id, &mut store, cache_ops, storage_ops);
+        for bundle in module.custom_sections(vfs::BUNDLE_SECTION) {
+            env.load_fs_bundle(&bundle)
+                .context("Failed to load the fs bundle")?;
+        }
         let instance = Instance::new(&mut store, &module, &import_object)?;
         let memory = instance
             .exports
@@ -70,6 +77,10 @@ impl WasmRun {
         let wasm_poll_entry = instance.exports.get_typed_function(&store, "sidevm_poll")?;
         env.set_memory(memory.clone());
         env.set_instance(instance);
+        if let Some(snapshot) = snapshot {
+            env.restore(&mut store, snapshot)
+                .context("Failed to restore the snapshot")?;
+        }
         env.set_gas_per_breath(gas_per_breath);
         env.set_weight(weight);
         Ok((
@@ -83,6 +94,13 @@ impl WasmRun {
             env,
         ))
     }
+
+    /// Take a snapshot of the guest to resume it later, if the guest has enabled it.
+    ///
+    /// Must be called at an idle point, that is, while the future is not being polled.
+    pub fn snapshot(&mut self) -> Result<VmSnapshot> {
+        self.env.snapshot(&mut self.store)
+    }
 }
 
 impl Future for WasmRun {
@@ -1,4 +1,4 @@
-use crate::env::DynCacheOps;
+use crate::env::{DynCacheOps, DynStorageOps, VmSnapshot};
 use crate::{env::OcallAborted, run::WasmRun};
 use crate::{ShortId, VmId};
 use anyhow::{Context as _, Result};
@@ -7,6 +7,9 @@ use phala_scheduler::TaskScheduler;
 use serde::{Deserialize, Serialize};
 use sidevm_env::messages::AccountId;
 use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
 use tokio::{
     sync::mpsc::{channel, Receiver, Sender},
     sync::oneshot::Sender as OneshotSender,
@@ -18,7 +21,17 @@ pub type CommandSender = Sender<Command>;
 
 #[derive(Debug)]
 pub enum Report {
-    VmTerminated { id: VmId, reason: ExitReason },
+    VmTerminated {
+        id: VmId,
+        reason: ExitReason,
+    },
+    /// The VM queries a contract. The reply carries the error if the query can not be served.
+    ContractQuery {
+        from: VmId,
+        contract_id: VmId,
+        payload: Vec<u8>,
+        reply_tx: OneshotSender<sidevm_env::Result<Vec<u8>>>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, derive_more::Display)]
@@ -56,6 +69,8 @@ pub enum Command {
     },
     // Update the task scheduling weight
     UpdateWeight(u32),
+    // Take a snapshot of the instance at the next idle point.
+    Snapshot(SyncSender<Result<VmSnapshot>>),
 }
 
 pub struct ServiceRun {
@@ -117,6 +132,9 @@ impl ServiceRun {
 }
 
 impl Spawner {
+    /// Start a VM instance. Returns the command sender, the handle of the instance and the flag
+    /// telling whether the guest has enabled snapshots.
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         &self,
         wasm_bytes: &[u8],
@@ -124,19 +142,27 @@ impl Spawner {
         id: VmId,
         gas_per_breath: u64,
         cache_ops: DynCacheOps,
+        storage_ops: Option<DynStorageOps>,
         weight: u32,
-    ) -> Result<(CommandSender, JoinHandle<ExitReason>)> {
+        snapshot: Option<VmSnapshot>,
+    ) -> Result<(CommandSender, JoinHandle<ExitReason>, Arc<AtomicBool>)> {
         let (cmd_tx, mut cmd_rx) = channel(128);
+        // Restoring the timers requires the runtime context.
+        let _guard = self.runtime_handle.enter();
         let (mut wasm_run, env) = WasmRun::run(
             wasm_bytes,
             max_memory_pages,
             id,
             gas_per_breath,
             cache_ops,
+            storage_ops,
             self.scheduler.clone(),
             weight,
+            snapshot,
         )
         .context("Failed to create sidevm instance")?;
+        env.set_report_tx(self.report_tx.clone());
+        let snapshot_enabled = env.snapshot_enabled();
         let spawner = self.runtime_handle.clone();
         let handle = self.spawn(async move {
             let vmid = ShortId(&id);
@@ -192,6 +218,9 @@ impl Spawner {
                             Some(Command::UpdateWeight(weight)) => {
                                 env.set_weight(weight);
                             }
+                            Some(Command::Snapshot(reply_tx)) => {
+                                let _ = reply_tx.send(wasm_run.snapshot());
+                            }
                         }
                     }
                     rv = &mut wasm_run => {
@@ -235,7 +264,7 @@ impl Spawner {
             }
             reason
         });
-        Ok((cmd_tx, handle))
+        Ok((cmd_tx, handle, snapshot_enabled))
     }
 
     pub fn spawn<O: Send + 'static>(
@@ -0,0 +1,720 @@
+//! An in-memory, quota limited filesystem backing the WASI file operations of a VM.
+//!
+//! The whole tree lives in the host memory and is dropped with the VM. The only preopened
+//! directory is the root `/` at fd 3. Symbolic links and hard links are not supported.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context as _};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use wasmer_wasi_types::wasi::{Errno, Fdflags, Filetype, Oflags, Whence};
+
+pub(crate) type Result<T, E = Errno> = core::result::Result<T, E>;
+
+pub(crate) type Inode = u64;
+
+/// The fd of the preopened root directory. 0, 1 and 2 are reserved for stdio.
+pub(crate) const ROOT_FD: u32 = 3;
+pub(crate) const ROOT_NAME: &str = "/";
+/// The default max bytes a VM can store in its filesystem.
+pub(crate) const DEFAULT_QUOTA: u64 = 16 * 1024 * 1024;
+/// The name of the wasm custom section carrying a tar bundle to preload.
+pub(crate) const BUNDLE_SECTION: &str = "sidevm-fs";
+
+const ROOT: Inode = 1;
+const MAX_FDS: usize = 1024;
+const MAX_NAME_LEN: usize = 255;
+/// The bytes charged to the quota for each node in addition to its content.
+const NODE_OVERHEAD: u64 = 256;
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Content {
+    File(#[serde(with = "serde_bytes")] Vec<u8>),
+    Dir(BTreeMap<String, Inode>),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Node {
+    content: Content,
+    parent: Inode,
+    /// False if the node has been unlinked but is still held by some fds.
+    linked: bool,
+    mtime: u64,
+}
+
+impl Node {
+    fn filetype(&self) -> Filetype {
+        match self.content {
+            Content::File(_) => Filetype::RegularFile,
+            Content::Dir(_) => Filetype::Directory,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct OpenFile {
+    inode: Inode,
+    offset: u64,
+    append: bool,
+}
+
+pub(crate) struct Stat {
+    pub inode: Inode,
+    pub filetype: Filetype,
+    pub size: u64,
+    pub mtime: u64,
+}
+
+pub(crate) struct DirEntry {
+    pub next: u64,
+    pub inode: Inode,
+    pub name: String,
+    pub filetype: Filetype,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Vfs {
+    nodes: BTreeMap<Inode, Node>,
+    next_inode: Inode,
+    fds: BTreeMap<u32, OpenFile>,
+    used: u64,
+    quota: u64,
+}
+
+impl Default for Vfs {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUOTA)
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|c| !c.is_empty() && *c != ".")
+}
+
+impl Vfs {
+    pub(crate) fn new(quota: u64) -> Self {
+        let root = Node {
+            content: Content::Dir(Default::default()),
+            parent: ROOT,
+            linked: true,
+            mtime: now(),
+        };
+        let root_fd = OpenFile {
+            inode: ROOT,
+            offset: 0,
+            append: false,
+        };
+        Self {
+            nodes: [(ROOT, root)].into_iter().collect(),
+            next_inode: ROOT + 1,
+            fds: [(ROOT_FD, root_fd)].into_iter().collect(),
+            used: NODE_OVERHEAD,
+            quota,
+        }
+    }
+
+    /// Preload the files in given tar bundle.
+    pub(crate) fn load_tar(&mut self, bundle: &[u8]) -> anyhow::Result<()> {
+        let mut archive = tar::Archive::new(bundle);
+        for entry in archive.entries().context("Invalid fs bundle")? {
+            let mut entry = entry.context("Invalid fs bundle entry")?;
+            let path = entry
+                .path()
+                .context("Invalid path in fs bundle")?
+                .to_str()
+                .ok_or_else(|| anyhow!("Non UTF-8 path in fs bundle"))?
+                .to_owned();
+            let err = |err: Errno| anyhow!("Failed to load {path} from fs bundle: {err:?}");
+            match entry.header().entry_type() {
+                tar::EntryType::Directory => {
+                    self.create_dir_all(&path).map_err(err)?;
+                }
+                tar::EntryType::Regular => {
+                    let mut data = vec![];
+                    entry
+                        .read_to_end(&mut data)
+                        .context("Failed to read fs bundle")?;
+                    let (parent, name) = match path.rsplit_once('/') {
+                        Some((parent, name)) => (self.create_dir_all(parent).map_err(err)?, name),
+                        None => (ROOT, path.as_str()),
+                    };
+                    let inode = match self.child(parent, name) {
+                        Ok(inode) => inode,
+                        Err(_) => self
+                            .create(parent, name, Content::File(vec![]))
+                            .map_err(err)?,
+                    };
+                    self.resize(inode, 0).map_err(err)?;
+                    self.write_at(inode, 0, &data).map_err(err)?;
+                }
+                other => warn!("Ignored {path} of type {other:?} in fs bundle"),
+            }
+        }
+        Ok(())
+    }
+
+    fn charge(&mut self, bytes: u64) -> Result<()> {
+        let used = self.used.saturating_add(bytes);
+        if used > self.quota {
+            return Err(Errno::Nospc);
+        }
+        self.used = used;
+        Ok(())
+    }
+
+    fn node(&self, inode: Inode) -> Result<&Node> {
+        self.nodes.get(&inode).ok_or(Errno::Noent)
+    }
+
+    fn node_mut(&mut self, inode: Inode) -> Result<&mut Node> {
+        self.nodes.get_mut(&inode).ok_or(Errno::Noent)
+    }
+
+    fn file(&self, fd: u32) -> Result<&OpenFile> {
+        self.fds.get(&fd).ok_or(Errno::Badf)
+    }
+
+    fn file_mut(&mut self, fd: u32) -> Result<&mut OpenFile> {
+        self.fds.get_mut(&fd).ok_or(Errno::Badf)
+    }
+
+    fn dir_entries(&self, inode: Inode) -> Result<&BTreeMap<String, Inode>> {
+        match &self.node(inode)?.content {
+            Content::Dir(entries) => Ok(entries),
+            Content::File(_) => Err(Errno::Notdir),
+        }
+    }
+
+    fn file_data(&self, inode: Inode) -> Result<&Vec<u8>> {
+        match &self.node(inode)?.content {
+            Content::File(data) => Ok(data),
+            Content::Dir(_) => Err(Errno::Isdir),
+        }
+    }
+
+    fn child(&self, dir: Inode, name: &str) -> Result<Inode> {
+        match name {
+            "." => Ok(dir),
+            ".." if dir == ROOT => Err(Errno::Notcapable),
+            ".." => Ok(self.node(dir)?.parent),
+            _ => self
+                .dir_entries(dir)?
+                .get(name)
+                .copied()
+                .ok_or(Errno::Noent),
+        }
+    }
+
+    fn lookup(&self, dir: Inode, path: &str) -> Result<Inode> {
+        components(path).try_fold(dir, |dir, name| self.child(dir, name))
+    }
+
+    /// Resolve the parent directory of given path, returns the parent inode and the last name.
+    fn lookup_parent<'p>(&self, dirfd: u32, path: &'p str) -> Result<(Inode, &'p str)> {
+        let dir = self.file(dirfd)?.inode;
+        let path = path.trim_end_matches('/');
+        let (parent, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => (self.lookup(dir, parent)?, name),
+            None => (dir, path),
+        };
+        if matches!(name, "" | "." | "..") {
+            return Err(Errno::Inval);
+        }
+        self.dir_entries(parent)?;
+        Ok((parent, name))
+    }
+
+    fn create(&mut self, parent: Inode, name: &str, content: Content) -> Result<Inode> {
+        if name.len() > MAX_NAME_LEN {
+            return Err(Errno::Nametoolong);
+        }
+        if self.dir_entries(parent)?.contains_key(name) {
+            return Err(Errno::Exist);
+        }
+        self.charge(NODE_OVERHEAD + name.len() as u64)?;
+        let inode = self.next_inode;
+        self.next_inode += 1;
+        let mtime = now();
+        self.nodes.insert(
+            inode,
+            Node {
+                content,
+                parent,
+                linked: true,
+                mtime,
+            },
+        );
+        let dir = self.node_mut(parent)?;
+        dir.mtime = mtime;
+        if let Content::Dir(entries) = &mut dir.content {
+            entries.insert(name.into(), inode);
+        }
+        Ok(inode)
+    }
+
+    fn create_dir_all(&mut self, path: &str) -> Result<Inode> {
+        let mut dir = ROOT;
+        for name in components(path) {
+            dir = match self.child(dir, name) {
+                Ok(inode) => inode,
+                Err(Errno::Noent) => self.create(dir, name, Content::Dir(Default::default()))?,
+                Err(err) => return Err(err),
+            };
+        }
+        self.dir_entries(dir)?;
+        Ok(dir)
+    }
+
+    fn unlink(&mut self, parent: Inode, name: &str) -> Result<()> {
+        let inode = self.child(parent, name)?;
+        let dir = self.node_mut(parent)?;
+        dir.mtime = now();
+        if let Content::Dir(entries) = &mut dir.content {
+            entries.remove(name);
+        }
+        self.used -= name.len() as u64;
+        self.node_mut(inode)?.linked = false;
+        self.release(inode);
+        Ok(())
+    }
+
+    /// Free the node if it is neither linked nor opened.
+    fn release(&mut self, inode: Inode) {
+        let Some(node) = self.nodes.get(&inode) else {
+            return;
+        };
+        if node.linked || self.fds.values().any(|f| f.inode == inode) {
+            return;
+        }
+        let size = match &node.content {
+            Content::File(data) => data.len() as u64,
+            Content::Dir(_) => 0,
+        };
+        self.used -= NODE_OVERHEAD + size;
+        self.nodes.remove(&inode);
+    }
+
+    fn resize(&mut self, inode: Inode, size: u64) -> Result<()> {
+        let len = self.file_data(inode)?.len() as u64;
+        if size > len {
+            self.charge(size - len)?;
+        } else {
+            self.used -= len - size;
+        }
+        let node = self.node_mut(inode)?;
+        node.mtime = now();
+        if let Content::File(data) = &mut node.content {
+            data.resize(size as usize, 0);
+        }
+        Ok(())
+    }
+
+    fn write_at(&mut self, inode: Inode, offset: u64, buf: &[u8]) -> Result<()> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        let end = offset.checked_add(buf.len() as u64).ok_or(Errno::Fbig)?;
+        if end > self.file_data(inode)?.len() as u64 {
+            self.resize(inode, end)?;
+        }
+        let node = self.node_mut(inode)?;
+        node.mtime = now();
+        if let Content::File(data) = &mut node.content {
+            data[offset as usize..end as usize].copy_from_slice(buf);
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, inode: Inode, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let data = self.file_data(inode)?;
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(len).min(data.len());
+        Ok(data[start..end].to_vec())
+    }
+
+    pub(crate) fn open(
+        &mut self,
+        dirfd: u32,
+        path: &str,
+        oflags: Oflags,
+        fdflags: Fdflags,
+    ) -> Result<u32> {
+        if self.fds.len() >= MAX_FDS {
+            return Err(Errno::Mfile);
+        }
+        // Only regular files can be created by open
+        if oflags.contains(Oflags::CREATE | Oflags::DIRECTORY) {
+            return Err(Errno::Inval);
+        }
+        let dir = self.file(dirfd)?.inode;
+        self.dir_entries(dir)?;
+        let inode = match self.lookup(dir, path) {
+            Ok(_) if oflags.contains(Oflags::CREATE | Oflags::EXCL) => return Err(Errno::Exist),
+            Ok(inode) => inode,
+            Err(Errno::Noent) if oflags.contains(Oflags::CREATE) => {
+                let (parent, name) = self.lookup_parent(dirfd, path)?;
+                self.create(parent, name, Content::File(vec![]))?
+            }
+            Err(err) => return Err(err),
+        };
+        let filetype = self.node(inode)?.filetype();
+        if oflags.contains(Oflags::DIRECTORY) && filetype != Filetype::Directory {
+            return Err(Errno::Notdir);
+        }
+        if oflags.contains(Oflags::TRUNC) {
+            self.resize(inode, 0)?;
+        }
+        let fd = (ROOT_FD..)
+            .find(|fd| !self.fds.contains_key(fd))
+            .ok_or(Errno::Mfile)?;
+        self.fds.insert(
+            fd,
+            OpenFile {
+                inode,
+                offset: 0,
+                append: fdflags.contains(Fdflags::APPEND),
+            },
+        );
+        Ok(fd)
+    }
+
+    pub(crate) fn close(&mut self, fd: u32) -> Result<()> {
+        let file = self.fds.remove(&fd).ok_or(Errno::Badf)?;
+        self.release(file.inode);
+        Ok(())
+    }
+
+    pub(crate) fn renumber(&mut self, from: u32, to: u32) -> Result<()> {
+        self.file(to)?;
+        let file = self.fds.remove(&from).ok_or(Errno::Badf)?;
+        if let Some(prev) = self.fds.insert(to, file) {
+            self.release(prev.inode);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn prestat_name(&self, fd: u32) -> Result<&'static str> {
+        match self.file(fd)?.inode {
+            ROOT if fd == ROOT_FD => Ok(ROOT_NAME),
+            _ => Err(Errno::Badf),
+        }
+    }
+
+    pub(crate) fn read(&mut self, fd: u32, len: usize) -> Result<Vec<u8>> {
+        let file = self.file(fd)?;
+        let data = self.read_at(file.inode, file.offset, len)?;
+        self.file_mut(fd)?.offset += data.len() as u64;
+        Ok(data)
+    }
+
+    pub(crate) fn pread(&self, fd: u32, len: usize, offset: u64) -> Result<Vec<u8>> {
+        self.read_at(self.file(fd)?.inode, offset, len)
+    }
+
+    pub(crate) fn write(&mut self, fd: u32, buf: &[u8]) -> Result<usize> {
+        let file = self.file(fd)?;
+        let inode = file.inode;
+        let offset = if file.append {
+            self.file_data(inode)?.len() as u64
+        } else {
+            file.offset
+        };
+        self.write_at(inode, offset, buf)?;
+        self.file_mut(fd)?.offset = offset + buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    pub(crate) fn pwrite(&mut self, fd: u32, buf: &[u8], offset: u64) -> Result<usize> {
+        self.write_at(self.file(fd)?.inode, offset, buf)?;
+        Ok(buf.len())
+    }
+
+    pub(crate) fn seek(&mut self, fd: u32, delta: i64, whence: Whence) -> Result<u64> {
+        let file = self.file(fd)?;
+        let base = match whence {
+            Whence::Set => 0,
+            Whence::Cur => file.offset,
+            Whence::End => self.file_data(file.inode)?.len() as u64,
+        };
+        let offset = if delta < 0 {
+            base.checked_sub(delta.unsigned_abs())
+        } else {
+            base.checked_add(delta as u64)
+        };
+        let offset = offset.ok_or(Errno::Inval)?;
+        self.file_mut(fd)?.offset = offset;
+        Ok(offset)
+    }
+
+    pub(crate) fn tell(&self, fd: u32) -> Result<u64> {
+        Ok(self.file(fd)?.offset)
+    }
+
+    pub(crate) fn allocate(&mut self, fd: u32, offset: u64, len: u64) -> Result<()> {
+        let inode = self.file(fd)?.inode;
+        let end = offset.checked_add(len).ok_or(Errno::Fbig)?;
+        if end > self.file_data(inode)?.len() as u64 {
+            self.resize(inode, end)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_size(&mut self, fd: u32, size: u64) -> Result<()> {
+        self.resize(self.file(fd)?.inode, size)
+    }
+
+    pub(crate) fn fdflags(&self, fd: u32) -> Result<(Filetype, Fdflags)> {
+        let file = self.file(fd)?;
+        let mut flags = Fdflags::empty();
+        if file.append {
+            flags |= Fdflags::APPEND;
+        }
+        Ok((self.node(file.inode)?.filetype(), flags))
+    }
+
+    pub(crate) fn set_fdflags(&mut self, fd: u32, flags: Fdflags) -> Result<()> {
+        self.file_mut(fd)?.append = flags.contains(Fdflags::APPEND);
+        Ok(())
+    }
+
+    fn stat_of(&self, inode: Inode) -> Result<Stat> {
+        let node = self.node(inode)?;
+        let size = match &node.content {
+            Content::File(data) => data.len() as u64,
+            Content::Dir(entries) => entries.len() as u64,
+        };
+        Ok(Stat {
+            inode,
+            filetype: node.filetype(),
+            size,
+            mtime: node.mtime,
+        })
+    }
+
+    pub(crate) fn stat(&self, fd: u32) -> Result<Stat> {
+        self.stat_of(self.file(fd)?.inode)
+    }
+
+    pub(crate) fn path_stat(&self, dirfd: u32, path: &str) -> Result<Stat> {
+        self.stat_of(self.lookup(self.file(dirfd)?.inode, path)?)
+    }
+
+    /// Set the modification time of an opened file. The time is left unchanged if `mtime` is None.
+    pub(crate) fn set_mtime(&mut self, fd: u32, mtime: Option<u64>) -> Result<()> {
+        let inode = self.file(fd)?.inode;
+        let node = self.node_mut(inode)?;
+        if let Some(mtime) = mtime {
+            node.mtime = mtime;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn path_set_mtime(
+        &mut self,
+        dirfd: u32,
+        path: &str,
+        mtime: Option<u64>,
+    ) -> Result<()> {
+        let inode = self.lookup(self.file(dirfd)?.inode, path)?;
+        let node = self.node_mut(inode)?;
+        if let Some(mtime) = mtime {
+            node.mtime = mtime;
+        }
+        Ok(())
+    }
+
+    /// List the entries of a directory, starting from given cookie.
+    pub(crate) fn read_dir(&self, fd: u32, cookie: u64) -> Result<Vec<DirEntry>> {
+        let inode = self.file(fd)?.inode;
+        let node = self.node(inode)?;
+        let entries = self.dir_entries(inode)?;
+        let dots = [(".", inode), ("..", node.parent)];
+        dots.into_iter()
+            .chain(entries.iter().map(|(name, inode)| (name.as_str(), *inode)))
+            .enumerate()
+            .skip(cookie as usize)
+            .map(|(i, (name, inode))| {
+                Ok(DirEntry {
+                    next: i as u64 + 1,
+                    inode,
+                    name: name.into(),
+                    filetype: self.node(inode)?.filetype(),
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn create_dir(&mut self, dirfd: u32, path: &str) -> Result<()> {
+        let (parent, name) = self.lookup_parent(dirfd, path)?;
+        self.create(parent, name, Content::Dir(Default::default()))?;
+        Ok(())
+    }
+
+    pub(crate) fn remove_dir(&mut self, dirfd: u32, path: &str) -> Result<()> {
+        let (parent, name) = self.lookup_parent(dirfd, path)?;
+        let inode = self.child(parent, name)?;
+        if !self.dir_entries(inode)?.is_empty() {
+            return Err(Errno::Notempty);
+        }
+        self.unlink(parent, name)
+    }
+
+    pub(crate) fn unlink_file(&mut self, dirfd: u32, path: &str) -> Result<()> {
+        let (parent, name) = self.lookup_parent(dirfd, path)?;
+        self.file_data(self.child(parent, name)?)?;
+        self.unlink(parent, name)
+    }
+
+    pub(crate) fn rename(
+        &mut self,
+        old_dirfd: u32,
+        old_path: &str,
+        new_dirfd: u32,
+        new_path: &str,
+    ) -> Result<()> {
+        let (old_parent, old_name) = self.lookup_parent(old_dirfd, old_path)?;
+        let (new_parent, new_name) = self.lookup_parent(new_dirfd, new_path)?;
+        if new_name.len() > MAX_NAME_LEN {
+            return Err(Errno::Nametoolong);
+        }
+        let inode = self.child(old_parent, old_name)?;
+        let is_dir = self.node(inode)?.filetype() == Filetype::Directory;
+        if is_dir {
+            // Refuse to move a directory into its own subtree.
+            let mut dir = new_parent;
+            while dir != ROOT {
+                if dir == inode {
+                    return Err(Errno::Inval);
+                }
+                dir = self.node(dir)?.parent;
+            }
+        }
+        let replace = match self.child(new_parent, new_name) {
+            Ok(target) if target == inode => return Ok(()),
+            Ok(target) => {
+                match (is_dir, &self.node(target)?.content) {
+                    (true, Content::Dir(entries)) if entries.is_empty() => {}
+                    (true, Content::Dir(_)) => return Err(Errno::Notempty),
+                    (true, Content::File(_)) => return Err(Errno::Notdir),
+                    (false, Content::Dir(_)) => return Err(Errno::Isdir),
+                    (false, Content::File(_)) => {}
+                }
+                true
+            }
+            Err(Errno::Noent) => false,
+            Err(err) => return Err(err),
+        };
+        // Charge before unlinking the target, so that it is kept if the quota is exceeded
+        self.charge(new_name.len() as u64)?;
+        if replace {
+            self.unlink(new_parent, new_name)?;
+        }
+        let old_dir = self.node_mut(old_parent)?;
+        old_dir.mtime = now();
+        if let Content::Dir(entries) = &mut old_dir.content {
+            entries.remove(old_name);
+        }
+        self.used -= old_name.len() as u64;
+        let new_dir = self.node_mut(new_parent)?;
+        new_dir.mtime = now();
+        if let Content::Dir(entries) = &mut new_dir.content {
+            entries.insert(new_name.into(), inode);
+        }
+        self.node_mut(inode)?.parent = new_parent;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_ops_work() {
+        let mut fs = Vfs::new(4096);
+        fs.create_dir(ROOT_FD, "data").unwrap();
+        let fd = fs
+            .open(ROOT_FD, "data/a.txt", Oflags::CREATE, Fdflags::empty())
+            .unwrap();
+        assert_eq!(fs.write(fd, b"hello world").unwrap(), 11);
+        assert_eq!(fs.seek(fd, -5, Whence::End).unwrap(), 6);
+        assert_eq!(fs.read(fd, 100).unwrap(), b"world");
+        fs.close(fd).unwrap();
+
+        fs.rename(ROOT_FD, "data/a.txt", ROOT_FD, "b.txt").unwrap();
+        assert_eq!(fs.path_stat(ROOT_FD, "./b.txt").unwrap().size, 11);
+        assert_eq!(
+            fs.path_stat(ROOT_FD, "data/a.txt").err(),
+            Some(Errno::Noent)
+        );
+        assert_eq!(fs.remove_dir(ROOT_FD, "data"), Ok(()));
+        assert_eq!(fs.path_stat(ROOT_FD, "..").err(), Some(Errno::Notcapable));
+
+        let names: Vec<_> = fs
+            .read_dir(ROOT_FD, 0)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, [".", "..", "b.txt"]);
+
+        // Quota exceeded
+        let fd = fs
+            .open(ROOT_FD, "b.txt", Oflags::TRUNC, Fdflags::APPEND)
+            .unwrap();
+        assert_eq!(fs.write(fd, &[0; 4096]), Err(Errno::Nospc));
+        // The space of an unlinked file is freed after closing
+        fs.write(fd, &[0; 1024]).unwrap();
+        fs.unlink_file(ROOT_FD, "b.txt").unwrap();
+        let used = fs.used;
+        fs.close(fd).unwrap();
+        assert_eq!(fs.used, used - 1024 - NODE_OVERHEAD);
+        assert_eq!(fs.used, NODE_OVERHEAD);
+    }
+
+    #[test]
+    fn failed_rename_keeps_the_target() {
+        let mut fs = Vfs::new(4096);
+        for name in ["a", "bb"] {
+            let fd = fs
+                .open(ROOT_FD, name, Oflags::CREATE, Fdflags::empty())
+                .unwrap();
+            fs.write(fd, name.as_bytes()).unwrap();
+            fs.close(fd).unwrap();
+        }
+        fs.quota = fs.used;
+        assert_eq!(fs.rename(ROOT_FD, "a", ROOT_FD, "bb"), Err(Errno::Nospc));
+        assert_eq!(fs.path_stat(ROOT_FD, "a").unwrap().size, 1);
+        assert_eq!(fs.path_stat(ROOT_FD, "bb").unwrap().size, 2);
+
+        fs.quota += 2;
+        fs.rename(ROOT_FD, "a", ROOT_FD, "bb").unwrap();
+        assert_eq!(fs.path_stat(ROOT_FD, "a").err(), Some(Errno::Noent));
+        assert_eq!(fs.path_stat(ROOT_FD, "bb").unwrap().size, 1);
+    }
+
+    #[test]
+    fn open_does_not_create_directories() {
+        let mut fs = Vfs::new(4096);
+        assert_eq!(
+            fs.open(
+                ROOT_FD,
+                "dir",
+                Oflags::CREATE | Oflags::DIRECTORY,
+                Fdflags::empty()
+            ),
+            Err(Errno::Inval)
+        );
+        assert_eq!(fs.path_stat(ROOT_FD, "dir").err(), Some(Errno::Noent));
+    }
+}
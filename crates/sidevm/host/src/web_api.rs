@@ -59,7 +59,7 @@ impl App {
         vmid[0..4].copy_from_slice(&id.to_be_bytes());
 
         println!("VM {id} running...");
-        let (sender, handle) = inner
+        let (sender, handle, _) = inner
             .spawner
             .start(
                 &wasm_bytes,
@@ -67,7 +67,9 @@ impl App {
                 vmid,
                 inner.args.gas_per_breath,
                 crate::simple_cache(),
+                None,
                 weight,
+                None,
             )
             .unwrap();
         inner.instances.insert(id, sender);
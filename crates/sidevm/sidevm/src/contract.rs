@@ -0,0 +1,29 @@
+//! Interacting with the contracts in the same cluster.
+
+use std::task::Poll;
+
+use crate::env::{tasks, OcallError, Result};
+use crate::{ocall, ResourceId};
+
+/// Query a contract with an ink message, using the contract of this VM as the origin.
+///
+/// The message is executed read-only against the current cluster storage. Returns the SCALE
+/// encoded `ContractExecResult` of the call.
+///
+/// # Example
+/// ```ignore
+/// let selector = 0x2f865bd9_u32.to_be_bytes();
+/// let result = sidevm::contract::query(&contract_id, &selector).await?;
+/// ```
+pub async fn query(contract_id: &[u8; 32], payload: &[u8]) -> Result<Vec<u8>> {
+    let res_id = ResourceId(ocall::contract_query(contract_id, payload)?);
+    futures::future::poll_fn(|cx| {
+        let waker_id = tasks::intern_waker(cx.waker().clone());
+        match ocall::poll(waker_id, res_id.0) {
+            Ok(data) => Poll::Ready(Ok(data)),
+            Err(OcallError::Pending) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    })
+    .await
+}
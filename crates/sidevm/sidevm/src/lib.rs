@@ -13,7 +13,10 @@ pub use env::spawn;
 pub use env::tasks as task;
 
 pub mod channel;
+pub mod contract;
 pub mod net;
+pub mod snapshot;
+pub mod storage;
 pub mod time;
 pub mod exec;
 
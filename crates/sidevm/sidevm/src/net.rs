@@ -11,6 +11,8 @@ use env::tls::TlsServerConfig;
 use crate::env::{self, tasks, Result};
 use crate::{ocall, ResourceId};
 
+pub use env::dns::{Record, RecordType};
+
 /// A TCP socket server, listening for connections.
 pub struct TcpListener {
     res_id: ResourceId,
@@ -110,6 +112,104 @@ impl TcpStream {
     }
 }
 
+/// A UDP socket.
+#[derive(Debug)]
+pub struct UdpSocket {
+    res_id: ResourceId,
+}
+
+impl UdpSocket {
+    /// Create a UDP socket bound to the specified address.
+    pub async fn bind(addr: &str) -> Result<Self> {
+        let res_id = ResourceId(ocall::udp_bind(addr)?);
+        Ok(Self { res_id })
+    }
+
+    /// Send a datagram to the given address. Returns the number of bytes sent.
+    pub async fn send_to(&self, buf: &[u8], target: SocketAddr) -> Result<usize> {
+        futures::future::poll_fn(|cx| self.poll_send_to(cx, buf, target)).await
+    }
+
+    /// Receive a datagram. Returns the number of bytes read and the address of the sender.
+    ///
+    /// The datagram is truncated if it doesn't fit in `buf`.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        futures::future::poll_fn(|cx| self.poll_recv_from(cx, buf)).await
+    }
+
+    /// Attempt to send a datagram to the given address.
+    pub fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<Result<usize>> {
+        use env::OcallError;
+        let waker_id = tasks::intern_waker(cx.waker().clone());
+        match ocall::udp_send_to(
+            waker_id,
+            self.res_id.0,
+            target.to_string().into(),
+            buf.into(),
+        ) {
+            Ok(len) => Poll::Ready(Ok(len as usize)),
+            Err(OcallError::Pending) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    /// Attempt to receive a datagram.
+    pub fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<(usize, SocketAddr)>> {
+        use env::OcallError;
+        let waker_id = tasks::intern_waker(cx.waker().clone());
+        match ocall::udp_recv_from(waker_id, self.res_id.0, buf) {
+            Ok((len, remote_addr)) => Poll::Ready(Ok((
+                len as usize,
+                remote_addr
+                    .parse()
+                    .expect("ocall::udp_recv_from returned an invalid remote address"),
+            ))),
+            Err(OcallError::Pending) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+/// Look up the DNS records of given type for the name.
+///
+/// Returns an empty list if the name has no such record.
+///
+/// # Example
+/// ```ignore
+/// use sidevm::net::{resolve, Record, RecordType};
+///
+/// for record in resolve("_http._tcp.example.com", RecordType::Srv).await? {
+///     if let Record::Srv { target, port, .. } = record {
+///         log::info!("Found service at {target}:{port}");
+///     }
+/// }
+/// ```
+pub async fn resolve(name: &str, record_type: RecordType) -> Result<Vec<Record>> {
+    use env::OcallError;
+    use scale::Decode;
+
+    let res_id = ResourceId(ocall::resolve(name, record_type)?);
+    let encoded = futures::future::poll_fn(|cx| {
+        let waker_id = tasks::intern_waker(cx.waker().clone());
+        match ocall::poll(waker_id, res_id.0) {
+            Ok(data) => Poll::Ready(Ok(data)),
+            Err(OcallError::Pending) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    })
+    .await?;
+    Decode::decode(&mut &encoded[..]).or(Err(OcallError::InvalidEncoding))
+}
+
 #[cfg(feature = "hyper")]
 pub use impl_hyper::{AddrIncoming, AddrStream, HttpConnector};
 #[cfg(feature = "hyper")]
@@ -0,0 +1,15 @@
+//! Resuming the VM across worker restarts.
+//!
+//! By default, the VM is started from scratch when the worker restarts, losing all the in-memory
+//! state. Once enabled, the worker snapshots the VM when taking a checkpoint and resumes it from
+//! there after a restart. Input channels and timers keep working after the resume, while sockets
+//! and pending operations fail with `IoError` and have to be recreated by the program.
+
+use super::*;
+
+use env::Result;
+
+/// Allow or disallow the worker to snapshot and resume this VM.
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    ocall::enable_snapshot(enabled)
+}
@@ -0,0 +1,23 @@
+//! Persistent key-value storage of the VM.
+//!
+//! Unlike the local cache, values stored here never expire. They are sealed on the worker disk and
+//! survive restarts of the VM and of the worker.
+
+use super::*;
+
+use env::Result;
+
+/// Get the value of given key.
+pub fn get(key: &[u8]) -> Result<Option<Vec<u8>>> {
+    ocall::storage_get(key)
+}
+
+/// Set the value of given key.
+pub fn set(key: &[u8], value: &[u8]) -> Result<()> {
+    ocall::storage_set(key, value)
+}
+
+/// Remove the value of given key. Returns the previous value if it existed.
+pub fn remove(key: &[u8]) -> Result<Option<Vec<u8>>> {
+    ocall::storage_remove(key)
+}
@@ -63,7 +63,11 @@ pub mod pallet {
 	use crate::types::*;
 	use core::fmt::Debug;
 	use frame_support::{
-		dispatch::DispatchResult, pallet_prelude::*, traits::StorageVersion, transactional,
+		dispatch::{DispatchResult, DispatchResultWithPostInfo},
+		pallet_prelude::*,
+		traits::StorageVersion,
+		transactional,
+		weights::Weight,
 	};
 	use frame_system::pallet_prelude::*;
 	use sp_core::H256;
@@ -76,6 +80,10 @@ pub mod pallet {
 		type OnResponse: OnResponse<Self::AccountId>;
 		type QueuePrefix: Get<&'static [u8]>;
 		type QueueCapacity: Get<u32>;
+		/// Dispatches the replies of names bound to a `CallbackTarget`
+		type CallbackDispatcher: CallbackDispatcher<Self::AccountId>;
+		/// The max weight a single callback can consume
+		type MaxCallbackWeight: Get<Weight>;
 	}
 
 	/// Anchor response handler trait
@@ -89,6 +97,42 @@ pub mod pallet {
 		}
 	}
 
+	/// Routes replies to the callback targets registered by `set_callback`
+	pub trait CallbackDispatcher<AccountId> {
+		/// Whether replies can be dispatched to the target
+		///
+		/// `set_callback` rejects the targets not supported.
+		fn supports(target: &CallbackTarget) -> bool;
+
+		/// Calls the target with the reply data
+		///
+		/// Must not consume more than `weight_limit`. Returns the weight actually consumed. The
+		/// storage changes of a failed dispatch are reverted.
+		fn dispatch(
+			target: &CallbackTarget,
+			name: H256,
+			submitter: AccountId,
+			data: Vec<u8>,
+			weight_limit: Weight,
+		) -> Result<Weight, DispatchError>;
+	}
+	// Default implementation, which supports no target
+	impl<AccountId> CallbackDispatcher<AccountId> for () {
+		fn supports(_target: &CallbackTarget) -> bool {
+			false
+		}
+
+		fn dispatch(
+			_target: &CallbackTarget,
+			_name: H256,
+			_submitter: AccountId,
+			_data: Vec<u8>,
+			_weight_limit: Weight,
+		) -> Result<Weight, DispatchError> {
+			Err(DispatchError::Other("No callback dispatcher"))
+		}
+	}
+
 	const STORAGE_VERSION: StorageVersion = StorageVersion::new(0);
 
 	#[pallet::pallet]
@@ -106,6 +150,13 @@ pub mod pallet {
 	pub type States<T> =
 		StorageDoubleMap<_, Blake2_128Concat, H256, Blake2_128Concat, KeyBytes, ValueBytes>;
 
+	/// The callback targets the replies of the names are routed to
+	///
+	/// Replies of names without a target are handled by `OnResponse`.
+	#[pallet::storage]
+	#[pallet::getter(fn callbacks)]
+	pub type Callbacks<T> = StorageMap<_, Blake2_128Concat, H256, CallbackTarget>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -117,6 +168,17 @@ pub mod pallet {
 			name: H256,
 			nonce: u128,
 		},
+		/// The callback target of a name is changed
+		CallbackSet {
+			name: H256,
+			target: Option<CallbackTarget>,
+		},
+		/// A reply couldn't be delivered to the callback target of the name
+		CallbackFailed {
+			name: H256,
+			target: CallbackTarget,
+			error: DispatchError,
+		},
 	}
 
 	#[pallet::error]
@@ -136,6 +198,8 @@ pub mod pallet {
 		QueueIsFull,
 		/// Trying to set an invalid queue head
 		InvalidQueueHead,
+		/// The callback target is not supported by the `CallbackDispatcher`
+		UnsupportedCallbackTarget,
 	}
 
 	#[pallet::call]
@@ -161,25 +225,30 @@ pub mod pallet {
 		}
 
 		/// Triggers a rollup with an optional nonce
+		///
+		/// Each action may trigger a callback, so the weight is charged for the worst case and the
+		/// unused part is refunded. A failed callback doesn't revert the rollup, but is reported
+		/// by `CallbackFailed`.
 		#[pallet::call_index(1)]
-		#[pallet::weight(0)]
+		#[pallet::weight(
+			Pallet::<T>::rollup_base_weight(tx)
+				.saturating_add(T::MaxCallbackWeight::get().saturating_mul(tx.actions.len() as u64))
+		)]
 		#[transactional]
 		pub fn rollup(
 			origin: OriginFor<T>,
 			name: H256,
 			tx: RollupTx,
 			nonce: u128,
-		) -> DispatchResult {
+		) -> DispatchResultWithPostInfo {
 			// Check submitter
 			let who = ensure_signed(origin)?;
+			let base_weight = Self::rollup_base_weight(&tx);
 			Self::ensure_name_owner(&name, &who)?;
 			// Check conditions
 			for cond in tx.conds {
-				let Cond::Eq(key, opt_value) = cond;
-				ensure!(
-					States::<T>::get(name, key) == opt_value,
-					Error::<T>::CondNotMet
-				);
+				let value = States::<T>::get(name, cond.key());
+				ensure!(cond.check(value.as_ref()), Error::<T>::CondNotMet);
 			}
 			// Apply updates
 			for (key, opt_value) in tx.updates {
@@ -190,15 +259,49 @@ pub mod pallet {
 				}
 			}
 			// Exec actions
+			let callback = Callbacks::<T>::get(name);
+			let mut callback_weight = Weight::zero();
 			for raw_act in tx.actions {
 				let act: Action =
 					Decode::decode(&mut &raw_act[..]).or(Err(Error::<T>::FailedToDecodeAction))?;
 				match act {
-					Action::Reply(data) => {
-						T::OnResponse::on_response(name, who.clone(), data.into())?
-					}
+					Action::Reply(data) => match &callback {
+						Some(target) => {
+							let limit = T::MaxCallbackWeight::get();
+							let result = frame_support::storage::with_storage_layer(|| {
+								T::CallbackDispatcher::dispatch(
+									target,
+									name,
+									who.clone(),
+									data.into(),
+									limit,
+								)
+							});
+							let used = match result {
+								Ok(used) => used.min(limit),
+								Err(error) => {
+									Self::deposit_event(Event::CallbackFailed {
+										name,
+										target: target.clone(),
+										error,
+									});
+									limit
+								}
+							};
+							callback_weight = callback_weight.saturating_add(used);
+						}
+						None => {
+							T::OnResponse::on_response(name, who.clone(), data.into())?;
+							// `OnResponse` doesn't report its weight, so charge the worst case
+							callback_weight =
+								callback_weight.saturating_add(T::MaxCallbackWeight::get());
+						}
+					},
 					Action::SetQueueHead(head) => {
-						Self::queue_head_set(&name, head)?;
+						Self::queue_head_set(&name, &[], head)?;
+					}
+					Action::SetNamedQueueHead(queue, head) => {
+						Self::queue_head_set(&name, &queue, head)?;
 					}
 				}
 			}
@@ -207,11 +310,52 @@ pub mod pallet {
 				name,
 				nonce,
 			});
+			Ok(Some(base_weight.saturating_add(callback_weight)).into())
+		}
+
+		/// Routes the replies of the name to a callback target, or back to the default
+		/// `OnResponse` handler if `None`
+		///
+		/// Only the owner of the name can set the target, and it must be supported by the
+		/// `CallbackDispatcher`.
+		#[pallet::call_index(2)]
+		#[pallet::weight(Weight::from_ref_time(10_000u64) + T::DbWeight::get().reads_writes(1u64, 1u64))]
+		#[transactional]
+		pub fn set_callback(
+			origin: OriginFor<T>,
+			name: H256,
+			target: Option<CallbackTarget>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_name_owner(&name, &who)?;
+			if let Some(target) = &target {
+				ensure!(
+					T::CallbackDispatcher::supports(target),
+					Error::<T>::UnsupportedCallbackTarget
+				);
+			}
+			Callbacks::<T>::set(name, target.clone());
+			Self::deposit_event(Event::CallbackSet { name, target });
 			Ok(())
 		}
 	}
 
 	impl<T: Config> Pallet<T> {
+		/// The weight of a rollup excluding the callbacks
+		///
+		/// Reads the owner and the callback, then one read per condition and one write per
+		/// update. Each action may also move a queue head.
+		fn rollup_base_weight(tx: &RollupTx) -> Weight {
+			let db = T::DbWeight::get();
+			Weight::from_ref_time(10_000u64)
+				.saturating_add(db.reads(2u64 + tx.conds.len() as u64))
+				.saturating_add(db.writes(tx.updates.len() as u64))
+				.saturating_add(
+					db.reads_writes(1u64, 1u64)
+						.saturating_mul(tx.actions.len() as u64),
+				)
+		}
+
 		/// Cheks the name is owned by the caller
 		fn ensure_name_owner(name: &H256, caller: &T::AccountId) -> DispatchResult {
 			let owner = SubmitterByNames::<T>::get(name).ok_or(Error::<T>::NameNotExist)?;
@@ -223,17 +367,29 @@ pub mod pallet {
 		///
 		/// Returns the index of the message if succeeded
 		pub fn push_message(name: &H256, data: ValueBytes) -> Result<u32, Error<T>> {
+			Self::push_message_to(name, &[], data)
+		}
+
+		/// Pushes a message to the sub-queue `queue` of the target rollup instance by `name`
+		///
+		/// Each sub-queue has its own head, tail and capacity. Returns the index of the message
+		/// if succeeded.
+		pub fn push_message_to(
+			name: &H256,
+			queue: &[u8],
+			data: ValueBytes,
+		) -> Result<u32, Error<T>> {
 			ensure!(
 				SubmitterByNames::<T>::contains_key(name),
 				Error::<T>::NameNotExist
 			);
 			ensure!(
-				Self::queue_len(name) < T::QueueCapacity::get(),
+				Self::named_queue_len(name, queue) < T::QueueCapacity::get(),
 				Error::<T>::QueueIsFull
 			);
-			let end = Self::queue_tail(name);
-			Self::queue_set(name, &end, data);
-			Self::queue_tail_set(name, end + 1);
+			let end = Self::named_queue_tail(name, queue);
+			Self::queue_set(name, queue, &end, data);
+			Self::queue_tail_set(name, queue, end + 1);
 			Ok(end)
 		}
 
@@ -241,71 +397,94 @@ pub mod pallet {
 		///
 		/// When `queue_head() == queue_tail()`, the queue is empty.
 		pub fn queue_head(name: &H256) -> u32 {
-			Self::queue_get_u32(name, b"_head").expect("BUG: Failed to decode queue head")
+			Self::named_queue_head(name, &[])
 		}
 
 		/// Returns the position of the message queue tail element
 		///
 		/// When `queue_head() == queue_tail()`, the queue is empty.
 		pub fn queue_tail(name: &H256) -> u32 {
-			Self::queue_get_u32(name, b"_tail").expect("BUG: Failed to decode queue tail")
+			Self::named_queue_tail(name, &[])
 		}
 
 		/// Returns number of elements in the queue
 		pub fn queue_len(name: &H256) -> u32 {
-			Self::queue_tail(name).saturating_sub(Self::queue_head(name))
+			Self::named_queue_len(name, &[])
+		}
+
+		/// Returns the position of the head element of the sub-queue `queue`
+		pub fn named_queue_head(name: &H256, queue: &[u8]) -> u32 {
+			Self::queue_get_u32(name, queue, b"_head").expect("BUG: Failed to decode queue head")
+		}
+
+		/// Returns the position of the tail element of the sub-queue `queue`
+		pub fn named_queue_tail(name: &H256, queue: &[u8]) -> u32 {
+			Self::queue_get_u32(name, queue, b"_tail").expect("BUG: Failed to decode queue tail")
+		}
+
+		/// Returns number of elements in the sub-queue `queue`
+		pub fn named_queue_len(name: &H256, queue: &[u8]) -> u32 {
+			Self::named_queue_tail(name, queue).saturating_sub(Self::named_queue_head(name, queue))
 		}
 	}
 
 	/// Private helper methods
 	impl<T: Config> Pallet<T> {
-		fn queue_get_u32(name: &H256, index: &[u8; 5]) -> Result<u32, impl Debug> {
-			let Some(bytes) = Self::queue_get(name, index) else {
+		fn queue_get_u32(name: &H256, queue: &[u8], index: &[u8; 5]) -> Result<u32, impl Debug> {
+			let Some(bytes) = Self::queue_get(name, queue, index) else {
 				return Ok(0);
 			};
 			u32::decode(&mut &bytes[..])
 		}
 
-		fn queue_set_u32(name: &H256, index: &[u8; 5], value: u32) {
+		fn queue_set_u32(name: &H256, queue: &[u8], index: &[u8; 5], value: u32) {
 			let value = value
 				.encode()
 				.try_into()
 				.expect("BUG: Failed to encode u32");
-			Self::queue_set(name, index, value);
+			Self::queue_set(name, queue, index, value);
 		}
 
-		fn queue_head_set(name: &H256, index: u32) -> DispatchResult {
-			let head = Self::queue_head(name);
-			let tail = Self::queue_tail(name);
+		fn queue_head_set(name: &H256, queue: &[u8], index: u32) -> DispatchResult {
+			let head = Self::named_queue_head(name, queue);
+			let tail = Self::named_queue_tail(name, queue);
 			ensure!(head <= index && index <= tail, Error::<T>::InvalidQueueHead);
 			for i in head..index {
-				Self::queue_remove(name, &i);
+				Self::queue_remove(name, queue, &i);
 			}
-			Self::queue_set_u32(name, b"_head", index);
+			Self::queue_set_u32(name, queue, b"_head", index);
 			Ok(())
 		}
 
-		fn queue_tail_set(name: &H256, index: u32) {
-			Self::queue_set_u32(name, b"_tail", index)
+		fn queue_tail_set(name: &H256, queue: &[u8], index: u32) {
+			Self::queue_set_u32(name, queue, b"_tail", index)
 		}
 
-		fn queue_key(index: &impl Encode) -> KeyBytes {
+		/// The storage key of an element of a queue
+		///
+		/// The default queue (empty name) lives directly under `QueuePrefix`. Named sub-queues
+		/// are put under `QueuePrefix ++ SCALE(queue)`, whose length prefix keeps them apart from
+		/// each other and from the default queue.
+		fn queue_key(queue: &[u8], index: &impl Encode) -> KeyBytes {
 			let mut key = T::QueuePrefix::get().to_vec();
+			if !queue.is_empty() {
+				queue.encode_to(&mut key);
+			}
 			index.encode_to(&mut key);
 			key.try_into()
 				.expect("BUG: Failed to make the queue key, the prefix might be too long")
 		}
 
-		fn queue_set(name: &H256, index: &impl Encode, data: ValueBytes) {
-			States::<T>::insert(name, Self::queue_key(index), data);
+		fn queue_set(name: &H256, queue: &[u8], index: &impl Encode, data: ValueBytes) {
+			States::<T>::insert(name, Self::queue_key(queue, index), data);
 		}
 
-		fn queue_get(name: &H256, index: &impl Encode) -> Option<ValueBytes> {
-			States::<T>::get(name, Self::queue_key(index))
+		fn queue_get(name: &H256, queue: &[u8], index: &impl Encode) -> Option<ValueBytes> {
+			States::<T>::get(name, Self::queue_key(queue, index))
 		}
 
-		fn queue_remove(name: &H256, index: &impl Encode) {
-			States::<T>::remove(name, Self::queue_key(index))
+		fn queue_remove(name: &H256, queue: &[u8], index: &impl Encode) {
+			States::<T>::remove(name, Self::queue_key(queue, index))
 		}
 	}
 
@@ -314,8 +493,8 @@ pub mod pallet {
 		use super::*;
 		use crate::{
 			mock::{
-				bvec, new_test_ext, set_block_1, take_events, Anchor, RuntimeEvent,
-				RuntimeOrigin as Origin, Test,
+				bvec, new_test_ext, set_block_1, take_callbacks, take_events, Anchor,
+				MaxCallbackWeight, RuntimeEvent, RuntimeOrigin as Origin, Test, CALLBACK_WEIGHT,
 			},
 			types::RollupTx,
 		};
@@ -395,6 +574,7 @@ pub mod pallet {
 					pair: bvec(b"polkadot_usd"),
 					price: 5_000000000000,
 					timestamp_ms: 1000,
+					request_id: None,
 				};
 				let act = Action::Reply(bvec(&resposne.encode()));
 				let _ = take_events();
@@ -428,6 +608,177 @@ pub mod pallet {
 			});
 		}
 
+		#[test]
+		fn rollup_conds_work() {
+			new_test_ext().execute_with(|| {
+				set_block_1();
+				assert_ok!(Anchor::claim_name(Origin::signed(1), NAME1));
+				assert_ok!(Anchor::rollup(
+					Origin::signed(1),
+					NAME1,
+					RollupTx {
+						conds: vec![],
+						actions: vec![],
+						updates: vec![
+							(bvec(b"version"), Some(bvec(&5u32.encode()))),
+							(bvec(b"owner"), Some(bvec(b"alice"))),
+						],
+					},
+					1u128
+				));
+
+				let check = |conds: Vec<Cond>| {
+					Anchor::rollup(
+						Origin::signed(1),
+						NAME1,
+						RollupTx {
+							conds,
+							actions: vec![],
+							updates: vec![],
+						},
+						2u128,
+					)
+				};
+				let version = || bvec(b"version");
+
+				assert_ok!(check(vec![Cond::Exists(bvec(b"owner"))]));
+				assert_noop!(
+					check(vec![Cond::Exists(bvec(b"nobody"))]),
+					Error::<Test>::CondNotMet
+				);
+
+				assert_ok!(check(vec![
+					Cond::Cmp(version(), CmpOp::Ge, 5),
+					Cond::Cmp(version(), CmpOp::Le, 5),
+					Cond::Cmp(version(), CmpOp::Gt, 4),
+					Cond::Cmp(version(), CmpOp::Lt, 6),
+				]));
+				assert_noop!(
+					check(vec![Cond::Cmp(version(), CmpOp::Ge, 6)]),
+					Error::<Test>::CondNotMet
+				);
+				assert_noop!(
+					check(vec![Cond::Cmp(version(), CmpOp::Lt, 5)]),
+					Error::<Test>::CondNotMet
+				);
+				// Absent keys are treated as zero
+				assert_ok!(check(vec![Cond::Cmp(bvec(b"timestamp"), CmpOp::Lt, 1000)]));
+				// Values which are not integers never match
+				assert_noop!(
+					check(vec![Cond::Cmp(bvec(b"owner"), CmpOp::Ge, 0)]),
+					Error::<Test>::CondNotMet
+				);
+
+				assert_ok!(check(vec![Cond::HasPrefix(bvec(b"owner"), bvec(b"ali"))]));
+				assert_noop!(
+					check(vec![Cond::HasPrefix(bvec(b"owner"), bvec(b"bob"))]),
+					Error::<Test>::CondNotMet
+				);
+				assert_noop!(
+					check(vec![Cond::HasPrefix(bvec(b"nobody"), bvec(b""))]),
+					Error::<Test>::CondNotMet
+				);
+			});
+		}
+
+		#[test]
+		fn callback_works() {
+			new_test_ext().execute_with(|| {
+				set_block_1();
+				assert_ok!(Anchor::claim_name(Origin::signed(1), NAME1));
+				let target = CallbackTarget::Evm {
+					contract: sp_core::H160([2u8; 20]),
+					selector: [1, 2, 3, 4],
+				};
+
+				// Only the owner can set the callback
+				assert_noop!(
+					Anchor::set_callback(Origin::signed(2), NAME1, Some(target.clone())),
+					Error::<Test>::NotOwner
+				);
+				// Targets the dispatcher can't call are rejected
+				let unsupported = CallbackTarget::Ink {
+					contract: sp_runtime::AccountId32::from([2u8; 32]),
+					selector: [1, 2, 3, 4],
+				};
+				assert_noop!(
+					Anchor::set_callback(Origin::signed(1), NAME1, Some(unsupported)),
+					Error::<Test>::UnsupportedCallbackTarget
+				);
+				assert_ok!(Anchor::set_callback(
+					Origin::signed(1),
+					NAME1,
+					Some(target.clone())
+				));
+				assert_eq!(Anchor::callbacks(NAME1), Some(target.clone()));
+
+				// Replies are routed to the target and the callback weight is accounted
+				let replies = || RollupTx {
+					conds: vec![],
+					actions: vec![
+						bvec(&Action::Reply(bvec(b"foo")).encode()),
+						bvec(&Action::Reply(bvec(b"bar")).encode()),
+					],
+					updates: vec![],
+				};
+				let _ = take_events();
+				let base_weight = Anchor::rollup_base_weight(&replies());
+				assert!(base_weight.ref_time() > 0);
+				let post_info = Anchor::rollup(Origin::signed(1), NAME1, replies(), 1u128).unwrap();
+				assert_eq!(
+					post_info.actual_weight,
+					Some(base_weight + CALLBACK_WEIGHT * 2)
+				);
+				assert_eq!(
+					take_callbacks(),
+					vec![
+						(target.clone(), NAME1, 1, b"foo".to_vec()),
+						(target, NAME1, 1, b"bar".to_vec()),
+					]
+				);
+
+				// Failed callbacks are reported without reverting the rollup
+				let failing = CallbackTarget::Handler(0);
+				assert_ok!(Anchor::set_callback(
+					Origin::signed(1),
+					NAME1,
+					Some(failing.clone())
+				));
+				let _ = take_events();
+				let post_info = Anchor::rollup(Origin::signed(1), NAME1, replies(), 2u128).unwrap();
+				assert_eq!(
+					post_info.actual_weight,
+					Some(base_weight + MaxCallbackWeight::get() * 2)
+				);
+				let failed = RuntimeEvent::Anchor(Event::<Test>::CallbackFailed {
+					name: NAME1,
+					target: failing,
+					error: DispatchError::Other("Handler failed"),
+				});
+				assert_eq!(
+					take_events(),
+					vec![
+						failed.clone(),
+						failed,
+						RuntimeEvent::Anchor(Event::<Test>::RollupExecuted {
+							submitter: 1,
+							name: NAME1,
+							nonce: 2,
+						}),
+					]
+				);
+
+				// Unset the callback falls back to `OnResponse`
+				assert_ok!(Anchor::set_callback(Origin::signed(1), NAME1, None));
+				assert_eq!(Anchor::callbacks(NAME1), None);
+				assert_noop!(
+					Anchor::rollup(Origin::signed(1), NAME1, replies(), 3u128),
+					crate::oracle::Error::<Test>::FailedToDecodeResponse
+				);
+				assert!(take_callbacks().is_empty());
+			});
+		}
+
 		#[test]
 		fn name_cannot_claim_twice() {
 			new_test_ext().execute_with(|| {
@@ -442,11 +793,19 @@ pub mod pallet {
 
 		#[test]
 		fn queue_key_is_correct() {
-			assert_eq!(&Anchor::queue_key(&1)[..], b"_queue/\x01\x00\x00\x00");
+			assert_eq!(&Anchor::queue_key(&[], &1)[..], b"_queue/\x01\x00\x00\x00");
 			assert_eq!(
-				&Anchor::queue_key(b"_head")[..],
+				&Anchor::queue_key(&[], b"_head")[..],
 				[95, 113, 117, 101, 117, 101, 47, 95, 104, 101, 97, 100]
 			);
+			assert_eq!(
+				&Anchor::queue_key(b"price", &1)[..],
+				b"_queue/\x14price\x01\x00\x00\x00"
+			);
+			assert_eq!(
+				&Anchor::queue_key(b"price", b"_head")[..],
+				b"_queue/\x14price_head"
+			);
 		}
 
 		#[test]
@@ -480,7 +839,7 @@ pub mod pallet {
 				assert_eq!(Anchor::queue_len(&NAME1), cap);
 
 				for i in 0..cap {
-					assert!(Anchor::queue_get(&NAME1, &i).is_some());
+					assert!(Anchor::queue_get(&NAME1, &[], &i).is_some());
 				}
 
 				// Pop all elements except the last one
@@ -499,10 +858,10 @@ pub mod pallet {
 				assert_eq!(Anchor::queue_tail(&NAME1), cap);
 				assert_eq!(Anchor::queue_len(&NAME1), 1);
 				for i in 0..cap - 1 {
-					assert!(Anchor::queue_get(&NAME1, &i).is_none());
+					assert!(Anchor::queue_get(&NAME1, &[], &i).is_none());
 				}
 				let last = cap - 1;
-				assert!(Anchor::queue_get(&NAME1, &last).is_some());
+				assert!(Anchor::queue_get(&NAME1, &[], &last).is_some());
 
 				// Pop the last one
 				assert_ok!(Anchor::rollup(
@@ -534,6 +893,66 @@ pub mod pallet {
 			});
 		}
 
+		#[test]
+		fn named_queues_work() {
+			new_test_ext().execute_with(|| {
+				set_block_1();
+				assert_ok!(Anchor::claim_name(Origin::signed(1), NAME1));
+
+				let cap = <<Test as Config>::QueueCapacity as Get<_>>::get();
+				for i in 0..cap {
+					assert_eq!(Anchor::push_message_to(&NAME1, b"a", bvec(b"foo")), Ok(i));
+				}
+				assert_eq!(
+					Anchor::push_message_to(&NAME1, b"a", bvec(b"foo")),
+					Err(Error::<Test>::QueueIsFull)
+				);
+				// Other queues have their own capacity
+				assert_eq!(Anchor::push_message_to(&NAME1, b"b", bvec(b"bar")), Ok(0));
+				assert_eq!(Anchor::push_message(&NAME1, bvec(b"baz")), Ok(0));
+				assert_eq!(Anchor::named_queue_len(&NAME1, b"a"), cap);
+				assert_eq!(Anchor::named_queue_len(&NAME1, b"b"), 1);
+				assert_eq!(Anchor::queue_len(&NAME1), 1);
+				// The empty name refers to the default queue
+				assert_eq!(Anchor::named_queue_len(&NAME1, b""), 1);
+
+				// Pop from a named queue only
+				assert_ok!(Anchor::rollup(
+					Origin::signed(1),
+					NAME1,
+					RollupTx {
+						conds: vec![],
+						actions: vec![bvec(
+							&Action::SetNamedQueueHead(bvec(b"a"), cap - 1).encode()
+						)],
+						updates: vec![],
+					},
+					1u128
+				));
+				assert_eq!(Anchor::named_queue_head(&NAME1, b"a"), cap - 1);
+				assert_eq!(Anchor::named_queue_len(&NAME1, b"a"), 1);
+				assert_eq!(Anchor::named_queue_len(&NAME1, b"b"), 1);
+				assert_eq!(Anchor::queue_len(&NAME1), 1);
+				for i in 0..cap - 1 {
+					assert!(Anchor::queue_get(&NAME1, b"a", &i).is_none());
+				}
+
+				assert_noop!(
+					Anchor::rollup(
+						Origin::signed(1),
+						NAME1,
+						RollupTx {
+							conds: vec![],
+							actions: vec![bvec(&Action::SetNamedQueueHead(bvec(b"b"), 2).encode())],
+							updates: vec![],
+						},
+						2u128
+					),
+					Error::<Test>::InvalidQueueHead
+				);
+			});
+		}
+
 		#[test]
 		fn queue_e2e() {
 			use kv_session::traits::KvSession;
@@ -606,10 +1025,28 @@ pub mod pallet {
 					// We don't have to submit it if there are no updates
 					return None;
 				}
+				use kv_session::traits::{CmpOp as KvCmpOp, Condition};
 				let conds = tx
 					.conditions
 					.into_iter()
-					.map(|(k, v)| Cond::Eq(k.try_into().unwrap(), v.map(|v| v.try_into().unwrap())))
+					.map(|cond| match cond {
+						Condition::Eq(k, v) => {
+							Cond::Eq(k.try_into().unwrap(), v.map(|v| v.try_into().unwrap()))
+						}
+						Condition::Exists(k) => Cond::Exists(k.try_into().unwrap()),
+						Condition::Cmp(k, op, n) => {
+							let op = match op {
+								KvCmpOp::Lt => CmpOp::Lt,
+								KvCmpOp::Le => CmpOp::Le,
+								KvCmpOp::Gt => CmpOp::Gt,
+								KvCmpOp::Ge => CmpOp::Ge,
+							};
+							Cond::Cmp(k.try_into().unwrap(), op, n)
+						}
+						Condition::HasPrefix(k, p) => {
+							Cond::HasPrefix(k.try_into().unwrap(), p.try_into().unwrap())
+						}
+					})
 					.collect();
 				let updates = tx
 					.updates
@@ -620,6 +1057,10 @@ pub mod pallet {
 				if let Some(head) = tx.queue_head {
 					actions.push(bvec(&Action::SetQueueHead(head).encode()));
 				}
+				for (queue, head) in tx.named_queue_heads {
+					let act = Action::SetNamedQueueHead(queue.try_into().unwrap(), head);
+					actions.push(bvec(&act.encode()));
+				}
 				Some(RollupTx {
 					conds,
 					actions,
@@ -641,6 +1082,10 @@ pub mod pallet {
 				assert_eq!(Anchor::queue_head(&NAME1), 0);
 				assert_eq!(Anchor::queue_tail(&NAME1), 2);
 				assert_eq!(Anchor::queue_len(&NAME1), 2);
+				assert_eq!(
+					Anchor::push_message_to(&NAME1, b"mouse", bvec(b"Jerry")),
+					Ok(0)
+				);
 
 				let rollup = {
 					// Create an client to consume the elements.
@@ -649,6 +1094,8 @@ pub mod pallet {
 					assert_eq!(client.pop().unwrap(), Some(b"Tom".to_vec()));
 					assert_eq!(client.pop().unwrap(), Some(b"Kitty".to_vec()));
 					assert_eq!(client.pop().unwrap(), None);
+					assert_eq!(client.pop_from(b"mouse").unwrap(), Some(b"Jerry".to_vec()));
+					assert_eq!(client.pop_from(b"mouse").unwrap(), None);
 
 					// Set kv should also works.
 					assert_eq!(client.get(b"foo").unwrap(), None);
@@ -671,10 +1118,13 @@ pub mod pallet {
 				assert_eq!(Anchor::queue_head(&NAME1), 2);
 				assert_eq!(Anchor::queue_tail(&NAME1), 2);
 				assert_eq!(Anchor::queue_len(&NAME1), 0);
+				assert_eq!(Anchor::named_queue_head(&NAME1, b"mouse"), 1);
+				assert_eq!(Anchor::named_queue_len(&NAME1, b"mouse"), 0);
 
 				// The kv set should be applied
 				let mut client = test_client();
 				assert_eq!(client.pop().unwrap(), None);
+				assert_eq!(client.pop_from(b"mouse").unwrap(), None);
 				assert_eq!(client.get(b"foo").unwrap(), Some(b"bar".to_vec()));
 
 				// Could not apply the duplicated updates
@@ -1,12 +1,15 @@
 use crate::{anchor, oracle};
 
-use frame_support::{pallet_prelude::ConstU32, parameter_types};
+use crate::types::CallbackTarget;
+use frame_support::{pallet_prelude::ConstU32, parameter_types, weights::Weight};
 use frame_system as system;
 use sp_core::H256;
 use sp_runtime::{
 	testing::Header,
 	traits::{BlakeTwo256, IdentityLookup},
+	DispatchError,
 };
+use std::cell::RefCell;
 
 pub(crate) type Balance = u128;
 
@@ -93,6 +96,45 @@ impl pallet_timestamp::Config for Test {
 
 parameter_types! {
 	pub const QueuePrefix: &'static [u8] = b"_queue/";
+	pub const MaxCallbackWeight: Weight = Weight::from_ref_time(1_000_000);
+}
+
+/// The weight consumed by each call to `MockCallbackDispatcher`
+pub const CALLBACK_WEIGHT: Weight = Weight::from_ref_time(1_000);
+
+thread_local! {
+	static CALLBACKS: RefCell<Vec<(CallbackTarget, H256, u64, Vec<u8>)>> = RefCell::new(vec![]);
+}
+
+/// Records the callbacks, and fails those targeting `CallbackTarget::Handler(0)`
+///
+/// Supports every target but ink! contracts.
+pub struct MockCallbackDispatcher;
+impl anchor::CallbackDispatcher<u64> for MockCallbackDispatcher {
+	fn supports(target: &CallbackTarget) -> bool {
+		!matches!(target, CallbackTarget::Ink { .. })
+	}
+
+	fn dispatch(
+		target: &CallbackTarget,
+		name: H256,
+		submitter: u64,
+		data: Vec<u8>,
+		_weight_limit: Weight,
+	) -> Result<Weight, DispatchError> {
+		if target == &CallbackTarget::Handler(0) {
+			return Err(DispatchError::Other("Handler failed"));
+		}
+		CALLBACKS.with(|cbs| {
+			cbs.borrow_mut()
+				.push((target.clone(), name, submitter, data))
+		});
+		Ok(CALLBACK_WEIGHT)
+	}
+}
+
+pub fn take_callbacks() -> Vec<(CallbackTarget, H256, u64, Vec<u8>)> {
+	CALLBACKS.with(|cbs| cbs.take())
 }
 
 impl anchor::Config for Test {
@@ -100,10 +142,20 @@ impl anchor::Config for Test {
 	type OnResponse = Oracle;
 	type QueuePrefix = QueuePrefix;
 	type QueueCapacity = ConstU32<3>;
+	type CallbackDispatcher = MockCallbackDispatcher;
+	type MaxCallbackWeight = MaxCallbackWeight;
+}
+
+parameter_types! {
+	pub const OracleRequestFee: Balance = CENTS;
+	pub const OracleRequestExpiry: u64 = 10;
 }
 
 impl oracle::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type RequestFee = OracleRequestFee;
+	type RequestExpiry = OracleRequestExpiry;
 }
 
 pub const DOLLARS: Balance = 1_000_000_000_000;
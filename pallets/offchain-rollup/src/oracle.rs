@@ -5,16 +5,28 @@ pub use self::pallet::*;
 #[frame_support::pallet]
 pub mod pallet {
 	use frame_support::{
-		dispatch::DispatchResult, pallet_prelude::*, traits::StorageVersion, transactional,
+		dispatch::DispatchResult,
+		pallet_prelude::*,
+		traits::{BalanceStatus, Currency, ReservableCurrency, StorageVersion},
+		transactional,
 	};
 	use frame_system::pallet_prelude::*;
 	use sp_core::H256;
 	use sp_runtime::AccountId32;
 	use sp_std::vec::Vec;
 
+	type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
 	#[pallet::config]
-	pub trait Config: frame_system::Config {
+	pub trait Config: frame_system::Config + crate::anchor::Config {
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// The fee reserved from the requester and paid to the submitter of the reply
+		type RequestFee: Get<BalanceOf<Self>>;
+		/// Number of blocks after which an unanswered request can be refunded
+		type RequestExpiry: Get<Self::BlockNumber>;
 	}
 
 	const STORAGE_VERSION: StorageVersion = StorageVersion::new(0);
@@ -38,6 +50,16 @@ pub mod pallet {
 		PriceQuote,
 	>;
 
+	/// The id of the next request
+	#[pallet::storage]
+	pub type NextRequestId<T> = StorageValue<_, u64, ValueQuery>;
+
+	/// Pending requests waiting for a reply or a refund
+	#[pallet::storage]
+	#[pallet::getter(fn requests)]
+	pub type Requests<T: Config> =
+		StorageMap<_, Twox64Concat, u64, RequestInfo<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -49,27 +71,119 @@ pub mod pallet {
 			pair: TradingPairBytes,
 			price: u128,
 		},
+		/// A request is pushed to the queue of the oracle contract
+		RequestSent {
+			contract: H256,
+			id: u64,
+			requester: T::AccountId,
+			fee: BalanceOf<T>,
+		},
+		/// A request is answered and the fee is paid to the submitter
+		RequestFulfilled {
+			id: u64,
+			submitter: T::AccountId,
+			fee: BalanceOf<T>,
+		},
+		/// An expired request is refunded to the requester
+		RequestRefunded {
+			id: u64,
+			requester: T::AccountId,
+			fee: BalanceOf<T>,
+		},
 	}
 
 	#[pallet::error]
 	pub enum Error<T> {
 		FailedToAuthenticateResponse,
 		FailedToDecodeResponse,
+		/// The request doesn't exist or was already answered or refunded
+		RequestNotFound,
+		/// The request is expired and can only be refunded
+		RequestExpired,
+		/// The request can't be refunded before it expires
+		RequestNotExpired,
+		/// The response doesn't answer the data of the request
+		ResponseMismatch,
+		/// The response is older than the stored quote
+		StaleResponse,
 	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		/// Sends a request to the oracle
+		///
+		/// The request fee is reserved from the caller until the request is answered or
+		/// refunded.
 		#[pallet::call_index(0)]
-		#[pallet::weight(0)]
+		#[pallet::weight(Weight::from_ref_time(10_000u64) + T::DbWeight::get().reads_writes(5u64, 5u64))]
 		#[transactional]
 		pub fn request(
 			origin: OriginFor<T>,
-			_name: H256,
-			_data: RequestBytes,
-			_nonce: u128,
+			name: H256,
+			data: RequestBytes,
+			nonce: u128,
 		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let fee = T::RequestFee::get();
+			T::Currency::reserve(&who, fee)?;
+
+			let id = NextRequestId::<T>::mutate(|id| {
+				let current = *id;
+				*id += 1;
+				current
+			});
+			let record = RequestRecord {
+				id,
+				data: data.clone(),
+				nonce,
+			};
+			crate::anchor::Pallet::<T>::push_message(
+				&name,
+				record
+					.encode()
+					.try_into()
+					.expect("BUG: Request record is always shorter than the max value size"),
+			)?;
+			let expire_at = frame_system::Pallet::<T>::block_number() + T::RequestExpiry::get();
+			Requests::<T>::insert(
+				id,
+				RequestInfo {
+					contract_id: name,
+					requester: who.clone(),
+					data,
+					fee,
+					expire_at,
+				},
+			);
+			Self::deposit_event(Event::RequestSent {
+				contract: name,
+				id,
+				requester: who,
+				fee,
+			});
+			Ok(())
+		}
+
+		/// Refunds the fee of an expired request to its requester
+		///
+		/// Can be called by anyone once the request is expired.
+		#[pallet::call_index(1)]
+		#[pallet::weight(Weight::from_ref_time(10_000u64) + T::DbWeight::get().reads_writes(2u64, 2u64))]
+		#[transactional]
+		pub fn refund(origin: OriginFor<T>, id: u64) -> DispatchResult {
 			ensure_signed(origin)?;
+			let request = Requests::<T>::get(id).ok_or(Error::<T>::RequestNotFound)?;
+			ensure!(
+				frame_system::Pallet::<T>::block_number() > request.expire_at,
+				Error::<T>::RequestNotExpired
+			);
+			Requests::<T>::remove(id);
+			T::Currency::unreserve(&request.requester, request.fee);
+			Self::deposit_event(Event::RequestRefunded {
+				id,
+				requester: request.requester,
+				fee: request.fee,
+			});
 			Ok(())
 		}
 	}
@@ -81,7 +195,15 @@ pub mod pallet {
 			if resp.contract_id != name {
 				return Err(Error::<T>::FailedToAuthenticateResponse.into());
 			}
-			// TODO: is timestamp newer?
+			if let Some(quote) = PriceFeeds::<T>::get(&resp.owner, &resp.pair) {
+				ensure!(
+					resp.timestamp_ms >= quote.timestamp_ms,
+					Error::<T>::StaleResponse
+				);
+			}
+			if let Some(id) = resp.request_id {
+				Self::fulfill_request(id, &name, &resp.pair, &submitter)?;
+			}
 			PriceFeeds::<T>::insert(
 				&resp.owner,
 				&resp.pair,
@@ -102,8 +224,40 @@ pub mod pallet {
 		}
 	}
 
-	// impl<T: Config> Pallet<T> {
-	// }
+	impl<T: Config> Pallet<T> {
+		/// Pays the fee of an answered request to the submitter of the reply
+		fn fulfill_request(
+			id: u64,
+			contract_id: &H256,
+			pair: &TradingPairBytes,
+			submitter: &T::AccountId,
+		) -> DispatchResult {
+			let request = Requests::<T>::get(id).ok_or(Error::<T>::RequestNotFound)?;
+			ensure!(
+				&request.contract_id == contract_id,
+				Error::<T>::FailedToAuthenticateResponse
+			);
+			// The request data is the trading pair to quote
+			ensure!(request.data[..] == pair[..], Error::<T>::ResponseMismatch);
+			ensure!(
+				frame_system::Pallet::<T>::block_number() <= request.expire_at,
+				Error::<T>::RequestExpired
+			);
+			Requests::<T>::remove(id);
+			T::Currency::repatriate_reserved(
+				&request.requester,
+				submitter,
+				request.fee,
+				BalanceStatus::Free,
+			)?;
+			Self::deposit_event(Event::RequestFulfilled {
+				id,
+				submitter: submitter.clone(),
+				fee: request.fee,
+			});
+			Ok(())
+		}
+	}
 
 	// Structures
 
@@ -117,13 +271,287 @@ pub mod pallet {
 		timestamp_ms: u64,
 	}
 
-	/// The reponse from the oracle Phat Contract (copied from Phat Contract)
+	/// A pending request
+	#[derive(Debug, PartialEq, Eq, Encode, Decode, Clone, scale_info::TypeInfo, MaxEncodedLen)]
+	pub struct RequestInfo<AccountId, Balance, BlockNumber> {
+		pub contract_id: H256,
+		pub requester: AccountId,
+		pub data: RequestBytes,
+		pub fee: Balance,
+		pub expire_at: BlockNumber,
+	}
+
+	/// The request pushed to the queue of the oracle Phat Contract
 	#[derive(Debug, PartialEq, Eq, Encode, Decode, Clone, scale_info::TypeInfo)]
+	pub struct RequestRecord {
+		pub id: u64,
+		pub data: RequestBytes,
+		pub nonce: u128,
+	}
+
+	/// The reponse from the oracle Phat Contract (copied from Phat Contract)
+	#[derive(Debug, PartialEq, Eq, Encode, Clone, scale_info::TypeInfo)]
 	pub struct ResponseRecord {
 		pub owner: AccountId32,
 		pub contract_id: H256,
 		pub pair: TradingPairBytes,
 		pub price: u128,
 		pub timestamp_ms: u64,
+		/// The id of the request this response answers, if any
+		///
+		/// Appended later, so it's missing in the records of the contracts deployed before.
+		pub request_id: Option<u64>,
+	}
+
+	impl Decode for ResponseRecord {
+		fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+			let owner = Decode::decode(input)?;
+			let contract_id = Decode::decode(input)?;
+			let pair = Decode::decode(input)?;
+			let price = Decode::decode(input)?;
+			let timestamp_ms = Decode::decode(input)?;
+			// A legacy record ends right after the timestamp
+			let request_id = match input.remaining_len()? {
+				Some(0) => None,
+				_ => Decode::decode(input)?,
+			};
+			Ok(Self {
+				owner,
+				contract_id,
+				pair,
+				price,
+				timestamp_ms,
+				request_id,
+			})
+		}
+	}
+
+	#[cfg(test)]
+	mod test {
+		use super::*;
+		use crate::{
+			anchor::Action,
+			mock::{
+				bvec, new_test_ext, set_block_1, Anchor, Balances, Oracle, RuntimeOrigin as Origin,
+				System, Test, CENTS,
+			},
+			types::RollupTx,
+		};
+		use frame_support::{assert_noop, assert_ok};
+
+		const NAME1: H256 = H256([1u8; 32]);
+		const OWNER: AccountId32 = AccountId32::new([0u8; 32]);
+
+		fn response(timestamp_ms: u64, request_id: Option<u64>) -> ResponseRecord {
+			ResponseRecord {
+				owner: OWNER,
+				contract_id: NAME1,
+				pair: bvec(b"polkadot_usd"),
+				price: 5_000000000000,
+				timestamp_ms,
+				request_id,
+			}
+		}
+
+		fn reply(resp: ResponseRecord) -> DispatchResult {
+			let act = Action::Reply(bvec(&resp.encode()));
+			Anchor::rollup(
+				Origin::signed(1),
+				NAME1,
+				RollupTx {
+					conds: vec![],
+					actions: vec![bvec(&act.encode())],
+					updates: vec![],
+				},
+				1u128,
+			)
+			.map(|_| ())
+			.map_err(|e| e.error)
+		}
+
+		fn setup() {
+			set_block_1();
+			assert_ok!(Anchor::claim_name(Origin::signed(1), NAME1));
+		}
+
+		#[test]
+		fn request_works() {
+			new_test_ext().execute_with(|| {
+				setup();
+				let free = Balances::free_balance(2);
+				assert_ok!(Oracle::request(
+					Origin::signed(2),
+					NAME1,
+					bvec(b"polkadot_usd"),
+					7
+				));
+				assert_eq!(Balances::reserved_balance(2), CENTS);
+				assert_eq!(Balances::free_balance(2), free - CENTS);
+				assert_eq!(
+					Oracle::requests(0),
+					Some(RequestInfo {
+						contract_id: NAME1,
+						requester: 2,
+						data: bvec(b"polkadot_usd"),
+						fee: CENTS,
+						expire_at: 11,
+					})
+				);
+				// The request is pushed to the anchor queue
+				assert_eq!(Anchor::queue_len(&NAME1), 1);
+				let msg =
+					crate::anchor::States::<Test>::get(NAME1, bvec(b"_queue/\0\0\0\0")).unwrap();
+				assert_eq!(
+					RequestRecord::decode(&mut &msg[..]).unwrap(),
+					RequestRecord {
+						id: 0,
+						data: bvec(b"polkadot_usd"),
+						nonce: 7,
+					}
+				);
+
+				// Can't request an unclaimed name
+				assert_noop!(
+					Oracle::request(Origin::signed(2), H256([2u8; 32]), bvec(b"ksm_usd"), 0),
+					crate::anchor::Error::<Test>::NameNotExist
+				);
+			});
+		}
+
+		#[test]
+		fn reply_pays_the_submitter() {
+			new_test_ext().execute_with(|| {
+				setup();
+				assert_ok!(Oracle::request(
+					Origin::signed(2),
+					NAME1,
+					bvec(b"polkadot_usd"),
+					0
+				));
+				let submitter_free = Balances::free_balance(1);
+
+				assert_ok!(reply(response(1000, Some(0))));
+				assert_eq!(Balances::reserved_balance(2), 0);
+				assert_eq!(Balances::free_balance(1), submitter_free + CENTS);
+				assert_eq!(Oracle::requests(0), None);
+				assert!(
+					Oracle::price_feeds(OWNER, bvec::<ConstU32<64>>(b"polkadot_usd")).is_some()
+				);
+
+				// Can't be answered twice
+				assert_noop!(
+					reply(response(1000, Some(0))),
+					Error::<Test>::RequestNotFound
+				);
+				// Nor refunded
+				assert_noop!(
+					Oracle::refund(Origin::signed(3), 0),
+					Error::<Test>::RequestNotFound
+				);
+			});
+		}
+
+		#[test]
+		fn expired_request_is_refunded() {
+			new_test_ext().execute_with(|| {
+				setup();
+				let free = Balances::free_balance(2);
+				assert_ok!(Oracle::request(
+					Origin::signed(2),
+					NAME1,
+					bvec(b"polkadot_usd"),
+					0
+				));
+
+				System::set_block_number(11);
+				assert_noop!(
+					Oracle::refund(Origin::signed(3), 0),
+					Error::<Test>::RequestNotExpired
+				);
+
+				System::set_block_number(12);
+				assert_noop!(
+					reply(response(1000, Some(0))),
+					Error::<Test>::RequestExpired
+				);
+				assert_ok!(Oracle::refund(Origin::signed(3), 0));
+				assert_eq!(Balances::reserved_balance(2), 0);
+				assert_eq!(Balances::free_balance(2), free);
+				assert_eq!(Oracle::requests(0), None);
+			});
+		}
+
+		#[test]
+		fn stale_response_is_rejected() {
+			new_test_ext().execute_with(|| {
+				setup();
+				assert_ok!(reply(response(1000, None)));
+				assert_noop!(reply(response(999, None)), Error::<Test>::StaleResponse);
+				assert_ok!(reply(response(1000, None)));
+				assert_ok!(reply(response(1001, None)));
+			});
+		}
+
+		#[test]
+		fn response_must_match_the_request() {
+			new_test_ext().execute_with(|| {
+				setup();
+				assert_ok!(Oracle::request(
+					Origin::signed(2),
+					NAME1,
+					bvec(b"ksm_usd"),
+					0
+				));
+				// The response quotes another pair
+				assert_noop!(
+					reply(response(1000, Some(0))),
+					Error::<Test>::ResponseMismatch
+				);
+				assert!(Oracle::requests(0).is_some());
+			});
+		}
+
+		#[test]
+		fn legacy_response_is_accepted() {
+			new_test_ext().execute_with(|| {
+				setup();
+				// Records from the contracts deployed before `request_id` was added
+				let resp = response(1000, None);
+				let legacy = (
+					&resp.owner,
+					&resp.contract_id,
+					&resp.pair,
+					resp.price,
+					resp.timestamp_ms,
+				)
+					.encode();
+				assert_eq!(ResponseRecord::decode(&mut &legacy[..]), Ok(resp.clone()));
+				let record = response(1000, Some(3)).encode();
+				assert_eq!(
+					ResponseRecord::decode(&mut &record[..]),
+					Ok(response(1000, Some(3)))
+				);
+
+				let act = Action::Reply(bvec(&legacy));
+				assert_ok!(Anchor::rollup(
+					Origin::signed(1),
+					NAME1,
+					RollupTx {
+						conds: vec![],
+						actions: vec![bvec(&act.encode())],
+						updates: vec![],
+					},
+					1u128,
+				));
+				assert_eq!(
+					Oracle::price_feeds(OWNER, bvec::<ConstU32<64>>(b"polkadot_usd")),
+					Some(PriceQuote {
+						contract_id: NAME1,
+						price: resp.price,
+						timestamp_ms: 1000,
+					})
+				);
+			});
+		}
 	}
 }
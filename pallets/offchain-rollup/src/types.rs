@@ -1,11 +1,14 @@
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, MaxEncodedLen};
 use frame_support::BoundedVec;
-use sp_core::ConstU32;
+use sp_core::{ConstU32, H160};
+use sp_runtime::AccountId32;
 use sp_std::vec::Vec;
 
 pub type ActionBytes = BoundedVec<u8, ConstU32<256>>;
 pub type KeyBytes = BoundedVec<u8, ConstU32<128>>;
 pub type ValueBytes = BoundedVec<u8, ConstU32<2048>>;
+/// Name of a sub-queue. The empty name refers to the default queue.
+pub type QueueNameBytes = BoundedVec<u8, ConstU32<32>>;
 
 // Almost copied from `phat-offchain-rollup/phat/src/lib.rs`.
 #[derive(Debug, Default, PartialEq, Eq, Encode, Decode, Clone, scale_info::TypeInfo)]
@@ -17,7 +20,74 @@ pub struct RollupTx {
 
 #[derive(Debug, PartialEq, Eq, Encode, Decode, Clone, scale_info::TypeInfo)]
 pub enum Cond {
+	/// The value of the key equals to the given value, or the key is absent if `None`
 	Eq(KeyBytes, Option<ValueBytes>),
+	/// The key exists
+	Exists(KeyBytes),
+	/// The value of the key, decoded as a SCALE-encoded unsigned integer, compared to the given
+	/// number with the operator is true
+	///
+	/// An absent key is treated as zero. Values which are not 1, 2, 4, 8 or 16 bytes long never
+	/// match.
+	Cmp(KeyBytes, CmpOp, u128),
+	/// The value of the key starts with the given bytes
+	HasPrefix(KeyBytes, ValueBytes),
+}
+
+#[derive(Debug, PartialEq, Eq, Encode, Decode, Clone, Copy, scale_info::TypeInfo)]
+pub enum CmpOp {
+	Lt,
+	Le,
+	Gt,
+	Ge,
+}
+
+impl Cond {
+	/// The key the condition is checked against
+	pub fn key(&self) -> &KeyBytes {
+		match self {
+			Cond::Eq(key, _)
+			| Cond::Exists(key)
+			| Cond::Cmp(key, _, _)
+			| Cond::HasPrefix(key, _) => key,
+		}
+	}
+
+	/// Checks the condition against the current value of the key
+	pub fn check(&self, value: Option<&ValueBytes>) -> bool {
+		match self {
+			Cond::Eq(_, expected) => value == expected.as_ref(),
+			Cond::Exists(_) => value.is_some(),
+			Cond::Cmp(_, op, rhs) => {
+				let lhs = match value {
+					None => 0,
+					Some(value) => match decode_uint(value) {
+						Some(n) => n,
+						None => return false,
+					},
+				};
+				match op {
+					CmpOp::Lt => lhs < *rhs,
+					CmpOp::Le => lhs <= *rhs,
+					CmpOp::Gt => lhs > *rhs,
+					CmpOp::Ge => lhs >= *rhs,
+				}
+			}
+			Cond::HasPrefix(_, prefix) => value.map_or(false, |v| v.starts_with(prefix)),
+		}
+	}
+}
+
+/// Decodes a SCALE-encoded u8, u16, u32, u64 or u128
+fn decode_uint(bytes: &[u8]) -> Option<u128> {
+	match bytes.len() {
+		1 | 2 | 4 | 8 | 16 => {
+			let mut buf = [0u8; 16];
+			buf[..bytes.len()].copy_from_slice(bytes);
+			Some(u128::from_le_bytes(buf))
+		}
+		_ => None,
+	}
 }
 
 // Defined for our own usage for now
@@ -26,4 +96,19 @@ pub enum Cond {
 pub enum Action {
 	Reply(ActionBytes),
 	SetQueueHead(u32),
+	SetNamedQueueHead(QueueNameBytes, u32),
+}
+
+/// Where the replies of a name are routed to
+#[derive(Debug, PartialEq, Eq, Encode, Decode, Clone, scale_info::TypeInfo, MaxEncodedLen)]
+pub enum CallbackTarget {
+	/// A handler implemented in the runtime, identified by an index chosen by the runtime
+	Handler(u32),
+	/// A message of an ink! contract, called with the selector followed by the reply
+	Ink {
+		contract: AccountId32,
+		selector: [u8; 4],
+	},
+	/// A function of an EVM contract, called with the selector followed by the reply
+	Evm { contract: H160, selector: [u8; 4] },
 }
@@ -4,6 +4,8 @@
 //!
 //! This is the central crate of Phala tightly-coupled pallets.
 
+#[cfg(target_arch = "wasm32")]
+extern crate ring_wasm as ring;
 #[cfg(target_arch = "wasm32")]
 extern crate webpki_wasm as webpki;
 
@@ -19,6 +19,9 @@ pub mod pallet {
 
 	use crate::mq::MessageOriginInfo;
 	use crate::utils::attestation::Error as AttestationError;
+	use crate::utils::attestation_dcap::{
+		self as attestation_dcap, DcapCollateral, Fmspc, QeIdentity, TcbInfo,
+	};
 	use phala_types::{
 		messaging::{
 			self, bind_topic, ContractClusterId, ContractId, DecodedMessage, GatekeeperChange,
@@ -201,6 +204,22 @@ pub mod pallet {
 	pub type MaxKnownPRuntimeConsensusVersion<T: Config> =
 		StorageValue<_, KnownConsensusVersion, ValueQuery>;
 
+	/// DER encoded root CA certificates trusted to issue the PCK certificates of the DCAP quotes
+	#[pallet::storage]
+	pub type DcapRootCerts<T: Config> = StorageValue<_, Vec<Vec<u8>>, ValueQuery>;
+
+	/// Mapping from FMSPC to the TCB info to evaluate the DCAP quotes with
+	#[pallet::storage]
+	pub type DcapTcbInfo<T: Config> = StorageMap<_, Twox64Concat, Fmspc, TcbInfo>;
+
+	/// The identity of the Quoting Enclaves trusted to sign the DCAP quotes
+	#[pallet::storage]
+	pub type DcapQeIdentity<T: Config> = StorageValue<_, QeIdentity>;
+
+	/// DER encoded CRLs of the PCK CAs to check the revocation of the PCK certificates with
+	#[pallet::storage]
+	pub type DcapPckCrls<T: Config> = StorageValue<_, Vec<Vec<u8>>, ValueQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -285,6 +304,20 @@ pub mod pallet {
 		NotMigrationRoot,
 		ParachainIdMismatch,
 		InvalidConsensusVersion,
+		// DCAP related
+		InvalidPckCertChain,
+		InvalidQuoteSignature,
+		UntrustedQuotingEnclave,
+		UnknownTcbInfo,
+		TcbRevoked,
+		DebugEnclaveRejected,
+		InvalidDcapRootCert,
+		/// The attestation of the simulated platform is only accepted in dev mode
+		SimulatedAttestationDisabled,
+		UnknownQeIdentity,
+		UnknownPckCrl,
+		PckCertRevoked,
+		InvalidDcapPckCrl,
 	}
 
 	#[pallet::call]
@@ -595,6 +628,7 @@ pub mod pallet {
 				T::VerifyPRuntime::get(),
 				PRuntimeAllowList::<T>::get(),
 				T::NoneAttestationEnabled::get(),
+				&OnChainDcapCollateral::<T>(PhantomData),
 			)
 			.map_err(Into::<Error<T>>::into)?;
 
@@ -860,6 +894,78 @@ pub mod pallet {
 			Self::deposit_event(Event::<T>::PRuntimeConsensusVersionChangedTo(version));
 			Ok(())
 		}
+
+		/// Sets [`DcapRootCerts`], the DER encoded root CA certificates for DCAP attestation
+		///
+		/// Can only be called by `GovernanceOrigin`.
+		#[pallet::call_index(15)]
+		#[pallet::weight(0)]
+		pub fn set_dcap_root_certs(origin: OriginFor<T>, certs: Vec<Vec<u8>>) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+			for cert in certs.iter() {
+				webpki::TrustAnchor::try_from_cert_der(cert)
+					.or(Err(Error::<T>::InvalidDcapRootCert))?;
+			}
+			DcapRootCerts::<T>::put(certs);
+			Ok(())
+		}
+
+		/// Sets or removes the [`DcapTcbInfo`] of the given FMSPC
+		///
+		/// The TCB info should be taken from the one published by Intel, with the levels in the
+		/// same order.
+		///
+		/// Can only be called by `GovernanceOrigin`.
+		#[pallet::call_index(16)]
+		#[pallet::weight(0)]
+		pub fn set_dcap_tcb_info(
+			origin: OriginFor<T>,
+			fmspc: Fmspc,
+			tcb_info: Option<TcbInfo>,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+			match tcb_info {
+				Some(tcb_info) => DcapTcbInfo::<T>::insert(fmspc, tcb_info),
+				None => DcapTcbInfo::<T>::remove(fmspc),
+			}
+			Ok(())
+		}
+
+		/// Sets or removes [`DcapQeIdentity`]
+		///
+		/// The QE identity should be taken from the one published by Intel, with the TCB levels in
+		/// the same order.
+		///
+		/// Can only be called by `GovernanceOrigin`.
+		#[pallet::call_index(17)]
+		#[pallet::weight(0)]
+		pub fn set_dcap_qe_identity(
+			origin: OriginFor<T>,
+			qe_identity: Option<QeIdentity>,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+			DcapQeIdentity::<T>::set(qe_identity);
+			Ok(())
+		}
+
+		/// Sets [`DcapPckCrls`], the DER encoded CRLs of the PCK CAs published by Intel
+		///
+		/// The quotes are rejected unless the CRL of the issuer of their PCK certificates is set.
+		///
+		/// Can only be called by `GovernanceOrigin`.
+		#[pallet::call_index(18)]
+		#[pallet::weight(0)]
+		pub fn set_dcap_pck_crls(origin: OriginFor<T>, crls: Vec<Vec<u8>>) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+			for crl in crls.iter() {
+				ensure!(
+					attestation_dcap::is_supported_pck_crl(crl),
+					Error::<T>::InvalidDcapPckCrl
+				);
+			}
+			DcapPckCrls::<T>::put(crls);
+			Ok(())
+		}
 	}
 
 	// TODO.kevin: Move it to mq
@@ -1174,10 +1280,43 @@ pub mod pallet {
 				AttestationError::UnknownQuoteBodyFormat => Self::UnknownQuoteBodyFormat,
 				AttestationError::InvalidUserDataHash => Self::InvalidRuntimeInfoHash,
 				AttestationError::NoneAttestationDisabled => Self::NoneAttestationDisabled,
+				AttestationError::InvalidPckCertChain => Self::InvalidPckCertChain,
+				AttestationError::InvalidQuoteSignature => Self::InvalidQuoteSignature,
+				AttestationError::UntrustedQuotingEnclave => Self::UntrustedQuotingEnclave,
+				AttestationError::UnknownTcbInfo => Self::UnknownTcbInfo,
+				AttestationError::TcbRevoked => Self::TcbRevoked,
+				AttestationError::DebugEnclaveRejected => Self::DebugEnclaveRejected,
+				AttestationError::SimulatedAttestationDisabled => {
+					Self::SimulatedAttestationDisabled
+				}
+				AttestationError::UnknownQeIdentity => Self::UnknownQeIdentity,
+				AttestationError::UnknownPckCrl => Self::UnknownPckCrl,
+				AttestationError::PckCertRevoked => Self::PckCertRevoked,
 			}
 		}
 	}
 
+	/// The DCAP collateral configured by the governance
+	struct OnChainDcapCollateral<T>(PhantomData<T>);
+
+	impl<T: Config> DcapCollateral for OnChainDcapCollateral<T> {
+		fn trusted_root_certs(&self) -> Vec<Vec<u8>> {
+			DcapRootCerts::<T>::get()
+		}
+
+		fn tcb_info(&self, fmspc: &Fmspc) -> Option<TcbInfo> {
+			DcapTcbInfo::<T>::get(fmspc)
+		}
+
+		fn qe_identity(&self) -> Option<QeIdentity> {
+			DcapQeIdentity::<T>::get()
+		}
+
+		fn pck_crls(&self) -> Option<Vec<Vec<u8>>> {
+			Some(DcapPckCrls::<T>::get())
+		}
+	}
+
 	#[cfg(test)]
 	mod test {
 		use frame_support::{assert_noop, assert_ok};
@@ -6,6 +6,8 @@ use sp_std::{borrow::ToOwned, convert::TryFrom, vec::Vec};
 
 use phala_types::{AttestationProvider, AttestationReport};
 
+use super::attestation_dcap::{self, DcapCollateral};
+
 #[derive(Encode, Decode, TypeInfo, Debug, Clone, PartialEq, Eq)]
 pub enum Error {
 	PRuntimeRejected,
@@ -17,6 +19,17 @@ pub enum Error {
 	UnknownQuoteBodyFormat,
 	InvalidUserDataHash,
 	NoneAttestationDisabled,
+	// DCAP related
+	InvalidPckCertChain,
+	InvalidQuoteSignature,
+	UntrustedQuotingEnclave,
+	UnknownTcbInfo,
+	TcbRevoked,
+	DebugEnclaveRejected,
+	SimulatedAttestationDisabled,
+	UnknownQeIdentity,
+	UnknownPckCrl,
+	PckCertRevoked,
 }
 
 #[derive(Encode, Decode, TypeInfo, Debug, Clone, PartialEq, Eq)]
@@ -126,6 +139,7 @@ pub fn validate(
 	verify_pruntime_hash: bool,
 	pruntime_allowlist: Vec<Vec<u8>>,
 	opt_out_enabled: bool,
+	dcap_collateral: &impl DcapCollateral,
 ) -> Result<ConfidentialReport, Error> {
 	match attestation {
 		Some(AttestationReport::SgxIas {
@@ -141,6 +155,29 @@ pub fn validate(
 			verify_pruntime_hash,
 			pruntime_allowlist,
 		),
+		Some(AttestationReport::SgxDcap { quote }) => validate_dcap_quote(
+			user_data_hash,
+			&quote,
+			dcap_collateral,
+			now,
+			verify_pruntime_hash,
+			pruntime_allowlist,
+		),
+		Some(AttestationReport::Simulated {
+			runtime_hash,
+			report_data,
+		}) => {
+			if !opt_out_enabled {
+				return Err(Error::SimulatedAttestationDisabled);
+			}
+			validate_simulated_report(
+				user_data_hash,
+				runtime_hash,
+				&report_data,
+				verify_pruntime_hash,
+				pruntime_allowlist,
+			)
+		}
 		None => {
 			if opt_out_enabled {
 				Ok(ConfidentialReport {
@@ -207,15 +244,262 @@ pub fn validate_ias_report(
 	})
 }
 
+pub fn validate_dcap_quote(
+	user_data_hash: &[u8],
+	quote: &[u8],
+	collateral: &impl DcapCollateral,
+	now: u64,
+	verify_pruntime_hash: bool,
+	pruntime_allowlist: Vec<Vec<u8>>,
+) -> Result<ConfidentialReport, Error> {
+	let fields = attestation_dcap::verify_quote(quote, collateral, now)?;
+
+	// Validate PRuntime
+	let pruntime_hash = fields.extend_mrenclave();
+	if verify_pruntime_hash && !pruntime_allowlist.contains(&pruntime_hash) {
+		return Err(Error::PRuntimeRejected);
+	}
+
+	// Unlike the IAS reports, the quotes carry no timestamp. The user data hash commits to the
+	// content being attested instead.
+	let commit = &fields.report_data[..32];
+	if commit != user_data_hash {
+		return Err(Error::InvalidUserDataHash);
+	}
+
+	Ok(ConfidentialReport {
+		provider: Some(AttestationProvider::Dcap),
+		runtime_hash: pruntime_hash,
+		confidence_level: fields.confidence_level,
+	})
+}
+
+/// Validates a report of the simulated platform, which proves nothing but the committed user data.
+pub fn validate_simulated_report(
+	user_data_hash: &[u8],
+	runtime_hash: Vec<u8>,
+	report_data: &[u8],
+	verify_pruntime_hash: bool,
+	pruntime_allowlist: Vec<Vec<u8>>,
+) -> Result<ConfidentialReport, Error> {
+	if verify_pruntime_hash && !pruntime_allowlist.contains(&runtime_hash) {
+		return Err(Error::PRuntimeRejected);
+	}
+
+	if report_data.get(..32) != Some(user_data_hash) {
+		return Err(Error::InvalidUserDataHash);
+	}
+
+	Ok(ConfidentialReport {
+		provider: Some(AttestationProvider::Simulated),
+		runtime_hash,
+		confidence_level: 128u8,
+	})
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
+	use crate::utils::attestation_dcap::{
+		Fmspc, QeIdentity, QeTcbLevel, TcbInfo, TcbLevel, TcbStatus,
+	};
 	use frame_support::assert_ok;
 
 	pub const ATTESTATION_SAMPLE: &[u8] = include_bytes!("../../sample/ias_attestation.json");
 	pub const ATTESTATION_TIMESTAMP: u64 = 1631441180; // 2021-09-12T18:06:20.402478
 	pub const PRUNTIME_HASH: &str = "518422fa769d2d55982015a0e0417c6a8521fdfc7308f5ec18aaa1b6924bd0f300000000815f42f11cf64430c30bab7816ba596a1da0130c3b028b673133a66cf9a3e0e6";
 
+	// Generated by `scripts/gen-dcap-sample.py`, with the PCK certificate chain issued by a test CA
+	pub const DCAP_QUOTE_SAMPLE: &[u8] = include_bytes!("../../sample/dcap_quote.bin");
+	pub const DCAP_ROOT_CA_SAMPLE: &[u8] = include_bytes!("../../sample/dcap_root_ca.der");
+	// The CRLs of the test PCK Platform CA, revoking an unrelated serial or the PCK of the sample
+	pub const DCAP_PCK_CRL_SAMPLE: &[u8] = include_bytes!("../../sample/dcap_pck_crl.der");
+	pub const DCAP_PCK_CRL_REVOKED_SAMPLE: &[u8] =
+		include_bytes!("../../sample/dcap_pck_crl_revoked.der");
+	pub const DCAP_TIMESTAMP: u64 = 1700000000; // 2023-11-14T22:13:20
+	pub const DCAP_USER_DATA_HASH: &str =
+		"73e18180bbe6f42aa62023fd7951919fb03f7cf0dc6725f51fa424ab751f215a";
+	pub const DCAP_PRUNTIME_HASH: &str = "3fcbf63597b1b3a2af5bf292cb21acdfa1b8b9d12e810d300fa2e52d06067fdd00000000f49a5b84061650ba0d336c0db5fa0ebe9c56556a949b1f661bfa6b94e809ddc1";
+	pub const DCAP_FMSPC: Fmspc = hex_literal::hex!("00906ed50000");
+	pub const DCAP_SGX_COMPONENTS: [u8; 16] = [4, 4, 3, 3, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+	pub const DCAP_PCE_SVN: u16 = 11;
+	pub const DCAP_QE_ISV_SVN: u16 = 8;
+
+	// A quote of a production platform and the collateral fetched from Intel's PCS, taken from
+	// the samples of Phala's dcap-qvl. The quote has "Hello, world!" as its report data.
+	pub const DCAP_INTEL_QUOTE: &[u8] = include_bytes!("../../sample/dcap_intel_quote.bin");
+	pub const DCAP_INTEL_ROOT_CA: &[u8] = include_bytes!("../../sample/dcap_intel_root_ca.der");
+	// The CRL of the Intel SGX PCK Processor CA
+	pub const DCAP_INTEL_PCK_CRL: &[u8] = include_bytes!("../../sample/dcap_intel_pck_crl.der");
+	pub const DCAP_INTEL_TCB_INFO: &[u8] = include_bytes!("../../sample/dcap_intel_tcb_info.json");
+	pub const DCAP_INTEL_QE_IDENTITY: &[u8] =
+		include_bytes!("../../sample/dcap_intel_qe_identity.json");
+	pub const DCAP_INTEL_TIMESTAMP: u64 = 1699301000; // 2023-11-06T20:03:20
+
+	struct TestCollateral {
+		root_certs: Vec<Vec<u8>>,
+		tcb_levels: Vec<TcbLevel>,
+		qe_identity: Option<QeIdentity>,
+		pck_crls: Option<Vec<Vec<u8>>>,
+	}
+
+	impl TestCollateral {
+		fn with_status(status: TcbStatus, advisory_ids: &[&str]) -> Self {
+			Self {
+				root_certs: vec![DCAP_ROOT_CA_SAMPLE.to_vec()],
+				tcb_levels: vec![TcbLevel {
+					sgx_components: DCAP_SGX_COMPONENTS,
+					pce_svn: DCAP_PCE_SVN,
+					status,
+					advisory_ids: advisory_ids
+						.iter()
+						.map(|id| id.as_bytes().to_vec())
+						.collect(),
+				}],
+				qe_identity: Some(QeIdentity {
+					mr_signer: DCAP_QE_MR_SIGNER,
+					isv_prod_id: DCAP_QE_ISV_PROD_ID,
+					miscselect: 0,
+					miscselect_mask: u32::MAX,
+					attributes: DCAP_QE_ATTRIBUTES,
+					attributes_mask: DCAP_QE_ATTRIBUTES_MASK,
+					tcb_levels: vec![QeTcbLevel {
+						isv_svn: DCAP_QE_ISV_SVN,
+						status: TcbStatus::UpToDate,
+						advisory_ids: vec![],
+					}],
+				}),
+				pck_crls: Some(vec![DCAP_PCK_CRL_SAMPLE.to_vec()]),
+			}
+		}
+	}
+
+	impl DcapCollateral for TestCollateral {
+		fn trusted_root_certs(&self) -> Vec<Vec<u8>> {
+			self.root_certs.clone()
+		}
+
+		fn tcb_info(&self, fmspc: &Fmspc) -> Option<TcbInfo> {
+			(fmspc == &DCAP_FMSPC).then(|| TcbInfo {
+				levels: self.tcb_levels.clone(),
+			})
+		}
+
+		fn qe_identity(&self) -> Option<QeIdentity> {
+			self.qe_identity.clone()
+		}
+
+		fn pck_crls(&self) -> Option<Vec<Vec<u8>>> {
+			self.pck_crls.clone()
+		}
+	}
+
+	/// The collateral in the JSON format of Intel's PCS
+	struct IntelCollateral {
+		tcb_info: serde_json::Value,
+		qe_identity: serde_json::Value,
+	}
+
+	impl IntelCollateral {
+		fn load() -> Self {
+			Self {
+				tcb_info: serde_json::from_slice(DCAP_INTEL_TCB_INFO).unwrap(),
+				qe_identity: serde_json::from_slice(DCAP_INTEL_QE_IDENTITY).unwrap(),
+			}
+		}
+
+		fn status(level: &serde_json::Value) -> TcbStatus {
+			match level["tcbStatus"].as_str().unwrap() {
+				"UpToDate" => TcbStatus::UpToDate,
+				"SWHardeningNeeded" => TcbStatus::SwHardeningNeeded,
+				"ConfigurationNeeded" => TcbStatus::ConfigurationNeeded,
+				"ConfigurationAndSWHardeningNeeded" => TcbStatus::ConfigurationAndSwHardeningNeeded,
+				"OutOfDate" => TcbStatus::OutOfDate,
+				"OutOfDateConfigurationNeeded" => TcbStatus::OutOfDateConfigurationNeeded,
+				"Revoked" => TcbStatus::Revoked,
+				status => panic!("unknown TCB status {}", status),
+			}
+		}
+
+		fn advisory_ids(level: &serde_json::Value) -> Vec<Vec<u8>> {
+			level["advisoryIDs"]
+				.as_array()
+				.map(|ids| {
+					ids.iter()
+						.map(|id| id.as_str().unwrap().as_bytes().to_vec())
+						.collect()
+				})
+				.unwrap_or_default()
+		}
+
+		fn hex_field<const N: usize>(value: &serde_json::Value) -> [u8; N] {
+			hex::decode(value.as_str().unwrap())
+				.unwrap()
+				.try_into()
+				.unwrap()
+		}
+	}
+
+	impl DcapCollateral for IntelCollateral {
+		fn trusted_root_certs(&self) -> Vec<Vec<u8>> {
+			vec![DCAP_INTEL_ROOT_CA.to_vec()]
+		}
+
+		fn tcb_info(&self, fmspc: &Fmspc) -> Option<TcbInfo> {
+			if fmspc != &Self::hex_field::<6>(&self.tcb_info["fmspc"]) {
+				return None;
+			}
+			let levels = self.tcb_info["tcbLevels"]
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|level| {
+					let mut sgx_components = [0u8; 16];
+					let components = level["tcb"]["sgxtcbcomponents"].as_array().unwrap();
+					for (svn, component) in sgx_components.iter_mut().zip(components) {
+						*svn = component["svn"].as_u64().unwrap() as u8;
+					}
+					TcbLevel {
+						sgx_components,
+						pce_svn: level["tcb"]["pcesvn"].as_u64().unwrap() as u16,
+						status: Self::status(level),
+						advisory_ids: Self::advisory_ids(level),
+					}
+				})
+				.collect();
+			Some(TcbInfo { levels })
+		}
+
+		fn qe_identity(&self) -> Option<QeIdentity> {
+			let identity = &self.qe_identity;
+			let hex_u32 = |value: &serde_json::Value| {
+				u32::from_str_radix(value.as_str().unwrap(), 16).unwrap()
+			};
+			Some(QeIdentity {
+				mr_signer: Self::hex_field(&identity["mrsigner"]),
+				isv_prod_id: identity["isvprodid"].as_u64().unwrap() as u16,
+				miscselect: hex_u32(&identity["miscselect"]),
+				miscselect_mask: hex_u32(&identity["miscselectMask"]),
+				attributes: Self::hex_field(&identity["attributes"]),
+				attributes_mask: Self::hex_field(&identity["attributesMask"]),
+				tcb_levels: identity["tcbLevels"]
+					.as_array()
+					.unwrap()
+					.iter()
+					.map(|level| QeTcbLevel {
+						isv_svn: level["tcb"]["isvsvn"].as_u64().unwrap() as u16,
+						status: Self::status(level),
+						advisory_ids: Self::advisory_ids(level),
+					})
+					.collect(),
+			})
+		}
+
+		fn pck_crls(&self) -> Option<Vec<Vec<u8>>> {
+			Some(vec![DCAP_INTEL_PCK_CRL.to_vec()])
+		}
+	}
+
 	#[test]
 	fn test_ias_validator() {
 		let sample: serde_json::Value = serde_json::from_slice(ATTESTATION_SAMPLE).unwrap();
@@ -280,4 +564,317 @@ mod test {
 			vec![hex::decode(PRUNTIME_HASH).unwrap()]
 		));
 	}
+
+	#[test]
+	fn test_dcap_validator() {
+		let commit = hex::decode(DCAP_USER_DATA_HASH).unwrap();
+		let pruntime_hash = hex::decode(DCAP_PRUNTIME_HASH).unwrap();
+		let validate = |quote: &[u8], collateral: &TestCollateral| {
+			validate_dcap_quote(
+				&commit,
+				quote,
+				collateral,
+				DCAP_TIMESTAMP,
+				true,
+				vec![pruntime_hash.clone()],
+			)
+		};
+		let up_to_date = TestCollateral::with_status(TcbStatus::UpToDate, &[]);
+
+		assert_eq!(
+			validate(DCAP_QUOTE_SAMPLE, &up_to_date),
+			Ok(ConfidentialReport {
+				confidence_level: 1,
+				provider: Some(AttestationProvider::Dcap),
+				runtime_hash: pruntime_hash.clone(),
+			})
+		);
+
+		assert_eq!(
+			validate_dcap_quote(
+				&[0u8],
+				DCAP_QUOTE_SAMPLE,
+				&up_to_date,
+				DCAP_TIMESTAMP,
+				false,
+				vec![]
+			),
+			Err(Error::InvalidUserDataHash)
+		);
+
+		assert_eq!(
+			validate_dcap_quote(
+				&commit,
+				DCAP_QUOTE_SAMPLE,
+				&up_to_date,
+				DCAP_TIMESTAMP,
+				true,
+				vec![]
+			),
+			Err(Error::PRuntimeRejected)
+		);
+
+		// The report data is covered by the signature of the attestation key
+		let mut tampered = DCAP_QUOTE_SAMPLE.to_vec();
+		tampered[48 + 320] ^= 1;
+		assert_eq!(
+			validate(&tampered, &up_to_date),
+			Err(Error::InvalidQuoteSignature)
+		);
+		assert_eq!(
+			validate(&DCAP_QUOTE_SAMPLE[..1000], &up_to_date),
+			Err(Error::UnknownQuoteBodyFormat)
+		);
+
+		let untrusted = TestCollateral {
+			root_certs: vec![],
+			..TestCollateral::with_status(TcbStatus::UpToDate, &[])
+		};
+		assert_eq!(
+			validate(DCAP_QUOTE_SAMPLE, &untrusted),
+			Err(Error::InvalidPckCertChain)
+		);
+
+		let mut newer_tcb = TestCollateral::with_status(TcbStatus::UpToDate, &[]);
+		newer_tcb.tcb_levels[0].pce_svn += 1;
+		assert_eq!(
+			validate(DCAP_QUOTE_SAMPLE, &newer_tcb),
+			Err(Error::UnknownTcbInfo)
+		);
+
+		assert_eq!(
+			validate(
+				DCAP_QUOTE_SAMPLE,
+				&TestCollateral::with_status(TcbStatus::Revoked, &[])
+			),
+			Err(Error::TcbRevoked)
+		);
+
+		let confidence_level = |status, advisory_ids: &[&str]| {
+			validate(
+				DCAP_QUOTE_SAMPLE,
+				&TestCollateral::with_status(status, advisory_ids),
+			)
+			.map(|report| report.confidence_level)
+		};
+		assert_eq!(confidence_level(TcbStatus::SwHardeningNeeded, &[]), Ok(2));
+		assert_eq!(
+			confidence_level(TcbStatus::ConfigurationNeeded, &["INTEL-SA-00334"]),
+			Ok(3)
+		);
+		assert_eq!(
+			confidence_level(TcbStatus::ConfigurationNeeded, &["INTEL-SA-00615"]),
+			Ok(4)
+		);
+		assert_eq!(confidence_level(TcbStatus::OutOfDate, &[]), Ok(5));
+	}
+
+	#[test]
+	fn test_dcap_pck_revocation() {
+		let commit = hex::decode(DCAP_USER_DATA_HASH).unwrap();
+		let validate = |crls: Option<Vec<&[u8]>>| {
+			let collateral = TestCollateral {
+				pck_crls: crls.map(|crls| crls.into_iter().map(<[u8]>::to_vec).collect()),
+				..TestCollateral::with_status(TcbStatus::UpToDate, &[])
+			};
+			validate_dcap_quote(
+				&commit,
+				DCAP_QUOTE_SAMPLE,
+				&collateral,
+				DCAP_TIMESTAMP,
+				false,
+				vec![],
+			)
+			.map(|report| report.confidence_level)
+		};
+
+		assert_eq!(validate(Some(vec![DCAP_PCK_CRL_SAMPLE])), Ok(1));
+		assert_eq!(
+			validate(Some(vec![DCAP_PCK_CRL_SAMPLE, DCAP_PCK_CRL_REVOKED_SAMPLE])),
+			Err(Error::PckCertRevoked)
+		);
+		assert_eq!(validate(Some(vec![])), Err(Error::UnknownPckCrl));
+		// A CRL not signed by the issuer of the PCK certificate is ignored
+		let mut forged = DCAP_PCK_CRL_SAMPLE.to_vec();
+		*forged.last_mut().unwrap() ^= 1;
+		assert_eq!(validate(Some(vec![&forged[..]])), Err(Error::UnknownPckCrl));
+		let mut forged = DCAP_PCK_CRL_REVOKED_SAMPLE.to_vec();
+		*forged.last_mut().unwrap() ^= 1;
+		assert_eq!(
+			validate(Some(vec![DCAP_PCK_CRL_SAMPLE, &forged[..]])),
+			Ok(1)
+		);
+		assert_eq!(
+			validate(Some(vec![DCAP_ROOT_CA_SAMPLE])),
+			Err(Error::UnknownPckCrl)
+		);
+		// The revocation check is skipped without the CRLs
+		assert_eq!(validate(None), Ok(1));
+	}
+
+	#[test]
+	fn test_dcap_qe_identity() {
+		let commit = hex::decode(DCAP_USER_DATA_HASH).unwrap();
+		let validate = |update: &dyn Fn(&mut QeIdentity)| {
+			let mut collateral = TestCollateral::with_status(TcbStatus::UpToDate, &[]);
+			update(collateral.qe_identity.as_mut().unwrap());
+			validate_dcap_quote(
+				&commit,
+				DCAP_QUOTE_SAMPLE,
+				&collateral,
+				DCAP_TIMESTAMP,
+				false,
+				vec![],
+			)
+			.map(|report| report.confidence_level)
+		};
+
+		assert_eq!(validate(&|_| {}), Ok(1));
+		assert_eq!(
+			validate(&|identity| identity.mr_signer[0] ^= 1),
+			Err(Error::UntrustedQuotingEnclave)
+		);
+		assert_eq!(
+			validate(&|identity| identity.isv_prod_id += 1),
+			Err(Error::UntrustedQuotingEnclave)
+		);
+		assert_eq!(
+			validate(&|identity| identity.miscselect = 1),
+			Err(Error::UntrustedQuotingEnclave)
+		);
+		// The masked attributes must match
+		assert_eq!(
+			validate(&|identity| identity.attributes[0] |= 0x02),
+			Err(Error::UntrustedQuotingEnclave)
+		);
+		// The QE is older than any known TCB level
+		assert_eq!(
+			validate(&|identity| identity.tcb_levels[0].isv_svn += 1),
+			Err(Error::UntrustedQuotingEnclave)
+		);
+		assert_eq!(
+			validate(&|identity| identity.tcb_levels[0].status = TcbStatus::Revoked),
+			Err(Error::TcbRevoked)
+		);
+		// The lower confidence of the platform and the QE is taken
+		assert_eq!(
+			validate(&|identity| identity.tcb_levels[0].status = TcbStatus::OutOfDate),
+			Ok(5)
+		);
+		assert_eq!(
+			validate(&|identity| {
+				identity.tcb_levels[0].status = TcbStatus::OutOfDate;
+				identity.tcb_levels.insert(
+					0,
+					QeTcbLevel {
+						isv_svn: DCAP_QE_ISV_SVN + 1,
+						status: TcbStatus::UpToDate,
+						advisory_ids: vec![],
+					},
+				);
+			}),
+			Ok(5)
+		);
+
+		let no_identity = TestCollateral {
+			qe_identity: None,
+			..TestCollateral::with_status(TcbStatus::UpToDate, &[])
+		};
+		assert_eq!(
+			validate_dcap_quote(
+				&commit,
+				DCAP_QUOTE_SAMPLE,
+				&no_identity,
+				DCAP_TIMESTAMP,
+				false,
+				vec![]
+			),
+			Err(Error::UnknownQeIdentity)
+		);
+	}
+
+	#[test]
+	fn test_dcap_validator_with_intel_collateral() {
+		let mut commit = b"Hello, world!".to_vec();
+		commit.resize(32, 0);
+		let collateral = IntelCollateral::load();
+
+		// The platform is at ConfigurationAndSWHardeningNeeded, with the advisories
+		// INTEL-SA-00289 and INTEL-SA-00615 out of the whitelist
+		let report = validate_dcap_quote(
+			&commit,
+			DCAP_INTEL_QUOTE,
+			&collateral,
+			DCAP_INTEL_TIMESTAMP,
+			false,
+			vec![],
+		)
+		.unwrap();
+		assert_eq!(report.provider, Some(AttestationProvider::Dcap));
+		assert_eq!(report.confidence_level, 4);
+
+		// The PCK certificate chain is rooted in the Intel SGX Root CA only
+		let untrusted = TestCollateral::with_status(TcbStatus::UpToDate, &[]);
+		assert_eq!(
+			validate_dcap_quote(
+				&commit,
+				DCAP_INTEL_QUOTE,
+				&untrusted,
+				DCAP_INTEL_TIMESTAMP,
+				false,
+				vec![]
+			),
+			Err(Error::InvalidPckCertChain)
+		);
+	}
+
+	#[test]
+	fn test_simulated_validator() {
+		let user_data_hash = [1u8; 32];
+		let runtime_hash = vec![2u8; 68];
+		let report = |report_data: Vec<u8>| {
+			Some(AttestationReport::Simulated {
+				runtime_hash: runtime_hash.clone(),
+				report_data,
+			})
+		};
+		let collateral = TestCollateral::with_status(TcbStatus::UpToDate, &[]);
+		let validate_with = |report, verify_pruntime_hash, opt_out_enabled| {
+			validate(
+				report,
+				&user_data_hash,
+				0,
+				verify_pruntime_hash,
+				vec![runtime_hash.clone()],
+				opt_out_enabled,
+				&collateral,
+			)
+		};
+		let report_data = [&user_data_hash[..], &[0u8; 32][..]].concat();
+
+		assert_eq!(
+			validate_with(report(report_data.clone()), true, true),
+			Ok(ConfidentialReport {
+				confidence_level: 128,
+				provider: Some(AttestationProvider::Simulated),
+				runtime_hash: runtime_hash.clone(),
+			})
+		);
+		assert_eq!(
+			validate_with(report(report_data.clone()), false, false),
+			Err(Error::SimulatedAttestationDisabled)
+		);
+		assert_eq!(
+			validate_with(report(vec![0u8; 64]), false, true),
+			Err(Error::InvalidUserDataHash)
+		);
+		assert_eq!(
+			validate_with(report(vec![]), false, true),
+			Err(Error::InvalidUserDataHash)
+		);
+		assert_eq!(
+			validate_simulated_report(&user_data_hash, vec![3u8; 68], &report_data, true, vec![]),
+			Err(Error::PRuntimeRejected)
+		);
+	}
 }
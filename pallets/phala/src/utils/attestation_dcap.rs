@@ -0,0 +1,625 @@
+//! Verification of the SGX ECDSA quotes (DCAP)
+//!
+//! A quote is signed by the Quoting Enclave (QE) with its attestation key. The attestation key is
+//! bound to the QE report, which is signed with the PCK (Provisioning Certification Key) of the
+//! platform. The PCK certificate chain, embedded in the quote, must lead to one of the trusted
+//! root CAs, and the PCK certificate must not be revoked by the CRL of its issuer. The QE report
+//! must match the QE identity published by Intel. The TCB levels of the platform, encoded in the
+//! PCK certificate, and of the QE are then evaluated against the TCB info of the FMSPC and the QE
+//! identity. The root CAs, the CRLs, the TCB info and the QE identity are the collateral
+//! configured on chain.
+
+use crate::constants::*;
+use crate::hashing::sha2_256;
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_std::{convert::TryFrom, vec, vec::Vec};
+
+use super::attestation::{Error, IasFields};
+
+/// The Family-Model-Stepping-Platform-CustomSKU of a platform
+pub type Fmspc = [u8; 6];
+
+#[derive(Encode, Decode, TypeInfo, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcbStatus {
+	UpToDate,
+	SwHardeningNeeded,
+	ConfigurationNeeded,
+	ConfigurationAndSwHardeningNeeded,
+	OutOfDate,
+	OutOfDateConfigurationNeeded,
+	Revoked,
+}
+
+/// A TCB level in the TCB info published by Intel
+#[derive(Encode, Decode, TypeInfo, Debug, Clone, PartialEq, Eq)]
+pub struct TcbLevel {
+	/// The minimal SVNs of the 16 SGX TCB components
+	pub sgx_components: [u8; 16],
+	/// The minimal SVN of the PCE
+	pub pce_svn: u16,
+	pub status: TcbStatus,
+	pub advisory_ids: Vec<Vec<u8>>,
+}
+
+/// The TCB levels of the platforms with the same FMSPC
+#[derive(Encode, Decode, TypeInfo, Debug, Clone, PartialEq, Eq)]
+pub struct TcbInfo {
+	/// Sorted from the highest level, as in the TCB info published by Intel
+	pub levels: Vec<TcbLevel>,
+}
+
+/// A TCB level of the Quoting Enclave
+#[derive(Encode, Decode, TypeInfo, Debug, Clone, PartialEq, Eq)]
+pub struct QeTcbLevel {
+	/// The minimal ISVSVN of the QE
+	pub isv_svn: u16,
+	pub status: TcbStatus,
+	pub advisory_ids: Vec<Vec<u8>>,
+}
+
+/// The identity of the Quoting Enclave published by Intel
+#[derive(Encode, Decode, TypeInfo, Debug, Clone, PartialEq, Eq)]
+pub struct QeIdentity {
+	pub mr_signer: [u8; 32],
+	pub isv_prod_id: u16,
+	/// The MISCSELECT of the QE report, masked with `miscselect_mask`, must equal `miscselect`
+	pub miscselect: u32,
+	pub miscselect_mask: u32,
+	/// The attributes of the QE report, masked with `attributes_mask`, must equal `attributes`
+	pub attributes: [u8; 16],
+	pub attributes_mask: [u8; 16],
+	/// Sorted from the highest level, as in the QE identity published by Intel
+	pub tcb_levels: Vec<QeTcbLevel>,
+}
+
+/// Provides the collateral to verify the DCAP quotes
+pub trait DcapCollateral {
+	/// The DER encoded root CA certificates which the PCK certificate chains must lead to
+	fn trusted_root_certs(&self) -> Vec<Vec<u8>>;
+	/// The TCB info of the platforms with the given FMSPC, if known
+	fn tcb_info(&self, fmspc: &Fmspc) -> Option<TcbInfo>;
+	/// The identity of the Quoting Enclaves trusted to sign the quotes, if known
+	fn qe_identity(&self) -> Option<QeIdentity>;
+	/// The DER encoded CRLs of the PCK CAs, or `None` to skip the revocation check
+	///
+	/// When checked, a CRL of the issuer of the PCK certificate must be present.
+	fn pck_crls(&self) -> Option<Vec<Vec<u8>>>;
+}
+
+const HEADER_LEN: usize = 48;
+const REPORT_BODY_LEN: usize = 384;
+const QUOTE_VERSION: u16 = 3;
+const ATTESTATION_KEY_TYPE_ECDSA_P256: u16 = 2;
+const CERT_DATA_TYPE_PCK_CHAIN: u16 = 5;
+const SGX_FLAGS_DEBUG: u8 = 0x02;
+
+const DER_BOOLEAN: u8 = 0x01;
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_OID: u8 = 0x06;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_VERSION: u8 = 0xa0;
+const DER_EXTENSIONS: u8 = 0xa3;
+
+/// OID 1.2.840.113741.1.13.1, the SGX extension of the PCK certificates
+const SGX_EXTENSION_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01];
+/// OID 1.2.840.113741.1.13.1.2, the TCB of the platform
+const SGX_TCB_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x02];
+/// OID 1.2.840.113741.1.13.1.4, the FMSPC of the platform
+const SGX_FMSPC_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x04];
+/// OID 1.2.840.10045.4.3.2, the signature algorithm of the PCK CRLs
+const ECDSA_WITH_SHA256_OID: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+
+struct Reader<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+		if self.data.len() < len {
+			return Err(Error::UnknownQuoteBodyFormat);
+		}
+		let (head, rest) = self.data.split_at(len);
+		self.data = rest;
+		Ok(head)
+	}
+
+	fn u16(&mut self) -> Result<u16, Error> {
+		Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+	}
+
+	fn u32(&mut self) -> Result<u32, Error> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+}
+
+struct Quote<'a> {
+	/// The header and the report body, signed by the attestation key
+	signed_data: &'a [u8],
+	report_body: &'a [u8],
+	isv_signature: &'a [u8],
+	attestation_key: &'a [u8],
+	qe_report: &'a [u8],
+	qe_signature: &'a [u8],
+	qe_auth_data: &'a [u8],
+	pck_cert_chain: &'a [u8],
+}
+
+fn parse_quote(quote: &[u8]) -> Result<Quote, Error> {
+	let mut reader = Reader { data: quote };
+	let mut header = Reader {
+		data: reader.take(HEADER_LEN)?,
+	};
+	if header.u16()? != QUOTE_VERSION || header.u16()? != ATTESTATION_KEY_TYPE_ECDSA_P256 {
+		return Err(Error::UnknownQuoteBodyFormat);
+	}
+	let report_body = reader.take(REPORT_BODY_LEN)?;
+	let signature_len = reader.u32()? as usize;
+	let mut reader = Reader {
+		data: reader.take(signature_len)?,
+	};
+	let isv_signature = reader.take(64)?;
+	let attestation_key = reader.take(64)?;
+	let qe_report = reader.take(REPORT_BODY_LEN)?;
+	let qe_signature = reader.take(64)?;
+	let auth_data_len = reader.u16()? as usize;
+	let qe_auth_data = reader.take(auth_data_len)?;
+	let cert_data_type = reader.u16()?;
+	let cert_data_len = reader.u32()? as usize;
+	let pck_cert_chain = reader.take(cert_data_len)?;
+	if cert_data_type != CERT_DATA_TYPE_PCK_CHAIN {
+		return Err(Error::UnknownQuoteBodyFormat);
+	}
+	Ok(Quote {
+		signed_data: &quote[..HEADER_LEN + REPORT_BODY_LEN],
+		report_body,
+		isv_signature,
+		attestation_key,
+		qe_report,
+		qe_signature,
+		qe_auth_data,
+		pck_cert_chain,
+	})
+}
+
+fn decode_pem_certs(pem: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+	let pem = core::str::from_utf8(pem).or(Err(Error::InvalidPckCertChain))?;
+	pem.split("-----BEGIN CERTIFICATE-----")
+		.skip(1)
+		.map(|block| {
+			let body = block
+				.split("-----END CERTIFICATE-----")
+				.next()
+				.unwrap_or_default();
+			let body: Vec<u8> = body.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+			base64::decode(body).or(Err(Error::InvalidPckCertChain))
+		})
+		.collect()
+}
+
+/// Reads a DER element with the given tag, returning its content and the rest of the input.
+fn der_expect(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+	let (&actual_tag, rest) = data.split_first()?;
+	if actual_tag != tag {
+		return None;
+	}
+	let (&len, mut rest) = rest.split_first()?;
+	let len = if len < 0x80 {
+		len as usize
+	} else {
+		let n = (len & 0x7f) as usize;
+		if n == 0 || n > 4 || rest.len() < n {
+			return None;
+		}
+		let len = rest[..n]
+			.iter()
+			.fold(0usize, |acc, &b| (acc << 8) | b as usize);
+		rest = &rest[n..];
+		len
+	};
+	if rest.len() < len {
+		return None;
+	}
+	Some((&rest[..len], &rest[len..]))
+}
+
+/// Skips a DER element of any type.
+fn der_skip(data: &[u8]) -> Option<&[u8]> {
+	let tag = *data.first()?;
+	der_expect(data, tag).map(|(_, rest)| rest)
+}
+
+/// Splits a DER element of any type off the input, returning the whole element and the rest.
+fn der_split(data: &[u8]) -> Option<(&[u8], &[u8])> {
+	let rest = der_skip(data)?;
+	Some((&data[..data.len() - rest.len()], rest))
+}
+
+/// Reads a DER bit string without unused bits.
+fn der_bit_string(data: &[u8]) -> Option<&[u8]> {
+	let (value, _) = der_expect(data, DER_BIT_STRING)?;
+	match value.split_first()? {
+		(&0, bits) => Some(bits),
+		_ => None,
+	}
+}
+
+fn der_uint(data: &[u8]) -> Option<u32> {
+	let (value, _) = der_expect(data, DER_INTEGER)?;
+	if value.is_empty() || value.len() > 5 || value[0] & 0x80 != 0 {
+		return None;
+	}
+	let value = value.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+	u32::try_from(value).ok()
+}
+
+/// Finds the content of the extension with the given OID in a DER encoded certificate.
+fn find_extension<'a>(cert: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+	let (cert, _) = der_expect(cert, DER_SEQUENCE)?;
+	let (mut tbs, _) = der_expect(cert, DER_SEQUENCE)?;
+	while !tbs.is_empty() {
+		if let Some((extensions, _)) = der_expect(tbs, DER_EXTENSIONS) {
+			let (mut extensions, _) = der_expect(extensions, DER_SEQUENCE)?;
+			while !extensions.is_empty() {
+				let (extension, rest) = der_expect(extensions, DER_SEQUENCE)?;
+				extensions = rest;
+				let (ext_oid, mut extension) = der_expect(extension, DER_OID)?;
+				if ext_oid != oid {
+					continue;
+				}
+				if let Some((_, rest)) = der_expect(extension, DER_BOOLEAN) {
+					// Skip the critical flag
+					extension = rest;
+				}
+				return der_expect(extension, DER_OCTET_STRING).map(|(value, _)| value);
+			}
+			return None;
+		}
+		tbs = der_skip(tbs)?;
+	}
+	None
+}
+
+/// The fields of a certificate to check its revocation with
+struct CertFields<'a> {
+	/// The content of the serial number
+	serial: &'a [u8],
+	/// The encoded issuer name
+	issuer: &'a [u8],
+	/// The encoded subject name
+	subject: &'a [u8],
+	public_key: &'a [u8],
+}
+
+fn parse_cert_fields(cert: &[u8]) -> Option<CertFields> {
+	let (cert, _) = der_expect(cert, DER_SEQUENCE)?;
+	let (tbs, _) = der_expect(cert, DER_SEQUENCE)?;
+	let tbs = der_expect(tbs, DER_VERSION).map_or(tbs, |(_, rest)| rest);
+	let (serial, tbs) = der_expect(tbs, DER_INTEGER)?;
+	// Skip the signature algorithm
+	let tbs = der_skip(tbs)?;
+	let (issuer, tbs) = der_split(tbs)?;
+	// Skip the validity
+	let tbs = der_skip(tbs)?;
+	let (subject, tbs) = der_split(tbs)?;
+	let (public_key_info, _) = der_expect(tbs, DER_SEQUENCE)?;
+	let public_key = der_bit_string(der_skip(public_key_info)?)?;
+	Some(CertFields {
+		serial,
+		issuer,
+		subject,
+		public_key,
+	})
+}
+
+/// A CRL signed with ECDSA P-256
+struct Crl<'a> {
+	/// The encoded issuer name
+	issuer: &'a [u8],
+	/// The contents of the revoked serial numbers
+	revoked_serials: Vec<&'a [u8]>,
+	signed_data: &'a [u8],
+	signature: &'a [u8],
+}
+
+fn parse_crl(crl: &[u8]) -> Option<Crl> {
+	let (crl, _) = der_expect(crl, DER_SEQUENCE)?;
+	let (signed_data, rest) = der_split(crl)?;
+	let (algorithm, rest) = der_expect(rest, DER_SEQUENCE)?;
+	let (algorithm, _) = der_expect(algorithm, DER_OID)?;
+	if algorithm != ECDSA_WITH_SHA256_OID {
+		return None;
+	}
+	let signature = der_bit_string(rest)?;
+
+	let (tbs, _) = der_expect(signed_data, DER_SEQUENCE)?;
+	let tbs = der_expect(tbs, DER_INTEGER).map_or(tbs, |(_, rest)| rest);
+	// Skip the signature algorithm
+	let tbs = der_skip(tbs)?;
+	let (issuer, tbs) = der_split(tbs)?;
+	// Skip the this update, then look for the revoked certificates among the optional fields
+	let mut tbs = der_skip(tbs)?;
+	let mut revoked_serials = Vec::new();
+	while !tbs.is_empty() {
+		if let Some((mut entries, _)) = der_expect(tbs, DER_SEQUENCE) {
+			while !entries.is_empty() {
+				let (entry, rest) = der_expect(entries, DER_SEQUENCE)?;
+				entries = rest;
+				let (serial, _) = der_expect(entry, DER_INTEGER)?;
+				revoked_serials.push(serial);
+			}
+		}
+		tbs = der_skip(tbs)?;
+	}
+	Some(Crl {
+		issuer,
+		revoked_serials,
+		signed_data,
+		signature,
+	})
+}
+
+/// Whether the DER encoded CRL can be used to check the revocation of the PCK certificates.
+pub fn is_supported_pck_crl(crl: &[u8]) -> bool {
+	parse_crl(crl).is_some()
+}
+
+/// Checks the PCK certificate, the first one of the chain, against the CRLs of its issuer.
+fn check_pck_revocation(certs: &[Vec<u8>], crls: &[Vec<u8>]) -> Result<(), Error> {
+	let pck = parse_cert_fields(&certs[0]).ok_or(Error::InvalidPckCertChain)?;
+	// The CRLs come from the collateral, so a CA certificate smuggled into the chain can not
+	// sign any of them.
+	let issuer_keys: Vec<&[u8]> = certs[1..]
+		.iter()
+		.filter_map(|cert| parse_cert_fields(cert))
+		.filter(|ca| ca.subject == pck.issuer)
+		.map(|ca| ca.public_key)
+		.collect();
+	let mut checked = false;
+	for crl in crls.iter().filter_map(|crl| parse_crl(crl)) {
+		if crl.issuer != pck.issuer {
+			continue;
+		}
+		let signed_by_issuer = issuer_keys.iter().any(|key| {
+			ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P256_SHA256_ASN1, key)
+				.verify(crl.signed_data, crl.signature)
+				.is_ok()
+		});
+		if !signed_by_issuer {
+			continue;
+		}
+		if crl.revoked_serials.contains(&pck.serial) {
+			return Err(Error::PckCertRevoked);
+		}
+		checked = true;
+	}
+	if !checked {
+		return Err(Error::UnknownPckCrl);
+	}
+	Ok(())
+}
+
+/// Checks the QE report against the QE identity, returning the TCB level of the QE.
+fn check_qe_identity<'a>(
+	qe_report: &[u8],
+	identity: &'a QeIdentity,
+) -> Result<&'a QeTcbLevel, Error> {
+	let miscselect = u32::from_le_bytes(qe_report[16..20].try_into().unwrap());
+	let attributes_matched = qe_report[48..64]
+		.iter()
+		.zip(identity.attributes_mask.iter())
+		.map(|(attribute, mask)| attribute & mask)
+		.eq(identity.attributes.iter().copied());
+	let isv_prod_id = u16::from_le_bytes(qe_report[256..258].try_into().unwrap());
+	let isv_svn = u16::from_le_bytes(qe_report[258..260].try_into().unwrap());
+	if qe_report[128..160] != identity.mr_signer
+		|| isv_prod_id != identity.isv_prod_id
+		|| miscselect & identity.miscselect_mask != identity.miscselect
+		|| !attributes_matched
+	{
+		return Err(Error::UntrustedQuotingEnclave);
+	}
+	identity
+		.tcb_levels
+		.iter()
+		.find(|level| isv_svn >= level.isv_svn)
+		.ok_or(Error::UntrustedQuotingEnclave)
+}
+
+/// The platform info in the SGX extension of a PCK certificate
+struct PckInfo {
+	fmspc: Fmspc,
+	sgx_components: [u8; 16],
+	pce_svn: u16,
+}
+
+fn parse_sgx_tcb(data: &[u8]) -> Option<([u8; 16], u16)> {
+	let (mut components, _) = der_expect(data, DER_SEQUENCE)?;
+	let mut sgx_components = [0u8; 16];
+	let mut pce_svn = None;
+	while !components.is_empty() {
+		let (component, rest) = der_expect(components, DER_SEQUENCE)?;
+		components = rest;
+		let (oid, value) = der_expect(component, DER_OID)?;
+		let (&index, prefix) = oid.split_last()?;
+		if prefix != SGX_TCB_OID {
+			continue;
+		}
+		match index {
+			1..=16 => sgx_components[index as usize - 1] = u8::try_from(der_uint(value)?).ok()?,
+			17 => pce_svn = Some(u16::try_from(der_uint(value)?).ok()?),
+			_ => {}
+		}
+	}
+	Some((sgx_components, pce_svn?))
+}
+
+fn parse_sgx_extension(extension: &[u8]) -> Option<PckInfo> {
+	let (mut items, _) = der_expect(extension, DER_SEQUENCE)?;
+	let mut fmspc = None;
+	let mut tcb = None;
+	while !items.is_empty() {
+		let (item, rest) = der_expect(items, DER_SEQUENCE)?;
+		items = rest;
+		let (oid, value) = der_expect(item, DER_OID)?;
+		if oid == SGX_TCB_OID {
+			tcb = Some(parse_sgx_tcb(value)?);
+		} else if oid == SGX_FMSPC_OID {
+			let (value, _) = der_expect(value, DER_OCTET_STRING)?;
+			fmspc = Some(Fmspc::try_from(value).ok()?);
+		}
+	}
+	let (sgx_components, pce_svn) = tcb?;
+	Some(PckInfo {
+		fmspc: fmspc?,
+		sgx_components,
+		pce_svn,
+	})
+}
+
+/// Converts a raw `r || s` ECDSA P-256 signature to ASN.1 DER.
+fn ecdsa_signature_to_der(signature: &[u8]) -> Vec<u8> {
+	fn push_integer(out: &mut Vec<u8>, mut value: &[u8]) {
+		while value.len() > 1 && value[0] == 0 {
+			value = &value[1..];
+		}
+		out.push(DER_INTEGER);
+		if value[0] & 0x80 != 0 {
+			out.push(value.len() as u8 + 1);
+			out.push(0);
+		} else {
+			out.push(value.len() as u8);
+		}
+		out.extend_from_slice(value);
+	}
+	let mut body = Vec::new();
+	push_integer(&mut body, &signature[..32]);
+	push_integer(&mut body, &signature[32..]);
+	let mut der = vec![DER_SEQUENCE, body.len() as u8];
+	der.extend_from_slice(&body);
+	der
+}
+
+fn confidence_level(status: TcbStatus, advisory_ids: &[Vec<u8>]) -> Result<u8, Error> {
+	// The same levels as the IAS quote status, see `IasFields::from_ias_report`
+	let confidence_level = match status {
+		TcbStatus::UpToDate => 1,
+		TcbStatus::SwHardeningNeeded => 2,
+		TcbStatus::ConfigurationNeeded | TcbStatus::ConfigurationAndSwHardeningNeeded => {
+			let whitelisted = advisory_ids.iter().all(|id| {
+				IAS_QUOTE_ADVISORY_ID_WHITELIST
+					.iter()
+					.any(|allowed| allowed.as_bytes() == &id[..])
+			});
+			if whitelisted {
+				3
+			} else {
+				4
+			}
+		}
+		TcbStatus::OutOfDate | TcbStatus::OutOfDateConfigurationNeeded => 5,
+		TcbStatus::Revoked => return Err(Error::TcbRevoked),
+	};
+	Ok(confidence_level)
+}
+
+/// Verifies a DCAP quote and returns the fields of the enclave report in it.
+pub fn verify_quote(
+	raw_quote: &[u8],
+	collateral: &impl DcapCollateral,
+	now: u64,
+) -> Result<IasFields, Error> {
+	let quote = parse_quote(raw_quote)?;
+
+	// Validate the PCK certificate chain
+	let root_certs = collateral.trusted_root_certs();
+	let trust_anchors = root_certs
+		.iter()
+		.filter_map(|cert| webpki::TrustAnchor::try_from_cert_der(cert).ok())
+		.collect::<Vec<_>>();
+	let certs = decode_pem_certs(quote.pck_cert_chain)?;
+	let (raw_pck_cert, chain) = certs.split_first().ok_or(Error::InvalidPckCertChain)?;
+	let chain: Vec<&[u8]> = chain
+		.iter()
+		.filter(|cert| !root_certs.contains(*cert))
+		.map(|cert| cert.as_slice())
+		.collect();
+	let pck_cert = webpki::EndEntityCert::try_from(raw_pck_cert.as_slice())
+		.or(Err(Error::InvalidPckCertChain))?;
+	pck_cert
+		.verify_is_valid_tls_server_cert(
+			DCAP_SUPPORTED_SIG_ALGS,
+			&webpki::TlsServerTrustAnchors(&trust_anchors),
+			&chain,
+			webpki::Time::from_seconds_since_unix_epoch(now),
+		)
+		.or(Err(Error::InvalidPckCertChain))?;
+	if let Some(crls) = collateral.pck_crls() {
+		check_pck_revocation(&certs, &crls)?;
+	}
+
+	// Validate the QE report signed by the PCK
+	pck_cert
+		.verify_signature(
+			&webpki::ECDSA_P256_SHA256,
+			quote.qe_report,
+			&ecdsa_signature_to_der(quote.qe_signature),
+		)
+		.or(Err(Error::InvalidQuoteSignature))?;
+	let qe_identity = collateral.qe_identity().ok_or(Error::UnknownQeIdentity)?;
+	let qe_tcb_level = check_qe_identity(quote.qe_report, &qe_identity)?;
+	// The QE commits the attestation key in its report data
+	let key_hash = sha2_256(&[quote.attestation_key, quote.qe_auth_data].concat());
+	if quote.qe_report[320..352] != key_hash {
+		return Err(Error::InvalidQuoteSignature);
+	}
+
+	// Validate the enclave report signed by the attestation key
+	let attestation_key = [&[0x04][..], quote.attestation_key].concat();
+	ring::signature::UnparsedPublicKey::new(
+		&ring::signature::ECDSA_P256_SHA256_FIXED,
+		attestation_key,
+	)
+	.verify(quote.signed_data, quote.isv_signature)
+	.or(Err(Error::InvalidQuoteSignature))?;
+
+	let report_body = quote.report_body;
+	if report_body[48] & SGX_FLAGS_DEBUG != 0 {
+		return Err(Error::DebugEnclaveRejected);
+	}
+
+	// Evaluate the TCB level of the platform
+	let pck_info = find_extension(raw_pck_cert, SGX_EXTENSION_OID)
+		.and_then(parse_sgx_extension)
+		.ok_or(Error::InvalidPckCertChain)?;
+	let tcb_info = collateral
+		.tcb_info(&pck_info.fmspc)
+		.ok_or(Error::UnknownTcbInfo)?;
+	let tcb_level = tcb_info
+		.levels
+		.iter()
+		.find(|level| {
+			pck_info.pce_svn >= level.pce_svn
+				&& pck_info
+					.sgx_components
+					.iter()
+					.zip(level.sgx_components.iter())
+					.all(|(svn, min_svn)| svn >= min_svn)
+		})
+		.ok_or(Error::UnknownTcbInfo)?;
+	// The lower confidence of the platform and the QE, the higher level
+	let platform_level = confidence_level(tcb_level.status, &tcb_level.advisory_ids)?;
+	let qe_level = confidence_level(qe_tcb_level.status, &qe_tcb_level.advisory_ids)?;
+
+	Ok(IasFields {
+		mr_enclave: report_body[64..96].try_into().unwrap(),
+		mr_signer: report_body[128..160].try_into().unwrap(),
+		isv_prod_id: report_body[256..258].try_into().unwrap(),
+		isv_svn: report_body[258..260].try_into().unwrap(),
+		report_data: report_body[320..384].try_into().unwrap(),
+		confidence_level: platform_level.max(qe_level),
+	})
+}
@@ -26,6 +26,22 @@ pub static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
 	&webpki::RSA_PKCS1_3072_8192_SHA384,
 ];
 
+/// Signature algorithms of the PCK certificate chains
+pub static DCAP_SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
+	&webpki::ECDSA_P256_SHA256,
+	&webpki::ECDSA_P256_SHA384,
+	&webpki::ECDSA_P384_SHA256,
+	&webpki::ECDSA_P384_SHA384,
+];
+/// MRSIGNER of the Quoting Enclaves signed by Intel
+pub const DCAP_QE_MR_SIGNER: [u8; 32] =
+	hex_literal::hex!("8c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff");
+/// ISVPRODID of the Quoting Enclaves signed by Intel
+pub const DCAP_QE_ISV_PROD_ID: u16 = 1;
+/// Attributes of the Quoting Enclaves, as in the QE identity published by Intel
+pub const DCAP_QE_ATTRIBUTES: [u8; 16] = hex_literal::hex!("11000000000000000000000000000000");
+pub const DCAP_QE_ATTRIBUTES_MASK: [u8; 16] = hex_literal::hex!("fbffffffffffffff0000000000000000");
+
 pub static IAS_SERVER_ROOTS: webpki::TlsServerTrustAnchors = webpki::TlsServerTrustAnchors(&[
     /*
      * -----BEGIN CERTIFICATE-----
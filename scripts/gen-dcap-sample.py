@@ -0,0 +1,195 @@
+#!/usr/bin/env python3
+"""Generate the DCAP quote sample for the attestation tests of the phala pallets.
+
+The quote follows the layout of the SGX ECDSA quote v3, but the PCK certificate chain is issued by
+a test root CA rather than Intel, so the sample only passes the verification with the test root CA
+trusted. Two CRLs of the test PCK Platform CA are generated along, one revoking an unrelated serial
+and one revoking the PCK certificate of the sample.
+
+Usage: scripts/gen-dcap-sample.py [output dir, default: pallets/phala/sample]
+"""
+
+import datetime
+import hashlib
+import struct
+import sys
+from pathlib import Path
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+from cryptography.x509.oid import NameOID
+
+SGX_EXTENSION_OID = "1.2.840.113741.1.13.1"
+QE_MR_SIGNER = bytes.fromhex("8c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff")
+QE_VENDOR_ID = bytes.fromhex("939a7233f79c4ca9940a0db3957f0607")
+QE_ISV_PROD_ID = 1
+QE_ISV_SVN = 8
+
+FMSPC = bytes.fromhex("00906ed50000")
+SGX_COMPONENTS = [4, 4, 3, 3, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+PCE_SVN = 11
+
+MR_ENCLAVE = hashlib.sha256(b"pruntime mr_enclave").digest()
+MR_SIGNER = hashlib.sha256(b"pruntime mr_signer").digest()
+ISV_PROD_ID = 0
+ISV_SVN = 0
+USER_DATA_HASH = hashlib.sha256(b"runtime info").digest()
+
+
+def der(tag, content):
+    length = len(content)
+    if length < 0x80:
+        encoded_length = bytes([length])
+    else:
+        raw = length.to_bytes((length.bit_length() + 7) // 8, "big")
+        encoded_length = bytes([0x80 | len(raw)]) + raw
+    return bytes([tag]) + encoded_length + content
+
+
+def der_oid(oid):
+    parts = [int(p) for p in oid.split(".")]
+    body = bytes([parts[0] * 40 + parts[1]])
+    for part in parts[2:]:
+        chunk = [part & 0x7F]
+        part >>= 7
+        while part:
+            chunk.insert(0, 0x80 | (part & 0x7F))
+            part >>= 7
+        body += bytes(chunk)
+    return der(0x06, body)
+
+
+def der_int(value):
+    raw = value.to_bytes(value.bit_length() // 8 + 1, "big")
+    return der(0x02, raw)
+
+
+def der_seq(*items):
+    return der(0x30, b"".join(items))
+
+
+def sgx_extension():
+    tcb = [der_seq(der_oid(f"{SGX_EXTENSION_OID}.2.{i + 1}"), der_int(svn))
+           for i, svn in enumerate(SGX_COMPONENTS)]
+    tcb.append(der_seq(der_oid(f"{SGX_EXTENSION_OID}.2.17"), der_int(PCE_SVN)))
+    tcb.append(der_seq(der_oid(f"{SGX_EXTENSION_OID}.2.18"), der(0x04, bytes(SGX_COMPONENTS))))
+    return der_seq(
+        der_seq(der_oid(f"{SGX_EXTENSION_OID}.1"), der(0x04, bytes(16))),
+        der_seq(der_oid(f"{SGX_EXTENSION_OID}.2"), der_seq(*tcb)),
+        der_seq(der_oid(f"{SGX_EXTENSION_OID}.3"), der(0x04, bytes(2))),
+        der_seq(der_oid(f"{SGX_EXTENSION_OID}.4"), der(0x04, FMSPC)),
+        der_seq(der_oid(f"{SGX_EXTENSION_OID}.5"), der(0x0A, b"\x00")),
+    )
+
+
+def name(common_name):
+    return x509.Name([
+        x509.NameAttribute(NameOID.COMMON_NAME, common_name),
+        x509.NameAttribute(NameOID.ORGANIZATION_NAME, "Phala Test"),
+    ])
+
+
+def issue(subject, subject_key, issuer, issuer_key, ca, extensions=()):
+    builder = (
+        x509.CertificateBuilder()
+        .subject_name(name(subject))
+        .issuer_name(name(issuer))
+        .public_key(subject_key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(datetime.datetime(2023, 1, 1))
+        .not_valid_after(datetime.datetime(2049, 12, 31))
+        .add_extension(x509.BasicConstraints(ca=ca, path_length=None), critical=True)
+    )
+    for extension in extensions:
+        builder = builder.add_extension(extension, critical=False)
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def crl(issuer, issuer_key, revoked_serials):
+    builder = (
+        x509.CertificateRevocationListBuilder()
+        .issuer_name(name(issuer))
+        .last_update(datetime.datetime(2023, 1, 1))
+        .next_update(datetime.datetime(2049, 12, 31))
+    )
+    for serial in revoked_serials:
+        builder = builder.add_revoked_certificate(
+            x509.RevokedCertificateBuilder()
+            .serial_number(serial)
+            .revocation_date(datetime.datetime(2023, 1, 1))
+            .build()
+        )
+    return builder.sign(issuer_key, hashes.SHA256()).public_bytes(serialization.Encoding.DER)
+
+
+def raw_signature(key, data):
+    r, s = decode_dss_signature(key.sign(data, ec.ECDSA(hashes.SHA256())))
+    return r.to_bytes(32, "big") + s.to_bytes(32, "big")
+
+
+def report_body(mr_enclave, mr_signer, isv_prod_id, isv_svn, report_data, flags):
+    body = bytearray(384)
+    body[48:56] = struct.pack("<Q", flags)
+    body[64:96] = mr_enclave
+    body[128:160] = mr_signer
+    body[256:260] = struct.pack("<HH", isv_prod_id, isv_svn)
+    body[320:384] = report_data.ljust(64, b"\x00")
+    return bytes(body)
+
+
+def main():
+    out_dir = Path(sys.argv[1] if len(sys.argv) > 1 else "pallets/phala/sample")
+    root_key = ec.generate_private_key(ec.SECP256R1())
+    ca_key = ec.generate_private_key(ec.SECP256R1())
+    pck_key = ec.generate_private_key(ec.SECP256R1())
+    attestation_key = ec.generate_private_key(ec.SECP256R1())
+
+    root = issue("Test SGX Root CA", root_key, "Test SGX Root CA", root_key, True)
+    ca = issue("Test SGX PCK Platform CA", ca_key, "Test SGX Root CA", root_key, True)
+    pck = issue(
+        "Test SGX PCK Certificate", pck_key, "Test SGX PCK Platform CA", ca_key, False,
+        [x509.UnrecognizedExtension(x509.ObjectIdentifier(SGX_EXTENSION_OID), sgx_extension())],
+    )
+    cert_chain = b"".join(c.public_bytes(serialization.Encoding.PEM) for c in (pck, ca, root)) + b"\x00"
+
+    header = struct.pack("<HHIHH", 3, 2, 0, 8, PCE_SVN) + QE_VENDOR_ID + bytes(20)
+    # attributes.flags: INIT | MODE64BIT, not DEBUG
+    isv_report = report_body(MR_ENCLAVE, MR_SIGNER, ISV_PROD_ID, ISV_SVN, USER_DATA_HASH, 0x05)
+    isv_signature = raw_signature(attestation_key, header + isv_report)
+
+    public_numbers = attestation_key.public_key().public_numbers()
+    raw_attestation_key = public_numbers.x.to_bytes(32, "big") + public_numbers.y.to_bytes(32, "big")
+    qe_auth_data = bytes(range(32))
+    # attributes.flags: INIT | MODE64BIT | PROVISIONKEY, as the QE identity published by Intel
+    qe_report = report_body(
+        hashlib.sha256(b"qe mr_enclave").digest(), QE_MR_SIGNER, QE_ISV_PROD_ID, QE_ISV_SVN,
+        hashlib.sha256(raw_attestation_key + qe_auth_data).digest(), 0x15,
+    )
+    qe_signature = raw_signature(pck_key, qe_report)
+
+    signature_data = (
+        isv_signature
+        + raw_attestation_key
+        + qe_report
+        + qe_signature
+        + struct.pack("<H", len(qe_auth_data)) + qe_auth_data
+        + struct.pack("<HI", 5, len(cert_chain)) + cert_chain
+    )
+    quote = header + isv_report + struct.pack("<I", len(signature_data)) + signature_data
+
+    (out_dir / "dcap_quote.bin").write_bytes(quote)
+    (out_dir / "dcap_root_ca.der").write_bytes(root.public_bytes(serialization.Encoding.DER))
+    ca_name = "Test SGX PCK Platform CA"
+    (out_dir / "dcap_pck_crl.der").write_bytes(crl(ca_name, ca_key, [pck.serial_number + 1]))
+    (out_dir / "dcap_pck_crl_revoked.der").write_bytes(
+        crl(ca_name, ca_key, [pck.serial_number + 1, pck.serial_number])
+    )
+    runtime_hash = MR_ENCLAVE + struct.pack("<HH", ISV_PROD_ID, ISV_SVN) + MR_SIGNER
+    print("user data hash:", USER_DATA_HASH.hex())
+    print("pruntime hash: ", runtime_hash.hex())
+
+
+if __name__ == "__main__":
+    main()
@@ -247,6 +247,8 @@ pub struct Args {
 enum RaOption {
     None,
     Ias,
+    Dcap,
+    Simulated,
 }
 
 impl From<RaOption> for Option<AttestationProvider> {
@@ -254,6 +256,8 @@ impl From<RaOption> for Option<AttestationProvider> {
         match other {
             RaOption::None => None,
             RaOption::Ias => Some(AttestationProvider::Ias),
+            RaOption::Dcap => Some(AttestationProvider::Dcap),
+            RaOption::Simulated => Some(AttestationProvider::Simulated),
         }
     }
 }
@@ -1,15 +1,31 @@
-use crate::pal_gramine::GraminePlatform;
+use crate::pal::Platform;
+use crate::pal_gramine;
 use anyhow::{Context, Result};
+use log::info;
 use phactory::RpcService;
 use phactory_api::{
     ecall_args::InitArgs, prpc::phactory_api_server::PhactoryApi,
     pruntime_client::new_pruntime_client,
 };
-use log::info;
+use phala_types::AttestationProvider;
+
+/// The DCAP root CA shipped along with the pRuntime, as a trusted file in the Gramine manifest.
+const DCAP_ROOT_CA_FILE: &str = "dcap-root-ca.der";
 
 pub(crate) async fn handover_from(url: &str, args: InitArgs) -> Result<()> {
-    let mut this = RpcService::new(GraminePlatform);
-    this.lock_phactory().init(args);
+    let mut this = RpcService::new(Platform::current());
+    let attestation_provider = pal_gramine::attestation_provider();
+    let dcap_root_certs = match attestation_provider {
+        Some(AttestationProvider::Dcap) => {
+            vec![std::fs::read(DCAP_ROOT_CA_FILE).context("Failed to read the DCAP root CA")?]
+        }
+        _ => vec![],
+    };
+    {
+        let mut phactory = this.lock_phactory();
+        phactory.init(args);
+        phactory.set_handover_attestation(attestation_provider, dcap_root_certs);
+    }
 
     let from_pruntime = new_pruntime_client(url.into());
     info!("Requesting for challenge");
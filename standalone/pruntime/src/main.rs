@@ -1,6 +1,8 @@
 mod api_server;
 mod ias;
+mod pal;
 mod pal_gramine;
+mod pal_simulation;
 mod runtime;
 
 use std::{env, thread};
@@ -62,10 +64,32 @@ struct Args {
     #[arg(default_value_t = 5)]
     max_checkpoint_files: u32,
 
+    /// Max number of delta checkpoints taken between two full checkpoints, 0 to disable deltas
+    ///
+    /// Deltas save disk space but not time: the whole state is serialized for each of them, and
+    /// restoring from one holds its full checkpoint in memory as well.
+    #[arg(long)]
+    #[arg(default_value_t = 11)]
+    max_checkpoint_deltas: u32,
+
+    /// Back up the checkpoints to the given place, and restore from it if there is no local one.
+    ///
+    /// Either `dir:<path>` or `s3://<endpoint>/<bucket>?region=<region>[&virtual_host=true]`. The
+    /// S3 credentials are read from the env CHECKPOINT_BACKUP_ACCESS_KEY and
+    /// CHECKPOINT_BACKUP_SECRET_KEY.
+    #[arg(long)]
+    checkpoint_backup: Option<String>,
+
     /// Measuring the time it takes to process each RPC call.
     #[arg(long)]
     measure_rpc_time: bool,
 
+    /// Decrypt the given checkpoint file, print a report of its content and exit.
+    ///
+    /// For a delta checkpoint, its base is looked up in the same directory.
+    #[arg(long)]
+    inspect_checkpoint: Option<String>,
+
     /// Handover key from another running pruntime instance
     #[arg(long)]
     request_handover_from: Option<String>,
@@ -80,6 +104,28 @@ struct Args {
     #[arg(long)]
     #[arg(default_value_t = 0)]
     safe_mode_level: u8,
+
+    /// Run on the simulated platform instead of SGX, for local development clusters.
+    ///
+    /// The sealed data is encrypted with a key kept in a plain file, and the attestation reports
+    /// are fake ones, which are only accepted by the chains in dev mode.
+    #[arg(long)]
+    simulation: bool,
+
+    /// The file holding the sealing key of the simulated platform, generated if it doesn't exist
+    #[arg(long)]
+    #[arg(default_value = "./data/simulation_sealing_key")]
+    sim_sealing_key: String,
+
+    /// The machine ID reported by the simulated platform, default to one derived from the
+    /// sealing key
+    #[arg(long)]
+    sim_machine_id: Option<String>,
+
+    /// The number of CPU cores reported by the simulated platform, default to the number of
+    /// cores of this machine
+    #[arg(long)]
+    sim_cpu_cores: Option<u32>,
 }
 
 #[rocket::main]
@@ -127,6 +173,20 @@ async fn main() -> Result<(), rocket::Error> {
 
     logger::init(running_under_gramine);
 
+    if args.simulation {
+        if running_under_gramine {
+            panic!("The simulated platform is not allowed in Gramine");
+        }
+        let config = pal_simulation::SimulationConfig {
+            sealing_key_path: args.sim_sealing_key.clone().into(),
+            machine_id: args.sim_machine_id.clone().map(String::into_bytes),
+            cpu_cores: args.sim_cpu_cores,
+        };
+        if let Err(err) = pal_simulation::init(config) {
+            panic!("Failed to init the simulated platform: {err:?}");
+        }
+    }
+
     let cores: u32 = args.cores.unwrap_or_else(|| num_cpus::get() as _);
     info!("Bench cores: {}", cores);
 
@@ -142,12 +202,21 @@ async fn main() -> Result<(), rocket::Error> {
             checkpoint_interval: args.checkpoint_interval,
             remove_corrupted_checkpoint: args.remove_corrupted_checkpoint,
             max_checkpoint_files: args.max_checkpoint_files,
+            max_checkpoint_deltas: args.max_checkpoint_deltas,
+            checkpoint_backup: args.checkpoint_backup,
             cores,
             public_port: args.public_port,
             safe_mode_level: args.safe_mode_level,
         }
     };
     info!("init_args: {:#?}", init_args);
+    if let Some(filename) = &args.inspect_checkpoint {
+        match runtime::ecall_inspect_checkpoint(&init_args, filename) {
+            Ok(report) => println!("{report}"),
+            Err(err) => error!("Failed to inspect checkpoint {filename}: {err:?}"),
+        }
+        return Ok(());
+    }
     if let Some(handover_from) = args.request_handover_from {
         info!("Starting handover from {handover_from}");
         handover::handover_from(&handover_from, init_args)
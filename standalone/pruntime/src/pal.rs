@@ -0,0 +1,128 @@
+//! The platform pRuntime runs on, chosen at startup.
+
+use std::path::Path;
+
+use phactory_pal::{AppInfo, AppVersion, Machine, MemoryStats, MemoryUsage, Sealing, RA};
+use phala_types::AttestationProvider;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::pal_gramine::GraminePlatform;
+use crate::pal_simulation::{self, SimulatedPlatform};
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Platform {
+    Gramine(GraminePlatform),
+    Simulated(SimulatedPlatform),
+}
+
+impl Platform {
+    /// The simulated platform if enabled, otherwise Gramine.
+    pub fn current() -> Self {
+        if pal_simulation::is_enabled() {
+            Platform::Simulated(SimulatedPlatform)
+        } else {
+            Platform::Gramine(GraminePlatform)
+        }
+    }
+}
+
+// The platform is saved in the checkpoints. Keep it in the format of `GraminePlatform` to stay
+// compatible with the existing checkpoints, and always restore to the current platform.
+impl Serialize for Platform {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GraminePlatform.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Platform {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        GraminePlatform::deserialize(deserializer)?;
+        Ok(Platform::current())
+    }
+}
+
+impl Sealing for Platform {
+    type SealError = anyhow::Error;
+    type UnsealError = anyhow::Error;
+
+    fn seal_data(&self, path: impl AsRef<Path>, data: &[u8]) -> Result<(), Self::SealError> {
+        match self {
+            Platform::Gramine(p) => Ok(p.seal_data(path, data)?),
+            Platform::Simulated(p) => p.seal_data(path, data),
+        }
+    }
+
+    fn unseal_data(&self, path: impl AsRef<Path>) -> Result<Option<Vec<u8>>, Self::UnsealError> {
+        match self {
+            Platform::Gramine(p) => Ok(p.unseal_data(path)?),
+            Platform::Simulated(p) => p.unseal_data(path),
+        }
+    }
+}
+
+impl RA for Platform {
+    type Error = anyhow::Error;
+
+    fn create_attestation_report(
+        &self,
+        provider: Option<AttestationProvider>,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Self::Error> {
+        match self {
+            Platform::Gramine(p) => p.create_attestation_report(provider, data),
+            Platform::Simulated(p) => p.create_attestation_report(provider, data),
+        }
+    }
+
+    fn quote_test(&self, provider: Option<AttestationProvider>) -> Result<(), Self::Error> {
+        match self {
+            Platform::Gramine(p) => p.quote_test(provider),
+            Platform::Simulated(p) => p.quote_test(provider),
+        }
+    }
+
+    fn measurement(&self) -> Option<Vec<u8>> {
+        match self {
+            Platform::Gramine(p) => p.measurement(),
+            Platform::Simulated(p) => p.measurement(),
+        }
+    }
+}
+
+impl Machine for Platform {
+    fn machine_id(&self) -> Vec<u8> {
+        match self {
+            Platform::Gramine(p) => p.machine_id(),
+            Platform::Simulated(p) => p.machine_id(),
+        }
+    }
+
+    fn cpu_core_num(&self) -> u32 {
+        match self {
+            Platform::Gramine(p) => p.cpu_core_num(),
+            Platform::Simulated(p) => p.cpu_core_num(),
+        }
+    }
+
+    fn cpu_feature_level(&self) -> u32 {
+        match self {
+            Platform::Gramine(p) => p.cpu_feature_level(),
+            Platform::Simulated(p) => p.cpu_feature_level(),
+        }
+    }
+}
+
+impl MemoryStats for Platform {
+    fn memory_usage(&self) -> MemoryUsage {
+        match self {
+            Platform::Gramine(p) => p.memory_usage(),
+            Platform::Simulated(p) => p.memory_usage(),
+        }
+    }
+}
+
+impl AppInfo for Platform {
+    fn app_version() -> AppVersion {
+        GraminePlatform::app_version()
+    }
+}
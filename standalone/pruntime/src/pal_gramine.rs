@@ -62,6 +62,11 @@ impl RA for GraminePlatform {
 
                 Ok(Encode::encode(&attestation_report))
             }
+            Some(AttestationProvider::Dcap) => {
+                let quote = ias::create_quote_vec(data)?;
+                let attestation_report = Some(phala_types::AttestationReport::SgxDcap { quote });
+                Ok(Encode::encode(&attestation_report))
+            }
             None => Ok(Encode::encode(&None::<AttestationProvider>)),
             _ => Err(anyhow!("Unknown attestation provider `{:?}`", provider)),
         }
@@ -69,7 +74,9 @@ impl RA for GraminePlatform {
 
     fn quote_test(&self, provider: Option<AttestationProvider>) -> Result<(), Self::Error> {
         match provider {
-            Some(AttestationProvider::Ias) => ias::create_quote_vec(&[0u8; 64]).map(|_| ()),
+            Some(AttestationProvider::Ias | AttestationProvider::Dcap) => {
+                ias::create_quote_vec(&[0u8; 64]).map(|_| ())
+            }
             None => Ok(()),
             _ => Err(anyhow!("Unknown attestation provider `{:?}`", provider)),
         }
@@ -173,6 +180,15 @@ pub(crate) fn is_gramine() -> bool {
     *IS_GRAMINE
 }
 
+/// The attestation provider configured in the Gramine manifest.
+pub(crate) fn attestation_provider() -> Option<AttestationProvider> {
+    match std::fs::read_to_string("/dev/attestation/attestation_type").as_deref() {
+        Ok("epid") => Some(AttestationProvider::Ias),
+        Ok("dcap") => Some(AttestationProvider::Dcap),
+        _ => None,
+    }
+}
+
 pub(crate) fn print_target_info() {
     use hex_fmt::HexFmt;
     if is_gramine() {
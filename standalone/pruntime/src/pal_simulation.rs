@@ -0,0 +1,194 @@
+//! A simulated platform to run pRuntime out of SGX, so that a local development cluster can run
+//! many workers on a single Linux box.
+//!
+//! The sealed data is encrypted with a key kept in a plain file, and the attestation reports are
+//! deterministic fake ones, which the chain accepts only when it runs in dev mode.
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use log::info;
+use once_cell::sync::OnceCell;
+use parity_scale_codec::Encode;
+use rand::RngCore as _;
+use sha2::{Digest as _, Sha256};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use phactory_pal::{AppInfo, AppVersion, Machine, MemoryStats, MemoryUsage, Sealing, RA};
+use phala_crypto::aead;
+use phala_types::AttestationProvider;
+
+use crate::pal_gramine::GraminePlatform;
+
+const SEALING_KEY_LEN: usize = 32;
+
+pub(crate) struct SimulationConfig {
+    /// The file holding the sealing key, generated if it doesn't exist
+    pub sealing_key_path: PathBuf,
+    /// The machine ID to report, default to one derived from the sealing key
+    pub machine_id: Option<Vec<u8>>,
+    /// The number of CPU cores to report, default to the number of cores of the box
+    pub cpu_cores: Option<u32>,
+}
+
+struct Simulation {
+    sealing_key: Vec<u8>,
+    machine_id: Vec<u8>,
+    cpu_cores: u32,
+}
+
+static SIMULATION: OnceCell<Simulation> = OnceCell::new();
+
+/// Enable the simulated platform. Must be called before any pRuntime instance is created.
+pub(crate) fn init(config: SimulationConfig) -> Result<()> {
+    let sealing_key = load_or_create_sealing_key(&config.sealing_key_path)?;
+    let machine_id = config.machine_id.unwrap_or_else(|| {
+        Sha256::digest([&b"machine_id:"[..], &sealing_key[..]].concat()).to_vec()
+    });
+    let cpu_cores = config.cpu_cores.unwrap_or_else(|| num_cpus::get() as _);
+    info!(
+        "Simulated platform enabled, machine_id: 0x{}, cpu cores: {cpu_cores}",
+        hex_fmt::HexFmt(&machine_id)
+    );
+    SIMULATION
+        .set(Simulation {
+            sealing_key,
+            machine_id,
+            cpu_cores,
+        })
+        .map_err(|_| anyhow!("Simulated platform already initialized"))
+}
+
+pub(crate) fn is_enabled() -> bool {
+    SIMULATION.get().is_some()
+}
+
+fn simulation() -> &'static Simulation {
+    SIMULATION
+        .get()
+        .expect("BUG: the simulated platform is not initialized")
+}
+
+fn load_or_create_sealing_key(path: &Path) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(key) if key.len() == SEALING_KEY_LEN => Ok(key),
+        Ok(_) => bail!("Invalid sealing key in {}", path.display()),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            let mut key = vec![0u8; SEALING_KEY_LEN];
+            rand::thread_rng().fill_bytes(&mut key);
+            std::fs::write(path, &key)
+                .with_context(|| format!("Failed to write sealing key {}", path.display()))?;
+            info!("Generated a new sealing key at {}", path.display());
+            Ok(key)
+        }
+        Err(err) => {
+            Err(err).with_context(|| format!("Failed to read sealing key {}", path.display()))
+        }
+    }
+}
+
+/// The fake mr_enclave, which only changes along with the version.
+fn simulated_mr_enclave() -> Vec<u8> {
+    Sha256::digest(format!("simulated pruntime v{}", env!("CARGO_PKG_VERSION"))).to_vec()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SimulatedPlatform;
+
+impl Sealing for SimulatedPlatform {
+    type SealError = anyhow::Error;
+    type UnsealError = anyhow::Error;
+
+    fn seal_data(&self, path: impl AsRef<Path>, data: &[u8]) -> Result<(), Self::SealError> {
+        let mut iv = aead::IV::default();
+        rand::thread_rng().fill_bytes(&mut iv);
+        let mut sealed = data.to_vec();
+        aead::encrypt(&iv, &simulation().sealing_key, &mut sealed)
+            .map_err(|err| anyhow!("Failed to seal data: {err:?}"))?;
+        std::fs::write(path, [&iv[..], &sealed[..]].concat())?;
+        Ok(())
+    }
+
+    fn unseal_data(&self, path: impl AsRef<Path>) -> Result<Option<Vec<u8>>, Self::UnsealError> {
+        let mut sealed = match std::fs::read(path) {
+            Err(err) if matches!(err.kind(), ErrorKind::NotFound) => return Ok(None),
+            other => other?,
+        };
+        if sealed.len() < aead::IV_BYTES {
+            bail!("Sealed data too short");
+        }
+        let (iv, cipher) = sealed.split_at_mut(aead::IV_BYTES);
+        let data = aead::decrypt(iv, &simulation().sealing_key, cipher)
+            .map_err(|err| anyhow!("Failed to unseal data: {err:?}"))?;
+        Ok(Some(data.to_vec()))
+    }
+}
+
+impl RA for SimulatedPlatform {
+    type Error = anyhow::Error;
+
+    fn create_attestation_report(
+        &self,
+        provider: Option<AttestationProvider>,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Self::Error> {
+        match provider {
+            Some(AttestationProvider::Simulated) => {
+                // Laid out the same as the runtime hash of SGX: mr_enclave, isv_prod_id, isv_svn
+                // and mr_signer.
+                let runtime_hash =
+                    [&simulated_mr_enclave()[..], &[0u8; 4][..], &[0u8; 32][..]].concat();
+                let attestation_report = Some(phala_types::AttestationReport::Simulated {
+                    runtime_hash,
+                    report_data: data.to_vec(),
+                });
+                Ok(Encode::encode(&attestation_report))
+            }
+            None => Ok(Encode::encode(&None::<AttestationProvider>)),
+            _ => Err(anyhow!(
+                "Attestation provider `{:?}` is not supported on the simulated platform",
+                provider
+            )),
+        }
+    }
+
+    fn quote_test(&self, provider: Option<AttestationProvider>) -> Result<(), Self::Error> {
+        match provider {
+            Some(AttestationProvider::Simulated) | None => Ok(()),
+            _ => Err(anyhow!(
+                "Attestation provider `{:?}` is not supported on the simulated platform",
+                provider
+            )),
+        }
+    }
+
+    fn measurement(&self) -> Option<Vec<u8>> {
+        Some(simulated_mr_enclave())
+    }
+}
+
+impl Machine for SimulatedPlatform {
+    fn machine_id(&self) -> Vec<u8> {
+        simulation().machine_id.clone()
+    }
+
+    fn cpu_core_num(&self) -> u32 {
+        simulation().cpu_cores
+    }
+
+    fn cpu_feature_level(&self) -> u32 {
+        GraminePlatform.cpu_feature_level()
+    }
+}
+
+impl MemoryStats for SimulatedPlatform {
+    fn memory_usage(&self) -> MemoryUsage {
+        // Both platforms run in the same process with the same allocator.
+        GraminePlatform.memory_usage()
+    }
+}
+
+impl AppInfo for SimulatedPlatform {
+    fn app_version() -> AppVersion {
+        GraminePlatform::app_version()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::pal_gramine::GraminePlatform;
+use crate::pal::Platform;
 
 use anyhow::Result;
 use core::sync::atomic::{AtomicU32, Ordering};
@@ -6,7 +6,7 @@ use log::info;
 use phactory::{benchmark, Phactory, RpcService};
 
 lazy_static::lazy_static! {
-    static ref APPLICATION: RpcService<GraminePlatform> = RpcService::new(GraminePlatform);
+    static ref APPLICATION: RpcService<Platform> = RpcService::new(Platform::current());
 }
 
 pub fn ecall_handle(action: u8, input: &[u8]) -> Result<Vec<u8>> {
@@ -19,6 +19,14 @@ pub fn ecall_getinfo() -> String {
     serde_json::to_string_pretty(&info).unwrap_or_default()
 }
 
+pub fn ecall_inspect_checkpoint(
+    args: &phactory_api::ecall_args::InitArgs,
+    filename: &str,
+) -> Result<String> {
+    let report = Phactory::inspect_checkpoint(&Platform::current(), args, filename.as_ref())?;
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
 pub fn ecall_sign_http_response(data: &[u8]) -> Option<String> {
     APPLICATION.lock_phactory().sign_http_response(data)
 }
@@ -31,7 +39,7 @@ pub fn ecall_init(args: phactory_api::ecall_args::InitArgs) -> Result<()> {
 
     if args.enable_checkpoint {
         match Phactory::restore_from_checkpoint(
-            &GraminePlatform,
+            &Platform::current(),
             &args,
         ) {
             Ok(Some(factory)) => {
@@ -17,8 +17,19 @@
 
 //! Some configurable implementations as associated type for the substrate runtime.
 
-use crate::{Authorship, Balances, NegativeImbalance};
-use frame_support::traits::{Currency, OnUnbalanced};
+use crate::{AccountId, Authorship, Balances, NegativeImbalance, PhatOracle, Runtime};
+use frame_support::{
+    pallet_prelude::Get,
+    traits::{Currency, OnUnbalanced},
+    weights::Weight,
+};
+use phat_offchain_rollup::{
+    anchor::{CallbackDispatcher, OnResponse},
+    types::CallbackTarget,
+};
+use sp_core::H256;
+use sp_runtime::DispatchError;
+use sp_std::vec::Vec;
 
 pub struct Author;
 impl OnUnbalanced<NegativeImbalance> for Author {
@@ -29,6 +40,43 @@ impl OnUnbalanced<NegativeImbalance> for Author {
     }
 }
 
+/// The `CallbackTarget::Handler` index of the oracle pallet
+pub const ORACLE_CALLBACK_HANDLER: u32 = 0;
+
+/// Routes the anchor replies to the pallets of the runtime
+///
+/// Only `CallbackTarget::Handler(ORACLE_CALLBACK_HANDLER)` is supported. Contract targets can't
+/// be set since there is no contract pallet in this runtime.
+pub struct RollupCallbackDispatcher;
+impl CallbackDispatcher<AccountId> for RollupCallbackDispatcher {
+    fn supports(target: &CallbackTarget) -> bool {
+        target == &CallbackTarget::Handler(ORACLE_CALLBACK_HANDLER)
+    }
+
+    fn dispatch(
+        target: &CallbackTarget,
+        name: H256,
+        submitter: AccountId,
+        data: Vec<u8>,
+        weight_limit: Weight,
+    ) -> Result<Weight, DispatchError> {
+        match target {
+            CallbackTarget::Handler(ORACLE_CALLBACK_HANDLER) => {
+                // Reads and writes the quote, the request and the balances of both parties
+                let weight = Weight::from_ref_time(10_000u64)
+                    + <Runtime as frame_system::Config>::DbWeight::get().reads_writes(4, 4);
+                if weight.any_gt(weight_limit) {
+                    return Err(DispatchError::Exhausted);
+                }
+                PhatOracle::on_response(name, submitter, data)?;
+                Ok(weight)
+            }
+            // Rejected by `supports`, so never set
+            _ => Err(DispatchError::Other("Unsupported callback target")),
+        }
+    }
+}
+
 #[cfg(test)]
 mod multiplier_tests {
     use pallet_transaction_payment::{Multiplier, TargetedFeeAdjustment};
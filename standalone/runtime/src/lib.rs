@@ -1502,6 +1502,7 @@ impl pallet_fat_tokenomic::Config for Runtime {
 parameter_types! {
     pub const QueuePrefix: &'static [u8] = b"_queue/";
     pub const QueueCapacity: u32 = 128;
+    pub const MaxCallbackWeight: Weight = Weight::from_ref_time(WEIGHT_REF_TIME_PER_SECOND / 10);
 }
 
 impl pallet_anchor::Config for Runtime {
@@ -1509,9 +1510,19 @@ impl pallet_anchor::Config for Runtime {
     type OnResponse = PhatOracle;
     type QueuePrefix = QueuePrefix;
     type QueueCapacity = QueueCapacity;
+    type CallbackDispatcher = impls::RollupCallbackDispatcher;
+    type MaxCallbackWeight = MaxCallbackWeight;
 }
+parameter_types! {
+    pub const OracleRequestFee: Balance = 1 * CENTS;
+    pub const OracleRequestExpiry: BlockNumber = 1 * HOURS;
+}
+
 impl pallet_oracle::Config for Runtime {
     type RuntimeEvent = RuntimeEvent;
+    type Currency = Balances;
+    type RequestFee = OracleRequestFee;
+    type RequestExpiry = OracleRequestExpiry;
 }
 
 impl puppets::parachain_info::Config for Runtime {}
@@ -1898,6 +1909,82 @@ mod tests {
             .fold(0, |acc, x| acc.checked_add(*x).unwrap());
     }
 
+    #[test]
+    fn rollup_replies_are_dispatched_to_the_callback() {
+        use frame_support::{assert_ok, traits::Currency};
+        use phat_offchain_rollup::types::{Action, CallbackTarget, RollupTx};
+        use sp_core::H256;
+
+        let mut t: sp_io::TestExternalities = frame_system::GenesisConfig::default()
+            .build_storage::<Runtime>()
+            .unwrap()
+            .into();
+        t.execute_with(|| {
+            System::set_block_number(1);
+            let submitter = AccountId::from([1u8; 32]);
+            let requester = AccountId::from([2u8; 32]);
+            let name = H256([1u8; 32]);
+            let pair = b"polkadot_usd".to_vec().try_into().unwrap();
+            Balances::make_free_balance_be(&submitter, 100 * DOLLARS);
+            Balances::make_free_balance_be(&requester, 100 * DOLLARS);
+
+            assert_ok!(PhatRollupAnchor::claim_name(
+                RuntimeOrigin::signed(submitter.clone()),
+                name
+            ));
+            assert_ok!(PhatRollupAnchor::set_callback(
+                RuntimeOrigin::signed(submitter.clone()),
+                name,
+                Some(CallbackTarget::Handler(impls::ORACLE_CALLBACK_HANDLER))
+            ));
+            assert_ok!(PhatOracle::request(
+                RuntimeOrigin::signed(requester.clone()),
+                name,
+                pair.clone(),
+                0
+            ));
+
+            let reply = |response: &[u8], nonce| {
+                let action = Action::Reply(response.to_vec().try_into().unwrap());
+                PhatRollupAnchor::rollup(
+                    RuntimeOrigin::signed(submitter.clone()),
+                    name,
+                    RollupTx {
+                        conds: vec![],
+                        actions: vec![action.encode().try_into().unwrap()],
+                        updates: vec![],
+                    },
+                    nonce,
+                )
+            };
+            let response = pallet_oracle::ResponseRecord {
+                owner: sp_runtime::AccountId32::from([0u8; 32]),
+                contract_id: name,
+                pair: pair.clone(),
+                price: 5_000000000000,
+                timestamp_ms: 1000,
+                request_id: Some(0),
+            };
+            let submitter_free = Balances::free_balance(&submitter);
+            assert_ok!(reply(&response.encode(), 1));
+            // The oracle handled the reply and paid the submitter
+            assert!(PhatOracle::price_feeds(response.owner.clone(), pair).is_some());
+            assert_eq!(PhatOracle::requests(0), None);
+            assert_eq!(
+                Balances::free_balance(&submitter),
+                submitter_free + OracleRequestFee::get()
+            );
+
+            // A reply the target can't handle is reported without failing the rollup
+            System::reset_events();
+            assert_ok!(reply(b"garbage", 2));
+            assert!(System::events().iter().any(|record| matches!(
+                record.event,
+                RuntimeEvent::PhatRollupAnchor(pallet_anchor::Event::CallbackFailed { .. })
+            )));
+        });
+    }
+
     #[test]
     fn call_size() {
         let size = core::mem::size_of::<RuntimeCall>();